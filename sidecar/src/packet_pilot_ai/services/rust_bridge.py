@@ -127,9 +127,12 @@ async def get_stream(
         return None
 
 
-async def get_capture_stats() -> Optional[dict]:
+async def get_capture_stats(filter: Optional[str] = None) -> Optional[dict]:
     """Get capture statistics including protocol hierarchy and conversations.
 
+    Args:
+        filter: Optional display filter to limit the statistics to
+
     Returns:
         Dict with capture statistics, or None on error:
         - summary: {total_frames, duration, protocol_count, tcp/udp_conversation_count, endpoint_count}
@@ -142,6 +145,7 @@ async def get_capture_stats() -> Optional[dict]:
         async with httpx.AsyncClient() as client:
             response = await client.get(
                 f"{RUST_BRIDGE_URL}/capture-stats",
+                params={"filter": filter} if filter else None,
                 timeout=30.0,
             )
             response.raise_for_status()
@@ -0,0 +1,72 @@
+//! Benchmarks of frame paging, search, capture stats and field extraction
+//! against a real sharkd, on a generated capture of 16,000 frames.
+//!
+//! Run with `cargo bench --features bench` (sharkd must be installed). The
+//! `run_benchmarks` command times the same operations on an open capture.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use packet_pilot_lib::testsupport::{pcap, system_client, SharkdClient};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// DNS + HTTP exchanges in the capture, 8 frames each
+const EXCHANGES: u32 = 2_000;
+
+const PAGE_SIZE: u32 = 500;
+
+fn setup(runtime: &Runtime) -> Arc<SharkdClient> {
+    let path = std::env::temp_dir().join("packetpilot-bench-medium.pcap");
+    pcap::write_pcap(&path, &pcap::medium_capture(EXCHANGES)).expect("write capture");
+
+    runtime.block_on(async {
+        let client = system_client().await.expect("start sharkd");
+        client
+            .load(&path.display().to_string())
+            .await
+            .expect("load capture");
+        Arc::new(client)
+    })
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("tokio runtime");
+    let client = setup(&runtime);
+    let frames = u64::from(EXCHANGES) * 8;
+
+    c.bench_function("frames_first_page", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.frames(0, PAGE_SIZE).await.unwrap() })
+    });
+    c.bench_function("frames_middle_page", |b| {
+        let skip = (frames / 2) as u32;
+        b.to_async(&runtime)
+            .iter(|| async { client.frames(skip, PAGE_SIZE).await.unwrap() })
+    });
+    c.bench_function("search", |b| {
+        b.to_async(&runtime).iter(|| async {
+            client
+                .search_frames("http.request", 0, PAGE_SIZE)
+                .await
+                .unwrap()
+        })
+    });
+    c.bench_function("capture_stats", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.capture_stats(None).await.unwrap() })
+    });
+    c.bench_function("field_extraction", |b| {
+        b.to_async(&runtime).iter(|| async {
+            client
+                .column_values("dns", &["dns.qry.name", "ip.src"], 10_000)
+                .await
+                .unwrap()
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = benchmarks
+}
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+//! Arbitrary sharkd output lines through every response parser: each must
+//! return a value or a `ParseError`, never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use packet_pilot_lib::testsupport::sharkd_parse as parse;
+
+fuzz_target!(|data: &[u8]| {
+    let result = match std::str::from_utf8(data).map(parse::response) {
+        Ok(Ok(result)) => result,
+        _ => return,
+    };
+
+    let _ = parse::status(result.clone());
+    let _ = parse::follow(result.clone());
+    let _ = parse::tree(result.clone());
+    let _ = parse::frame_details(result.clone());
+    let _ = parse::frame_bytes(result.clone());
+    if let Ok(frames) = parse::frames(result.clone()) {
+        // Whatever parses must serialize back for the frontend
+        serde_json::to_string(&frames).expect("frames serialize");
+    }
+    let _ = parse::taps(&result);
+});
@@ -0,0 +1,22 @@
+//! Arbitrary JSON as a `tap` result, read as each tap the client batches.
+//! A malformed tap must be an error, never a panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use packet_pilot_lib::testsupport::{
+    sharkd_parse as parse, Conversation, Endpoint, ProtocolNode, StatsNode,
+};
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let taps = match serde_json::from_slice::<Value>(data).map(|result| parse::taps(&result)) {
+        Ok(Ok(taps)) => taps,
+        _ => return,
+    };
+
+    let _ = parse::tap_field::<Vec<ProtocolNode>>(&taps, "phs", "protos");
+    let _ = parse::tap_field::<Vec<Conversation>>(&taps, "conv:TCP", "convs");
+    let _ = parse::tap_field::<Vec<Endpoint>>(&taps, "endpt:IPv4", "hosts");
+    let _ = parse::tap_field::<Vec<StatsNode>>(&taps, "stat:http", "stats");
+});
@@ -0,0 +1,756 @@
+//! Anonymization of capture data before it is shared (with the AI sidecar or
+//! as a file).
+//!
+//! Addresses are rewritten consistently within a session: IPv4/IPv6 with a
+//! keyed prefix-preserving map (two addresses sharing an N-bit prefix still
+//! do afterwards, so subnets stay recognizable) and MACs with a keyed hash.
+//! Payloads above the transport header can be stripped. The same map is used
+//! for sanitized captures and for frame summaries, so they can be compared.
+//!
+//! Rewritten captures keep valid IPv4 header checksums; TCP/UDP checksums
+//! are left stale.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, OnceLock};
+
+use crate::FrameData;
+
+const PCAPNG_SHB: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_IDB: u32 = 0x0000_0001;
+const PCAPNG_SPB: u32 = 0x0000_0003;
+const PCAPNG_EPB: u32 = 0x0000_0006;
+
+/// pcapng blocks dropped from sanitized captures: name resolution, decryption
+/// secrets, and custom blocks
+const DROPPED_BLOCKS: &[u32] = &[0x0000_0004, 0x0000_000A, 0x0000_0BAD, 0x4000_0BAD];
+
+/// Link types whose packets can be rewritten
+/// Largest packet or pcapng block read, so a corrupt length can't make us
+/// allocate gigabytes
+const MAX_RECORD_LEN: usize = 16 << 20;
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: &[u32] = &[12, 14, 101];
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+/// Stands in for withheld payload text
+pub const PAYLOAD_REMOVED: &str = "[payload removed]";
+
+/// Protocols whose Info column only describes headers, kept in summaries when
+/// payloads are stripped
+const HEADER_PROTOCOLS: &[&str] = &[
+    "ARP", "ICMP", "ICMPv6", "IPv4", "IPv6", "TCP", "UDP", "Ethernet", "LLC", "STP",
+];
+
+/// Protocol tree nodes kept in frame details when payloads are stripped
+const HEADER_TREE_NODES: &[&str] = &[
+    "frame", "eth", "sll", "vlan", "ip", "ipv6", "arp", "tcp", "udp", "icmp", "icmpv6",
+];
+
+/// What is anonymized, per session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnonymizationPolicy {
+    /// Anonymize frames and frame details the AI sidecar reads
+    pub enabled: bool,
+    /// Rewrite IPv4/IPv6 addresses (prefix-preserving)
+    pub ip_addresses: bool,
+    /// Rewrite MAC addresses
+    pub mac_addresses: bool,
+    /// Drop everything above the transport header
+    pub strip_payloads: bool,
+    /// Secret the address maps are derived from. The same key gives the same
+    /// addresses across sessions; a random one is used when omitted.
+    #[serde(skip_serializing)]
+    pub key: Option<String>,
+}
+
+impl Default for AnonymizationPolicy {
+    fn default() -> Self {
+        AnonymizationPolicy {
+            enabled: false,
+            ip_addresses: true,
+            mac_addresses: true,
+            strip_payloads: true,
+            key: None,
+        }
+    }
+}
+
+/// Summary of a written sanitized capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizeResult {
+    pub path: String,
+    pub packets: u64,
+    /// Packets of link types that couldn't be rewritten, copied with their
+    /// payload removed entirely
+    pub unsupported_packets: u64,
+}
+
+/// A session's policy with the address maps derived from its key
+pub struct Anonymizer {
+    policy: AnonymizationPolicy,
+    key: [u8; 32],
+    addresses: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+static ANONYMIZERS: OnceLock<Mutex<HashMap<String, Arc<Anonymizer>>>> = OnceLock::new();
+
+fn get_anonymizers() -> &'static Mutex<HashMap<String, Arc<Anonymizer>>> {
+    ANONYMIZERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn new_anonymizer(policy: AnonymizationPolicy) -> Result<Anonymizer, String> {
+    let key = match &policy.key {
+        Some(key) => Sha256::digest(key.as_bytes()).into(),
+        None => {
+            let mut key = [0u8; 32];
+            getrandom::getrandom(&mut key)
+                .map_err(|e| format!("Failed to generate anonymization key: {}", e))?;
+            key
+        }
+    };
+    Ok(Anonymizer {
+        policy,
+        key,
+        addresses: Mutex::new(HashMap::new()),
+    })
+}
+
+/// A session's anonymizer, created with the default policy on first use
+pub fn anonymizer(session_id: &str) -> Result<Arc<Anonymizer>, String> {
+    let mut anonymizers = get_anonymizers().lock();
+    if let Some(anonymizer) = anonymizers.get(session_id) {
+        return Ok(anonymizer.clone());
+    }
+    let anonymizer = Arc::new(new_anonymizer(AnonymizationPolicy::default())?);
+    anonymizers.insert(session_id.to_string(), anonymizer.clone());
+    Ok(anonymizer)
+}
+
+/// A session's policy
+pub fn policy(session_id: &str) -> Result<AnonymizationPolicy, String> {
+    Ok(anonymizer(session_id)?.policy.clone())
+}
+
+/// Set a session's policy. The address maps only change when a key is given.
+pub fn set_policy(session_id: &str, policy: AnonymizationPolicy) -> Result<(), String> {
+    let current = anonymizer(session_id)?;
+    let anonymizer = if policy.key.is_some() {
+        new_anonymizer(policy)?
+    } else {
+        Anonymizer {
+            policy,
+            key: current.key,
+            addresses: Mutex::new(current.addresses.lock().clone()),
+        }
+    };
+    get_anonymizers()
+        .lock()
+        .insert(session_id.to_string(), Arc::new(anonymizer));
+    Ok(())
+}
+
+/// The session's anonymizer when its policy applies to the AI sidecar
+pub fn for_sidecar(session_id: &str) -> Option<Arc<Anonymizer>> {
+    anonymizer(session_id)
+        .ok()
+        .filter(|anonymizer| anonymizer.policy.enabled)
+}
+
+impl Anonymizer {
+    /// Keyed pseudo-random bit for the first `bits` bits of `address`
+    fn prefix_bit(&self, address: &[u8], bits: usize) -> u8 {
+        let mut prefix = address.to_vec();
+        for (i, byte) in prefix.iter_mut().enumerate() {
+            let keep = bits.saturating_sub(i * 8).min(8);
+            *byte &= !(0xFFu16 >> keep) as u8;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        hasher.update((bits as u32).to_be_bytes());
+        hasher.update(&prefix);
+        hasher.finalize()[0] & 1
+    }
+
+    /// Prefix-preserving map of an IP address: bit N of the result is bit N
+    /// of the input flipped by a keyed function of the bits before it
+    fn map_ip(&self, address: &[u8]) -> Vec<u8> {
+        if let Some(mapped) = self.addresses.lock().get(address) {
+            return mapped.clone();
+        }
+        let mut mapped = address.to_vec();
+        for bit in 0..address.len() * 8 {
+            if self.prefix_bit(address, bit) == 1 {
+                mapped[bit / 8] ^= 0x80 >> (bit % 8);
+            }
+        }
+        self.addresses
+            .lock()
+            .insert(address.to_vec(), mapped.clone());
+        mapped
+    }
+
+    pub fn ipv4(&self, address: Ipv4Addr) -> Ipv4Addr {
+        if !self.policy.ip_addresses
+            || address.is_unspecified()
+            || address.is_broadcast()
+            || address.is_loopback()
+            || address.is_multicast()
+        {
+            return address;
+        }
+        let mapped = self.map_ip(&address.octets());
+        Ipv4Addr::new(mapped[0], mapped[1], mapped[2], mapped[3])
+    }
+
+    pub fn ipv6(&self, address: Ipv6Addr) -> Ipv6Addr {
+        if !self.policy.ip_addresses
+            || address.is_unspecified()
+            || address.is_loopback()
+            || address.is_multicast()
+        {
+            return address;
+        }
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&self.map_ip(&address.octets()));
+        Ipv6Addr::from(octets)
+    }
+
+    /// A locally administered MAC derived from the key; group addresses
+    /// (broadcast, multicast) are kept
+    pub fn mac(&self, address: [u8; 6]) -> [u8; 6] {
+        if !self.policy.mac_addresses || address[0] & 1 == 1 {
+            return address;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        hasher.update(b"mac");
+        hasher.update(address);
+        let digest = hasher.finalize();
+        let mut mapped = [0u8; 6];
+        mapped.copy_from_slice(&digest[..6]);
+        mapped[0] = (mapped[0] & 0xFC) | 0x02;
+        mapped
+    }
+
+    /// Replace an address-looking token, or None when it isn't one
+    fn replace_token(&self, token: &str) -> Option<String> {
+        if let Ok(address) = token.parse::<Ipv4Addr>() {
+            return Some(self.ipv4(address).to_string());
+        }
+        if let Ok(address) = token.parse::<Ipv6Addr>() {
+            return Some(self.ipv6(address).to_string());
+        }
+        if let Some(mac) = parse_hex_bytes::<6>(token) {
+            return Some(format_mac(self.mac(mac)));
+        }
+        // address:port
+        let (address, port) = token.rsplit_once(':')?;
+        let address = address.parse::<Ipv4Addr>().ok()?;
+        Some(format!("{}:{}", self.ipv4(address), port))
+    }
+
+    /// Replace a MAC written with its OUI resolved to a vendor name, e.g.
+    /// `Cisco_12:34:56`, given the station bytes and the text before them.
+    /// The vendor's OUI isn't known here, so the pseudonym is keyed on the
+    /// name instead: stable, but unrelated to the same MAC written in full.
+    fn replace_vendor_mac(&self, written: &mut String, token: &str) -> bool {
+        let station = match parse_hex_bytes::<3>(token) {
+            Some(station) if self.policy.mac_addresses => station,
+            _ => return false,
+        };
+        let vendor = match written.strip_suffix('_') {
+            Some(before) => {
+                let len = before
+                    .chars()
+                    .rev()
+                    .take_while(char::is_ascii_alphanumeric)
+                    .count();
+                &before[before.len() - len..]
+            }
+            None => return false,
+        };
+        if vendor.is_empty() {
+            return false;
+        }
+        let oui = Sha256::digest(vendor.as_bytes());
+        let address = [
+            oui[0] & 0xFE,
+            oui[1],
+            oui[2],
+            station[0],
+            station[1],
+            station[2],
+        ];
+        written.truncate(written.len() - vendor.len() - 1);
+        written.push_str(&format_mac(self.mac(address)));
+        true
+    }
+
+    /// Rewrite the IP and MAC addresses written in free text
+    pub fn anonymize_text(&self, text: &str) -> String {
+        let is_address_char = |c: char| c.is_ascii_hexdigit() || c == '.' || c == ':';
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(is_address_char) {
+            out.push_str(&rest[..start]);
+            let run = &rest[start..];
+            let end = run.find(|c: char| !is_address_char(c)).unwrap_or(run.len());
+            // Sentence punctuation isn't part of the address
+            let token = run[..end].trim_end_matches(['.', ':']);
+            let token = if token.is_empty() { &run[..end] } else { token };
+            match self.replace_token(token) {
+                Some(replaced) => out.push_str(&replaced),
+                None if self.replace_vendor_mac(&mut out, token) => {}
+                None => out.push_str(token),
+            }
+            rest = &run[token.len()..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Whether payloads are to be withheld, not just their addresses rewritten
+    pub fn strips_payloads(&self) -> bool {
+        self.policy.strip_payloads
+    }
+
+    /// Sanitize frame summaries in place
+    pub fn anonymize_frames(&self, frames: &mut [FrameData]) {
+        for frame in frames {
+            frame.source = self.anonymize_text(&frame.source);
+            frame.destination = self.anonymize_text(&frame.destination);
+            if self.policy.strip_payloads && !HEADER_PROTOCOLS.contains(&frame.protocol.as_str()) {
+                frame.info = PAYLOAD_REMOVED.to_string();
+            } else {
+                frame.info = self.anonymize_text(&frame.info);
+            }
+            for value in &mut frame.custom {
+                *value = self.anonymize_text(value);
+            }
+        }
+    }
+
+    /// Sanitize a sharkd frame details tree: addresses in every label are
+    /// rewritten, raw bytes are dropped, and with payload stripping only the
+    /// header protocols' subtrees are kept
+    pub fn anonymize_tree(&self, details: &mut Value) {
+        if let Some(object) = details.as_object_mut() {
+            object.remove("bytes");
+            object.remove("ds");
+        }
+        if self.policy.strip_payloads {
+            if let Some(Value::Array(nodes)) = details.get_mut("tree") {
+                nodes.retain(|node| {
+                    node.get("f")
+                        .and_then(Value::as_str)
+                        .map_or(false, |f| HEADER_TREE_NODES.contains(&f))
+                });
+            }
+        }
+        self.anonymize_value(details);
+    }
+
+    /// Rewrite the addresses in every string of a JSON value
+    pub fn anonymize_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.anonymize_text(text),
+            Value::Array(items) => items.iter_mut().for_each(|v| self.anonymize_value(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.anonymize_value(v)),
+            _ => {}
+        }
+    }
+
+    fn rewrite_mac(&self, packet: &mut [u8], offset: usize) {
+        if let Some(bytes) = packet.get_mut(offset..offset + 6) {
+            let mut mac = [0u8; 6];
+            mac.copy_from_slice(bytes);
+            bytes.copy_from_slice(&self.mac(mac));
+        }
+    }
+
+    fn rewrite_ipv4(&self, packet: &mut [u8], offset: usize) {
+        if let Some(bytes) = packet.get_mut(offset..offset + 4) {
+            let address = Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]);
+            bytes.copy_from_slice(&self.ipv4(address).octets());
+        }
+    }
+
+    fn rewrite_ipv6(&self, packet: &mut [u8], offset: usize) {
+        if let Some(bytes) = packet.get_mut(offset..offset + 16) {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(bytes);
+            bytes.copy_from_slice(&self.ipv6(Ipv6Addr::from(octets)).octets());
+        }
+    }
+
+    /// Rewrite an IP packet starting at `offset`; returns the length to keep
+    fn rewrite_ip(&self, packet: &mut [u8], offset: usize) -> usize {
+        let version = match packet.get(offset) {
+            Some(b) => b >> 4,
+            None => return packet.len(),
+        };
+        let (header_len, protocol, fragment) = match version {
+            4 if packet.len() >= offset + 20 => {
+                let header_len = usize::from(packet[offset] & 0x0F) * 4;
+                self.rewrite_ipv4(packet, offset + 12);
+                self.rewrite_ipv4(packet, offset + 16);
+                ipv4_checksum(packet, offset, header_len);
+                let fragment =
+                    u16::from_be_bytes([packet[offset + 6], packet[offset + 7]]) & 0x1FFF != 0;
+                (header_len, packet[offset + 9], fragment)
+            }
+            6 if packet.len() >= offset + 40 => {
+                self.rewrite_ipv6(packet, offset + 8);
+                self.rewrite_ipv6(packet, offset + 24);
+                (40, packet[offset + 6], false)
+            }
+            _ => return packet.len(),
+        };
+        if !self.policy.strip_payloads {
+            return packet.len();
+        }
+
+        // Keep the transport header, drop what it carries
+        let transport = offset + header_len;
+        let transport_len = match (fragment, protocol) {
+            (true, _) => 0,
+            (false, 6) => packet
+                .get(transport + 12)
+                .map_or(0, |b| usize::from(b >> 4) * 4),
+            (false, 17) | (false, 1) | (false, 58) => 8,
+            _ => 0,
+        };
+        (transport + transport_len).min(packet.len())
+    }
+
+    /// Rewrite one packet of a link type in place; returns the length to
+    /// keep, or None when the link type isn't supported
+    fn rewrite_packet(&self, packet: &mut [u8], linktype: u32) -> Option<usize> {
+        let (mut offset, mut ethertype) = match linktype {
+            LINKTYPE_ETHERNET if packet.len() >= 14 => {
+                self.rewrite_mac(packet, 0);
+                self.rewrite_mac(packet, 6);
+                (14, u16::from_be_bytes([packet[12], packet[13]]))
+            }
+            LINKTYPE_LINUX_SLL if packet.len() >= 16 => {
+                if u16::from_be_bytes([packet[4], packet[5]]) == 6 {
+                    self.rewrite_mac(packet, 6);
+                }
+                (16, u16::from_be_bytes([packet[14], packet[15]]))
+            }
+            LINKTYPE_LINUX_SLL2 if packet.len() >= 20 => {
+                if packet[11] == 6 {
+                    self.rewrite_mac(packet, 12);
+                }
+                (20, u16::from_be_bytes([packet[0], packet[1]]))
+            }
+            LINKTYPE_IPV4 | LINKTYPE_IPV6 => return Some(self.rewrite_ip(packet, 0)),
+            t if LINKTYPE_RAW.contains(&t) => return Some(self.rewrite_ip(packet, 0)),
+            LINKTYPE_ETHERNET | LINKTYPE_LINUX_SLL | LINKTYPE_LINUX_SLL2 => {
+                return Some(packet.len())
+            }
+            _ => return None,
+        };
+
+        // 802.1Q / 802.1ad tags
+        while matches!(ethertype, 0x8100 | 0x88A8) && packet.len() >= offset + 4 {
+            ethertype = u16::from_be_bytes([packet[offset + 2], packet[offset + 3]]);
+            offset += 4;
+        }
+        Some(match ethertype {
+            0x0800 | 0x86DD => self.rewrite_ip(packet, offset),
+            0x0806 => {
+                // Ethernet/IPv4 ARP: sender and target hardware and protocol addresses
+                if packet.len() >= offset + 28 && packet[offset + 4] == 6 && packet[offset + 5] == 4
+                {
+                    self.rewrite_mac(packet, offset + 8);
+                    self.rewrite_ipv4(packet, offset + 14);
+                    self.rewrite_mac(packet, offset + 18);
+                    self.rewrite_ipv4(packet, offset + 24);
+                }
+                packet.len()
+            }
+            _ if self.policy.strip_payloads => offset,
+            _ => packet.len(),
+        })
+    }
+}
+
+/// Colon-separated hex bytes, e.g. a MAC address
+fn parse_hex_bytes<const N: usize>(token: &str) -> Option<[u8; N]> {
+    let parts: Vec<&str> = token.split(':').collect();
+    if parts.len() != N || parts.iter().any(|p| p.len() != 2) {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (byte, part) in bytes.iter_mut().zip(parts) {
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn format_mac(mac: [u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Recompute the IPv4 header checksum after rewriting addresses
+fn ipv4_checksum(packet: &mut [u8], offset: usize, header_len: usize) {
+    if header_len < 20 || packet.len() < offset + header_len {
+        return;
+    }
+    packet[offset + 10] = 0;
+    packet[offset + 11] = 0;
+    let sum: u32 = packet[offset..offset + header_len]
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    let folded = (sum & 0xFFFF) + (sum >> 16);
+    let checksum = !(((folded & 0xFFFF) + (folded >> 16)) as u16);
+    packet[offset + 10..offset + 12].copy_from_slice(&checksum.to_be_bytes());
+}
+
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, String> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("Failed to read capture: {}", e)),
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize, big_endian: bool) -> u32 {
+    let raw = [
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ];
+    if big_endian {
+        u32::from_be_bytes(raw)
+    } else {
+        u32::from_le_bytes(raw)
+    }
+}
+
+fn u32_bytes(value: u32, big_endian: bool) -> [u8; 4] {
+    if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    }
+}
+
+impl Anonymizer {
+    /// Rewrite a packet, stripping packets of unsupported link types to
+    /// nothing. Returns the kept bytes and whether the link type was supported.
+    fn sanitize_packet<'a>(&self, packet: &'a mut [u8], linktype: u32) -> (&'a [u8], bool) {
+        match self.rewrite_packet(packet, linktype) {
+            Some(keep) => (&packet[..keep], true),
+            None => (&packet[..0], false),
+        }
+    }
+
+    fn write_pcap<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        magic: [u8; 4],
+        result: &mut AnonymizeResult,
+    ) -> Result<(), String> {
+        let big_endian = matches!(u32::from_be_bytes(magic), 0xA1B2_C3D4 | 0xA1B2_3C4D);
+        let mut header = [0u8; 24];
+        header[..4].copy_from_slice(&magic);
+        if !read_exact_or_eof(reader, &mut header[4..])? {
+            return Err("Truncated pcap header".to_string());
+        }
+        let snaplen = u32_at(&header, 16, big_endian) as usize;
+        let max_len = match snaplen {
+            0 => MAX_RECORD_LEN,
+            snaplen => snaplen.min(MAX_RECORD_LEN),
+        };
+        let linktype = u32_at(&header, 20, big_endian) & 0x0FFF_FFFF;
+        writer.write_all(&header).map_err(|e| e.to_string())?;
+
+        let mut record = [0u8; 16];
+        while read_exact_or_eof(reader, &mut record)? {
+            let incl_len = u32_at(&record, 8, big_endian) as usize;
+            if incl_len > max_len {
+                return Err(format!(
+                    "Invalid pcap record: {} bytes captured, more than the {} allowed",
+                    incl_len, max_len
+                ));
+            }
+            let mut packet = vec![0u8; incl_len];
+            if !read_exact_or_eof(reader, &mut packet)? {
+                return Err("Truncated pcap record".to_string());
+            }
+            let (kept, supported) = self.sanitize_packet(&mut packet, linktype);
+            record[8..12].copy_from_slice(&u32_bytes(kept.len() as u32, big_endian));
+            writer.write_all(&record).map_err(|e| e.to_string())?;
+            writer.write_all(kept).map_err(|e| e.to_string())?;
+            result.packets += 1;
+            if !supported {
+                result.unsupported_packets += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_pcapng<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        magic: [u8; 4],
+        result: &mut AnonymizeResult,
+    ) -> Result<(), String> {
+        let mut big_endian = false;
+        let mut linktypes: Vec<u32> = Vec::new();
+        let mut header = [0u8; 8];
+        header[..4].copy_from_slice(&magic);
+        let mut have_header = read_exact_or_eof(reader, &mut header[4..])?;
+
+        while have_header {
+            // A section header's byte-order magic decides how the section is read
+            if u32_at(&header, 0, false) == PCAPNG_SHB {
+                let mut byte_order = [0u8; 4];
+                if !read_exact_or_eof(reader, &mut byte_order)? {
+                    return Err("Truncated pcapng section header".to_string());
+                }
+                big_endian = u32_at(&byte_order, 0, false) != PCAPNG_BYTE_ORDER_MAGIC;
+                linktypes.clear();
+                let total_len = u32_at(&header, 4, big_endian) as usize;
+                if !(16..=MAX_RECORD_LEN).contains(&total_len) {
+                    return Err("Invalid pcapng section header".to_string());
+                }
+                let mut rest = vec![0u8; total_len - 12];
+                if !read_exact_or_eof(reader, &mut rest)? {
+                    return Err("Truncated pcapng section header".to_string());
+                }
+                writer.write_all(&header).map_err(|e| e.to_string())?;
+                writer.write_all(&byte_order).map_err(|e| e.to_string())?;
+                writer.write_all(&rest).map_err(|e| e.to_string())?;
+            } else {
+                let block_type = u32_at(&header, 0, big_endian);
+                let total_len = u32_at(&header, 4, big_endian) as usize;
+                if !(12..=MAX_RECORD_LEN).contains(&total_len) || total_len % 4 != 0 {
+                    return Err("Invalid pcapng block length".to_string());
+                }
+                let mut body = vec![0u8; total_len - 8];
+                if !read_exact_or_eof(reader, &mut body)? {
+                    return Err("Truncated pcapng block".to_string());
+                }
+                match block_type {
+                    PCAPNG_IDB => {
+                        linktypes.push(u32::from(if big_endian {
+                            u16::from_be_bytes([body[0], body[1]])
+                        } else {
+                            u16::from_le_bytes([body[0], body[1]])
+                        }));
+                        writer.write_all(&header).map_err(|e| e.to_string())?;
+                        writer.write_all(&body).map_err(|e| e.to_string())?;
+                    }
+                    PCAPNG_EPB if body.len() >= 24 => {
+                        let interface = u32_at(&body, 0, big_endian) as usize;
+                        let caplen = (u32_at(&body, 12, big_endian) as usize)
+                            .min(body.len().saturating_sub(24));
+                        let linktype = linktypes.get(interface).copied().unwrap_or(0);
+                        let mut packet = body[20..20 + caplen].to_vec();
+                        let (kept, supported) = self.sanitize_packet(&mut packet, linktype);
+
+                        // Rebuilt without options, which may carry comments
+                        let padded = kept.len().div_ceil(4) * 4;
+                        let new_len = (8 + 20 + padded + 4) as u32;
+                        let mut block = Vec::with_capacity(new_len as usize);
+                        block.extend_from_slice(&u32_bytes(PCAPNG_EPB, big_endian));
+                        block.extend_from_slice(&u32_bytes(new_len, big_endian));
+                        block.extend_from_slice(&body[..12]);
+                        block.extend_from_slice(&u32_bytes(kept.len() as u32, big_endian));
+                        block.extend_from_slice(&body[16..20]);
+                        block.extend_from_slice(kept);
+                        block.resize(block.len() + padded - kept.len(), 0);
+                        block.extend_from_slice(&u32_bytes(new_len, big_endian));
+                        writer.write_all(&block).map_err(|e| e.to_string())?;
+
+                        result.packets += 1;
+                        if !supported {
+                            result.unsupported_packets += 1;
+                        }
+                    }
+                    PCAPNG_SPB if body.len() >= 8 => {
+                        // Simple packets have no captured length to shrink, so
+                        // only their addresses are rewritten
+                        let linktype = linktypes.first().copied().unwrap_or(0);
+                        let data_end = body.len() - 4;
+                        let supported = self
+                            .rewrite_packet(&mut body[4..data_end], linktype)
+                            .is_some();
+                        if !supported {
+                            body[4..data_end].iter_mut().for_each(|b| *b = 0);
+                            result.unsupported_packets += 1;
+                        }
+                        writer.write_all(&header).map_err(|e| e.to_string())?;
+                        writer.write_all(&body).map_err(|e| e.to_string())?;
+                        result.packets += 1;
+                    }
+                    t if DROPPED_BLOCKS.contains(&t) => {}
+                    _ => {
+                        writer.write_all(&header).map_err(|e| e.to_string())?;
+                        writer.write_all(&body).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+            have_header = read_exact_or_eof(reader, &mut header)?;
+        }
+        Ok(())
+    }
+
+    /// Write a sanitized copy of a pcap or pcapng capture to `dest`
+    pub fn anonymize_capture(&self, source: &str, dest: &str) -> Result<AnonymizeResult, String> {
+        if source == dest {
+            return Err("The sanitized capture can't overwrite the original".to_string());
+        }
+        let file = File::open(source).map_err(|e| format!("Failed to open {}: {}", source, e))?;
+        let mut reader = BufReader::new(file);
+        let out = File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest, e))?;
+        let mut writer = BufWriter::new(out);
+
+        let mut result = AnonymizeResult {
+            path: dest.to_string(),
+            packets: 0,
+            unsupported_packets: 0,
+        };
+        let mut magic = [0u8; 4];
+        if !read_exact_or_eof(&mut reader, &mut magic)? {
+            return Err("File is too short to be a capture".to_string());
+        }
+        match u32::from_le_bytes(magic) {
+            0xA1B2_C3D4 | 0xA1B2_3C4D | 0xD4C3_B2A1 | 0x4D3C_B2A1 => {
+                self.write_pcap(&mut reader, &mut writer, magic, &mut result)?
+            }
+            PCAPNG_SHB => self.write_pcapng(&mut reader, &mut writer, magic, &mut result)?,
+            _ if magic[0] == 0x1F && magic[1] == 0x8B => {
+                return Err("Compressed captures must be decompressed first".to_string())
+            }
+            _ => return Err("Unrecognized capture file format".to_string()),
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+        Ok(result)
+    }
+}
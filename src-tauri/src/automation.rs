@@ -0,0 +1,266 @@
+//! Named sequences of backend actions, for repeating an analysis in one go
+//! (load a capture → apply a filter → export statistics → write a report).
+//!
+//! String settings of a step may reference run parameters as `{{name}}`, so
+//! one automation can be pointed at this week's capture or output folder.
+//! Progress is reported per step through `automation-progress` events.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{columns, evidence, frame_marks, har, report, sessions, storage};
+
+pub const STORE_NAME: &str = "automation";
+
+/// One action of an automation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    /// Open a capture in the session
+    LoadCapture { path: String },
+    /// Restrict the following steps to frames matching a display filter
+    /// (an empty filter clears it)
+    ApplyFilter { filter: String },
+    /// Switch to a saved column layout
+    ApplyLayout { name: String },
+    /// Write protocol hierarchy, conversations and endpoints as JSON
+    ExportStats { dest: String },
+    /// Write HTTP exchanges as a HAR file
+    ExportHar { dest: String },
+    /// Write an HTML report
+    GenerateReport {
+        dest: String,
+        #[serde(default)]
+        options: Option<report::ReportOptions>,
+    },
+}
+
+impl Step {
+    fn action(&self) -> &'static str {
+        match self {
+            Step::LoadCapture { .. } => "load_capture",
+            Step::ApplyFilter { .. } => "apply_filter",
+            Step::ApplyLayout { .. } => "apply_layout",
+            Step::ExportStats { .. } => "export_stats",
+            Step::ExportHar { .. } => "export_har",
+            Step::GenerateReport { .. } => "generate_report",
+        }
+    }
+
+    /// The step with `{{name}}` references replaced by run parameters
+    fn with_params(&self, params: &HashMap<String, String>) -> Result<Step, String> {
+        let mut value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        substitute(&mut value, params)?;
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+}
+
+fn substitute(value: &mut Value, params: &HashMap<String, String>) -> Result<(), String> {
+    match value {
+        Value::String(text) => {
+            for (name, param) in params {
+                *text = text.replace(&format!("{{{{{}}}}}", name), param);
+            }
+            if let Some(start) = text.find("{{") {
+                let rest = &text[start + 2..];
+                let name = rest.split("}}").next().unwrap_or(rest);
+                return Err(format!("Missing parameter: {}", name));
+            }
+            Ok(())
+        }
+        Value::Array(items) => items.iter_mut().try_for_each(|v| substitute(v, params)),
+        Value::Object(map) => map.values_mut().try_for_each(|v| substitute(v, params)),
+        _ => Ok(()),
+    }
+}
+
+/// A named sequence of steps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Automation {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<Step>,
+}
+
+/// Progress of a running automation
+#[derive(Debug, Clone, Serialize)]
+pub struct AutomationProgress {
+    pub name: String,
+    /// Index of the step, from 0
+    pub step: usize,
+    pub total: usize,
+    pub action: String,
+    /// `running`, `done`, or `failed`
+    pub state: String,
+    pub error: Option<String>,
+}
+
+/// Result of a completed automation
+#[derive(Debug, Clone, Serialize)]
+pub struct AutomationResult {
+    pub name: String,
+    pub steps_run: usize,
+    /// Files written by the steps
+    pub outputs: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AutomationStore {
+    automations: Vec<Automation>,
+}
+
+static AUTOMATIONS: OnceLock<Mutex<AutomationStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<AutomationStore> {
+    AUTOMATIONS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Saved automations
+pub fn list_automations() -> Vec<Automation> {
+    get_store().lock().automations.clone()
+}
+
+/// Add or replace (by name) an automation
+pub fn save_automation(automation: Automation) -> Result<(), String> {
+    if automation.name.trim().is_empty() {
+        return Err("Automation needs a name".to_string());
+    }
+    if automation.steps.is_empty() {
+        return Err(format!("Automation '{}' has no steps", automation.name));
+    }
+    let mut store = get_store().lock();
+    store.automations.retain(|a| a.name != automation.name);
+    store.automations.push(automation);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove an automation
+pub fn remove_automation(name: &str) -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.automations.retain(|a| a.name != name);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Run one step. `filter` carries the filter set by `ApplyFilter` to later
+/// steps; files written are added to `outputs`.
+async fn run_step(
+    step: &Step,
+    session_id: Option<&str>,
+    filter: &mut Option<String>,
+    outputs: &mut Vec<String>,
+) -> Result<(), String> {
+    let session = sessions::resolve(session_id);
+    match step {
+        Step::LoadCapture { path } => {
+            let result = crate::load_pcap(path.clone(), session_id.map(String::from)).await?;
+            if let Some(e) = result.error {
+                return Err(e);
+            }
+        }
+        Step::ApplyFilter { filter: next } => {
+            let next = next.trim();
+            if next.is_empty() {
+                *filter = None;
+            } else {
+                if !crate::sharkd(session_id)?.check_filter(next).await? {
+                    return Err(format!("Invalid filter expression: {}", next));
+                }
+                *filter = Some(next.to_string());
+            }
+        }
+        Step::ApplyLayout { name } => {
+            columns::apply_layout(name)?;
+        }
+        Step::ExportStats { dest } => {
+            let stats = crate::sharkd(session_id)?
+                .capture_stats(frame_marks::with_exclusions(&session, filter.as_deref()).as_deref())
+                .await?;
+            let json = serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?;
+            std::fs::write(dest, json).map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+            evidence::record_export(dest, "stats");
+            outputs.push(dest.clone());
+        }
+        Step::ExportHar { dest } => {
+            let source = crate::sharkd(session_id)?
+                .loaded_file()
+                .ok_or_else(|| "No capture file loaded".to_string())?;
+            let filter = frame_marks::with_exclusions(&session, filter.as_deref());
+            har::export_har(&source, dest, filter.as_deref())?;
+            evidence::record_export(dest, "har");
+            outputs.push(dest.clone());
+        }
+        Step::GenerateReport { dest, options } => {
+            let client = crate::sharkd(session_id)?;
+            let status = client.status().await?;
+            let stats = client
+                .capture_stats(frame_marks::with_exclusions(&session, filter.as_deref()).as_deref())
+                .await?;
+            report::generate_report(&status, &stats, dest, &options.clone().unwrap_or_default())?;
+            evidence::record_export(dest, "report");
+            outputs.push(dest.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Run a saved automation step by step, stopping at the first failure
+pub async fn run_automation(
+    name: &str,
+    params: &HashMap<String, String>,
+    session_id: Option<&str>,
+) -> Result<AutomationResult, String> {
+    let automation = list_automations()
+        .into_iter()
+        .find(|a| a.name == name)
+        .ok_or_else(|| format!("Unknown automation: {}", name))?;
+
+    let total = automation.steps.len();
+    let emit = |step: usize, action: &str, state: &str, error: Option<String>| {
+        crate::emit_event(
+            "automation-progress",
+            AutomationProgress {
+                name: name.to_string(),
+                step,
+                total,
+                action: action.to_string(),
+                state: state.to_string(),
+                error,
+            },
+        );
+    };
+
+    let mut filter = None;
+    let mut outputs = Vec::new();
+    for (index, step) in automation.steps.iter().enumerate() {
+        emit(index, step.action(), "running", None);
+        let result = match step.with_params(params) {
+            Ok(step) => run_step(&step, session_id, &mut filter, &mut outputs).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            emit(index, step.action(), "failed", Some(e.clone()));
+            return Err(format!(
+                "Step {} ({}) failed: {}",
+                index + 1,
+                step.action(),
+                e
+            ));
+        }
+        emit(index, step.action(), "done", None);
+    }
+
+    Ok(AutomationResult {
+        name: name.to_string(),
+        steps_run: total,
+        outputs,
+    })
+}
@@ -0,0 +1,115 @@
+//! Timings of the sharkd operations behind the packet list and statistics
+//! views, for checking performance work against the open capture in a
+//! development build. The criterion suite in `benches/` measures the same
+//! operations on a generated capture.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Runs of each operation
+const ITERATIONS: u32 = 5;
+
+/// Frames per page, as the packet list requests them
+const PAGE_SIZE: u32 = 500;
+
+/// Frames scanned for field extraction
+const FIELD_SCAN_LIMIT: u32 = 10_000;
+
+/// Timing of one operation
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub iterations: u32,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    /// A page of the packet list, at offsets spread over the capture
+    FramesPage,
+    /// A page of frames matching a display filter
+    Search,
+    /// Protocol hierarchy, conversations and endpoints
+    CaptureStats,
+    /// Field values through custom columns
+    FieldExtraction,
+}
+
+impl Operation {
+    const ALL: [Operation; 4] = [
+        Operation::FramesPage,
+        Operation::Search,
+        Operation::CaptureStats,
+        Operation::FieldExtraction,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Operation::FramesPage => "frames_page",
+            Operation::Search => "search",
+            Operation::CaptureStats => "capture_stats",
+            Operation::FieldExtraction => "field_extraction",
+        }
+    }
+
+    async fn run(self, client: &SharkdClient, total: u64, iteration: u32) -> Result<(), String> {
+        match self {
+            Operation::FramesPage => {
+                let last_page = total.saturating_sub(u64::from(PAGE_SIZE));
+                let skip = last_page * u64::from(iteration) / u64::from(ITERATIONS);
+                client.frames(skip as u32, PAGE_SIZE).await?;
+            }
+            Operation::Search => {
+                client.search_frames("tcp || udp", 0, PAGE_SIZE).await?;
+            }
+            Operation::CaptureStats => {
+                client.capture_stats(None).await?;
+            }
+            Operation::FieldExtraction => {
+                client
+                    .column_values("ip", &["ip.src", "ip.dst"], FIELD_SCAN_LIMIT)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+fn summarize(name: &str, samples: &[Duration]) -> BenchmarkResult {
+    let total: Duration = samples.iter().sum();
+    BenchmarkResult {
+        name: name.to_string(),
+        iterations: samples.len() as u32,
+        mean_ms: millis(total) / samples.len().max(1) as f64,
+        min_ms: samples.iter().min().copied().map_or(0.0, millis),
+        max_ms: samples.iter().max().copied().map_or(0.0, millis),
+    }
+}
+
+/// Time each operation against the capture loaded in `client`
+pub async fn run(client: &SharkdClient) -> Result<Vec<BenchmarkResult>, String> {
+    if client.loaded_file().is_none() {
+        return Err("No capture loaded".to_string());
+    }
+    let total = client.status().await?.frames.unwrap_or(0);
+
+    let mut results = Vec::new();
+    for operation in Operation::ALL {
+        let mut samples = Vec::new();
+        for iteration in 0..ITERATIONS {
+            let started = Instant::now();
+            operation.run(client, total, iteration).await?;
+            samples.push(started.elapsed());
+        }
+        results.push(summarize(operation.name(), &samples));
+    }
+    Ok(results)
+}
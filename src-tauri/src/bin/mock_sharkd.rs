@@ -0,0 +1,5 @@
+//! Stand-in for `sharkd -` used by integration tests (see `testsupport`)
+
+fn main() {
+    packet_pilot_lib::testsupport::mock_sharkd::run()
+}
@@ -0,0 +1,300 @@
+//! PacketPilot analysis bundles.
+//!
+//! A bundle is a single JSON file carrying the analysis state (marked and
+//! ignored frames, selection), the profile files that affect decoding
+//! (decode-as, saved filters, coloring rules), a rendered report, and
+//! optionally the capture itself, so another analyst can pick up where the
+//! work left off.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cache_manager::{self, CacheCategory};
+use crate::selection::FrameRange;
+use crate::sharkd_client::SharkdClient;
+use crate::{
+    capture_edit, capture_file, comments, frame_marks, profile, report, selection, storage,
+};
+
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Profile files carried in a bundle
+const BUNDLED_PROFILE_FILES: &[&str] = &["decode_as_entries", "dfilters", "colorfilters"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnalysisBundle {
+    format_version: u32,
+    created: u64,
+    capture_name: String,
+    /// Hash of the bundled capture, or of the source when it isn't bundled
+    capture_sha256: Option<String>,
+    /// Frame numbers below refer to the bundled capture when there is one,
+    /// which has no ignored frames left in it
+    #[serde(default)]
+    marked_frames: Vec<u32>,
+    ignored_frames: Vec<u32>,
+    selection: Option<FrameRange>,
+    report_html: Option<String>,
+    /// Profile file name -> contents
+    profile_files: BTreeMap<String, String>,
+    /// Base64-encoded capture, when included
+    capture: Option<String>,
+}
+
+/// Summary of an exported bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleExportResult {
+    pub path: String,
+    pub includes_capture: bool,
+    pub profile_files: Vec<String>,
+}
+
+/// Summary of an imported bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleImportResult {
+    /// Where the bundled capture was extracted, if the bundle had one
+    pub capture_path: Option<String>,
+    pub capture_name: String,
+    pub marked_frames: Vec<u32>,
+    pub ignored_frames: Vec<u32>,
+    pub selection: Option<FrameRange>,
+    /// Where the bundled report was written, if the bundle had one
+    pub report_path: Option<String>,
+    pub profile_files: Vec<String>,
+}
+
+/// Number of a frame once the (sorted) `ignored` frames are dropped from the
+/// capture, None when it is one of them
+fn renumber(frame: u32, ignored: &[u32]) -> Option<u32> {
+    match ignored.binary_search(&frame) {
+        Ok(_) => None,
+        Err(before) => Some(frame - before as u32),
+    }
+}
+
+/// A range's frames once `ignored` are dropped, None when none remain
+fn renumber_range(range: FrameRange, ignored: &[u32]) -> Option<FrameRange> {
+    // The first kept frame at or after `start` and the last at or before `end`
+    let start = range.start - ignored.partition_point(|&f| f < range.start) as u32;
+    let end = range.end - ignored.partition_point(|&f| f <= range.end) as u32;
+    (start <= end).then_some(FrameRange { start, end })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `name` in `dir`, with a numeric suffix if a file of that name exists so
+/// nothing (such as the original capture) is overwritten
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.split_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(path)
+}
+
+/// Package the analysis of the capture open in `session` into a bundle at
+/// `dest`
+pub async fn export_analysis_bundle(
+    client: &SharkdClient,
+    session: &str,
+    dest: &str,
+    include_capture: bool,
+) -> Result<BundleExportResult, String> {
+    let source = client
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+    let capture_name = Path::new(&source)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "capture.pcapng".to_string());
+
+    let exclusions = frame_marks::with_exclusions(session, None);
+    let report_html = match (
+        client.status().await,
+        client.capture_stats(exclusions.as_deref()).await,
+    ) {
+        (Ok(status), Ok(stats)) => Some(report::render_report(
+            &status,
+            &stats,
+            &report::ReportOptions::default(),
+        )),
+        _ => None,
+    };
+
+    let profile_dir = profile::profile_dir()?;
+    let profile_files: BTreeMap<String, String> = BUNDLED_PROFILE_FILES
+        .iter()
+        .filter_map(|name| {
+            std::fs::read_to_string(profile_dir.join(name))
+                .ok()
+                .map(|text| (name.to_string(), text))
+        })
+        .collect();
+
+    let mut marked_frames = frame_marks::marked_frames(session);
+    let mut ignored_frames = frame_marks::ignored_frames(session);
+    let mut selection = selection::get_selection(session);
+
+    // Ship the capture trimmed of ignored frames
+    let capture = if include_capture {
+        // Added comments are written into the copy, so it's rewritten when
+        // there are any even if no frames are ignored
+        let filter = match &exclusions {
+            Some(filter) => Some(filter.as_str()),
+            None if !comments::for_capture(&source).is_empty() => Some("frame"),
+            None => None,
+        };
+        let bytes = match filter {
+            Some(filter) => {
+                let trimmed = cache_manager::new_entry(
+                    CacheCategory::Scratch,
+                    &format!("bundle-{}.pcapng", storage::now_secs()),
+                    None,
+                )?;
+                let trimmed_path = trimmed.display().to_string();
+                capture_edit::write_filtered(&source, filter, &trimmed_path)?;
+                let bytes = std::fs::read(&trimmed);
+                let _ = std::fs::remove_file(&trimmed);
+                bytes
+            }
+            None => std::fs::read(&source),
+        }
+        .map_err(|e| format!("Failed to read capture: {}", e))?;
+
+        // The trimmed copy numbers its frames without the ignored ones, so
+        // the state saved with it is renumbered to match
+        marked_frames = marked_frames
+            .iter()
+            .filter_map(|&frame| renumber(frame, &ignored_frames))
+            .collect();
+        selection = selection.and_then(|range| renumber_range(range, &ignored_frames));
+        ignored_frames.clear();
+
+        Some((sha256_hex(&bytes), BASE64.encode(bytes)))
+    } else {
+        None
+    };
+    let (capture_sha256, capture) = match capture {
+        Some((hash, encoded)) => (Some(hash), Some(encoded)),
+        None => (capture_file::sha256_file(&source).ok(), None),
+    };
+
+    let bundle = AnalysisBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created: storage::now_secs(),
+        capture_name,
+        capture_sha256,
+        marked_frames,
+        ignored_frames,
+        selection,
+        report_html,
+        profile_files,
+        capture,
+    };
+
+    let text =
+        serde_json::to_string(&bundle).map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    std::fs::write(dest, text).map_err(|e| format!("Failed to write bundle to {}: {}", dest, e))?;
+
+    Ok(BundleExportResult {
+        path: dest.to_string(),
+        includes_capture: bundle.capture.is_some(),
+        profile_files: bundle.profile_files.keys().cloned().collect(),
+    })
+}
+
+/// Unpack a bundle: the capture and report are written to `dest_dir`
+/// (default: next to the bundle), under a new name if a file of the same
+/// name is already there, and profile files into the managed profile.
+/// The caller is responsible for restarting sharkd, loading the capture, and
+/// restoring the ignored frames and selection.
+pub fn import_analysis_bundle(
+    path: &str,
+    dest_dir: Option<&str>,
+) -> Result<BundleImportResult, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle: AnalysisBundle =
+        serde_json::from_str(&text).map_err(|e| format!("Not a PacketPilot bundle: {}", e))?;
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format {} is newer than this version of PacketPilot supports",
+            bundle.format_version
+        ));
+    }
+
+    let dest_dir = match dest_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir),
+    };
+    std::fs::create_dir_all(&dest_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", dest_dir, e))?;
+
+    // Only use the file name part so a crafted bundle can't write elsewhere
+    let capture_name = Path::new(&bundle.capture_name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "capture.pcapng".to_string());
+
+    let capture_path = match &bundle.capture {
+        Some(encoded) => {
+            let bytes = BASE64
+                .decode(encoded)
+                .map_err(|e| format!("Bundle capture is corrupt: {}", e))?;
+            let capture_path = unused_path(&dest_dir, &capture_name);
+            std::fs::write(&capture_path, bytes)
+                .map_err(|e| format!("Failed to write {:?}: {}", capture_path, e))?;
+            Some(capture_path.display().to_string())
+        }
+        None => None,
+    };
+
+    let report_path = match &bundle.report_html {
+        Some(html) => {
+            let report_path = unused_path(&dest_dir, &format!("{}.report.html", capture_name));
+            std::fs::write(&report_path, html)
+                .map_err(|e| format!("Failed to write {:?}: {}", report_path, e))?;
+            Some(report_path.display().to_string())
+        }
+        None => None,
+    };
+
+    let profile_dir = profile::profile_dir()?;
+    let mut profile_files = Vec::new();
+    for (name, contents) in &bundle.profile_files {
+        if !BUNDLED_PROFILE_FILES.contains(&name.as_str()) {
+            continue;
+        }
+        std::fs::write(profile_dir.join(name), contents)
+            .map_err(|e| format!("Failed to restore profile file {}: {}", name, e))?;
+        profile_files.push(name.clone());
+    }
+
+    Ok(BundleImportResult {
+        capture_path,
+        capture_name,
+        marked_frames: bundle.marked_frames,
+        ignored_frames: bundle.ignored_frames,
+        selection: bundle.selection,
+        report_path,
+        profile_files,
+    })
+}
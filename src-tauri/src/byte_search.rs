@@ -0,0 +1,201 @@
+//! Search of packet bytes for a hex pattern or a string, returning where in
+//! each frame it occurs.
+//!
+//! A `frame contains` (or, ignoring case, `frame matches`) filter narrows the
+//! capture to candidate frames in one sharkd request; each candidate's bytes
+//! are then fetched and scanned for the offsets. Results are cached per
+//! capture and pattern, since paging through them re-runs the same search.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Frames fetched from sharkd per request while listing candidates
+const PAGE_SIZE: u32 = 5000;
+
+/// Most matching frames scanned for offsets by one search
+const MAX_MATCH_FRAMES: usize = 1000;
+
+/// Searches kept in the cache
+const MAX_CACHED_SEARCHES: usize = 16;
+
+/// How a byte search pattern is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternKind {
+    /// Hex bytes, e.g. `de ad be ef`, `de:ad:be:ef` or `deadbeef`
+    Hex,
+    /// UTF-8 text
+    String,
+}
+
+/// Where a pattern occurs in one frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ByteMatch {
+    pub frame: u32,
+    /// Byte offsets of each occurrence
+    pub offsets: Vec<u32>,
+}
+
+/// Result of a byte search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ByteSearchResult {
+    /// The pattern's bytes, as searched for
+    pub pattern: Vec<u8>,
+    pub matches: Vec<ByteMatch>,
+    /// More frames matched than were scanned
+    pub truncated: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    capture: String,
+    pattern: Vec<u8>,
+    ignore_case: bool,
+    exclusions: Option<String>,
+}
+
+static CACHE: OnceLock<Mutex<HashMap<CacheKey, Arc<ByteSearchResult>>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<HashMap<CacheKey, Arc<ByteSearchResult>>> {
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Forget cached searches, e.g. after frames were ignored or the capture
+/// changed on disk
+pub fn clear_cache() {
+    get_cache().lock().clear();
+}
+
+/// The bytes a pattern stands for
+pub fn parse_pattern(pattern: &str, kind: PatternKind) -> Result<Vec<u8>, String> {
+    let bytes = match kind {
+        PatternKind::String => pattern.as_bytes().to_vec(),
+        PatternKind::Hex => {
+            let digits: String = pattern
+                .chars()
+                .filter(|c| !c.is_whitespace() && !matches!(c, ':' | '-' | '.'))
+                .collect();
+            let digits = digits
+                .strip_prefix("0x")
+                .or_else(|| digits.strip_prefix("0X"))
+                .unwrap_or(&digits);
+            if digits.len() % 2 != 0 {
+                return Err("Hex pattern needs an even number of digits".to_string());
+            }
+            (0..digits.len())
+                .step_by(2)
+                .map(|i| {
+                    u8::from_str_radix(&digits[i..i + 2], 16)
+                        .map_err(|_| format!("Invalid hex pattern: {}", pattern))
+                })
+                .collect::<Result<Vec<u8>, String>>()?
+        }
+    };
+    if bytes.is_empty() {
+        return Err("Search pattern is empty".to_string());
+    }
+    Ok(bytes)
+}
+
+/// Display filter selecting frames that contain the pattern
+fn candidate_filter(pattern: &[u8], ignore_case: bool) -> String {
+    if ignore_case {
+        // PCRE over the frame bytes, each byte escaped
+        let escaped: String = pattern.iter().map(|b| format!("\\\\x{:02x}", b)).collect();
+        format!("frame matches \"(?i){}\"", escaped)
+    } else {
+        let hex: Vec<String> = pattern.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("frame contains {}", hex.join(":"))
+    }
+}
+
+/// Offsets of every occurrence of `pattern` in `bytes`, overlapping ones
+/// included
+fn find_offsets(bytes: &[u8], pattern: &[u8], ignore_case: bool) -> Vec<u32> {
+    if pattern.len() > bytes.len() {
+        return Vec::new();
+    }
+    bytes
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            if ignore_case {
+                window.eq_ignore_ascii_case(pattern)
+            } else {
+                *window == pattern
+            }
+        })
+        .map(|(offset, _)| offset as u32)
+        .collect()
+}
+
+/// Frames of the loaded capture containing `pattern`, with offsets.
+/// `exclusions` is a filter frames must also match (the ignored-frame
+/// exclusion).
+pub async fn search_bytes(
+    client: &SharkdClient,
+    pattern: Vec<u8>,
+    ignore_case: bool,
+    exclusions: Option<&str>,
+) -> Result<Arc<ByteSearchResult>, String> {
+    let key = CacheKey {
+        capture: client
+            .loaded_file()
+            .ok_or_else(|| "No capture file loaded".to_string())?,
+        pattern,
+        ignore_case,
+        exclusions: exclusions.map(String::from),
+    };
+    if let Some(result) = get_cache().lock().get(&key) {
+        return Ok(result.clone());
+    }
+
+    let mut filter = candidate_filter(&key.pattern, ignore_case);
+    if let Some(exclusions) = exclusions {
+        filter = format!("({}) && {}", exclusions, filter);
+    }
+
+    // Candidate frames, one more than are scanned to tell if there are more
+    let operation = client.operation();
+    let mut candidates = Vec::new();
+    let mut skip = 0;
+    while candidates.len() <= MAX_MATCH_FRAMES {
+        operation.check()?;
+        let (page, _) = client.search_frames(&filter, skip, PAGE_SIZE).await?;
+        candidates.extend(page.iter().map(|f| f.number));
+        let fetched = page.len() as u32;
+        skip += fetched;
+        if fetched < PAGE_SIZE {
+            break;
+        }
+    }
+    let truncated = candidates.len() > MAX_MATCH_FRAMES;
+    candidates.truncate(MAX_MATCH_FRAMES);
+
+    let mut matches = Vec::new();
+    for frame in candidates {
+        operation.check()?;
+        let bytes = client.frame_bytes(frame).await?;
+        let offsets = find_offsets(&bytes, &key.pattern, ignore_case);
+        // The filter also matches reassembled data outside this frame's bytes
+        if !offsets.is_empty() {
+            matches.push(ByteMatch { frame, offsets });
+        }
+    }
+
+    let result = Arc::new(ByteSearchResult {
+        pattern: key.pattern.clone(),
+        matches,
+        truncated,
+    });
+    let mut cache = get_cache().lock();
+    if cache.len() >= MAX_CACHED_SEARCHES {
+        cache.clear();
+    }
+    cache.insert(key, result.clone());
+    Ok(result)
+}
@@ -0,0 +1,237 @@
+//! Lifecycle of the temporary files PacketPilot creates: work directories
+//! for external tools, intermediate captures, and the like.
+//!
+//! Each category lives in its own directory under the system temp dir and
+//! has a size quota; when a category grows past it, its least recently used
+//! entries are evicted. Entries can be tied to a capture and are removed when
+//! that capture is closed, and everything is removed on exit.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use crate::storage;
+
+pub const STORE_NAME: &str = "cache";
+
+/// Directory under the system temp dir holding all categories
+const CACHE_DIR_NAME: &str = "packetpilot-cache";
+
+/// Kinds of temporary data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheCategory {
+    /// Captures exported for external tools, and the logs the tools write
+    ExternalTools,
+    /// Intermediate files of a single operation (trimmed captures, ...)
+    Scratch,
+}
+
+impl CacheCategory {
+    pub const ALL: &'static [CacheCategory] =
+        &[CacheCategory::ExternalTools, CacheCategory::Scratch];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            CacheCategory::ExternalTools => "external-tools",
+            CacheCategory::Scratch => "scratch",
+        }
+    }
+
+    fn default_quota(self) -> u64 {
+        match self {
+            CacheCategory::ExternalTools => 2 << 30,
+            CacheCategory::Scratch => 1 << 30,
+        }
+    }
+}
+
+/// Disk usage of a category
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    pub category: CacheCategory,
+    pub bytes: u64,
+    pub entries: usize,
+    pub quota: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheSettings {
+    /// Quota overrides in bytes
+    #[serde(default)]
+    quotas: BTreeMap<CacheCategory, u64>,
+}
+
+/// Entries tied to the capture they were made from
+static OWNERS: OnceLock<Mutex<BTreeMap<PathBuf, String>>> = OnceLock::new();
+
+static SETTINGS: OnceLock<Mutex<CacheSettings>> = OnceLock::new();
+
+fn get_owners() -> &'static Mutex<BTreeMap<PathBuf, String>> {
+    OWNERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn get_settings() -> &'static Mutex<CacheSettings> {
+    SETTINGS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_settings().lock() = storage::load_json(STORE_NAME);
+}
+
+fn quota(category: CacheCategory) -> u64 {
+    get_settings()
+        .lock()
+        .quotas
+        .get(&category)
+        .copied()
+        .unwrap_or_else(|| category.default_quota())
+}
+
+/// Change a category's size quota and evict down to it
+pub fn set_quota(category: CacheCategory, bytes: u64) -> Result<(), String> {
+    {
+        let mut settings = get_settings().lock();
+        settings.quotas.insert(category, bytes);
+        storage::save_json(STORE_NAME, &*settings)?;
+    }
+    enforce_quota(category);
+    Ok(())
+}
+
+fn category_dir(category: CacheCategory) -> PathBuf {
+    std::env::temp_dir()
+        .join(CACHE_DIR_NAME)
+        .join(category.dir_name())
+}
+
+/// Reserve a new entry (file or directory) named `name` in a category,
+/// optionally tied to the capture it was made from. Space is made for it by
+/// evicting old entries first. Returns the entry's path, which doesn't exist yet.
+pub fn new_entry(
+    category: CacheCategory,
+    name: &str,
+    capture: Option<&str>,
+) -> Result<PathBuf, String> {
+    let dir = category_dir(category);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    enforce_quota(category);
+
+    let path = dir.join(name);
+    if let Some(capture) = capture {
+        get_owners()
+            .lock()
+            .insert(path.clone(), capture.to_string());
+    }
+    Ok(path)
+}
+
+fn entry_size(path: &Path) -> u64 {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|e| entry_size(&e.path()))
+                    .sum()
+            })
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// A category's entries with size and last use, oldest first
+fn entries(category: CacheCategory) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut entries: Vec<(PathBuf, u64, SystemTime)> = std::fs::read_dir(category_dir(category))
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .map(|e| {
+                    let path = e.path();
+                    let used = e
+                        .metadata()
+                        .and_then(|m| m.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+                    let size = entry_size(&path);
+                    (path, size, used)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by_key(|(_, _, used)| *used);
+    entries
+}
+
+fn remove_entry(path: &Path) {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    if let Err(e) = result {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            eprintln!("Warning: Failed to remove cached {:?}: {}", path, e);
+        }
+    }
+    get_owners().lock().remove(path);
+}
+
+/// Evict least recently used entries until the category fits its quota
+fn enforce_quota(category: CacheCategory) {
+    let quota = quota(category);
+    let entries = entries(category);
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    for (path, size, _) in entries {
+        if total <= quota {
+            break;
+        }
+        remove_entry(&path);
+        total = total.saturating_sub(size);
+    }
+}
+
+/// Disk usage of every category
+pub fn usage() -> Vec<CacheUsage> {
+    CacheCategory::ALL
+        .iter()
+        .map(|&category| {
+            let entries = entries(category);
+            CacheUsage {
+                category,
+                bytes: entries.iter().map(|(_, size, _)| size).sum(),
+                entries: entries.len(),
+                quota: quota(category),
+            }
+        })
+        .collect()
+}
+
+/// Remove every entry of a category, or of all categories
+pub fn clear(category: Option<CacheCategory>) {
+    let categories = match category {
+        Some(category) => vec![category],
+        None => CacheCategory::ALL.to_vec(),
+    };
+    for category in categories {
+        for (path, _, _) in entries(category) {
+            remove_entry(&path);
+        }
+    }
+}
+
+/// Remove the entries made from a capture that was closed
+pub fn release_capture(capture: &str) {
+    let paths: Vec<PathBuf> = get_owners()
+        .lock()
+        .iter()
+        .filter(|(_, owner)| owner.as_str() == capture)
+        .map(|(path, _)| path.clone())
+        .collect();
+    for path in paths {
+        remove_entry(&path);
+    }
+}
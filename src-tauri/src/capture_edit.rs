@@ -0,0 +1,332 @@
+//! Writing derived capture files (excerpts, filtered subsets, merges, splits,
+//! time-shifted and deduplicated copies) with the Wireshark command-line
+//! tools. Comments added in the app are written into every derived capture.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::cache_manager::{self, CacheCategory};
+use crate::sharkd_client::SharkdClient;
+use crate::{comments, storage, wireshark_tools};
+
+/// Description of a written excerpt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcerptResult {
+    pub path: String,
+    /// Display filter used to select the excerpt's frames
+    pub filter: String,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// Tells apart scratch copies made in the same second
+static SCRATCH_COPIES: AtomicU64 = AtomicU64::new(0);
+
+/// A scratch copy of `source` with its added comments written in, or None
+/// when it has none
+fn commented_copy(source: &str) -> Result<Option<PathBuf>, String> {
+    let copy = cache_manager::new_entry(
+        CacheCategory::Scratch,
+        &format!(
+            "commented-{}-{}.pcapng",
+            storage::now_secs(),
+            SCRATCH_COPIES.fetch_add(1, Ordering::Relaxed)
+        ),
+        None,
+    )?;
+    if comments::write_with_comments(source, &copy.display().to_string())? {
+        Ok(Some(copy))
+    } else {
+        let _ = std::fs::remove_file(&copy);
+        Ok(None)
+    }
+}
+
+/// Run `write` on `sources` with their added comments written in. Frame
+/// numbers of added comments refer to the whole original capture, so they
+/// are written in before anything filters, drops or reorders frames.
+fn with_comments<T>(
+    sources: &[String],
+    write: impl FnOnce(&[String]) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut copies = Vec::new();
+    let mut inputs = Vec::with_capacity(sources.len());
+    for source in sources {
+        match commented_copy(source) {
+            Ok(Some(copy)) => {
+                inputs.push(copy.display().to_string());
+                copies.push(copy);
+            }
+            Ok(None) => inputs.push(source.clone()),
+            Err(e) => {
+                for copy in &copies {
+                    let _ = std::fs::remove_file(copy);
+                }
+                return Err(e);
+            }
+        }
+    }
+    let result = write(&inputs);
+    for copy in &copies {
+        let _ = std::fs::remove_file(copy);
+    }
+    result
+}
+
+/// Write the frames of `source` matching a display filter to `dest` (pcapng),
+/// keeping comments added to them in the app
+pub fn write_filtered(source: &str, filter: &str, dest: &str) -> Result<(), String> {
+    with_comments(&[source.to_string()], |inputs| {
+        wireshark_tools::run_tool(
+            "tshark",
+            &[
+                "-r".to_string(),
+                inputs[0].clone(),
+                "-Y".to_string(),
+                filter.to_string(),
+                "-F".to_string(),
+                "pcapng".to_string(),
+                "-w".to_string(),
+                dest.to_string(),
+            ],
+        )
+        .map(|_| ())
+    })
+}
+
+/// Merge captures into `dest` (pcapng) with mergecap, interleaving their
+/// packets in timestamp order
+pub fn merge_captures(paths: &[String], dest: &str) -> Result<(), String> {
+    if paths.len() < 2 {
+        return Err("Select at least two captures to merge".to_string());
+    }
+    if paths.iter().any(|p| p == dest) {
+        return Err("The merged capture can't overwrite one of its inputs".to_string());
+    }
+    with_comments(paths, |inputs| {
+        let mut args = vec![
+            "-F".to_string(),
+            "pcapng".to_string(),
+            "-w".to_string(),
+            dest.to_string(),
+        ];
+        args.extend(inputs.iter().cloned());
+        wireshark_tools::run_tool("mergecap", &args).map(|_| ())
+    })
+}
+
+/// Largest duplicate window editcap accepts, in packets
+const MAX_DEDUP_WINDOW: u32 = 1_000_000;
+
+/// `<name>-<suffix>.pcapng` next to `source`
+fn sibling_path(source: &str, suffix: &str) -> String {
+    let path = Path::new(source);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "capture".to_string());
+    path.with_file_name(format!("{}-{}.pcapng", stem, suffix))
+        .display()
+        .to_string()
+}
+
+/// Write a copy of `source` to `<name>-dedup.pcapng` without frames that
+/// repeat one of the previous `window` frames (5 when omitted), as SPAN ports
+/// capturing both directions produce. Returns the written path.
+pub fn deduplicate(source: &str, window: Option<u32>) -> Result<String, String> {
+    let window = window.unwrap_or(5);
+    if window == 0 || window > MAX_DEDUP_WINDOW {
+        return Err(format!(
+            "Duplicate window must be between 1 and {} packets",
+            MAX_DEDUP_WINDOW
+        ));
+    }
+    let dest = sibling_path(source, "dedup");
+    with_comments(&[source.to_string()], |inputs| {
+        wireshark_tools::run_tool(
+            "editcap",
+            &[
+                "-F".to_string(),
+                "pcapng".to_string(),
+                "-D".to_string(),
+                window.to_string(),
+                inputs[0].clone(),
+                dest.clone(),
+            ],
+        )
+    })?;
+    Ok(dest)
+}
+
+/// Write a copy of `source` to `dest` (or `<name>-shifted.pcapng` next to it)
+/// with every timestamp moved by `offset_secs`, to line up captures from
+/// devices whose clocks disagree. Returns the written path.
+pub fn time_shift(source: &str, offset_secs: f64, dest: Option<&str>) -> Result<String, String> {
+    if !offset_secs.is_finite() {
+        return Err("Invalid time offset".to_string());
+    }
+    let dest = match dest {
+        Some(dest) => dest.to_string(),
+        None => sibling_path(source, "shifted"),
+    };
+    if dest == source {
+        return Err("The shifted capture can't overwrite the original".to_string());
+    }
+    with_comments(&[source.to_string()], |inputs| {
+        wireshark_tools::run_tool(
+            "editcap",
+            &[
+                "-F".to_string(),
+                "pcapng".to_string(),
+                "-t".to_string(),
+                offset_secs.to_string(),
+                inputs[0].clone(),
+                dest.clone(),
+            ],
+        )
+    })?;
+    Ok(dest)
+}
+
+/// How `split_capture` cuts a capture into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitMode {
+    /// A new file every `value` packets
+    Packets,
+    /// A new file every `value` seconds of capture time
+    Seconds,
+}
+
+/// Split a capture into chunks with editcap, written next to it (or into
+/// `out_dir`) as `<name>-part_NNNNN_<time>.pcapng`. Returns the chunk paths
+/// in order.
+pub fn split_capture(
+    path: &str,
+    mode: SplitMode,
+    value: u64,
+    out_dir: Option<&str>,
+) -> Result<Vec<String>, String> {
+    if value == 0 {
+        return Err("Split size must be greater than zero".to_string());
+    }
+    let source = Path::new(path);
+    let dir = match out_dir {
+        Some(dir) => Path::new(dir).to_path_buf(),
+        None => source.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "capture".to_string());
+    let prefix = format!("{}-part", stem);
+    let dest = dir.join(format!("{}.pcapng", prefix));
+
+    let flag = match mode {
+        SplitMode::Packets => "-c",
+        SplitMode::Seconds => "-i",
+    };
+    let started = SystemTime::now() - Duration::from_secs(1);
+    with_comments(&[path.to_string()], |inputs| {
+        wireshark_tools::run_tool(
+            "editcap",
+            &[
+                "-F".to_string(),
+                "pcapng".to_string(),
+                flag.to_string(),
+                value.to_string(),
+                inputs[0].clone(),
+                dest.display().to_string(),
+            ],
+        )
+    })?;
+
+    // editcap numbers the chunks: <prefix>_00000_<timestamp>.pcapng. Chunks
+    // left from an earlier split are told apart by their age.
+    let chunk_prefix = format!("{}_", prefix);
+    let entries =
+        std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let written = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map_or(false, |modified| modified >= started);
+            name.starts_with(&chunk_prefix) && name.ends_with(".pcapng") && written
+        })
+        .map(|entry| entry.path().display().to_string())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Build a filter matching traffic between the frame's outermost IP endpoints
+fn conversation_filter(columns: &[String]) -> Option<String> {
+    let value = |i: usize| columns.get(i).map(String::as_str).filter(|v| !v.is_empty());
+    if let (Some(src), Some(dst)) = (value(1), value(2)) {
+        return Some(format!("ip.addr == {} && ip.addr == {}", src, dst));
+    }
+    if let (Some(src), Some(dst)) = (value(3), value(4)) {
+        return Some(format!("ipv6.addr == {} && ipv6.addr == {}", src, dst));
+    }
+    None
+}
+
+/// Write a small capture containing the frames around `frame_num`:
+/// everything within the time window, optionally limited to the frame's conversation.
+pub async fn export_excerpt(
+    client: &SharkdClient,
+    source: &str,
+    frame_num: u32,
+    seconds_before: f64,
+    seconds_after: f64,
+    conversation_only: bool,
+    dest: &str,
+) -> Result<ExcerptResult, String> {
+    let rows = client
+        .column_values(
+            &format!("frame.number == {}", frame_num),
+            &[
+                "frame.time_relative",
+                "ip.src:1",
+                "ip.dst:1",
+                "ipv6.src:1",
+                "ipv6.dst:1",
+            ],
+            1,
+        )
+        .await?;
+    let columns = rows
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Frame {} not found", frame_num))?;
+    let time: f64 = columns
+        .first()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| format!("Frame {} has no timestamp", frame_num))?;
+
+    let start_time = (time - seconds_before.max(0.0)).max(0.0);
+    let end_time = time + seconds_after.max(0.0);
+    let mut filter = format!(
+        "frame.time_relative >= {:.6} && frame.time_relative <= {:.6}",
+        start_time, end_time
+    );
+    if conversation_only {
+        if let Some(conversation) = conversation_filter(&columns) {
+            filter = format!("{} && {}", filter, conversation);
+        }
+    }
+
+    write_filtered(source, &filter, dest)?;
+
+    Ok(ExcerptResult {
+        path: dest.to_string(),
+        filter,
+        start_time,
+        end_time,
+    })
+}
@@ -0,0 +1,448 @@
+//! Capture file integrity checks and properties.
+//!
+//! Verifies pcap/pcapng structure, detects truncation, hashes the file, and
+//! watches the loaded capture so the user is warned if it changes on disk
+//! while a session has it open. File properties (`capture_info`) come from
+//! capinfos, so they can be read before committing to loading a huge file.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use crate::wireshark_tools;
+
+const PCAPNG_SHB: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_EPB: u32 = 0x0000_0006;
+const PCAPNG_SPB: u32 = 0x0000_0003;
+const PCAPNG_OBSOLETE_PB: u32 = 0x0000_0002;
+
+/// How often the loaded capture is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Result of verifying a capture file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureVerification {
+    pub path: String,
+    /// Hex-encoded SHA256 of the file contents
+    pub sha256: String,
+    pub size: u64,
+    /// "pcap", "pcapng", "gzip", or "unknown"
+    pub format: String,
+    /// Packet records found while walking the file structure
+    pub packet_count: u64,
+    /// The file ends in the middle of a record or block
+    pub truncated: bool,
+    /// Structural problems found (empty when the file is well formed)
+    pub issues: Vec<String>,
+    pub valid: bool,
+}
+
+/// Integrity state of the currently loaded capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureIntegrity {
+    pub path: String,
+    /// Hash recorded by the last `verify_capture` of this file, if any
+    pub sha256: Option<String>,
+    /// The file changed on disk since it was loaded
+    pub modified: bool,
+}
+
+/// Capture file properties, as capinfos reports them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureInfo {
+    pub path: String,
+    /// Size on disk in bytes
+    pub size: u64,
+    /// "gzip", "zstd", or "lz4" when the file is compressed
+    pub compression: Option<String>,
+    /// File format ("Wireshark/... - pcapng")
+    pub file_type: Option<String>,
+    /// Link-layer encapsulation ("Ethernet", "Per packet", ...)
+    pub encapsulation: Option<String>,
+    pub packets: Option<u64>,
+    /// Seconds since the epoch of the first and last packets
+    pub first_packet: Option<f64>,
+    pub last_packet: Option<f64>,
+    /// Seconds between the first and last packets
+    pub duration: Option<f64>,
+    /// From the pcapng section header
+    pub hardware: Option<String>,
+    pub os: Option<String>,
+    pub application: Option<String>,
+    pub interfaces: Option<u32>,
+}
+
+#[derive(Debug)]
+struct WatchState {
+    integrity: CaptureIntegrity,
+    size: u64,
+    mtime: Option<SystemTime>,
+}
+
+/// Watch state for the loaded capture
+static WATCH: OnceLock<Mutex<Option<WatchState>>> = OnceLock::new();
+
+/// Bumped on every new watch so stale watcher threads exit
+static WATCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn get_watch() -> &'static Mutex<Option<WatchState>> {
+    WATCH.get_or_init(|| Mutex::new(None))
+}
+
+/// Compute the hex-encoded SHA256 of a file
+pub fn sha256_file(path: &str) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Read exactly `buf.len()` bytes, returning false on a clean or partial EOF
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            Err(e) => return Err(format!("Failed to read capture: {}", e)),
+        }
+    }
+    Ok(true)
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let b = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(b)
+    } else {
+        u32::from_le_bytes(b)
+    }
+}
+
+/// Walk pcap records after the global header
+fn check_pcap<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    big_endian: bool,
+    verification: &mut CaptureVerification,
+) -> Result<(), String> {
+    let mut pos = 24u64;
+    let mut header = [0u8; 16];
+    while pos < size {
+        if pos + 16 > size || !read_full(reader, &mut header)? {
+            verification.truncated = true;
+            verification
+                .issues
+                .push(format!("Truncated record header at offset {}", pos));
+            return Ok(());
+        }
+        let incl_len = read_u32(&header[8..12], big_endian) as u64;
+        pos += 16;
+        if pos + incl_len > size {
+            verification.truncated = true;
+            verification.issues.push(format!(
+                "Record at offset {} claims {} bytes but the file ends first",
+                pos - 16,
+                incl_len
+            ));
+            return Ok(());
+        }
+        reader
+            .seek(SeekFrom::Current(incl_len as i64))
+            .map_err(|e| format!("Failed to seek capture: {}", e))?;
+        pos += incl_len;
+        verification.packet_count += 1;
+    }
+    Ok(())
+}
+
+/// Walk pcapng blocks, validating lengths and section byte order
+fn check_pcapng<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    verification: &mut CaptureVerification,
+) -> Result<(), String> {
+    let mut pos = 0u64;
+    let mut big_endian = false;
+    let mut header = [0u8; 12];
+    while pos < size {
+        if pos + 12 > size || !read_full(reader, &mut header)? {
+            verification.truncated = true;
+            verification
+                .issues
+                .push(format!("Truncated block header at offset {}", pos));
+            return Ok(());
+        }
+
+        // The block type of a section header is endian-neutral; its byte-order
+        // magic decides how the rest of the section is read
+        if read_u32(&header[0..4], false) == PCAPNG_SHB {
+            big_endian = match read_u32(&header[8..12], false) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => {
+                    verification.issues.push(format!(
+                        "Section header at offset {} has an invalid byte-order magic",
+                        pos
+                    ));
+                    return Ok(());
+                }
+            };
+        } else if pos == 0 {
+            verification
+                .issues
+                .push("pcapng file does not start with a section header".to_string());
+            return Ok(());
+        }
+
+        let block_type = read_u32(&header[0..4], big_endian);
+        let total_len = read_u32(&header[4..8], big_endian) as u64;
+        if total_len < 12 || !total_len.is_multiple_of(4) {
+            verification.issues.push(format!(
+                "Block at offset {} has invalid length {}",
+                pos, total_len
+            ));
+            return Ok(());
+        }
+        if pos + total_len > size {
+            verification.truncated = true;
+            verification.issues.push(format!(
+                "Block at offset {} claims {} bytes but the file ends first",
+                pos, total_len
+            ));
+            return Ok(());
+        }
+
+        // Skip the body (the 4 bytes already read past the length belong to it)
+        reader
+            .seek(SeekFrom::Start(pos + total_len - 4))
+            .map_err(|e| format!("Failed to seek capture: {}", e))?;
+        let mut trailer = [0u8; 4];
+        if !read_full(reader, &mut trailer)? {
+            verification.truncated = true;
+            return Ok(());
+        }
+        if read_u32(&trailer, big_endian) as u64 != total_len {
+            verification.issues.push(format!(
+                "Block at offset {} has mismatched leading and trailing lengths",
+                pos
+            ));
+            return Ok(());
+        }
+
+        if matches!(block_type, PCAPNG_EPB | PCAPNG_SPB | PCAPNG_OBSOLETE_PB) {
+            verification.packet_count += 1;
+        }
+        pos += total_len;
+    }
+    Ok(())
+}
+
+/// Hash a capture file and validate its pcap/pcapng structure
+pub fn verify_capture(path: &str) -> Result<CaptureVerification, String> {
+    let sha256 = sha256_file(path)?;
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat {}: {}", path, e))?
+        .len();
+    let mut reader = BufReader::new(file);
+
+    let mut verification = CaptureVerification {
+        path: path.to_string(),
+        sha256,
+        size,
+        format: "unknown".to_string(),
+        packet_count: 0,
+        truncated: false,
+        issues: Vec::new(),
+        valid: false,
+    };
+
+    let mut magic = [0u8; 4];
+    if !read_full(&mut reader, &mut magic)? {
+        verification
+            .issues
+            .push("File is too short to be a capture".to_string());
+        return Ok(verification);
+    }
+
+    match u32::from_le_bytes(magic) {
+        0xA1B2_C3D4 | 0xA1B2_3C4D | 0xD4C3_B2A1 | 0x4D3C_B2A1 => {
+            verification.format = "pcap".to_string();
+            let big_endian = matches!(u32::from_be_bytes(magic), 0xA1B2_C3D4 | 0xA1B2_3C4D);
+            let mut rest = [0u8; 20];
+            if !read_full(&mut reader, &mut rest)? {
+                verification.truncated = true;
+                verification
+                    .issues
+                    .push("Truncated pcap global header".to_string());
+            } else {
+                check_pcap(&mut reader, size, big_endian, &mut verification)?;
+            }
+        }
+        PCAPNG_SHB => {
+            verification.format = "pcapng".to_string();
+            reader
+                .seek(SeekFrom::Start(0))
+                .map_err(|e| format!("Failed to seek capture: {}", e))?;
+            check_pcapng(&mut reader, size, &mut verification)?;
+        }
+        _ if magic[0] == 0x1F && magic[1] == 0x8B => {
+            verification.format = "gzip".to_string();
+            verification
+                .issues
+                .push("Compressed capture; structure was not checked".to_string());
+        }
+        _ => verification
+            .issues
+            .push("Unrecognized capture file format".to_string()),
+    }
+
+    verification.valid = verification.format != "unknown" && !verification.truncated && {
+        verification.format == "gzip" || verification.issues.is_empty()
+    };
+
+    // Remember the hash for the loaded capture
+    if let Some(state) = get_watch().lock().as_mut() {
+        if state.integrity.path == path {
+            state.integrity.sha256 = Some(verification.sha256.clone());
+        }
+    }
+
+    Ok(verification)
+}
+
+/// Compression format from a file's magic bytes
+fn compression(magic: &[u8]) -> Option<&'static str> {
+    match magic {
+        [0x1F, 0x8B, ..] => Some("gzip"),
+        [0x28, 0xB5, 0x2F, 0xFD, ..] => Some("zstd"),
+        [0x04, 0x22, 0x4D, 0x18, ..] => Some("lz4"),
+        _ => None,
+    }
+}
+
+/// Read a capture file's properties with capinfos, without loading it
+pub fn capture_info(path: &str) -> Result<CaptureInfo, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to stat {}: {}", path, e))?
+        .len();
+    let mut magic = [0u8; 4];
+    let magic = match File::open(path).map(|mut f| f.read(&mut magic)) {
+        Ok(Ok(n)) => &magic[..n],
+        _ => &[][..],
+    };
+
+    // -M: exact numbers, -S: times as seconds since the epoch
+    let output = wireshark_tools::run_tool(
+        "capinfos",
+        &["-M".to_string(), "-S".to_string(), path.to_string()],
+    )?;
+
+    let mut info = CaptureInfo {
+        path: path.to_string(),
+        size,
+        compression: compression(magic).map(String::from),
+        ..Default::default()
+    };
+    for line in output.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        if value.is_empty() || value == "n/a" {
+            continue;
+        }
+        let text = Some(value.to_string());
+        // Numbers come with units ("12.3 seconds")
+        let number = value
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<f64>().ok());
+        match key {
+            "File type" => info.file_type = text,
+            "File encapsulation" => info.encapsulation = text,
+            "Number of packets" => info.packets = number.map(|n| n as u64),
+            "Capture duration" => info.duration = number,
+            "First packet time" | "Earliest packet time" => info.first_packet = number,
+            "Last packet time" | "Latest packet time" => info.last_packet = number,
+            "Capture hardware" => info.hardware = text,
+            "Capture oper-sys" => info.os = text,
+            "Capture application" => info.application = text,
+            "Number of interfaces in file" => info.interfaces = number.map(|n| n as u32),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn file_signature(path: &str) -> Option<(u64, Option<SystemTime>)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Start watching a freshly loaded capture for on-disk changes.
+/// Emits `capture-file-changed` once when the file's size or mtime changes.
+pub fn watch(path: &str) {
+    let (size, mtime) = file_signature(path).unwrap_or((0, None));
+    let generation = WATCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    *get_watch().lock() = Some(WatchState {
+        integrity: CaptureIntegrity {
+            path: path.to_string(),
+            sha256: None,
+            modified: false,
+        },
+        size,
+        mtime,
+    });
+
+    let path = path.to_string();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        if WATCH_GENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+
+        let current = file_signature(&path);
+        let mut guard = get_watch().lock();
+        let state = match guard.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+        if current != Some((state.size, state.mtime)) {
+            state.integrity.modified = true;
+            let integrity = state.integrity.clone();
+            drop(guard);
+            eprintln!("Warning: capture file changed on disk: {}", path);
+            crate::stats_cache::invalidate(&path);
+            crate::emit_event("capture-file-changed", integrity);
+            return;
+        }
+    });
+}
+
+/// Integrity state of the loaded capture, if one is being watched
+pub fn get_integrity() -> Option<CaptureIntegrity> {
+    get_watch().lock().as_ref().map(|s| s.integrity.clone())
+}
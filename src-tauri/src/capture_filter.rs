@@ -0,0 +1,143 @@
+//! Capture (libpcap/BPF) filter validation.
+//!
+//! Capture filters use a different syntax from display filters and are
+//! compiled by libpcap, not sharkd, so they're checked by having dumpcap
+//! compile them (`dumpcap -d`) for the interface the capture will use.
+
+use serde::Serialize;
+use std::process::Command;
+
+use crate::wireshark_tools;
+
+/// Result of compiling a capture filter
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureFilterCheck {
+    pub valid: bool,
+    /// libpcap's error message when the filter doesn't compile
+    pub error: Option<String>,
+    /// Byte offset and length of the offending token, when the error names one
+    pub offset: Option<usize>,
+    pub length: Option<usize>,
+    /// Compiled BPF program, one instruction per line
+    pub instructions: Vec<String>,
+}
+
+impl CaptureFilterCheck {
+    fn valid(instructions: Vec<String>) -> Self {
+        CaptureFilterCheck {
+            valid: true,
+            error: None,
+            offset: None,
+            length: None,
+            instructions,
+        }
+    }
+}
+
+/// Pull libpcap's message out of dumpcap's stderr, e.g. "syntax error" or
+/// "unknown host 'foo'"
+fn pcap_error(stderr: &str) -> String {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    // dumpcap: Invalid capture filter "..." for interface '...'.
+    // <blank>
+    // That string isn't a valid capture filter (<pcap error>).
+    for line in &lines {
+        if let Some(rest) = line.strip_prefix("That string isn't a valid capture filter (") {
+            return rest.trim_end_matches('.').trim_end_matches(')').to_string();
+        }
+    }
+    lines
+        .iter()
+        .map(|l| l.trim_start_matches("dumpcap: "))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The token libpcap complains about: quoted (`unknown host 'foo'`) or after
+/// a colon (`illegal token: @`)
+fn offending_token(error: &str) -> Option<&str> {
+    let mut quoted = error.split('\'');
+    if let (Some(_), Some(token), Some(_)) = (quoted.next(), quoted.next(), quoted.next()) {
+        if !token.is_empty() {
+            return Some(token);
+        }
+    }
+    error
+        .rsplit_once(": ")
+        .map(|(_, token)| token.trim())
+        .filter(|token| !token.is_empty() && !token.contains(' '))
+}
+
+/// Position of `token` in `filter`, preferring a whole-word match
+fn locate(filter: &str, token: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '.' || c == ':' || c == '-';
+    filter
+        .match_indices(token)
+        .map(|(i, _)| i)
+        .find(|&i| {
+            let before = filter[..i].chars().next_back().is_none_or(|c| !is_word(c));
+            let after = filter[i + token.len()..]
+                .chars()
+                .next()
+                .is_none_or(|c| !is_word(c));
+            before && after
+        })
+        .or_else(|| filter.find(token))
+}
+
+/// Compile a capture filter with dumpcap. `interface` picks the link type the
+/// filter is compiled for; dumpcap's default interface is used otherwise.
+pub fn check_capture_filter(
+    filter: &str,
+    interface: Option<&str>,
+) -> Result<CaptureFilterCheck, String> {
+    if filter.trim().is_empty() {
+        return Ok(CaptureFilterCheck::valid(Vec::new()));
+    }
+
+    let path = wireshark_tools::find_tool("dumpcap")?;
+    let mut command = Command::new(&path);
+    if let Some(interface) = interface.filter(|i| !i.is_empty()) {
+        command.args(["-i", interface]);
+    }
+    command.args(["-f", filter, "-d"]);
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run dumpcap at {:?}: {}", path, e))?;
+
+    if output.status.success() {
+        let instructions = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with('('))
+            .map(str::to_string)
+            .collect();
+        return Ok(CaptureFilterCheck::valid(instructions));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Failures that aren't about the filter (no such interface, no capture
+    // permissions, ...) are reported as errors rather than an invalid filter
+    if !stderr.contains("Invalid capture filter") {
+        return Err(format!(
+            "dumpcap failed ({}): {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+
+    let error = pcap_error(&stderr);
+    let token = offending_token(&error);
+    let offset = token.and_then(|t| locate(filter, t));
+    Ok(CaptureFilterCheck {
+        valid: false,
+        offset,
+        length: offset.and(token.map(str::len)),
+        error: Some(error),
+        instructions: Vec::new(),
+    })
+}
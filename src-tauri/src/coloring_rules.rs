@@ -0,0 +1,162 @@
+//! Coloring rules of the managed profile (its `colorfilters` file).
+//!
+//! sharkd colors frames (`bg`/`fg`) from these rules when it starts, so
+//! changes take effect after a restart. While a color-blind palette is
+//! applied, edits go to the rules as written and the palette is re-applied
+//! over them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{palette, profile};
+
+/// One coloring rule, in priority order (the first matching rule wins)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColoringRule {
+    pub name: String,
+    /// Display filter selecting the frames to color
+    pub filter: String,
+    /// Text color, `#rrggbb`
+    pub foreground: String,
+    /// Background color, `#rrggbb`
+    pub background: String,
+    pub enabled: bool,
+}
+
+/// Colors of a new rule, `#rrggbb`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleColors {
+    #[serde(default = "default_foreground")]
+    pub foreground: String,
+    pub background: String,
+}
+
+fn default_foreground() -> String {
+    "#000000".to_string()
+}
+
+/// `#rrggbb` from a `colorfilters` `[r,g,b]` with 16-bit components
+fn hex_color(text: &str) -> Option<String> {
+    let parts: Vec<u16> = text
+        .split(',')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [r, g, b] => Some(format!("#{:02x}{:02x}{:02x}", r >> 8, g >> 8, b >> 8)),
+        _ => None,
+    }
+}
+
+/// `[r,g,b]` with 16-bit components from `#rrggbb`
+fn rule_color(hex: &str) -> Result<String, String> {
+    let digits = hex.trim().trim_start_matches('#');
+    let value = match u32::from_str_radix(digits, 16) {
+        Ok(value) if digits.len() == 6 => value,
+        _ => return Err(format!("Invalid color '{}', expected #rrggbb", hex)),
+    };
+    let channel = |shift: u32| ((value >> shift) & 0xff) * 257;
+    Ok(format!("[{},{},{}]", channel(16), channel(8), channel(0)))
+}
+
+/// Parse an `@name@filter@[fg][bg]` line (`!@...` when disabled)
+fn parse_rule(line: &str) -> Option<ColoringRule> {
+    let (enabled, rest) = match line.strip_prefix('!') {
+        Some(rest) => (false, rest),
+        None => (true, line),
+    };
+    let (name, rest) = rest.strip_prefix('@')?.split_once('@')?;
+    let (filter, colors) = rest.rsplit_once("@[")?;
+    let (fg, bg) = colors.trim_end().strip_suffix(']')?.split_once("][")?;
+    Some(ColoringRule {
+        name: name.to_string(),
+        filter: filter.to_string(),
+        foreground: hex_color(fg)?,
+        background: hex_color(bg)?,
+        enabled,
+    })
+}
+
+/// The rules file's text, starting from Wireshark's defaults when the profile
+/// has none yet
+fn read_rules() -> Result<String, String> {
+    let path = palette::rules_file(&profile::profile_dir()?);
+    let path = if path.is_file() {
+        path
+    } else {
+        palette::global_colorfilters()?
+    };
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+}
+
+/// Write the rules file, re-applying the active palette over it
+fn write_rules(text: &str) -> Result<(), String> {
+    let dir = profile::profile_dir()?;
+    let preset = palette::active_preset(&dir);
+    let path = palette::rules_file(&dir);
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    if let Some(preset) = preset {
+        palette::apply_palette(preset)?;
+    }
+    Ok(())
+}
+
+/// Coloring rules in priority order
+pub fn list_rules() -> Result<Vec<ColoringRule>, String> {
+    Ok(read_rules()?.lines().filter_map(parse_rule).collect())
+}
+
+/// Add a rule at `position` in the priority order (first when omitted)
+pub fn add_rule(
+    name: &str,
+    filter: &str,
+    colors: &RuleColors,
+    position: Option<usize>,
+) -> Result<Vec<ColoringRule>, String> {
+    let name = name.trim();
+    let filter = filter.trim();
+    if name.is_empty() || filter.is_empty() {
+        return Err("A coloring rule needs a name and a filter".to_string());
+    }
+    if name.contains('@') || filter.contains('@') || filter.contains('\n') {
+        return Err("Coloring rule names and filters can't contain '@'".to_string());
+    }
+    let line = format!(
+        "@{}@{}@{}{}",
+        name,
+        filter,
+        rule_color(&colors.foreground)?,
+        rule_color(&colors.background)?
+    );
+
+    let text = read_rules()?;
+    let mut lines: Vec<&str> = text.lines().collect();
+    let rule_lines: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| parse_rule(l).is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let index = match position.and_then(|p| rule_lines.get(p)) {
+        Some(&index) => index,
+        None if position.is_some() => rule_lines.last().map_or(lines.len(), |i| i + 1),
+        None => rule_lines.first().copied().unwrap_or(lines.len()),
+    };
+    lines.insert(index, &line);
+
+    write_rules(&(lines.join("\n") + "\n"))?;
+    list_rules()
+}
+
+/// Remove the rules with a name
+pub fn remove_rule(name: &str) -> Result<Vec<ColoringRule>, String> {
+    let text = read_rules()?;
+    let before = text.lines().count();
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| parse_rule(l).map_or(true, |rule| rule.name != name))
+        .collect();
+    if lines.len() == before {
+        return Err(format!("No coloring rule named '{}'", name));
+    }
+    write_rules(&(lines.join("\n") + "\n"))?;
+    list_rules()
+}
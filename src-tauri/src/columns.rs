@@ -0,0 +1,193 @@
+//! Custom packet list columns: dissector fields (e.g. `http.host`,
+//! `dns.qry.name`) shown after the standard columns, and named column
+//! layouts ("VoIP", "Web", ...) bundling them with the visibility, order and
+//! widths of every column.
+//!
+//! sharkd returns custom columns instead of, not alongside, the standard
+//! ones, so their values are fetched with a second `frames` request for the
+//! same page and matched up by frame number.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::{field_catalog, storage};
+
+pub const STORE_NAME: &str = "columns";
+
+/// A column showing a dissector field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomColumn {
+    pub title: String,
+    /// Field filter name, e.g. `http.host`
+    pub field: String,
+    /// Which occurrence to show (1 = first, -1 = last); 0 shows them all,
+    /// comma-separated
+    #[serde(default)]
+    pub occurrence: i32,
+}
+
+impl CustomColumn {
+    /// sharkd custom column spec, `field:occurrence`
+    pub fn spec(&self) -> String {
+        format!("{}:{}", self.field.trim(), self.occurrence)
+    }
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+/// Display settings of one packet list column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutColumn {
+    /// Standard column (`number`, `time`, `source`, `destination`, `protocol`,
+    /// `length`, `info`) or `custom:<field>` for a custom column
+    pub key: String,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    /// Width in pixels; the frontend default when unset
+    #[serde(default)]
+    pub width: Option<u32>,
+}
+
+/// A named column layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnLayout {
+    pub name: String,
+    /// Columns in display order
+    #[serde(default)]
+    pub columns: Vec<LayoutColumn>,
+    /// Custom columns the layout shows
+    #[serde(default)]
+    pub custom: Vec<CustomColumn>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ColumnStore {
+    columns: Vec<CustomColumn>,
+    #[serde(default)]
+    layouts: Vec<ColumnLayout>,
+    #[serde(default)]
+    active_layout: Option<String>,
+    /// Capture path -> layout applied when it is opened
+    #[serde(default)]
+    capture_layouts: BTreeMap<String, String>,
+}
+
+static COLUMNS: OnceLock<Mutex<ColumnStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<ColumnStore> {
+    COLUMNS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Configured custom columns, in display order
+pub fn get_columns() -> Vec<CustomColumn> {
+    get_store().lock().columns.clone()
+}
+
+/// Check custom column fields against the field catalog, when it is available
+fn validate(columns: &[CustomColumn]) -> Result<(), String> {
+    for column in columns {
+        if column.field.trim().is_empty() {
+            return Err(format!("Column '{}' needs a field", column.title));
+        }
+        if let Ok(None) = field_catalog::field_info(&column.field) {
+            return Err(format!("Unknown field: {}", column.field));
+        }
+    }
+    Ok(())
+}
+
+/// Replace the custom columns
+pub fn set_columns(columns: Vec<CustomColumn>) -> Result<(), String> {
+    validate(&columns)?;
+    let mut store = get_store().lock();
+    store.columns = columns;
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Saved column layouts
+pub fn list_layouts() -> Vec<ColumnLayout> {
+    get_store().lock().layouts.clone()
+}
+
+/// Name of the layout last applied, if any
+pub fn active_layout() -> Option<String> {
+    get_store().lock().active_layout.clone()
+}
+
+/// Add or replace (by name) a column layout
+pub fn save_layout(layout: ColumnLayout) -> Result<(), String> {
+    if layout.name.trim().is_empty() {
+        return Err("A layout needs a name".to_string());
+    }
+    validate(&layout.custom)?;
+    let mut store = get_store().lock();
+    store.layouts.retain(|l| l.name != layout.name);
+    store.layouts.push(layout);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Delete a column layout, along with captures defaulting to it
+pub fn delete_layout(name: &str) -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.layouts.retain(|l| l.name != name);
+    store.capture_layouts.retain(|_, layout| layout != name);
+    if store.active_layout.as_deref() == Some(name) {
+        store.active_layout = None;
+    }
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Make a layout active, switching the custom columns to the layout's
+pub fn apply_layout(name: &str) -> Result<ColumnLayout, String> {
+    let mut store = get_store().lock();
+    let layout = store
+        .layouts
+        .iter()
+        .find(|l| l.name == name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown column layout: {}", name))?;
+    store.columns = layout.custom.clone();
+    store.active_layout = Some(layout.name.clone());
+    storage::save_json(STORE_NAME, &*store)?;
+    Ok(layout)
+}
+
+/// Set (or with `None`, clear) the layout applied when a capture is opened
+pub fn set_capture_layout(path: &str, name: Option<&str>) -> Result<(), String> {
+    let mut store = get_store().lock();
+    match name {
+        Some(name) => {
+            if !store.layouts.iter().any(|l| l.name == name) {
+                return Err(format!("Unknown column layout: {}", name));
+            }
+            store
+                .capture_layouts
+                .insert(path.to_string(), name.to_string());
+        }
+        None => {
+            store.capture_layouts.remove(path);
+        }
+    }
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Apply a capture's default layout, if it has one. Returns the layout.
+pub fn apply_capture_layout(path: &str) -> Option<ColumnLayout> {
+    let name = get_store().lock().capture_layouts.get(path).cloned()?;
+    match apply_layout(&name) {
+        Ok(layout) => Some(layout),
+        Err(e) => {
+            eprintln!("Warning: Failed to apply column layout: {}", e);
+            None
+        }
+    }
+}
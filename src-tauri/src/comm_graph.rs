@@ -0,0 +1,227 @@
+//! Host communication graph built from the IP conversation/endpoint taps.
+//!
+//! Nodes are hosts (or GeoIP groups) weighted by bytes; edges are the
+//! conversations between them. Rendered as JSON for the UI's force-directed
+//! view, or DOT/GraphML for external tools.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::sharkd_client::{Endpoint, HostTraffic, SharkdClient};
+
+/// Output format of the graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+    Graphml,
+}
+
+/// How hosts are grouped into nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphGrouping {
+    /// One node per address
+    #[default]
+    Host,
+    /// One node per GeoIP country (hosts without GeoIP data stay individual)
+    Country,
+    /// One node per GeoIP autonomous system organization
+    AsOrg,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub frames: u64,
+    pub bytes: u64,
+    /// Number of hosts folded into this node
+    pub hosts: usize,
+    /// GeoIP country of the node, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub frames: u64,
+    pub bytes: u64,
+}
+
+/// Node/edge graph of host communication
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+fn node_id(endpoint: Option<&Endpoint>, host: &str, grouping: GraphGrouping) -> String {
+    let group = endpoint.and_then(|e| match grouping {
+        GraphGrouping::Host => None,
+        GraphGrouping::Country => e.geoip_country.clone(),
+        GraphGrouping::AsOrg => e.geoip_as_org.clone(),
+    });
+    group.unwrap_or_else(|| host.to_string())
+}
+
+/// Build the graph from host traffic
+pub fn build_graph(traffic: &HostTraffic, grouping: GraphGrouping) -> CommGraph {
+    let endpoints: BTreeMap<&str, &Endpoint> = traffic
+        .endpoints
+        .iter()
+        .map(|e| (e.host.as_str(), e))
+        .collect();
+    let id_for = |host: &str| node_id(endpoints.get(host).copied(), host, grouping);
+
+    let mut nodes: BTreeMap<String, GraphNode> = BTreeMap::new();
+    for endpoint in &traffic.endpoints {
+        let id = id_for(&endpoint.host);
+        let node = nodes.entry(id.clone()).or_insert_with(|| GraphNode {
+            id,
+            frames: 0,
+            bytes: 0,
+            hosts: 0,
+            country: endpoint.geoip_country.clone(),
+        });
+        node.frames += endpoint.rxf + endpoint.txf;
+        node.bytes += endpoint.rxb + endpoint.txb;
+        node.hosts += 1;
+    }
+
+    // Undirected edges keyed by ordered node pair
+    let mut edges: BTreeMap<(String, String), GraphEdge> = BTreeMap::new();
+    for conv in &traffic.conversations {
+        let (a, b) = (id_for(&conv.saddr), id_for(&conv.daddr));
+        if a == b {
+            // Traffic within a group
+            continue;
+        }
+        let key = if a < b { (a, b) } else { (b, a) };
+        let edge = edges.entry(key.clone()).or_insert_with(|| GraphEdge {
+            source: key.0,
+            target: key.1,
+            frames: 0,
+            bytes: 0,
+        });
+        edge.frames += conv.rxf + conv.txf;
+        edge.bytes += conv.rxb + conv.txb;
+    }
+
+    CommGraph {
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_values().collect(),
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_dot(graph: &CommGraph) -> String {
+    let max_bytes = graph
+        .edges
+        .iter()
+        .map(|e| e.bytes)
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let mut out = String::from("graph packetpilot {\n  node [shape=ellipse];\n");
+    for node in &graph.nodes {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\\n{} bytes\"];\n",
+            escape_dot(&node.id),
+            escape_dot(&node.id),
+            node.bytes
+        ));
+    }
+    for edge in &graph.edges {
+        // Line width scales with the edge's share of the heaviest edge
+        let width = 1.0 + 7.0 * edge.bytes as f64 / max_bytes as f64;
+        out.push_str(&format!(
+            "  \"{}\" -- \"{}\" [label=\"{}\", weight={}, penwidth={:.1}];\n",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target),
+            edge.bytes,
+            edge.bytes,
+            width
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_graphml(graph: &CommGraph) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"frames\" for=\"all\" attr.name=\"frames\" attr.type=\"long\"/>\n",
+        "  <key id=\"bytes\" for=\"all\" attr.name=\"bytes\" attr.type=\"long\"/>\n",
+        "  <key id=\"country\" for=\"node\" attr.name=\"country\" attr.type=\"string\"/>\n",
+        "  <graph id=\"packetpilot\" edgedefault=\"undirected\">\n",
+    ));
+    for node in &graph.nodes {
+        out.push_str(&format!(
+            "    <node id=\"{}\"><data key=\"frames\">{}</data><data key=\"bytes\">{}</data>",
+            escape_xml(&node.id),
+            node.frames,
+            node.bytes
+        ));
+        if let Some(country) = &node.country {
+            out.push_str(&format!(
+                "<data key=\"country\">{}</data>",
+                escape_xml(country)
+            ));
+        }
+        out.push_str("</node>\n");
+    }
+    for (i, edge) in graph.edges.iter().enumerate() {
+        out.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"><data key=\"frames\">{}</data><data key=\"bytes\">{}</data></edge>\n",
+            i,
+            escape_xml(&edge.source),
+            escape_xml(&edge.target),
+            edge.frames,
+            edge.bytes
+        ));
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+/// Render a graph in the requested format
+pub fn render(graph: &CommGraph, format: GraphFormat) -> Result<String, String> {
+    match format {
+        GraphFormat::Json => serde_json::to_string_pretty(graph)
+            .map_err(|e| format!("Failed to serialize graph: {}", e)),
+        GraphFormat::Dot => Ok(render_dot(graph)),
+        GraphFormat::Graphml => Ok(render_graphml(graph)),
+    }
+}
+
+/// Build and render the communication graph of the loaded capture.
+/// Writes it to `dest` when given; always returns the rendered graph.
+pub async fn export_comm_graph(
+    client: &SharkdClient,
+    format: GraphFormat,
+    grouping: GraphGrouping,
+    filter: Option<&str>,
+    dest: Option<&str>,
+) -> Result<String, String> {
+    let traffic = client.host_traffic(filter).await?;
+    let rendered = render(&build_graph(&traffic, grouping), format)?;
+    if let Some(dest) = dest {
+        std::fs::write(dest, &rendered).map_err(|e| format!("Failed to write graph: {}", e))?;
+    }
+    Ok(rendered)
+}
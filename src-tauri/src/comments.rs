@@ -0,0 +1,98 @@
+//! Packet comments added in the app.
+//!
+//! Comments are set in sharkd with `setcomment` and also kept here per
+//! capture file, so they are re-applied when the capture is opened again and
+//! written into pcapng files exported from it (sharkd can't save captures).
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::sharkd_client::SharkdClient;
+use crate::{storage, wireshark_tools};
+
+pub const STORE_NAME: &str = "comments";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CommentStore {
+    /// Capture path -> frame number -> comment
+    captures: HashMap<String, BTreeMap<u32, String>>,
+}
+
+static COMMENTS: OnceLock<Mutex<CommentStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<CommentStore> {
+    COMMENTS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Comments added to a capture's frames
+pub fn for_capture(capture: &str) -> BTreeMap<u32, String> {
+    get_store()
+        .lock()
+        .captures
+        .get(capture)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Set (or with `None`, remove) the comment on a frame of the loaded capture
+pub async fn set_comment(
+    client: &SharkdClient,
+    capture: &str,
+    frame: u32,
+    comment: Option<&str>,
+) -> Result<(), String> {
+    let comment = comment.map(str::trim).filter(|c| !c.is_empty());
+    client.set_comment(frame, comment).await?;
+
+    let mut store = get_store().lock();
+    let comments = store.captures.entry(capture.to_string()).or_default();
+    match comment {
+        Some(comment) => {
+            comments.insert(frame, comment.to_string());
+        }
+        None => {
+            comments.remove(&frame);
+        }
+    }
+    if comments.is_empty() {
+        store.captures.remove(capture);
+    }
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Re-apply a capture's comments after it is loaded
+pub async fn apply_comments(client: &SharkdClient, capture: &str) {
+    for (frame, comment) in for_capture(capture) {
+        if let Err(e) = client.set_comment(frame, Some(&comment)).await {
+            eprintln!(
+                "Warning: Failed to restore comment on frame {}: {}",
+                frame, e
+            );
+        }
+    }
+}
+
+/// Copy `source` to `dest` (pcapng) with its added comments written in.
+/// Returns false, without writing, when it has none.
+pub fn write_with_comments(source: &str, dest: &str) -> Result<bool, String> {
+    let comments = for_capture(source);
+    if comments.is_empty() {
+        return Ok(false);
+    }
+    let mut args = vec!["-F".to_string(), "pcapng".to_string()];
+    for (frame, comment) in &comments {
+        args.push("-a".to_string());
+        args.push(format!("{}:{}", frame, comment));
+    }
+    args.push(source.to_string());
+    args.push(dest.to_string());
+    wireshark_tools::run_tool("editcap", &args)?;
+    Ok(true)
+}
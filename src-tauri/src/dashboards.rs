@@ -0,0 +1,230 @@
+//! Saved queries and dashboard definitions.
+//!
+//! A dashboard is an ordered list of panels, each a query expression plus the
+//! chart type the frontend should render it with. Query results are cached per
+//! capture so re-opening a dashboard doesn't rescan unchanged data.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::query::{self, QueryResult};
+use crate::sharkd_client::SharkdClient;
+use crate::{frame_marks, storage};
+
+pub const STORE_NAME: &str = "dashboards";
+
+/// A named query expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+    pub expr: String,
+}
+
+/// How a panel's result is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartType {
+    #[default]
+    Table,
+    Bar,
+    Pie,
+    Line,
+    Number,
+}
+
+/// One panel of a dashboard. `query_id` refers to a saved query; otherwise `expr` is used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardPanel {
+    pub title: String,
+    #[serde(default)]
+    pub query_id: Option<String>,
+    #[serde(default)]
+    pub expr: Option<String>,
+    #[serde(default)]
+    pub chart: ChartType,
+}
+
+/// A named set of panels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dashboard {
+    pub id: String,
+    pub name: String,
+    pub panels: Vec<DashboardPanel>,
+}
+
+/// Result of one panel; a failing query doesn't fail the whole dashboard
+#[derive(Debug, Clone, Serialize)]
+pub struct PanelResult {
+    pub title: String,
+    pub chart: ChartType,
+    pub expr: String,
+    pub result: Option<QueryResult>,
+    pub error: Option<String>,
+    /// Served from the result cache
+    pub cached: bool,
+}
+
+/// Results for every panel of a dashboard, in panel order
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardResult {
+    pub id: String,
+    pub name: String,
+    pub panels: Vec<PanelResult>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DashboardStore {
+    queries: Vec<SavedQuery>,
+    dashboards: Vec<Dashboard>,
+}
+
+static DASHBOARDS: OnceLock<Mutex<DashboardStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<DashboardStore> {
+    DASHBOARDS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Query results keyed by (capture, effective exclusions, expression)
+static RESULT_CACHE: OnceLock<Mutex<HashMap<String, QueryResult>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<HashMap<String, QueryResult>> {
+    RESULT_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Saved queries
+pub fn list_queries() -> Vec<SavedQuery> {
+    get_store().lock().queries.clone()
+}
+
+/// Add or replace (by id) a saved query. The expression must compile.
+pub fn save_query(saved: SavedQuery) -> Result<(), String> {
+    query::compile(&saved.expr)?;
+    let mut store = get_store().lock();
+    store.queries.retain(|q| q.id != saved.id);
+    store.queries.push(saved);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove a saved query
+pub fn remove_query(id: &str) -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.queries.retain(|q| q.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Dashboard definitions
+pub fn list_dashboards() -> Vec<Dashboard> {
+    get_store().lock().dashboards.clone()
+}
+
+/// Add or replace (by id) a dashboard
+pub fn save_dashboard(dashboard: Dashboard) -> Result<(), String> {
+    for panel in &dashboard.panels {
+        if panel.query_id.is_none() && panel.expr.is_none() {
+            return Err(format!(
+                "Panel '{}' needs a query_id or an expr",
+                panel.title
+            ));
+        }
+    }
+    let mut store = get_store().lock();
+    store.dashboards.retain(|d| d.id != dashboard.id);
+    store.dashboards.push(dashboard);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove a dashboard
+pub fn remove_dashboard(id: &str) -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.dashboards.retain(|d| d.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Drop cached results (called when a new capture is loaded)
+pub fn clear_cache() {
+    get_cache().lock().clear();
+}
+
+/// Run every panel of a dashboard against the capture loaded in `session`,
+/// reusing cached results where possible
+pub async fn run_dashboard(
+    client: &SharkdClient,
+    session: &str,
+    id: &str,
+) -> Result<DashboardResult, String> {
+    let (dashboard, queries) = {
+        let store = get_store().lock();
+        let dashboard = store
+            .dashboards
+            .iter()
+            .find(|d| d.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown dashboard: {}", id))?;
+        (dashboard, store.queries.clone())
+    };
+
+    let capture = client.loaded_file().unwrap_or_default();
+    let exclusions = frame_marks::with_exclusions(session, None).unwrap_or_default();
+
+    let mut panels = Vec::with_capacity(dashboard.panels.len());
+    for panel in &dashboard.panels {
+        let expr = match &panel.query_id {
+            Some(query_id) => queries
+                .iter()
+                .find(|q| &q.id == query_id)
+                .map(|q| q.expr.clone())
+                .ok_or_else(|| format!("Unknown saved query: {}", query_id)),
+            None => Ok(panel.expr.clone().unwrap_or_default()),
+        };
+
+        let mut panel_result = PanelResult {
+            title: panel.title.clone(),
+            chart: panel.chart,
+            expr: expr.clone().unwrap_or_default(),
+            result: None,
+            error: None,
+            cached: false,
+        };
+
+        let expr = match expr {
+            Ok(expr) => expr,
+            Err(e) => {
+                panel_result.error = Some(e);
+                panels.push(panel_result);
+                continue;
+            }
+        };
+
+        let key = format!("{}\n{}\n{}", capture, exclusions, expr);
+        let cached = get_cache().lock().get(&key).cloned();
+        if let Some(cached) = cached {
+            panel_result.result = Some(cached);
+            panel_result.cached = true;
+            panels.push(panel_result);
+            continue;
+        }
+
+        match query::run_query(client, session, &expr).await {
+            Ok(result) => {
+                get_cache().lock().insert(key, result.clone());
+                panel_result.result = Some(result);
+            }
+            Err(e) => panel_result.error = Some(e),
+        }
+        panels.push(panel_result);
+    }
+
+    Ok(DashboardResult {
+        id: dashboard.id,
+        name: dashboard.name,
+        panels,
+    })
+}
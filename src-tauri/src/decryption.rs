@@ -0,0 +1,295 @@
+//! TLS decryption hints.
+//!
+//! After a capture loads, its TLS sessions are listed and matched against
+//! keylog files found in common locations (`SSLKEYLOGFILE`, next to the
+//! capture, the home directory), telling the user which sessions a keylog
+//! would decrypt. A keylog named by `SSLKEYLOGFILE` is applied automatically.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::sharkd_client::SharkdClient;
+
+/// Upper bound on handshake frames scanned
+const HANDSHAKE_SCAN_LIMIT: u32 = 100_000;
+
+/// Keylog file names looked for next to the capture and in the home directory
+const KEYLOG_NAMES: &[&str] = &[
+    "sslkeylog.log",
+    "sslkeylog.txt",
+    "keylog.txt",
+    "keys.log",
+    ".ssl-key.log",
+    ".sslkeylog",
+];
+
+/// A TLS session in the capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsSessionHint {
+    pub tcp_stream: String,
+    pub server_name: Option<String>,
+    pub cipher_suite: Option<String>,
+    /// ClientHello random (hex), the key keylog entries are matched on
+    pub client_random: String,
+    /// First candidate keylog that has secrets for this session
+    pub keylog_match: Option<String>,
+    /// The session uses RSA key exchange, so the server's private key would also work
+    pub rsa_key_possible: bool,
+}
+
+/// A keylog file found on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeylogCandidate {
+    pub path: String,
+    /// "env", "capture-dir", or "home"
+    pub source: String,
+    /// Sessions in the capture this keylog can decrypt
+    pub matching_sessions: usize,
+}
+
+/// Result of `decryption_hints`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecryptionHints {
+    pub tls_sessions: usize,
+    pub sessions: Vec<TlsSessionHint>,
+    pub keylog_candidates: Vec<KeylogCandidate>,
+    /// Keylog currently applied to sharkd, if any
+    pub active_keylog: Option<String>,
+}
+
+#[derive(Default)]
+struct HintState {
+    /// Keylog applied through `tls.keylog_file`
+    active_keylog: Option<String>,
+    /// Keylog chosen by the user, applied in preference to `SSLKEYLOGFILE`
+    configured_keylog: Option<String>,
+    /// Hints computed for the loaded capture
+    cached: Option<(String, DecryptionHints)>,
+}
+
+static STATE: OnceLock<Mutex<HintState>> = OnceLock::new();
+
+fn get_state() -> &'static Mutex<HintState> {
+    STATE.get_or_init(|| Mutex::new(HintState::default()))
+}
+
+/// Apply the keylog configured with `configure_keylog`, or else the one named
+/// by `SSLKEYLOGFILE`, if set and present.
+/// Called before loading a capture, since preferences apply to subsequent loads.
+pub async fn apply_env_keylog(client: &SharkdClient) {
+    let configured = get_state().lock().configured_keylog.clone();
+    if let Some(path) = configured {
+        if let Err(e) = client.set_pref("tls.keylog_file", &path).await {
+            eprintln!("Warning: Failed to apply TLS keylog {}: {}", path, e);
+        }
+        return;
+    }
+    let path = match std::env::var_os("SSLKEYLOGFILE").map(PathBuf::from) {
+        Some(path) if path.is_file() => path.to_string_lossy().to_string(),
+        _ => return,
+    };
+    match client.set_pref("tls.keylog_file", &path).await {
+        Ok(()) => {
+            println!("Applied TLS keylog from SSLKEYLOGFILE: {}", path);
+            get_state().lock().active_keylog = Some(path);
+        }
+        Err(e) => eprintln!("Warning: Failed to apply SSLKEYLOGFILE: {}", e),
+    }
+}
+
+/// Use `path` as the TLS keylog (`tls.keylog_file`), or stop using one with
+/// `None`. Takes effect when the capture is next loaded.
+pub async fn configure_keylog(client: &SharkdClient, path: Option<&str>) -> Result<(), String> {
+    let path = path.map(str::trim).filter(|p| !p.is_empty());
+    if let Some(path) = path {
+        if !Path::new(path).is_file() {
+            return Err(format!("Keylog file not found: {}", path));
+        }
+    }
+    client
+        .set_pref("tls.keylog_file", path.unwrap_or_default())
+        .await?;
+
+    let mut state = get_state().lock();
+    state.configured_keylog = path.map(String::from);
+    state.active_keylog = path.map(String::from);
+    state.cached = None;
+    Ok(())
+}
+
+/// Drop cached hints (called when a new capture is loaded)
+pub fn clear_cache() {
+    get_state().lock().cached = None;
+}
+
+/// Keylog currently applied to sharkd, for tools run outside it
+pub fn active_keylog() -> Option<String> {
+    get_state().lock().active_keylog.clone()
+}
+
+fn normalize_hex(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Client randoms with secrets in a keylog file (`<LABEL> <client_random> <secret>`)
+fn keylog_randoms(path: &Path) -> HashSet<String> {
+    std::fs::read_to_string(path)
+        .map(|text| {
+            text.lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| line.split_whitespace().nth(1))
+                .map(normalize_hex)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Keylog files in common locations, in priority order
+fn keylog_candidates(capture: Option<&str>) -> Vec<(PathBuf, &'static str)> {
+    let mut candidates = Vec::new();
+    if let Some(env) = std::env::var_os("SSLKEYLOGFILE") {
+        candidates.push((PathBuf::from(env), "env"));
+    }
+
+    if let Some(capture) = capture.map(Path::new) {
+        if let Some(dir) = capture.parent() {
+            // <capture>.keylog / <capture stem>.keys next to the capture
+            for ext in ["keylog", "keys"] {
+                candidates.push((capture.with_extension(ext), "capture-dir"));
+            }
+            for name in KEYLOG_NAMES {
+                candidates.push((dir.join(name), "capture-dir"));
+            }
+        }
+    }
+
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    if let Some(home) = home.map(PathBuf::from) {
+        for name in KEYLOG_NAMES {
+            candidates.push((home.join(name), "home"));
+        }
+        candidates.push((home.join("Desktop").join("sslkeylog.log"), "home"));
+    }
+
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|(path, _)| path.is_file() && seen.insert(path.clone()))
+        .collect()
+}
+
+/// TLS sessions keyed by TCP stream, from ClientHello and ServerHello frames
+async fn tls_sessions(client: &SharkdClient) -> Result<Vec<TlsSessionHint>, String> {
+    let mut sessions: BTreeMap<String, TlsSessionHint> = BTreeMap::new();
+
+    for row in client
+        .column_values(
+            "tls.handshake.type == 1",
+            &[
+                "tcp.stream",
+                "tls.handshake.extensions_server_name:1",
+                "tls.handshake.random:1",
+            ],
+            HANDSHAKE_SCAN_LIMIT,
+        )
+        .await?
+    {
+        let (stream, server_name, random) = match row.as_slice() {
+            [stream, server_name, random, ..] => (stream, server_name, random),
+            _ => continue,
+        };
+        sessions
+            .entry(stream.clone())
+            .or_insert_with(|| TlsSessionHint {
+                tcp_stream: stream.clone(),
+                server_name: Some(server_name.clone()).filter(|s| !s.is_empty()),
+                cipher_suite: None,
+                client_random: normalize_hex(random),
+                keylog_match: None,
+                rsa_key_possible: false,
+            });
+    }
+
+    for row in client
+        .column_values(
+            "tls.handshake.type == 2",
+            &[
+                "tcp.stream",
+                "tls.handshake.ciphersuite:1",
+                "tls.handshake.extensions.supported_version:1",
+            ],
+            HANDSHAKE_SCAN_LIMIT,
+        )
+        .await?
+    {
+        let (stream, cipher, version) = match row.as_slice() {
+            [stream, cipher, version, ..] => (stream, cipher, version),
+            _ => continue,
+        };
+        if let Some(session) = sessions.get_mut(stream) {
+            let tls13 = version.contains("1.3")
+                || cipher.starts_with("TLS_AES")
+                || cipher.starts_with("TLS_CHACHA20");
+            session.rsa_key_possible = !tls13 && cipher.starts_with("TLS_RSA_");
+            session.cipher_suite = Some(cipher.clone()).filter(|c| !c.is_empty());
+        }
+    }
+
+    Ok(sessions.into_values().collect())
+}
+
+async fn compute_hints(
+    client: &SharkdClient,
+    capture: Option<&str>,
+) -> Result<DecryptionHints, String> {
+    let mut sessions = tls_sessions(client).await?;
+
+    let mut candidates = Vec::new();
+    if !sessions.is_empty() {
+        for (path, source) in keylog_candidates(capture) {
+            let randoms = keylog_randoms(&path);
+            let path = path.to_string_lossy().to_string();
+            let mut matching_sessions = 0;
+            for session in sessions.iter_mut() {
+                if randoms.contains(&session.client_random) {
+                    matching_sessions += 1;
+                    session.keylog_match.get_or_insert_with(|| path.clone());
+                }
+            }
+            candidates.push(KeylogCandidate {
+                path,
+                source: source.to_string(),
+                matching_sessions,
+            });
+        }
+    }
+
+    Ok(DecryptionHints {
+        tls_sessions: sessions.len(),
+        sessions,
+        keylog_candidates: candidates,
+        active_keylog: get_state().lock().active_keylog.clone(),
+    })
+}
+
+/// TLS sessions in the loaded capture and which of them could be decrypted
+/// with keylogs found on disk. Cached per capture.
+pub async fn decryption_hints(client: &SharkdClient) -> Result<DecryptionHints, String> {
+    let capture = client.loaded_file();
+    let key = capture.clone().unwrap_or_default();
+    if let Some((cached_for, hints)) = &get_state().lock().cached {
+        if *cached_for == key {
+            return Ok(hints.clone());
+        }
+    }
+
+    let hints = compute_hints(client, capture.as_deref()).await?;
+    get_state().lock().cached = Some((key, hints.clone()));
+    Ok(hints)
+}
@@ -0,0 +1,221 @@
+//! Decryption keys for protocols Wireshark decrypts from UAT tables: IPsec
+//! ESP security associations, 802.11 WEP/WPA keys, and SNMPv3 USM users.
+//!
+//! Keys are persisted and pushed to sharkd with `setconf` on `uat:<table>`,
+//! which appends one record per call. Since records can't be removed from a
+//! running sharkd, changing the key set restarts it.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::sharkd_client::SharkdClient;
+use crate::storage;
+
+pub const STORE_NAME: &str = "decryption-keys";
+
+/// 802.11 key types, named as in Wireshark's `80211_keys` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WlanKeyType {
+    #[serde(rename = "wep")]
+    Wep,
+    /// `passphrase[:ssid]`
+    #[serde(rename = "wpa-pwd")]
+    WpaPwd,
+    /// Pre-shared key as 64 hex digits
+    #[serde(rename = "wpa-psk")]
+    WpaPsk,
+}
+
+impl WlanKeyType {
+    fn as_str(self) -> &'static str {
+        match self {
+            WlanKeyType::Wep => "wep",
+            WlanKeyType::WpaPwd => "wpa-pwd",
+            WlanKeyType::WpaPsk => "wpa-psk",
+        }
+    }
+}
+
+/// Kind-specific key material
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KeyKind {
+    /// IPsec ESP security association. Algorithms use Wireshark's names,
+    /// e.g. `AES-CBC [RFC3602]` and `HMAC-SHA-256-128 [RFC4868]`.
+    IpsecSa {
+        /// "IPv4", "IPv6", or "Any IP"
+        protocol: String,
+        /// Source/destination address, `*` for any
+        src: String,
+        dst: String,
+        /// SPI as hex (`0x...`), `*` for any
+        spi: String,
+        encryption: String,
+        #[serde(default)]
+        encryption_key: String,
+        authentication: String,
+        #[serde(default)]
+        authentication_key: String,
+    },
+    /// 802.11 WEP or WPA key
+    Wlan { key_type: WlanKeyType, key: String },
+    /// SNMPv3 USM user
+    SnmpUser {
+        /// Engine ID as hex, empty for any engine
+        #[serde(default)]
+        engine_id: String,
+        username: String,
+        /// "MD5", "SHA1", "SHA2-224", ...
+        auth_model: String,
+        auth_password: String,
+        /// "DES", "AES", "AES192", or "AES256"
+        #[serde(default)]
+        priv_proto: String,
+        #[serde(default)]
+        priv_password: String,
+    },
+}
+
+/// A stored decryption key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecryptionKeyConfig {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: KeyKind,
+}
+
+impl KeyKind {
+    /// UAT table and record fields for this key
+    fn uat_record(&self) -> (&'static str, Vec<&str>) {
+        match self {
+            KeyKind::IpsecSa {
+                protocol,
+                src,
+                dst,
+                spi,
+                encryption,
+                encryption_key,
+                authentication,
+                authentication_key,
+            } => (
+                "esp_sa",
+                vec![
+                    protocol.as_str(),
+                    src.as_str(),
+                    dst.as_str(),
+                    spi.as_str(),
+                    encryption.as_str(),
+                    encryption_key.as_str(),
+                    authentication.as_str(),
+                    authentication_key.as_str(),
+                ],
+            ),
+            KeyKind::Wlan { key_type, key } => {
+                ("80211_keys", vec![key_type.as_str(), key.as_str()])
+            }
+            KeyKind::SnmpUser {
+                engine_id,
+                username,
+                auth_model,
+                auth_password,
+                priv_proto,
+                priv_password,
+            } => (
+                "snmp_users",
+                vec![
+                    engine_id.as_str(),
+                    username.as_str(),
+                    auth_model.as_str(),
+                    auth_password.as_str(),
+                    priv_proto.as_str(),
+                    priv_password.as_str(),
+                ],
+            ),
+        }
+    }
+
+    /// Boolean preference that must be on for the key to be used
+    fn enable_pref(&self) -> Option<&'static str> {
+        match self {
+            KeyKind::IpsecSa { .. } => Some("esp.enable_encryption_decode"),
+            KeyKind::Wlan { .. } => Some("wlan.enable_decryption"),
+            KeyKind::SnmpUser { .. } => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            KeyKind::IpsecSa { spi, .. } if spi.trim().is_empty() => {
+                Err("An IPsec SA needs an SPI (or * for any)".to_string())
+            }
+            KeyKind::Wlan { key, .. } if key.trim().is_empty() => {
+                Err("A wireless key can't be empty".to_string())
+            }
+            KeyKind::SnmpUser { username, .. } if username.trim().is_empty() => {
+                Err("An SNMP user needs a user name".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyStore {
+    keys: Vec<DecryptionKeyConfig>,
+}
+
+static KEYS: OnceLock<Mutex<KeyStore>> = OnceLock::new();
+
+fn get_keys() -> &'static Mutex<KeyStore> {
+    KEYS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_keys().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Configured decryption keys
+pub fn list_keys() -> Vec<DecryptionKeyConfig> {
+    get_keys().lock().keys.clone()
+}
+
+/// Add or replace (by id) a decryption key
+pub fn save_key(config: DecryptionKeyConfig) -> Result<(), String> {
+    config.kind.validate()?;
+    let mut store = get_keys().lock();
+    store.keys.retain(|k| k.id != config.id);
+    store.keys.push(config);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove a decryption key
+pub fn remove_key(id: &str) -> Result<(), String> {
+    let mut store = get_keys().lock();
+    store.keys.retain(|k| k.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Push every stored key to a freshly spawned sharkd
+pub async fn apply_keys(client: &SharkdClient) {
+    let keys = list_keys();
+
+    let mut enable: Vec<&str> = keys.iter().filter_map(|k| k.kind.enable_pref()).collect();
+    enable.sort_unstable();
+    enable.dedup();
+    for pref in enable {
+        if let Err(e) = client.set_pref(pref, "TRUE").await {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    for key in &keys {
+        let (table, fields) = key.kind.uat_record();
+        if let Err(e) = client.add_uat_record(table, &fields).await {
+            eprintln!("Warning: Failed to apply decryption key {}: {}", key.id, e);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Frame dissections exported as Wireshark-compatible PDML, JSON, or the
+//! printable text of the packet details pane, for pasting into tickets and
+//! reports. Produced by tshark, so they match what Wireshark exports.
+
+use serde::{Deserialize, Serialize};
+
+use crate::selection::FrameRange;
+use crate::wireshark_tools;
+
+/// Most frames exported in one dissection
+const MAX_DISSECTION_FRAMES: u32 = 10_000;
+
+/// Output format of a dissection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DissectionFormat {
+    Pdml,
+    Json,
+    /// Packet details as printed by Wireshark ("File > Export Packet Dissections > As Plain Text")
+    #[default]
+    Text,
+}
+
+impl DissectionFormat {
+    fn tshark_args(self) -> &'static [&'static str] {
+        match self {
+            DissectionFormat::Pdml => &["-T", "pdml"],
+            DissectionFormat::Json => &["-T", "json"],
+            DissectionFormat::Text => &["-T", "text", "-V"],
+        }
+    }
+}
+
+/// A dissection, written to a file or returned inline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DissectionExport {
+    pub format: DissectionFormat,
+    pub range: FrameRange,
+    /// Where the dissection was written, when a destination was given
+    pub path: Option<String>,
+    /// The dissection, when no destination was given
+    pub content: Option<String>,
+}
+
+/// Dissect the frames of `source` in `range` that also match `filter` (e.g.
+/// the ignored-frame exclusion). `include_bytes` adds the hex dump of each
+/// frame (text and JSON only; PDML always carries the bytes).
+pub fn dissect_frames(
+    source: &str,
+    range: FrameRange,
+    format: DissectionFormat,
+    filter: Option<&str>,
+    include_bytes: bool,
+) -> Result<String, String> {
+    if range.end - range.start >= MAX_DISSECTION_FRAMES {
+        return Err(format!(
+            "Too many frames to export at once (at most {})",
+            MAX_DISSECTION_FRAMES
+        ));
+    }
+    let display_filter = match filter.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(f) => format!("({}) && ({})", range.filter(), f),
+        None => range.filter(),
+    };
+
+    let mut args = vec![
+        "-r".to_string(),
+        source.to_string(),
+        "-Y".to_string(),
+        display_filter,
+    ];
+    args.extend(format.tshark_args().iter().map(|a| a.to_string()));
+    if include_bytes && format != DissectionFormat::Pdml {
+        args.push("-x".to_string());
+    }
+    wireshark_tools::run_tool("tshark", &args)
+}
@@ -0,0 +1,311 @@
+//! Evidence-handling session mode.
+//!
+//! In read-only mode the command layer refuses everything that modifies
+//! capture data, writes derived captures (edits, merges, splits, excerpts,
+//! selections, anonymized copies), or changes persistent analysis
+//! configuration: comments, time references, columns and layouts, saved
+//! filters and queries, dashboards, automations, capture tags, notification
+//! sinks, decryption keys and TLS keylogs, protobuf settings, coloring
+//! rules, preferences, imports and backup restores, and undo/redo (which can
+//! restore comments and columns). Marked and ignored frames, filter history,
+//! sessions, and app-level settings (external tools, upload destinations,
+//! redaction and anonymization policies) stay writable. While it is on,
+//! opens, closes, and exports are also written to a signed chain-of-custody
+//! log.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+
+use crate::{capture_file, storage};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable read-only mode
+pub fn set_read_only(enabled: bool) {
+    // Record the transition while evidence mode is (still) on
+    if !enabled {
+        record("evidence-mode", "", false, Some("disabled".to_string()));
+    }
+    READ_ONLY.store(enabled, Ordering::SeqCst);
+    if enabled {
+        record("evidence-mode", "", false, Some("enabled".to_string()));
+    }
+    println!("Read-only mode {}", if enabled { "enabled" } else { "disabled" });
+    crate::emit_event("read-only-changed", enabled);
+}
+
+/// Whether read-only mode is on
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// Refuse a mutating operation while read-only mode is on
+pub fn ensure_writable(operation: &str) -> Result<(), String> {
+    if is_read_only() {
+        return Err(format!("{} is disabled in read-only mode", operation));
+    }
+    Ok(())
+}
+
+// ============================================
+// Chain of custody
+// ============================================
+
+/// Custody log file (JSON Lines, append-only)
+const CUSTODY_LOG_FILE: &str = "custody-log.jsonl";
+
+/// Machine key used to sign custody entries
+const CUSTODY_KEY_FILE: &str = "custody.key";
+
+/// A single chain-of-custody record. Each entry's signature covers the
+/// previous entry's signature, so removing or editing entries breaks the chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyEntry {
+    pub seq: u64,
+    /// Unix timestamp (seconds)
+    pub timestamp: u64,
+    /// "evidence-mode", "open", "close", or "export"
+    pub event: String,
+    pub path: String,
+    /// SHA256 of the file at `path`, when applicable
+    pub sha256: Option<String>,
+    pub detail: Option<String>,
+    pub prev_signature: String,
+    /// Hex HMAC-SHA256 over the entry (excluding this field) with the machine key
+    pub signature: String,
+}
+
+/// The custody log with the result of verifying its signature chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyLog {
+    pub entries: Vec<CustodyEntry>,
+    /// Every signature is valid and links to its predecessor
+    pub verified: bool,
+}
+
+/// Serializes appends so sequence numbers and the chain stay consistent
+static CUSTODY_LOCK: Mutex<()> = Mutex::new(());
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block_key = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block_key.map(|b| b ^ 0x36));
+    inner.update(message);
+    let inner_hash = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block_key.map(|b| b ^ 0x5c));
+    outer.update(inner_hash);
+    outer.finalize().into()
+}
+
+/// Load the machine key, generating it on first use. A key file of the
+/// wrong size is an error rather than replaced, since a new key would make
+/// every existing entry fail verification.
+fn machine_key() -> Result<Vec<u8>, String> {
+    let path = storage::data_dir()?.join(CUSTODY_KEY_FILE);
+    match std::fs::read(&path) {
+        Ok(key) if key.len() == 32 => return Ok(key),
+        Ok(key) => {
+            return Err(format!(
+                "Custody key {:?} is corrupt ({} bytes, expected 32)",
+                path,
+                key.len()
+            ))
+        }
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("Failed to read custody key: {}", e))
+        }
+        Err(_) => {}
+    }
+    let mut key = vec![0u8; 32];
+    getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate key: {}", e))?;
+    std::fs::write(&path, &key).map_err(|e| format!("Failed to store custody key: {}", e))?;
+    Ok(key)
+}
+
+fn sign_entry(key: &[u8], entry: &CustodyEntry) -> String {
+    let payload = format!(
+        "{}|{}|{}|{}|{}|{}|{}",
+        entry.seq,
+        entry.timestamp,
+        entry.event,
+        entry.path,
+        entry.sha256.as_deref().unwrap_or(""),
+        entry.detail.as_deref().unwrap_or(""),
+        entry.prev_signature
+    );
+    hex(&hmac_sha256(key, payload.as_bytes()))
+}
+
+fn read_entries() -> Result<Vec<CustodyEntry>, String> {
+    let path = storage::data_dir()?.join(CUSTODY_LOG_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return Ok(Vec::new()),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| format!("Corrupt custody log entry: {}", e))
+        })
+        .collect()
+}
+
+fn append_entry(
+    event: &str,
+    path: &str,
+    timestamp: u64,
+    sha256: Option<String>,
+    detail: Option<String>,
+) -> Result<(), String> {
+    let _guard = CUSTODY_LOCK.lock();
+    let key = machine_key()?;
+    let last = read_entries()?.pop();
+
+    let mut entry = CustodyEntry {
+        seq: last.as_ref().map(|e| e.seq + 1).unwrap_or(1),
+        timestamp,
+        event: event.to_string(),
+        path: path.to_string(),
+        sha256,
+        detail,
+        prev_signature: last.map(|e| e.signature).unwrap_or_default(),
+        signature: String::new(),
+    };
+    entry.signature = sign_entry(&key, &entry);
+
+    let line = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize custody entry: {}", e))?;
+    let log_path = storage::data_dir()?.join(CUSTODY_LOG_FILE);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("Failed to open custody log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to append custody log: {}", e))
+}
+
+/// Work for the custody log thread
+enum CustodyJob {
+    Record {
+        event: String,
+        path: String,
+        timestamp: u64,
+        hash: bool,
+        detail: Option<String>,
+    },
+    /// Reply once every earlier job is done
+    Flush(mpsc::Sender<()>),
+}
+
+static CUSTODY_QUEUE: OnceLock<Mutex<mpsc::Sender<CustodyJob>>> = OnceLock::new();
+
+/// Hashing large captures takes a while, so events are hashed and appended
+/// on a thread of their own, one at a time and in the order they happened
+fn custody_queue() -> &'static Mutex<mpsc::Sender<CustodyJob>> {
+    CUSTODY_QUEUE.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for job in rx {
+                match job {
+                    CustodyJob::Record {
+                        event,
+                        path,
+                        timestamp,
+                        hash,
+                        detail,
+                    } => {
+                        let sha256 = if hash {
+                            capture_file::sha256_file(&path).ok()
+                        } else {
+                            None
+                        };
+                        if let Err(e) = append_entry(&event, &path, timestamp, sha256, detail) {
+                            eprintln!("Warning: Failed to record custody event: {}", e);
+                        }
+                    }
+                    CustodyJob::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Mutex::new(tx)
+    })
+}
+
+/// Record a custody event if evidence mode is on, hashing `path` when asked.
+/// Failures are logged rather than failing the operation being recorded.
+fn record(event: &str, path: &str, hash: bool, detail: Option<String>) {
+    if !is_read_only() {
+        return;
+    }
+    let job = CustodyJob::Record {
+        event: event.to_string(),
+        path: path.to_string(),
+        timestamp: storage::now_secs(),
+        hash,
+        detail,
+    };
+    if custody_queue().lock().send(job).is_err() {
+        eprintln!("Warning: Failed to record custody event: the custody log thread stopped");
+    }
+}
+
+/// Wait until every custody event recorded so far is in the log
+pub fn flush() {
+    let (done, wait) = mpsc::channel();
+    if custody_queue().lock().send(CustodyJob::Flush(done)).is_ok() {
+        let _ = wait.recv();
+    }
+}
+
+/// Record that a capture was opened (with its hash)
+pub fn record_open(path: &str) {
+    record("open", path, true, None);
+}
+
+/// Record that a capture was closed (with its hash, to show it is unchanged)
+pub fn record_close(path: &str) {
+    record("close", path, true, None);
+}
+
+/// Record an export, hashing the written output
+pub fn record_export(output_path: &str, kind: &str) {
+    record("export", output_path, true, Some(kind.to_string()));
+}
+
+/// Read the custody log and verify its signature chain
+pub fn get_custody_log() -> Result<CustodyLog, String> {
+    let _guard = CUSTODY_LOCK.lock();
+    let entries = read_entries()?;
+    let verified = if entries.is_empty() {
+        true
+    } else {
+        let key = machine_key()?;
+        let mut prev = String::new();
+        entries.iter().all(|entry| {
+            let ok = entry.prev_signature == prev && sign_entry(&key, entry) == entry.signature;
+            prev = entry.signature.clone();
+            ok
+        })
+    };
+    Ok(CustodyLog { entries, verified })
+}
@@ -0,0 +1,197 @@
+//! Handing captures off to external programs (Wireshark GUI, Zeek, Suricata,
+//! ...) for the analysis PacketPilot doesn't do itself.
+//!
+//! The chosen scope is written to a fresh work directory, and the tool is
+//! launched there so that tools which write logs to the current directory
+//! (Zeek, Suricata) keep them next to the capture.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use crate::cache_manager::{self, CacheCategory};
+use crate::{capture_edit, frame_marks, selection, storage, wireshark_tools};
+
+pub const STORE_NAME: &str = "external-tools";
+
+/// An external program a capture can be opened in. `{file}` and `{dir}` in
+/// `args` are replaced by the exported capture and its work directory; when
+/// `{file}` doesn't appear, the capture is passed as the last argument.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalTool {
+    pub id: String,
+    pub name: String,
+    /// Program name (looked up like the Wireshark tools) or full path
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Which frames are handed to the tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolScope {
+    /// The whole capture (minus ignored frames)
+    #[default]
+    Capture,
+    /// The current frame selection
+    Selection,
+    /// Frames matching a display filter
+    Filter { filter: String },
+}
+
+/// A launched external tool
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalLaunch {
+    pub tool: String,
+    /// Capture handed to the tool
+    pub capture_path: String,
+    /// Directory the tool runs in (and writes its logs to)
+    pub work_dir: String,
+    pub pid: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ToolStore {
+    tools: Vec<ExternalTool>,
+}
+
+impl Default for ToolStore {
+    fn default() -> Self {
+        let tool = |id: &str, name: &str, program: &str, args: &[&str]| ExternalTool {
+            id: id.to_string(),
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        };
+        ToolStore {
+            tools: vec![
+                tool("wireshark", "Wireshark", "wireshark", &["-r", "{file}"]),
+                tool("zeek", "Zeek", "zeek", &["-C", "-r", "{file}"]),
+                tool(
+                    "suricata",
+                    "Suricata",
+                    "suricata",
+                    &["-r", "{file}", "-l", "{dir}"],
+                ),
+            ],
+        }
+    }
+}
+
+static TOOLS: OnceLock<Mutex<ToolStore>> = OnceLock::new();
+
+fn get_tools() -> &'static Mutex<ToolStore> {
+    TOOLS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_tools().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Configured external tools
+pub fn list_tools() -> Vec<ExternalTool> {
+    get_tools().lock().tools.clone()
+}
+
+/// Add or replace (by id) an external tool
+pub fn save_tool(tool: ExternalTool) -> Result<(), String> {
+    if tool.program.trim().is_empty() {
+        return Err(format!("Tool '{}' needs a program", tool.name));
+    }
+    let mut store = get_tools().lock();
+    store.tools.retain(|t| t.id != tool.id);
+    store.tools.push(tool);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove an external tool
+pub fn remove_tool(id: &str) -> Result<(), String> {
+    let mut store = get_tools().lock();
+    store.tools.retain(|t| t.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Write the frames in `scope` to `dest`, with `session`'s ignored frames
+/// left out
+fn export_scope(session: &str, source: &str, scope: &ToolScope, dest: &str) -> Result<(), String> {
+    match scope {
+        ToolScope::Capture => match frame_marks::with_exclusions(session, None) {
+            Some(filter) => capture_edit::write_filtered(source, &filter, dest),
+            None => std::fs::copy(source, dest)
+                .map(|_| ())
+                .map_err(|e| format!("Failed to copy capture to {}: {}", dest, e)),
+        },
+        ToolScope::Selection => selection::export_selection(session, source, dest).map(|_| ()),
+        ToolScope::Filter { filter } => {
+            let filter = frame_marks::with_exclusions(session, Some(filter)).unwrap_or_default();
+            capture_edit::write_filtered(source, &filter, dest)
+        }
+    }
+}
+
+/// Export `scope` of the capture at `source` (open in `session`) and launch
+/// the tool on it
+pub fn open_in_external_tool(
+    tool_id: &str,
+    scope: &ToolScope,
+    session: &str,
+    source: &str,
+) -> Result<ExternalLaunch, String> {
+    let tool = list_tools()
+        .into_iter()
+        .find(|t| t.id == tool_id)
+        .ok_or_else(|| format!("Unknown external tool: {}", tool_id))?;
+
+    let work_dir = cache_manager::new_entry(
+        CacheCategory::ExternalTools,
+        &format!("{}-{}", tool.id, storage::now_secs()),
+        Some(source),
+    )?;
+    std::fs::create_dir_all(&work_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", work_dir, e))?;
+    let capture_path = work_dir.join("capture.pcapng").display().to_string();
+    let work_dir = work_dir.display().to_string();
+    export_scope(session, source, scope, &capture_path)?;
+
+    let mut args: Vec<String> = tool
+        .args
+        .iter()
+        .map(|a| {
+            a.replace("{file}", &capture_path)
+                .replace("{dir}", &work_dir)
+        })
+        .collect();
+    if !tool.args.iter().any(|a| a.contains("{file}")) {
+        args.push(capture_path.clone());
+    }
+
+    // Bare program names may be Wireshark tools installed outside PATH
+    let program: PathBuf = if Path::new(&tool.program).components().count() > 1 {
+        tool.program.clone().into()
+    } else {
+        wireshark_tools::find_tool(&tool.program).unwrap_or_else(|_| tool.program.clone().into())
+    };
+
+    let mut child = Command::new(&program)
+        .args(&args)
+        .current_dir(&work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to launch {}: {}", tool.name, e))?;
+    let pid = child.id();
+    // Reap the process when it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+
+    Ok(ExternalLaunch {
+        tool: tool.name,
+        capture_path,
+        work_dir,
+        pid,
+    })
+}
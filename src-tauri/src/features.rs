@@ -0,0 +1,154 @@
+//! One map of which features work in this installation, combining sharkd's
+//! `info` (its taps and follow protocols vary by Wireshark version), install
+//! health, the Wireshark tools found, read-only mode, and the AI sidecar.
+//!
+//! Served to the frontend and, through `/capabilities`, to the sidecar, so
+//! neither has to find out by calling a command and seeing it fail.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::{evidence, python_sidecar, sharkd_client, wireshark_tools};
+
+/// Follow protocols always listed, as unavailable when sharkd lacks them
+const FOLLOW_PROTOCOLS: &[&str] = &["tcp", "udp", "http", "http2", "tls", "quic", "websocket"];
+
+/// sharkd taps backing optional analyses: (feature, tap)
+const TAP_FEATURES: &[(&str, &str)] = &[
+    ("expert_info", "expert"),
+    ("rtp_streams", "rtp-streams"),
+    ("voip_calls", "voip-calls"),
+];
+
+/// Wireshark tools backing optional features: (feature, tool)
+const TOOL_FEATURES: &[(&str, &str)] = &[
+    ("capture_editing", "editcap"),
+    ("capture_filter_check", "dumpcap"),
+    ("capture_info", "capinfos"),
+    ("capture_merge", "mergecap"),
+    ("field_catalog", "tshark"),
+];
+
+/// Features that change files and are refused in read-only mode
+const MUTATING_FEATURES: &[&str] = &["capture_editing"];
+
+/// Whether a feature can be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureStatus {
+    Available,
+    /// Not supported by this sharkd/Wireshark version or not running
+    Unavailable,
+    /// A required program (sharkd, tshark, ...) wasn't found
+    NeedsInstall,
+    /// Turned off by read-only (evidence-handling) mode
+    Disabled,
+}
+
+/// Feature availability
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureFlags {
+    /// Wireshark version sharkd reports
+    pub sharkd_version: Option<String>,
+    pub read_only: bool,
+    pub features: BTreeMap<String, FeatureStatus>,
+}
+
+fn available(yes: bool) -> FeatureStatus {
+    if yes {
+        FeatureStatus::Available
+    } else {
+        FeatureStatus::Unavailable
+    }
+}
+
+/// Names of the entries of a list in sharkd's `info` response
+fn info_taps<'a>(info: &'a Value, list: &str) -> Vec<&'a str> {
+    info.get(list)
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|e| e.get("tap").and_then(Value::as_str))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Work out which features are usable right now
+pub async fn feature_flags() -> FeatureFlags {
+    let info = match crate::sharkd(None) {
+        Ok(client) => client.info().await.ok(),
+        Err(_) => None,
+    };
+
+    // Filesystem and process checks block; keep them off the async runtime
+    let (install_ok, tools, sidecar_running) = tauri::async_runtime::spawn_blocking(|| {
+        let tools: Vec<(&str, bool)> = TOOL_FEATURES
+            .iter()
+            .map(|(feature, tool)| (*feature, wireshark_tools::find_tool(tool).is_ok()))
+            .collect();
+        (
+            sharkd_client::get_install_health().ok,
+            tools,
+            python_sidecar::get_sidecar_status().is_running,
+        )
+    })
+    .await
+    .unwrap_or((false, Vec::new(), false));
+
+    let read_only = evidence::is_read_only();
+    let mut features = BTreeMap::new();
+
+    let sharkd = match (&info, install_ok) {
+        (Some(_), _) => FeatureStatus::Available,
+        (None, true) => FeatureStatus::Unavailable,
+        (None, false) => FeatureStatus::NeedsInstall,
+    };
+    features.insert("sharkd".to_string(), sharkd);
+
+    let info = info.unwrap_or(Value::Null);
+    let follow = info_taps(&info, "follow");
+    for protocol in FOLLOW_PROTOCOLS {
+        let tap = format!("follow:{}", protocol);
+        features.insert(
+            format!("follow_{}", protocol),
+            available(follow.contains(&tap.as_str())),
+        );
+    }
+    let taps = info_taps(&info, "taps");
+    for (feature, tap) in TAP_FEATURES {
+        features.insert(feature.to_string(), available(taps.contains(tap)));
+    }
+    features.insert(
+        "export_objects".to_string(),
+        available(!info_taps(&info, "eo").is_empty()),
+    );
+    features.insert(
+        "io_graph".to_string(),
+        available(sharkd == FeatureStatus::Available),
+    );
+
+    for (feature, found) in tools {
+        let status = if !found {
+            FeatureStatus::NeedsInstall
+        } else if read_only && MUTATING_FEATURES.contains(&feature) {
+            FeatureStatus::Disabled
+        } else {
+            FeatureStatus::Available
+        };
+        features.insert(feature.to_string(), status);
+    }
+
+    features.insert("ai_sidecar".to_string(), available(sidecar_running));
+
+    FeatureFlags {
+        sharkd_version: info
+            .get("version")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        read_only,
+        features,
+    }
+}
@@ -0,0 +1,251 @@
+//! Catalog of dissector fields from `tshark -G fields` / `tshark -G values`.
+//!
+//! Built once and cached in memory and on disk (keyed by the tshark version,
+//! so a Wireshark upgrade triggers a rebuild). Used to validate and explain
+//! display filters and as the filter bar's autocomplete fallback.
+//!
+//! The on-disk copy is a JSON store like the app's other settings rather
+//! than a SQLite database: the catalog is only ever read whole into memory
+//! and searched there, so a database would add a native dependency without
+//! serving any query.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::{storage, wireshark_tools};
+
+const STORE_NAME: &str = "field-catalog";
+
+/// Default number of results returned by `search_fields`
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// A named value of an enumerated/boolean field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldValue {
+    pub value: String,
+    pub label: String,
+}
+
+/// Metadata for a single display filter field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldInfo {
+    /// Filter name, e.g. `tcp.flags.syn`
+    pub name: String,
+    /// Human-readable name, e.g. "Syn"
+    pub display_name: String,
+    /// Field type, e.g. `FT_BOOLEAN`
+    pub field_type: String,
+    /// Abbreviation of the protocol the field belongs to
+    pub parent_protocol: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<FieldValue>,
+}
+
+/// All known fields, keyed by filter name, plus protocol names
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FieldCatalog {
+    /// First line of `tshark --version` the catalog was built with
+    pub version: String,
+    pub fields: HashMap<String, FieldInfo>,
+    /// Protocol abbreviation -> descriptive name
+    pub protocols: HashMap<String, String>,
+}
+
+static CATALOG: OnceLock<Mutex<Option<Arc<FieldCatalog>>>> = OnceLock::new();
+
+fn get_catalog_state() -> &'static Mutex<Option<Arc<FieldCatalog>>> {
+    CATALOG.get_or_init(|| Mutex::new(None))
+}
+
+/// Parse `tshark -G fields` output.
+/// Protocol lines: `P  name  abbrev`; field lines:
+/// `F  name  abbrev  type  parent  base  bitmask  blurb`
+fn parse_fields(text: &str, catalog: &mut FieldCatalog) {
+    for line in text.lines() {
+        let cols: Vec<&str> = line.split('\t').collect();
+        match cols.as_slice() {
+            ["P", name, abbrev, ..] => {
+                catalog
+                    .protocols
+                    .insert(abbrev.to_string(), name.to_string());
+            }
+            ["F", name, abbrev, field_type, parent, rest @ ..] => {
+                let description = rest.get(2).copied().unwrap_or("").to_string();
+                catalog.fields.insert(
+                    abbrev.to_string(),
+                    FieldInfo {
+                        name: abbrev.to_string(),
+                        display_name: name.to_string(),
+                        field_type: field_type.to_string(),
+                        parent_protocol: parent.to_string(),
+                        description,
+                        values: Vec::new(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse `tshark -G values` output into the catalog's fields.
+/// `V  field  value  label`, `R  field  low  high  label`, `T  field  true  false`
+fn parse_values(text: &str, catalog: &mut FieldCatalog) {
+    for line in text.lines() {
+        let cols: Vec<&str> = line.split('\t').collect();
+        let (field, values) = match cols.as_slice() {
+            ["V", field, value, label, ..] => (*field, vec![(value.to_string(), label.to_string())]),
+            ["R", field, low, high, label, ..] => {
+                (*field, vec![(format!("{}-{}", low, high), label.to_string())])
+            }
+            ["T", field, true_label, false_label, ..] => (
+                *field,
+                vec![
+                    ("1".to_string(), true_label.to_string()),
+                    ("0".to_string(), false_label.to_string()),
+                ],
+            ),
+            _ => continue,
+        };
+        if let Some(info) = catalog.fields.get_mut(field) {
+            info.values.extend(
+                values
+                    .into_iter()
+                    .map(|(value, label)| FieldValue { value, label }),
+            );
+        }
+    }
+}
+
+fn tshark_version() -> Result<String, String> {
+    let output = wireshark_tools::run_tool("tshark", &["--version".to_string()])?;
+    Ok(output.lines().next().unwrap_or("").trim().to_string())
+}
+
+fn build_catalog(version: String) -> Result<FieldCatalog, String> {
+    let mut catalog = FieldCatalog {
+        version,
+        ..FieldCatalog::default()
+    };
+    let fields = wireshark_tools::run_tool("tshark", &["-G".to_string(), "fields".to_string()])?;
+    parse_fields(&fields, &mut catalog);
+
+    // Value strings are optional extras; keep the catalog if they fail
+    match wireshark_tools::run_tool("tshark", &["-G".to_string(), "values".to_string()]) {
+        Ok(values) => parse_values(&values, &mut catalog),
+        Err(e) => eprintln!("Warning: Failed to load field values: {}", e),
+    }
+
+    println!(
+        "Field catalog built: {} fields, {} protocols",
+        catalog.fields.len(),
+        catalog.protocols.len()
+    );
+    if let Err(e) = storage::write_json(STORE_NAME, &catalog) {
+        eprintln!("Warning: Failed to store field catalog: {}", e);
+    }
+    Ok(catalog)
+}
+
+/// Load the stored catalog if it matches the installed tshark, else build it
+fn load_or_build(force_rebuild: bool) -> Result<FieldCatalog, String> {
+    let version = tshark_version()?;
+    if !force_rebuild {
+        let stored: FieldCatalog = storage::load_json(STORE_NAME);
+        if stored.version == version && !stored.fields.is_empty() {
+            return Ok(stored);
+        }
+    }
+    build_catalog(version)
+}
+
+/// Get the field catalog, loading or building it on first use
+pub fn get_catalog() -> Result<Arc<FieldCatalog>, String> {
+    // Hold the lock while building so concurrent callers don't run tshark twice
+    let mut state = get_catalog_state().lock();
+    if let Some(catalog) = state.as_ref() {
+        return Ok(catalog.clone());
+    }
+    let catalog = Arc::new(load_or_build(false)?);
+    *state = Some(catalog.clone());
+    Ok(catalog)
+}
+
+/// Rebuild the catalog from tshark, replacing the cached copy
+pub fn rebuild_catalog() -> Result<Arc<FieldCatalog>, String> {
+    let mut state = get_catalog_state().lock();
+    let catalog = Arc::new(load_or_build(true)?);
+    *state = Some(catalog.clone());
+    Ok(catalog)
+}
+
+/// Build the catalog in the background so the first lookup is fast
+pub fn warm() {
+    std::thread::spawn(|| {
+        if let Err(e) = get_catalog() {
+            eprintln!("Warning: Failed to build field catalog: {}", e);
+        }
+    });
+}
+
+/// Look up a single field by filter name
+pub fn field_info(name: &str) -> Result<Option<FieldInfo>, String> {
+    Ok(get_catalog()?.fields.get(name.trim()).cloned())
+}
+
+/// Every field in the catalog sorted by name, optionally only those of one
+/// protocol (by abbreviation, e.g. `tcp`)
+pub fn list_fields(protocol: Option<&str>) -> Result<Vec<FieldInfo>, String> {
+    let catalog = get_catalog()?;
+    let protocol = protocol.map(str::trim).filter(|p| !p.is_empty());
+    let mut fields: Vec<FieldInfo> = catalog
+        .fields
+        .values()
+        .filter(|f| protocol.is_none_or(|p| f.parent_protocol == p))
+        .cloned()
+        .collect();
+    fields.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(fields)
+}
+
+/// Search fields by name, display name, or description (case-insensitive).
+/// Exact and prefix matches on the filter name rank first.
+pub fn search_fields(query: &str, limit: usize) -> Result<Vec<FieldInfo>, String> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let catalog = get_catalog()?;
+
+    let mut matches: Vec<(u8, &FieldInfo)> = catalog
+        .fields
+        .values()
+        .filter_map(|info| {
+            let name = info.name.to_lowercase();
+            let rank = if name == query {
+                0
+            } else if name.starts_with(&query) {
+                1
+            } else if name.contains(&query) {
+                2
+            } else if info.display_name.to_lowercase().contains(&query)
+                || info.description.to_lowercase().contains(&query)
+            {
+                3
+            } else {
+                return None;
+            };
+            Some((rank, info))
+        })
+        .collect();
+
+    matches.sort_by(|(rank_a, a), (rank_b, b)| rank_a.cmp(rank_b).then_with(|| a.name.cmp(&b.name)));
+    Ok(matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, info)| info.clone())
+        .collect())
+}
@@ -0,0 +1,235 @@
+//! Side-by-side comparison of two display filters within one capture, e.g.
+//! traffic before and after an incident, to isolate what changed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::frame_marks;
+use crate::sharkd_client::{Conversation, ProtocolNode, SharkdClient};
+
+/// Number of top conversations (by bytes) taken from each side
+const TOP_CONVERSATIONS: usize = 20;
+
+/// A protocol hierarchy node under both filters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolDiff {
+    /// Slash-separated path from the hierarchy root, e.g. `eth/ip/tcp/tls`
+    pub path: String,
+    pub frames_a: u64,
+    pub frames_b: u64,
+    pub bytes_a: u64,
+    pub bytes_b: u64,
+    /// Percentage of each side's frames carrying this protocol
+    pub share_a: f64,
+    pub share_b: f64,
+}
+
+/// Which side(s) a conversation appears on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    OnlyA,
+    OnlyB,
+    Both,
+}
+
+/// A top conversation under either filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationDiff {
+    /// "tcp" or "udp"
+    pub kind: String,
+    pub src: String,
+    pub dst: String,
+    pub frames_a: u64,
+    pub frames_b: u64,
+    pub bytes_a: u64,
+    pub bytes_b: u64,
+    pub presence: Presence,
+    /// Filter to select this conversation
+    pub filter: Option<String>,
+}
+
+/// Result of `compare_filters`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterComparison {
+    pub filter_a: String,
+    pub filter_b: String,
+    pub total_frames_a: u64,
+    pub total_frames_b: u64,
+    /// Protocols ordered by the size of their share change
+    pub protocols: Vec<ProtocolDiff>,
+    /// Conversations ordered by the size of their byte change
+    pub conversations: Vec<ConversationDiff>,
+}
+
+/// Flatten a protocol hierarchy into path -> (frames, bytes)
+fn flatten(nodes: &[ProtocolNode], prefix: &str, out: &mut BTreeMap<String, (u64, u64)>) {
+    for node in nodes {
+        let path = if prefix.is_empty() {
+            node.protocol.clone()
+        } else {
+            format!("{}/{}", prefix, node.protocol)
+        };
+        let entry = out.entry(path.clone()).or_default();
+        entry.0 += node.frames;
+        entry.1 += node.bytes;
+        flatten(&node.children, &path, out);
+    }
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+fn diff_protocols(a: &[ProtocolNode], b: &[ProtocolNode]) -> (u64, u64, Vec<ProtocolDiff>) {
+    let total_a: u64 = a.iter().map(|n| n.frames).sum();
+    let total_b: u64 = b.iter().map(|n| n.frames).sum();
+
+    let (mut flat_a, mut flat_b) = (BTreeMap::new(), BTreeMap::new());
+    flatten(a, "", &mut flat_a);
+    flatten(b, "", &mut flat_b);
+
+    let mut paths: Vec<&String> = flat_a.keys().chain(flat_b.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut diffs: Vec<ProtocolDiff> = paths
+        .into_iter()
+        .map(|path| {
+            let (frames_a, bytes_a) = flat_a.get(path).copied().unwrap_or_default();
+            let (frames_b, bytes_b) = flat_b.get(path).copied().unwrap_or_default();
+            ProtocolDiff {
+                path: path.clone(),
+                frames_a,
+                frames_b,
+                bytes_a,
+                bytes_b,
+                share_a: percent(frames_a, total_a),
+                share_b: percent(frames_b, total_b),
+            }
+        })
+        .collect();
+    diffs.sort_by(|x, y| {
+        (y.share_b - y.share_a)
+            .abs()
+            .total_cmp(&(x.share_b - x.share_a).abs())
+    });
+
+    (total_a, total_b, diffs)
+}
+
+fn endpoint(addr: &str, port: &Option<String>) -> String {
+    match port {
+        Some(p) if !p.is_empty() => format!("{}:{}", addr, p),
+        _ => addr.to_string(),
+    }
+}
+
+fn top_conversations(convs: &[Conversation]) -> Vec<&Conversation> {
+    let mut sorted: Vec<&Conversation> = convs.iter().collect();
+    sorted.sort_by_key(|c| std::cmp::Reverse(c.rxb + c.txb));
+    sorted.truncate(TOP_CONVERSATIONS);
+    sorted
+}
+
+fn diff_conversations(kind: &str, a: &[Conversation], b: &[Conversation]) -> Vec<ConversationDiff> {
+    // Index both sides fully so a top conversation on one side still shows its
+    // counts on the other even if it isn't in that side's top list
+    let index = |convs: &[Conversation]| -> BTreeMap<(String, String), (u64, u64)> {
+        convs
+            .iter()
+            .map(|c| {
+                (
+                    (endpoint(&c.saddr, &c.sport), endpoint(&c.daddr, &c.dport)),
+                    (c.rxf + c.txf, c.rxb + c.txb),
+                )
+            })
+            .collect()
+    };
+    let (index_a, index_b) = (index(a), index(b));
+
+    let mut diffs: BTreeMap<(String, String), ConversationDiff> = BTreeMap::new();
+    for conv in top_conversations(a).into_iter().chain(top_conversations(b)) {
+        let key = (
+            endpoint(&conv.saddr, &conv.sport),
+            endpoint(&conv.daddr, &conv.dport),
+        );
+        if diffs.contains_key(&key) {
+            continue;
+        }
+        let side_a = index_a.get(&key).copied();
+        let side_b = index_b.get(&key).copied();
+        let presence = match (side_a, side_b) {
+            (Some(_), Some(_)) => Presence::Both,
+            (Some(_), None) => Presence::OnlyA,
+            _ => Presence::OnlyB,
+        };
+        let (frames_a, bytes_a) = side_a.unwrap_or_default();
+        let (frames_b, bytes_b) = side_b.unwrap_or_default();
+        diffs.insert(
+            key.clone(),
+            ConversationDiff {
+                kind: kind.to_string(),
+                src: key.0,
+                dst: key.1,
+                frames_a,
+                frames_b,
+                bytes_a,
+                bytes_b,
+                presence,
+                filter: conv.filter.clone(),
+            },
+        );
+    }
+    diffs.into_values().collect()
+}
+
+/// Run the protocol hierarchy and conversation taps under two filters and diff them,
+/// leaving out `session`'s ignored frames
+pub async fn compare_filters(
+    client: &SharkdClient,
+    session: &str,
+    filter_a: &str,
+    filter_b: &str,
+) -> Result<FilterComparison, String> {
+    for filter in [filter_a, filter_b] {
+        if !filter.trim().is_empty() && !client.check_filter(filter).await? {
+            return Err(format!("Invalid filter: {}", filter));
+        }
+    }
+
+    let stats_a = client
+        .capture_stats(frame_marks::with_exclusions(session, Some(filter_a)).as_deref())
+        .await?;
+    let stats_b = client
+        .capture_stats(frame_marks::with_exclusions(session, Some(filter_b)).as_deref())
+        .await?;
+
+    let (total_frames_a, total_frames_b, protocols) =
+        diff_protocols(&stats_a.protocol_hierarchy, &stats_b.protocol_hierarchy);
+
+    let mut conversations = diff_conversations(
+        "tcp",
+        &stats_a.tcp_conversations,
+        &stats_b.tcp_conversations,
+    );
+    conversations.extend(diff_conversations(
+        "udp",
+        &stats_a.udp_conversations,
+        &stats_b.udp_conversations,
+    ));
+    conversations.sort_by_key(|c| std::cmp::Reverse(c.bytes_a.abs_diff(c.bytes_b)));
+
+    Ok(FilterComparison {
+        filter_a: filter_a.to_string(),
+        filter_b: filter_b.to_string(),
+        total_frames_a,
+        total_frames_b,
+        protocols,
+        conversations,
+    })
+}
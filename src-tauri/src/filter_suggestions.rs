@@ -0,0 +1,189 @@
+//! Ready-made display filters for a protocol tree item, for the details
+//! pane's "Apply as Filter" / "Prepare as Filter" menus and conversation
+//! filters for the frame it belongs to.
+//!
+//! sharkd gives each filterable tree item its filter (`f`, e.g.
+//! `ip.src == 10.0.0.1`); the variants are built from that, so values are
+//! quoted and formatted the way Wireshark itself writes them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sharkd_client::{SharkdClient, TreeNode};
+
+/// Fields read for conversation filters, in the order used below
+const CONVERSATION_FIELDS: &[&str] = &[
+    "eth.src",
+    "eth.dst",
+    "ip.src",
+    "ip.dst",
+    "ipv6.src",
+    "ipv6.dst",
+    "tcp.stream",
+    "udp.stream",
+];
+
+/// A suggested filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterSuggestion {
+    /// Menu label, e.g. `Selected`, `Not Selected`, `TCP conversation`
+    pub label: String,
+    pub filter: String,
+}
+
+/// Filters for a tree item and its frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterSuggestions {
+    /// The item's field, e.g. `ip.src`
+    pub field: Option<String>,
+    /// Variants of the item's own filter
+    pub item: Vec<FilterSuggestion>,
+    /// Conversations the frame belongs to
+    pub conversations: Vec<FilterSuggestion>,
+}
+
+/// The tree item at `path`: indexes into the top-level items, then into
+/// each item's children
+fn node_at<'a>(tree: &'a [TreeNode], path: &[usize]) -> Result<&'a TreeNode, String> {
+    let (first, rest) = path
+        .split_first()
+        .ok_or_else(|| "No tree item selected".to_string())?;
+    let mut node = tree
+        .get(*first)
+        .ok_or_else(|| format!("No tree item at {:?}", path))?;
+    for &index in rest {
+        node = node
+            .children
+            .get(index)
+            .ok_or_else(|| format!("No tree item at {:?}", path))?;
+    }
+    Ok(node)
+}
+
+fn suggestion(label: &str, filter: String) -> FilterSuggestion {
+    FilterSuggestion {
+        label: label.to_string(),
+        filter,
+    }
+}
+
+/// `Selected`, `Not Selected`, and combined with `current` (the filter in
+/// the filter bar) when one is given
+fn item_filters(selected: &str, field: &str, current: Option<&str>) -> Vec<FilterSuggestion> {
+    let mut filters = vec![
+        suggestion("Selected", selected.to_string()),
+        suggestion("Not Selected", format!("!({})", selected)),
+    ];
+    if let Some(value) = selected.strip_prefix(field).and_then(|rest| {
+        rest.trim_start()
+            .strip_prefix("==")
+            .map(str::trim_start)
+            .filter(|v| !v.is_empty())
+    }) {
+        filters.push(suggestion("Not Equal", format!("{} != {}", field, value)));
+    }
+    if selected != field {
+        filters.push(suggestion("Field Present", field.to_string()));
+    }
+    if let Some(current) = current.map(str::trim).filter(|c| !c.is_empty()) {
+        filters.extend([
+            suggestion(
+                "...and Selected",
+                format!("({}) && ({})", current, selected),
+            ),
+            suggestion("...or Selected", format!("({}) || ({})", current, selected)),
+            suggestion(
+                "...and not Selected",
+                format!("({}) && !({})", current, selected),
+            ),
+            suggestion(
+                "...or not Selected",
+                format!("({}) || !({})", current, selected),
+            ),
+        ]);
+    }
+    filters
+}
+
+/// Ethernet, IP, TCP and UDP conversation filters for a frame
+async fn conversation_filters(
+    client: &SharkdClient,
+    frame: u32,
+) -> Result<Vec<FilterSuggestion>, String> {
+    let values = client
+        .column_values(
+            &format!("frame.number == {}", frame),
+            CONVERSATION_FIELDS,
+            1,
+        )
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let value = |field: &str| {
+        let index = CONVERSATION_FIELDS.iter().position(|f| *f == field)?;
+        values
+            .get(index)
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    let mut filters = Vec::new();
+    let pairs = [
+        ("Ethernet conversation", "eth.addr", "eth.src", "eth.dst"),
+        ("IPv4 conversation", "ip.addr", "ip.src", "ip.dst"),
+        ("IPv6 conversation", "ipv6.addr", "ipv6.src", "ipv6.dst"),
+    ];
+    for (label, field, src, dst) in pairs {
+        if let (Some(a), Some(b)) = (value(src), value(dst)) {
+            filters.push(suggestion(
+                label,
+                format!("{} == {} && {} == {}", field, a, field, b),
+            ));
+        }
+    }
+    if let Some(stream) = value("tcp.stream") {
+        filters.push(suggestion(
+            "TCP conversation",
+            format!("tcp.stream eq {}", stream),
+        ));
+    }
+    if let Some(stream) = value("udp.stream") {
+        filters.push(suggestion(
+            "UDP conversation",
+            format!("udp.stream eq {}", stream),
+        ));
+    }
+    Ok(filters)
+}
+
+/// Filters for the tree item at `path` of `frame`, combined with `current`
+/// where it applies
+pub async fn suggest_filters(
+    client: &SharkdClient,
+    frame: u32,
+    path: &[usize],
+    current: Option<&str>,
+) -> Result<FilterSuggestions, String> {
+    let details = client.frame_details(frame).await?;
+    let node = node_at(&details.tree, path)?;
+
+    let (field, item) = match node.filter.as_deref().map(str::trim) {
+        Some(selected) if !selected.is_empty() => {
+            let field = selected
+                .split_whitespace()
+                .next()
+                .unwrap_or(selected)
+                .to_string();
+            let item = item_filters(selected, &field, current);
+            (Some(field), item)
+        }
+        _ => (None, Vec::new()),
+    };
+
+    Ok(FilterSuggestions {
+        field,
+        item,
+        conversations: conversation_filters(client, frame).await?,
+    })
+}
@@ -0,0 +1,211 @@
+//! Export of the packet list to CSV or JSON Lines, for pivoting packet
+//! summaries in spreadsheets and notebooks.
+//!
+//! The columns are those the packet list shows: the active layout's visible
+//! columns in its order, or the standard columns followed by the custom ones.
+//! Frames are fetched from sharkd a page at a time and written as they come,
+//! so large captures aren't held in memory.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::{columns, FrameData};
+
+/// Frames fetched from sharkd per request
+const PAGE_SIZE: u32 = 5000;
+
+/// Standard columns: key, CSV header
+const STANDARD_COLUMNS: &[(&str, &str)] = &[
+    ("number", "No."),
+    ("time", "Time"),
+    ("source", "Source"),
+    ("destination", "Destination"),
+    ("protocol", "Protocol"),
+    ("length", "Length"),
+    ("info", "Info"),
+];
+
+/// Output formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per frame, keyed by column
+    Jsonl,
+}
+
+/// Result of an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameExportResult {
+    pub path: String,
+    pub frames: u64,
+    /// Column headers, in output order
+    pub columns: Vec<String>,
+}
+
+/// A column of the export
+struct ExportColumn {
+    /// JSON key: the standard column's key or the custom column's field
+    key: String,
+    /// CSV header
+    title: String,
+    value: ColumnValue,
+}
+
+enum ColumnValue {
+    Standard(&'static str),
+    /// Index into the frame's custom values
+    Custom(usize),
+}
+
+/// The packet list's columns, in display order
+fn export_columns() -> Vec<ExportColumn> {
+    let custom = columns::get_columns();
+    let custom_column = |index: usize| ExportColumn {
+        key: custom[index].field.clone(),
+        title: custom[index].title.clone(),
+        value: ColumnValue::Custom(index),
+    };
+    let standard_column = |key: &str| {
+        STANDARD_COLUMNS
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(key, title)| ExportColumn {
+                key: key.to_string(),
+                title: title.to_string(),
+                value: ColumnValue::Standard(key),
+            })
+    };
+
+    let layout = columns::active_layout()
+        .and_then(|name| columns::list_layouts().into_iter().find(|l| l.name == name))
+        .filter(|layout| !layout.columns.is_empty());
+    match layout {
+        Some(layout) => layout
+            .columns
+            .iter()
+            .filter(|c| c.visible)
+            .filter_map(|c| match c.key.strip_prefix("custom:") {
+                Some(field) => custom
+                    .iter()
+                    .position(|column| column.field == field)
+                    .map(custom_column),
+                None => standard_column(&c.key),
+            })
+            .collect(),
+        None => STANDARD_COLUMNS
+            .iter()
+            .filter_map(|(key, _)| standard_column(key))
+            .chain((0..custom.len()).map(custom_column))
+            .collect(),
+    }
+}
+
+fn column_value<'a>(frame: &'a FrameData, column: &ExportColumn) -> std::borrow::Cow<'a, str> {
+    match column.value {
+        ColumnValue::Standard("number") => frame.number.to_string().into(),
+        ColumnValue::Standard("time") => frame.time.as_str().into(),
+        ColumnValue::Standard("source") => frame.source.as_str().into(),
+        ColumnValue::Standard("destination") => frame.destination.as_str().into(),
+        ColumnValue::Standard("protocol") => frame.protocol.as_str().into(),
+        ColumnValue::Standard("length") => frame.length.as_str().into(),
+        ColumnValue::Standard(_) => frame.info.as_str().into(),
+        ColumnValue::Custom(index) => frame
+            .custom
+            .get(index)
+            .map(String::as_str)
+            .unwrap_or_default()
+            .into(),
+    }
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_row<W: Write>(
+    writer: &mut W,
+    format: FrameExportFormat,
+    columns: &[ExportColumn],
+    frame: &FrameData,
+) -> std::io::Result<()> {
+    match format {
+        FrameExportFormat::Csv => {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|c| csv_field(&column_value(frame, c)))
+                .collect();
+            writeln!(writer, "{}", row.join(","))
+        }
+        FrameExportFormat::Jsonl => {
+            // Written by hand so keys keep the column order
+            let fields: Vec<String> = columns
+                .iter()
+                .map(|c| {
+                    let value = match c.value {
+                        ColumnValue::Standard("number") => frame.number.to_string(),
+                        _ => serde_json::Value::from(column_value(frame, c).as_ref()).to_string(),
+                    };
+                    format!("{}:{}", serde_json::Value::from(c.key.as_str()), value)
+                })
+                .collect();
+            writeln!(writer, "{{{}}}", fields.join(","))
+        }
+    }
+}
+
+/// Write the frames matching `filter` (all when None) to `path`, paging
+/// through sharkd
+pub async fn export_frames(
+    filter: Option<String>,
+    format: FrameExportFormat,
+    path: &str,
+    session_id: Option<String>,
+) -> Result<FrameExportResult, String> {
+    let columns = export_columns();
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", path, e);
+
+    if format == FrameExportFormat::Csv {
+        let header: Vec<String> = columns.iter().map(|c| csv_field(&c.title)).collect();
+        writeln!(writer, "{}", header.join(",")).map_err(write_error)?;
+    }
+
+    let client = crate::sharkd(session_id.as_deref())?;
+    let operation = client.operation();
+    let mut skip = 0;
+    loop {
+        operation.check()?;
+        let page = crate::get_frames_with_columns(
+            skip,
+            PAGE_SIZE,
+            filter.clone(),
+            None,
+            session_id.clone(),
+        )
+        .await?;
+        for frame in &page.frames {
+            write_row(&mut writer, format, &columns, frame).map_err(write_error)?;
+        }
+        let fetched = page.frames.len() as u32;
+        skip += fetched;
+        if fetched < PAGE_SIZE {
+            break;
+        }
+    }
+    writer.flush().map_err(write_error)?;
+
+    Ok(FrameExportResult {
+        path: path.to_string(),
+        frames: u64::from(skip),
+        columns: columns.into_iter().map(|c| c.title).collect(),
+    })
+}
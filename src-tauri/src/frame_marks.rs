@@ -0,0 +1,169 @@
+//! Per-capture frame mark state (marked and ignored frames), kept for each
+//! session so captures open side by side don't share marks.
+//!
+//! Marked frames are flagged in frame listings, mirroring Wireshark's "Mark
+//! Packet". Ignored frames are masked from frame listings, counts, and
+//! statistics by folding an exclusion filter into the sharkd requests,
+//! mirroring Wireshark's "Ignore Packet". Both are kept by frame number, so
+//! they hold across filter changes.
+
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+#[derive(Debug, Default)]
+struct FrameMarks {
+    marked: BTreeSet<u32>,
+    ignored: BTreeSet<u32>,
+}
+
+/// Frame mark state of each session's loaded capture, by session ID
+static FRAME_MARKS: OnceLock<Mutex<HashMap<String, FrameMarks>>> = OnceLock::new();
+
+/// Run `f` on a session's mark state
+fn with_marks<T>(session: &str, f: impl FnOnce(&mut FrameMarks) -> T) -> T {
+    let mut sessions = FRAME_MARKS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock();
+    f(sessions.entry(session.to_string()).or_default())
+}
+
+/// Flip the mark on each frame, returning the new number of marked frames
+pub fn toggle_marked(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        for frame in frames.into_iter().filter(|&n| n > 0) {
+            if !marks.marked.remove(&frame) {
+                marks.marked.insert(frame);
+            }
+        }
+        marks.marked.len()
+    })
+}
+
+/// Mark frames, returning the new number of marked frames
+pub fn mark_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        marks.marked.extend(frames.into_iter().filter(|&n| n > 0));
+        marks.marked.len()
+    })
+}
+
+/// Unmark frames, returning the new number of marked frames
+pub fn unmark_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        for frame in frames {
+            marks.marked.remove(&frame);
+        }
+        marks.marked.len()
+    })
+}
+
+/// Replace the marked frames, returning the new number of marked frames
+pub fn set_marked_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        marks.marked = frames.into_iter().filter(|&n| n > 0).collect();
+        marks.marked.len()
+    })
+}
+
+/// Marked frame numbers in ascending order
+pub fn marked_frames(session: &str) -> Vec<u32> {
+    with_marks(session, |marks| marks.marked.iter().copied().collect())
+}
+
+/// Whether a frame is marked
+pub fn is_marked(session: &str, frame: u32) -> bool {
+    with_marks(session, |marks| marks.marked.contains(&frame))
+}
+
+/// Add frames to the ignore list, returning the new number of ignored frames
+pub fn ignore_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        marks.ignored.extend(frames.into_iter().filter(|&n| n > 0));
+        marks.ignored.len()
+    })
+}
+
+/// Remove frames from the ignore list, returning the new number of ignored frames
+pub fn unignore_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        for frame in frames {
+            marks.ignored.remove(&frame);
+        }
+        marks.ignored.len()
+    })
+}
+
+/// Replace the ignore list, returning the new number of ignored frames
+pub fn set_ignored_frames(session: &str, frames: impl IntoIterator<Item = u32>) -> usize {
+    with_marks(session, |marks| {
+        marks.ignored = frames.into_iter().filter(|&n| n > 0).collect();
+        marks.ignored.len()
+    })
+}
+
+/// Ignored frame numbers in ascending order
+pub fn ignored_frames(session: &str) -> Vec<u32> {
+    with_marks(session, |marks| marks.ignored.iter().copied().collect())
+}
+
+/// Number of ignored frames
+pub fn ignored_count(session: &str) -> u64 {
+    with_marks(session, |marks| marks.ignored.len() as u64)
+}
+
+/// Drop a session's mark state (called when it loads a new capture or closes)
+pub fn clear(session: &str) {
+    if let Some(sessions) = FRAME_MARKS.get() {
+        sessions.lock().remove(session);
+    }
+}
+
+/// Render frame numbers as a display filter set, collapsing consecutive runs
+/// into ranges: `{1 2 5..9}`
+fn frame_set(frames: &BTreeSet<u32>) -> String {
+    let mut parts = Vec::new();
+    let mut iter = frames.iter().copied();
+    if let Some(first) = iter.next() {
+        let (mut start, mut end) = (first, first);
+        for n in iter {
+            if n == end + 1 {
+                end = n;
+                continue;
+            }
+            parts.push(format_run(start, end));
+            start = n;
+            end = n;
+        }
+        parts.push(format_run(start, end));
+    }
+    format!("{{{}}}", parts.join(" "))
+}
+
+fn format_run(start: u32, end: u32) -> String {
+    match end - start {
+        0 => start.to_string(),
+        1 => format!("{} {}", start, end),
+        _ => format!("{}..{}", start, end),
+    }
+}
+
+/// Combine a display filter with the exclusion filter for a session's
+/// ignored frames. Returns `None` when there is neither a filter nor
+/// anything ignored.
+pub fn with_exclusions(session: &str, filter: Option<&str>) -> Option<String> {
+    let filter = filter.map(str::trim).filter(|f| !f.is_empty());
+    let ignored = with_marks(session, |marks| {
+        (!marks.ignored.is_empty()).then(|| frame_set(&marks.ignored))
+    });
+    let ignored = match ignored {
+        Some(ignored) => ignored,
+        None => return filter.map(String::from),
+    };
+
+    let exclusion = format!("!(frame.number in {})", ignored);
+    Some(match filter {
+        Some(f) => format!("({}) && {}", f, exclusion),
+        None => exclusion,
+    })
+}
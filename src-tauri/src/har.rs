@@ -0,0 +1,301 @@
+//! HAR (HTTP Archive 1.2) export of the capture's HTTP traffic.
+//!
+//! Request/response pairs are reconstructed by tshark (so reassembly follows
+//! the managed profile's preferences, and TLS is decrypted with the keylog
+//! applied to sharkd) and matched through `http.request_in`.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::{decryption, wireshark_tools};
+
+/// Fields extracted per HTTP frame
+const HAR_FIELDS: &[&str] = &[
+    "frame.number",
+    "frame.time_epoch",
+    "tcp.stream",
+    "ip.dst",
+    "ipv6.dst",
+    "http.request.method",
+    "http.request.full_uri",
+    "http.request.version",
+    "http.request.line",
+    "http.response.version",
+    "http.response.code",
+    "http.response.phrase",
+    "http.response.line",
+    "http.content_type",
+    "http.file_data",
+    "http.request_in",
+    "http.location",
+];
+
+/// Summary of a written HAR file
+#[derive(Debug, Clone, Serialize)]
+pub struct HarExportResult {
+    pub path: String,
+    /// Request/response entries written
+    pub entries: usize,
+    /// Requests with no matching response in the capture
+    pub unanswered: usize,
+}
+
+/// One HTTP frame as extracted by tshark: field name -> occurrences
+type Layers = HashMap<String, Vec<String>>;
+
+fn first<'a>(layers: &'a Layers, field: &str) -> Option<&'a str> {
+    layers
+        .get(field)
+        .and_then(|v| v.first())
+        .map(String::as_str)
+}
+
+/// Convert a Unix timestamp to an ISO 8601 UTC date-time with milliseconds
+fn iso8601(epoch: f64) -> String {
+    let millis = (epoch * 1000.0).round() as i64;
+    let secs = millis.div_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+/// Parse `Name: value\r\n` header lines into HAR name/value pairs
+fn headers(lines: Option<&Vec<String>>) -> Vec<Value> {
+    lines
+        .into_iter()
+        .flatten()
+        .filter_map(|line| line.trim_end().split_once(':'))
+        .map(|(name, value)| json!({ "name": name.trim(), "value": value.trim() }))
+        .collect()
+}
+
+fn query_string(url: &str) -> Vec<Value> {
+    url.split_once('?')
+        .map(|(_, query)| query.split('#').next().unwrap_or_default())
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            json!({ "name": name, "value": value })
+        })
+        .collect()
+}
+
+/// tshark renders `http.file_data` as hex bytes; decode it, falling back to
+/// the raw string for versions that render it as text
+fn body_bytes(raw: &str) -> Vec<u8> {
+    let hex: String = raw.chars().filter(|&c| c != ':').collect();
+    if !hex.is_empty() && hex.len().is_multiple_of(2) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        let decoded: Option<Vec<u8>> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect();
+        if let Some(bytes) = decoded {
+            return bytes;
+        }
+    }
+    raw.as_bytes().to_vec()
+}
+
+/// HAR `content` object: text bodies inline, binary bodies base64-encoded
+fn content(layers: &Layers) -> Value {
+    let mime_type = first(layers, "http.content_type").unwrap_or("");
+    let body = first(layers, "http.file_data")
+        .map(body_bytes)
+        .unwrap_or_default();
+    let mut content = json!({ "size": body.len(), "mimeType": mime_type });
+    if !body.is_empty() {
+        match String::from_utf8(body) {
+            Ok(text) => content["text"] = json!(text),
+            Err(e) => {
+                content["text"] = json!(BASE64.encode(e.into_bytes()));
+                content["encoding"] = json!("base64");
+            }
+        }
+    }
+    content
+}
+
+fn har_entry(request: &Layers, response: Option<&Layers>) -> Value {
+    let url = first(request, "http.request.full_uri").unwrap_or("");
+    let started = first(request, "frame.time_epoch")
+        .and_then(|t| t.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    let request_body_size = first(request, "http.file_data")
+        .map(|b| body_bytes(b).len() as i64)
+        .unwrap_or(0);
+
+    let (response_json, wait_ms) = match response {
+        Some(resp) => {
+            let answered = first(resp, "frame.time_epoch")
+                .and_then(|t| t.parse::<f64>().ok())
+                .unwrap_or(started);
+            let content = content(resp);
+            let body_size = content["size"].as_i64().unwrap_or(0);
+            (
+                json!({
+                    "status": first(resp, "http.response.code")
+                        .and_then(|c| c.parse::<u16>().ok())
+                        .unwrap_or(0),
+                    "statusText": first(resp, "http.response.phrase").unwrap_or(""),
+                    "httpVersion": first(resp, "http.response.version").unwrap_or(""),
+                    "cookies": [],
+                    "headers": headers(resp.get("http.response.line")),
+                    "content": content,
+                    "redirectURL": first(resp, "http.location").unwrap_or(""),
+                    "headersSize": -1,
+                    "bodySize": body_size,
+                }),
+                ((answered - started) * 1000.0).max(0.0),
+            )
+        }
+        // HAR requires a response object; status 0 marks "no response"
+        None => (
+            json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            }),
+            0.0,
+        ),
+    };
+
+    let mut entry = json!({
+        "startedDateTime": iso8601(started),
+        "time": wait_ms,
+        "request": {
+            "method": first(request, "http.request.method").unwrap_or(""),
+            "url": url,
+            "httpVersion": first(request, "http.request.version").unwrap_or(""),
+            "cookies": [],
+            "headers": headers(request.get("http.request.line")),
+            "queryString": query_string(url),
+            "headersSize": -1,
+            "bodySize": request_body_size,
+        },
+        "response": response_json,
+        "cache": {},
+        "timings": { "send": 0, "wait": wait_ms, "receive": 0 },
+    });
+    if let Some(server) = first(request, "ip.dst").or_else(|| first(request, "ipv6.dst")) {
+        entry["serverIPAddress"] = json!(server);
+    }
+    if let Some(stream) = first(request, "tcp.stream") {
+        entry["connection"] = json!(stream);
+    }
+    entry
+}
+
+/// Extract HTTP frames with tshark
+fn extract_http(source: &str, filter: Option<&str>) -> Result<Vec<Layers>, String> {
+    let display_filter = match filter.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(f) => format!("(http.request || http.response) && ({})", f),
+        None => "http.request || http.response".to_string(),
+    };
+
+    let mut args = vec![
+        "-r".to_string(),
+        source.to_string(),
+        "-Y".to_string(),
+        display_filter,
+        "-T".to_string(),
+        "json".to_string(),
+    ];
+    // The keylog is set on sharkd at runtime, not in the profile tshark reads
+    if let Some(keylog) = decryption::active_keylog() {
+        args.push("-o".to_string());
+        args.push(format!("tls.keylog_file:{}", keylog));
+    }
+    for field in HAR_FIELDS {
+        args.push("-e".to_string());
+        args.push(field.to_string());
+    }
+
+    let output = wireshark_tools::run_tool("tshark", &args)?;
+    let packets: Vec<Value> = serde_json::from_str(output.trim())
+        .map_err(|e| format!("Failed to parse tshark output: {}", e))?;
+
+    Ok(packets
+        .into_iter()
+        .filter_map(|p| serde_json::from_value(p.pointer("/_source/layers")?.clone()).ok())
+        .collect())
+}
+
+/// Write the HTTP request/response pairs of `source` (optionally limited by a
+/// display filter) to `dest` as a HAR 1.2 file
+pub fn export_har(
+    source: &str,
+    dest: &str,
+    filter: Option<&str>,
+) -> Result<HarExportResult, String> {
+    let frames = extract_http(source, filter)?;
+
+    // Responses indexed by the request frame they answer
+    let responses: HashMap<&str, &Layers> = frames
+        .iter()
+        .filter_map(|f| first(f, "http.request_in").map(|req| (req, f)))
+        .collect();
+
+    let mut unanswered = 0;
+    let entries: Vec<Value> = frames
+        .iter()
+        .filter(|f| f.contains_key("http.request.method"))
+        .map(|request| {
+            let response = first(request, "frame.number").and_then(|n| responses.get(n).copied());
+            if response.is_none() {
+                unanswered += 1;
+            }
+            har_entry(request, response)
+        })
+        .collect();
+
+    let har = json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "PacketPilot", "version": env!("CARGO_PKG_VERSION") },
+            "pages": [],
+            "entries": &entries,
+        }
+    });
+    let entry_count = entries.len();
+    let text = serde_json::to_string_pretty(&har)
+        .map_err(|e| format!("Failed to serialize HAR: {}", e))?;
+    std::fs::write(dest, text).map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+
+    Ok(HarExportResult {
+        path: dest.to_string(),
+        entries: entry_count,
+        unanswered,
+    })
+}
@@ -0,0 +1,111 @@
+//! Classic offset / hex / ASCII dumps of frames, as Wireshark copies them
+//! ("Copy > Bytes as Hex + ASCII Dump"), for tickets and chat.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Most frames dumped at once
+const MAX_HEX_DUMP_FRAMES: usize = 1000;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// A hex dump, written to a file or returned inline (e.g. for the clipboard)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexDumpExport {
+    /// Frames in the dump
+    pub frames: Vec<u32>,
+    /// Where the dump was written, when a destination was given
+    pub path: Option<String>,
+    /// The dump, when no destination was given
+    pub content: Option<String>,
+}
+
+/// One line of a hex dump: up to 16 bytes at `offset`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexLine {
+    pub offset: u32,
+    /// Space-separated hex bytes, with an extra space after the eighth
+    pub hex: String,
+    /// The bytes as printable ASCII, `.` for the rest
+    pub ascii: String,
+}
+
+/// Split bytes into hex dump lines
+pub fn hex_lines(bytes: &[u8]) -> Vec<HexLine> {
+    bytes
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let mut hex = String::with_capacity(BYTES_PER_LINE * 3);
+            for (i, byte) in chunk.iter().enumerate() {
+                if i > 0 {
+                    hex.push(' ');
+                }
+                if i == BYTES_PER_LINE / 2 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x}", byte));
+            }
+            let ascii = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            HexLine {
+                offset: (line * BYTES_PER_LINE) as u32,
+                hex,
+                ascii,
+            }
+        })
+        .collect()
+}
+
+/// Dump bytes 16 to a line:
+/// `0000  45 00 00 3c 1c 46 40 00  40 06 b1 e6 c0 a8 00 68   E..<.F@.@......h`
+pub fn hex_dump(bytes: &[u8]) -> String {
+    // Offsets grow past four digits only for frames over 64 KiB
+    let width = if bytes.len() > 0x10000 { 8 } else { 4 };
+    let mut out = String::new();
+    for line in hex_lines(bytes) {
+        out.push_str(&format!(
+            "{:0width$x}  {:<hex_width$}   {}\n",
+            line.offset,
+            line.hex,
+            line.ascii,
+            width = width,
+            hex_width = BYTES_PER_LINE * 3,
+        ));
+    }
+    out
+}
+
+/// Dumps of several frames, each under a `Frame N (L bytes)` heading
+pub async fn dump_frames(client: &SharkdClient, frames: &[u32]) -> Result<String, String> {
+    if frames.is_empty() {
+        return Err("No frames to dump".to_string());
+    }
+    if frames.len() > MAX_HEX_DUMP_FRAMES {
+        return Err(format!(
+            "Too many frames to dump at once (at most {})",
+            MAX_HEX_DUMP_FRAMES
+        ));
+    }
+    let operation = client.operation();
+    let mut out = String::new();
+    for (i, &frame) in frames.iter().enumerate() {
+        operation.check()?;
+        let bytes = client.frame_bytes(frame).await?;
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("Frame {} ({} bytes)\n", frame, bytes.len()));
+        out.push_str(&hex_dump(&bytes));
+    }
+    Ok(out)
+}
@@ -0,0 +1,257 @@
+//! History of analyzed captures, keyed by content hash.
+//!
+//! Lets the app recognize a capture it has seen before (even when renamed or
+//! moved) so prior work isn't repeated, and find past captures again by tag,
+//! name, capture time, or the hosts they involve.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::sharkd_client::SharkdClient;
+use crate::{capture_file, storage};
+
+/// Hosts kept in a capture's summary
+const SUMMARY_TOP_HOSTS: usize = 10;
+
+pub const STORE_NAME: &str = "capture-history";
+
+/// A previously analyzed capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    pub sha256: String,
+    /// Path the capture was last opened from
+    pub path: String,
+    pub file_name: String,
+    pub size: u64,
+    /// Unix timestamps (seconds)
+    pub first_opened: u64,
+    pub last_opened: u64,
+    pub open_count: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Summary of the capture's contents, taken when it was last opened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<CaptureSummary>,
+}
+
+/// Searchable summary of a capture's contents
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub frames: u64,
+    /// Unix timestamps (seconds) of the first and last frame
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    /// Busiest hosts by bytes
+    pub top_hosts: Vec<String>,
+}
+
+/// Criteria for `search_captures`; all given criteria must match
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CaptureSearch {
+    /// Case-insensitive text matched against file name, path, tags, and top hosts
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Tags the capture must all carry
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Unix timestamps (seconds); the capture's time span must overlap this range
+    #[serde(default)]
+    pub from: Option<f64>,
+    #[serde(default)]
+    pub to: Option<f64>,
+    /// A host that must be among the capture's top hosts
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
+/// Result of checking a file against the history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownCapture {
+    pub known: bool,
+    pub sha256: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<CaptureRecord>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryStore {
+    captures: BTreeMap<String, CaptureRecord>,
+}
+
+static HISTORY: OnceLock<Mutex<HistoryStore>> = OnceLock::new();
+
+fn get_history() -> &'static Mutex<HistoryStore> {
+    HISTORY.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Check whether a capture with the same contents was analyzed before
+pub fn check_known_capture(path: &str) -> Result<KnownCapture, String> {
+    let sha256 = capture_file::sha256_file(path)?;
+    let record = get_history().lock().captures.get(&sha256).cloned();
+    Ok(KnownCapture {
+        known: record.is_some(),
+        sha256,
+        record,
+    })
+}
+
+/// Summarize the capture loaded in `client` for later searches
+pub async fn summarize(client: &SharkdClient) -> Result<CaptureSummary, String> {
+    let status = client.status().await?;
+    let start = client
+        .column_values("frame.number == 1", &["frame.time_epoch"], 1)
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|row| row.first().and_then(|t| t.parse::<f64>().ok()));
+    let end = start.map(|start| start + status.duration.unwrap_or(0.0));
+
+    let mut endpoints = client.host_endpoints().await?;
+    endpoints.sort_by_key(|e| std::cmp::Reverse(e.rxb + e.txb));
+    let top_hosts = endpoints
+        .into_iter()
+        .take(SUMMARY_TOP_HOSTS)
+        .map(|e| e.host)
+        .collect();
+
+    Ok(CaptureSummary {
+        frames: status.frames.unwrap_or(0),
+        start,
+        end,
+        top_hosts,
+    })
+}
+
+/// Record that a capture was opened, with a summary of its contents when available
+pub fn record_open(path: &str, summary: Option<CaptureSummary>) -> Result<CaptureRecord, String> {
+    let sha256 = capture_file::sha256_file(path)?;
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    let now = storage::now_secs();
+
+    let mut history = get_history().lock();
+    let record = history
+        .captures
+        .entry(sha256.clone())
+        .or_insert_with(|| CaptureRecord {
+            sha256,
+            path: path.to_string(),
+            file_name: file_name.clone(),
+            size,
+            first_opened: now,
+            last_opened: now,
+            open_count: 0,
+            tags: Vec::new(),
+            summary: None,
+        });
+    record.path = path.to_string();
+    record.file_name = file_name;
+    record.last_opened = now;
+    record.open_count += 1;
+    if summary.is_some() {
+        record.summary = summary;
+    }
+    let record = record.clone();
+
+    storage::schedule_save(STORE_NAME, &*history)?;
+    Ok(record)
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_history().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Recently opened captures, most recent first
+pub fn list_recent(limit: usize) -> Vec<CaptureRecord> {
+    let mut records: Vec<CaptureRecord> = get_history().lock().captures.values().cloned().collect();
+    records.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+    records.truncate(limit);
+    records
+}
+
+/// Replace the tags of a capture in the history
+pub fn tag_capture(sha256: &str, tags: Vec<String>) -> Result<CaptureRecord, String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    let mut history = get_history().lock();
+    let record = history
+        .captures
+        .get_mut(sha256)
+        .ok_or_else(|| format!("Unknown capture: {}", sha256))?;
+    record.tags = tags;
+    let record = record.clone();
+
+    storage::schedule_save(STORE_NAME, &*history)?;
+    Ok(record)
+}
+
+fn matches(record: &CaptureRecord, search: &CaptureSearch) -> bool {
+    let summary = record.summary.clone().unwrap_or_default();
+
+    if let Some(text) = search
+        .text
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        let text = text.to_lowercase();
+        let found = [&record.file_name, &record.path]
+            .into_iter()
+            .chain(&record.tags)
+            .chain(&summary.top_hosts)
+            .any(|field| field.to_lowercase().contains(&text));
+        if !found {
+            return false;
+        }
+    }
+
+    let has_tag = |tag: &String| record.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+    if !search.tags.iter().all(has_tag) {
+        return false;
+    }
+
+    if let Some(host) = search.host.as_deref().filter(|h| !h.is_empty()) {
+        if !summary.top_hosts.iter().any(|h| h == host) {
+            return false;
+        }
+    }
+
+    if search.from.is_some() || search.to.is_some() {
+        // Captures without a recorded time span can't match a time range
+        let (start, end) = match (summary.start, summary.end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        if search.from.is_some_and(|from| end < from) || search.to.is_some_and(|to| start > to) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Captures in the history matching every given criterion, most recent first
+pub fn search_captures(search: &CaptureSearch) -> Vec<CaptureRecord> {
+    let mut records: Vec<CaptureRecord> = get_history()
+        .lock()
+        .captures
+        .values()
+        .filter(|record| matches(record, search))
+        .cloned()
+        .collect();
+    records.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+    records
+}
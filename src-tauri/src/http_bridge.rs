@@ -4,22 +4,29 @@
 //! to fetch packet data from the Rust-managed sharkd process.
 
 use axum::{
-    extract::Json,
+    extract::{Json, Query},
     routing::{get, post},
     Router,
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use tower_http::cors::{Any, CorsLayer};
 
-use crate::{get_sharkd, FrameData, FramesResult};
+use crate::mobile::MobileSummary;
+use crate::redaction::RedactionPolicy;
+use crate::sharkd_client::{Conversation, Endpoint, TlsDecryption};
+use crate::{frames_with_positions, sharkd, FrameData, FramesResult};
 
 /// Request to fetch frames
 #[derive(Debug, Deserialize)]
 pub struct FramesRequest {
     pub skip: u32,
     pub limit: u32,
+    /// Include each frame's position within the result alongside its frame number
+    #[serde(default)]
+    pub include_position: bool,
 }
 
 /// Request to fetch frame details
@@ -48,6 +55,9 @@ pub struct SearchRequest {
     pub limit: u32,
     #[serde(default)]
     pub skip: u32,
+    /// Include each frame's position within the matches alongside its frame number
+    #[serde(default)]
+    pub include_position: bool,
 }
 
 fn default_limit() -> u32 {
@@ -65,7 +75,11 @@ pub struct SearchResult {
 /// Request to follow a stream
 #[derive(Debug, Deserialize)]
 pub struct StreamRequest {
+    /// Stream index, or for QUIC the connection number
     pub stream_id: u32,
+    /// QUIC stream within the connection
+    #[serde(default)]
+    pub sub_stream: Option<u32>,
     #[serde(default = "default_protocol")]
     pub protocol: String,
     #[serde(default = "default_format")]
@@ -104,6 +118,25 @@ pub struct StreamResponse {
     pub client_bytes: u64,
     pub segments: Vec<StreamSegment>,
     pub combined_text: Option<String>,
+    /// For TLS: `decrypted`, `no_keys`, or `no_application_data`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsDecryption>,
+    /// For WebSocket: the individual frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<StreamMessage>>,
+}
+
+/// A WebSocket frame of a followed stream
+#[derive(Debug, Serialize)]
+pub struct StreamMessage {
+    pub frame: u32,
+    pub direction: String,
+    /// `text`, `binary`, `close`, `ping`, `pong`, or `continuation`
+    pub kind: String,
+    pub fin: bool,
+    pub size: u64,
+    /// Payload in the requested format, when known
+    pub data: Option<String>,
 }
 
 /// Summary stats for capture
@@ -115,6 +148,9 @@ pub struct StatsSummary {
     pub tcp_conversation_count: usize,
     pub udp_conversation_count: usize,
     pub endpoint_count: usize,
+    /// Display filter the stats are limited to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
 }
 
 /// Protocol node for response (re-exported from sharkd_client)
@@ -123,6 +159,11 @@ pub struct ProtocolNodeResponse {
     pub protocol: String,
     pub frames: u64,
     pub bytes: u64,
+    /// Share of all frames, in percent
+    pub frame_percent: f64,
+    /// Display filter selecting the protocol's frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
     pub children: Vec<ProtocolNodeResponse>,
 }
 
@@ -151,6 +192,60 @@ pub struct EndpointResponse {
     pub tx_bytes: u64,
 }
 
+impl From<Conversation> for ConversationResponse {
+    fn from(c: Conversation) -> Self {
+        ConversationResponse {
+            src_addr: c.saddr,
+            dst_addr: c.daddr,
+            src_port: c.sport,
+            dst_port: c.dport,
+            rx_frames: c.rxf,
+            rx_bytes: c.rxb,
+            tx_frames: c.txf,
+            tx_bytes: c.txb,
+            filter: c.filter,
+        }
+    }
+}
+
+impl From<Endpoint> for EndpointResponse {
+    fn from(e: Endpoint) -> Self {
+        EndpointResponse {
+            host: e.host,
+            port: e.port,
+            rx_frames: e.rxf,
+            rx_bytes: e.rxb,
+            tx_frames: e.txf,
+            tx_bytes: e.txb,
+        }
+    }
+}
+
+/// Traffic on a single VLAN
+#[derive(Debug, Serialize)]
+pub struct VlanStatsResponse {
+    pub vlan_id: String,
+    pub filter: String,
+    pub conversations: Vec<ConversationResponse>,
+    pub endpoints: Vec<EndpointResponse>,
+}
+
+/// Traffic through one kind of tunnel, unwrapped one level
+#[derive(Debug, Serialize)]
+pub struct TunnelStatsResponse {
+    pub kind: String,
+    pub filter: String,
+    pub tunnel_endpoints: Vec<ConversationResponse>,
+    pub inner_conversations: Vec<ConversationResponse>,
+}
+
+/// Response for VLAN/tunnel breakdown
+#[derive(Debug, Serialize)]
+pub struct OverlayStatsResponse {
+    pub vlans: Vec<VlanStatsResponse>,
+    pub tunnels: Vec<TunnelStatsResponse>,
+}
+
 /// Response for capture statistics
 #[derive(Debug, Serialize)]
 pub struct CaptureStatsResponse {
@@ -161,20 +256,119 @@ pub struct CaptureStatsResponse {
     pub endpoints: Vec<EndpointResponse>,
 }
 
+/// Query for capture statistics
+#[derive(Debug, Default, Deserialize)]
+pub struct CaptureStatsQuery {
+    /// Limit statistics to frames matching this display filter
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Recompute instead of using cached statistics
+    #[serde(default)]
+    pub force_refresh: bool,
+}
+
+/// Query for field metadata lookup
+#[derive(Debug, Deserialize)]
+pub struct FieldInfoQuery {
+    pub name: String,
+}
+
+/// Query for the field catalog
+#[derive(Debug, Deserialize)]
+pub struct FieldsQuery {
+    #[serde(default)]
+    pub protocol: Option<String>,
+}
+
+/// Query for field search
+#[derive(Debug, Deserialize)]
+pub struct SearchFieldsQuery {
+    pub query: String,
+    #[serde(default = "default_field_search_limit")]
+    pub limit: usize,
+}
+
+fn default_field_search_limit() -> usize {
+    crate::field_catalog::DEFAULT_SEARCH_LIMIT
+}
+
+/// Request body for an aggregate query
+#[derive(Debug, Deserialize)]
+pub struct QueryRequest {
+    pub expr: String,
+}
+
+/// Session capabilities for the sidecar
+#[derive(Debug, Serialize)]
+pub struct CapabilitiesResponse {
+    /// Mutating operations are refused (evidence-handling mode)
+    pub read_only: bool,
+    /// Which features work in this installation
+    pub features: BTreeMap<String, crate::features::FeatureStatus>,
+}
+
 /// Handler for GET /health
 async fn health_handler() -> &'static str {
     "ok"
 }
 
+/// Handler for GET /capabilities
+async fn capabilities_handler() -> Json<CapabilitiesResponse> {
+    let flags = crate::features::feature_flags().await;
+    Json(CapabilitiesResponse {
+        read_only: flags.read_only,
+        features: flags.features,
+    })
+}
+
+/// Apply the active session's anonymization policy and the redaction policy
+/// to frames sent to the sidecar
+fn sanitize_frames(frames: &mut [FrameData]) {
+    if let Some(anonymizer) = crate::anonymize::for_sidecar(&crate::sessions::resolve(None)) {
+        anonymizer.anonymize_frames(frames);
+    }
+    crate::redaction::redact_frames(&crate::redaction::policy(), frames);
+}
+
+/// Serialize a response for the sidecar with the session's anonymization
+/// policy applied to every address in it, including generated filters
+fn anonymized<T: Serialize>(session: &str, response: T) -> Json<serde_json::Value> {
+    let mut value = serde_json::to_value(response).unwrap_or_default();
+    if let Some(anonymizer) = crate::anonymize::for_sidecar(session) {
+        anonymizer.anonymize_value(&mut value);
+    }
+    Json(value)
+}
+
 /// Handler for POST /frames
 async fn get_frames_handler(Json(req): Json<FramesRequest>) -> Json<FramesResult> {
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
-        if let Ok(frames) = client.frames(req.skip, req.limit) {
-            if let Ok(status) = client.status() {
+    if let Ok(client) = sharkd(None) {
+        let session = crate::sessions::resolve(None);
+        // Ignored frames are masked by folding an exclusion into the filter
+        let frames = match crate::frame_marks::with_exclusions(&session, None) {
+            Some(filter) => client
+                .search_frames(&filter, req.skip, req.limit)
+                .await
+                .map(|(f, _)| f),
+            None => client.frames(req.skip, req.limit).await,
+        };
+        if let Ok(frames) = frames {
+            if let Ok(status) = client.status().await {
+                let mut frames = if req.include_position {
+                    frames_with_positions(&session, frames, req.skip)
+                } else {
+                    frames
+                        .into_iter()
+                        .map(|frame| FrameData::new(frame, &session))
+                        .collect()
+                };
+                sanitize_frames(&mut frames);
                 return Json(FramesResult {
-                    frames: frames.into_iter().map(FrameData::from).collect(),
-                    total: status.frames.unwrap_or(0),
+                    frames,
+                    total: status
+                        .frames
+                        .unwrap_or(0)
+                        .saturating_sub(crate::frame_marks::ignored_count(&session)),
                 });
             }
         }
@@ -189,9 +383,17 @@ async fn get_frames_handler(Json(req): Json<FramesRequest>) -> Json<FramesResult
 async fn get_frame_details_handler(
     Json(req): Json<FrameDetailsRequest>,
 ) -> Json<serde_json::Value> {
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
-        if let Ok(details) = client.frame(req.frame_num) {
+    if let Ok(client) = sharkd(None) {
+        if let Ok(mut details) = client.frame(req.frame_num).await {
+            // Plain-language summary for the AI to ground its explanation on
+            if let Ok(narration) = crate::narration::narrate_frame(&client, req.frame_num).await {
+                details["narration"] = serde_json::Value::String(narration);
+            }
+            if let Some(anonymizer) = crate::anonymize::for_sidecar(&crate::sessions::resolve(None))
+            {
+                anonymizer.anonymize_tree(&mut details);
+            }
+            crate::redaction::redact_value(&crate::redaction::policy(), &mut details);
             return Json(details);
         }
     }
@@ -200,9 +402,8 @@ async fn get_frame_details_handler(
 
 /// Handler for POST /check-filter
 async fn check_filter_handler(Json(req): Json<CheckFilterRequest>) -> Json<CheckFilterResponse> {
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
-        if let Ok(valid) = client.check_filter(&req.filter) {
+    if let Ok(client) = sharkd(None) {
+        if let Ok(valid) = client.check_filter(&req.filter).await {
             return Json(CheckFilterResponse { valid });
         }
     }
@@ -211,10 +412,10 @@ async fn check_filter_handler(Json(req): Json<CheckFilterRequest>) -> Json<Check
 
 /// Handler for POST /search - search packets with a display filter
 async fn search_handler(Json(req): Json<SearchRequest>) -> Json<SearchResult> {
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
+    if let Ok(client) = sharkd(None) {
+        let session = crate::sessions::resolve(None);
         // First validate the filter
-        if let Ok(valid) = client.check_filter(&req.filter) {
+        if let Ok(valid) = client.check_filter(&req.filter).await {
             if !valid {
                 return Json(SearchResult {
                     frames: vec![],
@@ -224,10 +425,21 @@ async fn search_handler(Json(req): Json<SearchRequest>) -> Json<SearchResult> {
             }
         }
 
-        // Execute the search
-        if let Ok((frames, total)) = client.search_frames(&req.filter, req.skip, req.limit) {
+        // Execute the search, masking ignored frames
+        let filter = crate::frame_marks::with_exclusions(&session, Some(&req.filter))
+            .unwrap_or_else(|| req.filter.clone());
+        if let Ok((frames, total)) = client.search_frames(&filter, req.skip, req.limit).await {
+            let mut frames = if req.include_position {
+                frames_with_positions(&session, frames, req.skip)
+            } else {
+                frames
+                    .into_iter()
+                    .map(|frame| FrameData::new(frame, &session))
+                    .collect()
+            };
+            sanitize_frames(&mut frames);
             return Json(SearchResult {
-                frames: frames.into_iter().map(FrameData::from).collect(),
+                frames,
                 total_matching: total,
                 filter_applied: req.filter,
             });
@@ -240,8 +452,38 @@ async fn search_handler(Json(req): Json<SearchRequest>) -> Json<SearchResult> {
     })
 }
 
-/// Handler for POST /stream - follow a TCP/UDP stream
-async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<StreamResponse> {
+/// Decode a base64 follow payload for display: `hex`, `raw` (base64 as is),
+/// or ASCII by default. Sensitive content is masked per the redaction policy.
+fn format_payload(data: &str, format: &str, policy: &RedactionPolicy) -> String {
+    let data = &crate::redaction::redact_base64(policy, data);
+    match format {
+        "hex" => {
+            // Decode base64 and convert to hex
+            BASE64
+                .decode(data)
+                .map(|bytes| {
+                    bytes
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .unwrap_or_else(|_| data.to_string())
+        }
+        "raw" => data.to_string(), // Keep base64 for raw
+        _ => {
+            // ascii (default) - decode base64 to string
+            BASE64
+                .decode(data)
+                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+                .unwrap_or_else(|_| "[binary data]".to_string())
+        }
+    }
+}
+
+/// Handler for POST /stream - follow a TCP/UDP/TLS/WebSocket/QUIC stream
+async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<serde_json::Value> {
+    let session = crate::sessions::resolve(None);
     let empty_response = StreamResponse {
         server: EndpointInfo {
             host: String::new(),
@@ -255,11 +497,21 @@ async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<StreamResponse>
         client_bytes: 0,
         segments: vec![],
         combined_text: None,
+        tls: None,
+        messages: None,
     };
 
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
-        if let Ok(stream) = client.follow_stream(&req.protocol, req.stream_id) {
+    if let Ok(client) = sharkd(None) {
+        if let Ok(stream) = client
+            .follow_stream(&req.protocol, req.stream_id, req.sub_stream)
+            .await
+        {
+            let policy = crate::redaction::policy();
+            // Under the anonymization policy's payload stripping only the
+            // endpoints and sizes go to the sidecar
+            let strip_payloads = crate::anonymize::for_sidecar(&session)
+                .map_or(false, |anonymizer| anonymizer.strips_payloads());
+
             // Decode and format the payload segments
             let segments: Vec<StreamSegment> = stream
                 .payloads
@@ -271,28 +523,10 @@ async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<StreamResponse>
                         "server_to_client"
                     };
 
-                    let data = match req.format.as_str() {
-                        "hex" => {
-                            // Decode base64 and convert to hex
-                            BASE64
-                                .decode(&p.d)
-                                .map(|bytes| {
-                                    bytes
-                                        .iter()
-                                        .map(|b| format!("{:02x}", b))
-                                        .collect::<Vec<_>>()
-                                        .join(" ")
-                                })
-                                .unwrap_or_else(|_| p.d.clone())
-                        }
-                        "raw" => p.d.clone(), // Keep base64 for raw
-                        _ => {
-                            // ascii (default) - decode base64 to string
-                            BASE64
-                                .decode(&p.d)
-                                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
-                                .unwrap_or_else(|_| "[binary data]".to_string())
-                        }
+                    let data = if strip_payloads {
+                        crate::anonymize::PAYLOAD_REMOVED.to_string()
+                    } else {
+                        format_payload(&p.d, &req.format, &policy)
                     };
 
                     StreamSegment {
@@ -303,8 +537,28 @@ async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<StreamResponse>
                 })
                 .collect();
 
+            let messages = stream.messages.as_ref().map(|messages| {
+                messages
+                    .iter()
+                    .map(|m| StreamMessage {
+                        frame: m.frame,
+                        direction: m.direction.clone(),
+                        kind: m.kind.clone(),
+                        fin: m.fin,
+                        size: m.size,
+                        data: m
+                            .data
+                            .as_deref()
+                            .filter(|_| !strip_payloads)
+                            .map(|d| format_payload(d, &req.format, &policy)),
+                    })
+                    .collect()
+            });
+
             // Build combined text for ASCII format
-            let combined_text = if req.format == "ascii" || req.format.is_empty() {
+            let combined_text = if strip_payloads {
+                None
+            } else if req.format == "ascii" || req.format.is_empty() {
                 Some(
                     segments
                         .iter()
@@ -316,27 +570,36 @@ async fn stream_handler(Json(req): Json<StreamRequest>) -> Json<StreamResponse>
                 None
             };
 
-            return Json(StreamResponse {
-                server: EndpointInfo {
-                    host: stream.shost,
-                    port: stream.sport,
+            return anonymized(
+                &session,
+                StreamResponse {
+                    server: EndpointInfo {
+                        host: stream.shost,
+                        port: stream.sport,
+                    },
+                    client: EndpointInfo {
+                        host: stream.chost,
+                        port: stream.cport,
+                    },
+                    server_bytes: stream.sbytes,
+                    client_bytes: stream.cbytes,
+                    segments,
+                    combined_text,
+                    tls: stream.tls,
+                    messages,
                 },
-                client: EndpointInfo {
-                    host: stream.chost,
-                    port: stream.cport,
-                },
-                server_bytes: stream.sbytes,
-                client_bytes: stream.cbytes,
-                segments,
-                combined_text,
-            });
+            );
         }
     }
-    Json(empty_response)
+    anonymized(&session, empty_response)
 }
 
-/// Handler for GET /capture-stats - get capture statistics
-async fn capture_stats_handler() -> Json<CaptureStatsResponse> {
+/// Handler for GET /capture-stats?filter=...&force_refresh=... - get capture
+/// statistics, optionally limited to a display filter. Statistics are cached
+/// per capture and filter unless `force_refresh` is set.
+async fn capture_stats_handler(Query(query): Query<CaptureStatsQuery>) -> Json<serde_json::Value> {
+    let session = crate::sessions::resolve(None);
+    let user_filter = query.filter.filter(|f| !f.trim().is_empty());
     let empty_response = CaptureStatsResponse {
         summary: StatsSummary {
             total_frames: 0,
@@ -345,6 +608,7 @@ async fn capture_stats_handler() -> Json<CaptureStatsResponse> {
             tcp_conversation_count: 0,
             udp_conversation_count: 0,
             endpoint_count: 0,
+            filter: user_filter.clone(),
         },
         protocol_hierarchy: vec![],
         tcp_conversations: vec![],
@@ -352,72 +616,174 @@ async fn capture_stats_handler() -> Json<CaptureStatsResponse> {
         endpoints: vec![],
     };
 
-    let client_guard = get_sharkd().lock();
-    if let Some(client) = client_guard.as_ref() {
+    if let Ok(client) = sharkd(None) {
         // Get basic status for frame count and duration
-        let status = client.status().ok();
+        let status = client.status().await.ok();
 
         // Get capture statistics (single batched sharkd request - 4 taps in 1 call)
-        if let Ok(stats) = client.capture_stats() {
+        // Ignored frames are excluded from statistics
+        let filter = crate::frame_marks::with_exclusions(&session, user_filter.as_deref());
+        if let Ok(stats) =
+            crate::stats_cache::capture_stats(&client, filter.as_deref(), query.force_refresh).await
+        {
             let protocol_hierarchy = convert_protocol_nodes(&stats.protocol_hierarchy);
             let protocol_count = count_protocols(&stats.protocol_hierarchy);
+            let total_frames = if user_filter.is_some() {
+                // Under a filter, the hierarchy roots count the matching frames
+                stats.protocol_hierarchy.iter().map(|n| n.frames).sum()
+            } else {
+                status
+                    .as_ref()
+                    .and_then(|s| s.frames)
+                    .unwrap_or(0)
+                    .saturating_sub(crate::frame_marks::ignored_count(&session))
+            };
 
-            return Json(CaptureStatsResponse {
-                summary: StatsSummary {
-                    total_frames: status.as_ref().and_then(|s| s.frames).unwrap_or(0),
-                    duration: status.as_ref().and_then(|s| s.duration),
-                    protocol_count,
-                    tcp_conversation_count: stats.tcp_conversations.len(),
-                    udp_conversation_count: stats.udp_conversations.len(),
-                    endpoint_count: stats.endpoints.len(),
+            return anonymized(
+                &session,
+                CaptureStatsResponse {
+                    summary: StatsSummary {
+                        total_frames,
+                        duration: status.as_ref().and_then(|s| s.duration),
+                        protocol_count,
+                        tcp_conversation_count: stats.tcp_conversations.len(),
+                        udp_conversation_count: stats.udp_conversations.len(),
+                        endpoint_count: stats.endpoints.len(),
+                        filter: user_filter,
+                    },
+                    protocol_hierarchy,
+                    tcp_conversations: stats
+                        .tcp_conversations
+                        .into_iter()
+                        .map(ConversationResponse::from)
+                        .collect(),
+                    udp_conversations: stats
+                        .udp_conversations
+                        .into_iter()
+                        .map(ConversationResponse::from)
+                        .collect(),
+                    endpoints: stats
+                        .endpoints
+                        .into_iter()
+                        .map(EndpointResponse::from)
+                        .collect(),
                 },
-                protocol_hierarchy,
-                tcp_conversations: stats
-                    .tcp_conversations
-                    .into_iter()
-                    .map(|c| ConversationResponse {
-                        src_addr: c.saddr,
-                        dst_addr: c.daddr,
-                        src_port: c.sport,
-                        dst_port: c.dport,
-                        rx_frames: c.rxf,
-                        rx_bytes: c.rxb,
-                        tx_frames: c.txf,
-                        tx_bytes: c.txb,
-                        filter: c.filter,
-                    })
-                    .collect(),
-                udp_conversations: stats
-                    .udp_conversations
-                    .into_iter()
-                    .map(|c| ConversationResponse {
-                        src_addr: c.saddr,
-                        dst_addr: c.daddr,
-                        src_port: c.sport,
-                        dst_port: c.dport,
-                        rx_frames: c.rxf,
-                        rx_bytes: c.rxb,
-                        tx_frames: c.txf,
-                        tx_bytes: c.txb,
-                        filter: c.filter,
-                    })
-                    .collect(),
-                endpoints: stats
-                    .endpoints
-                    .into_iter()
-                    .map(|e| EndpointResponse {
-                        host: e.host,
-                        port: e.port,
-                        rx_frames: e.rxf,
-                        rx_bytes: e.rxb,
-                        tx_frames: e.txf,
-                        tx_bytes: e.txb,
-                    })
-                    .collect(),
-            });
+            );
         }
     }
-    Json(empty_response)
+    anonymized(&session, empty_response)
+}
+
+/// Handler for GET /mobile - GTP, Diameter and SCTP summary
+async fn mobile_handler(Query(query): Query<CaptureStatsQuery>) -> Json<serde_json::Value> {
+    let session = crate::sessions::resolve(None);
+    if let Ok(client) = sharkd(None) {
+        let filter = crate::frame_marks::with_exclusions(&session, query.filter.as_deref());
+        if let Ok(summary) = crate::mobile::mobile_summary(&client, filter.as_deref()).await {
+            return anonymized(&session, summary);
+        }
+    }
+    anonymized(&session, MobileSummary::default())
+}
+
+/// Handler for GET /overlay-stats - per-VLAN and per-tunnel traffic breakdown
+async fn overlay_stats_handler() -> Json<serde_json::Value> {
+    let session = crate::sessions::resolve(None);
+    if let Ok(client) = sharkd(None) {
+        let exclusions = crate::frame_marks::with_exclusions(&session, None);
+        if let Ok(overlay) = client.overlay_stats(exclusions.as_deref()).await {
+            return anonymized(
+                &session,
+                OverlayStatsResponse {
+                    vlans: overlay
+                        .vlans
+                        .into_iter()
+                        .map(|v| VlanStatsResponse {
+                            vlan_id: v.vlan_id,
+                            filter: v.filter,
+                            conversations: v.conversations.into_iter().map(Into::into).collect(),
+                            endpoints: v.endpoints.into_iter().map(Into::into).collect(),
+                        })
+                        .collect(),
+                    tunnels: overlay
+                        .tunnels
+                        .into_iter()
+                        .map(|t| TunnelStatsResponse {
+                            kind: t.kind,
+                            filter: t.filter,
+                            tunnel_endpoints: t
+                                .tunnel_endpoints
+                                .into_iter()
+                                .map(Into::into)
+                                .collect(),
+                            inner_conversations: t
+                                .inner_conversations
+                                .into_iter()
+                                .map(Into::into)
+                                .collect(),
+                        })
+                        .collect(),
+                },
+            );
+        }
+    }
+    anonymized(
+        &session,
+        OverlayStatsResponse {
+            vlans: vec![],
+            tunnels: vec![],
+        },
+    )
+}
+
+/// Handler for GET /field-info?name=tcp.flags.syn - display filter field metadata
+async fn field_info_handler(Query(query): Query<FieldInfoQuery>) -> Json<serde_json::Value> {
+    match crate::field_catalog::field_info(&query.name) {
+        Ok(Some(info)) => Json(serde_json::to_value(info).unwrap_or_default()),
+        Ok(None) => Json(serde_json::json!({"error": format!("Unknown field: {}", query.name)})),
+        Err(e) => Json(serde_json::json!({"error": e})),
+    }
+}
+
+/// Handler for GET /fields?protocol=tcp - the dissector field catalog
+async fn fields_handler(Query(query): Query<FieldsQuery>) -> Json<serde_json::Value> {
+    match crate::field_catalog::list_fields(query.protocol.as_deref()) {
+        Ok(fields) => Json(serde_json::to_value(fields).unwrap_or_default()),
+        Err(e) => Json(serde_json::json!({"error": e})),
+    }
+}
+
+/// Handler for GET /search-fields?query=syn - search dissector fields
+async fn search_fields_handler(
+    Query(query): Query<SearchFieldsQuery>,
+) -> Json<Vec<crate::field_catalog::FieldInfo>> {
+    Json(crate::field_catalog::search_fields(&query.query, query.limit).unwrap_or_default())
+}
+
+/// Handler for GET /saved-filters - the user's named filters
+async fn saved_filters_handler() -> Json<Vec<crate::saved_filters::SavedFilter>> {
+    Json(crate::saved_filters::list_saved_filters())
+}
+
+/// Handler for GET /filter-history - recently applied filters, most recent first
+async fn filter_history_handler() -> Json<Vec<crate::saved_filters::FilterHistoryEntry>> {
+    Json(crate::saved_filters::list_filter_history(None))
+}
+
+/// Handler for POST /query - aggregate query over capture fields
+async fn query_handler(Json(req): Json<QueryRequest>) -> Json<serde_json::Value> {
+    if let Ok(client) = sharkd(None) {
+        let session = crate::sessions::resolve(None);
+        return match crate::query::run_query(&client, &session, &req.expr).await {
+            Ok(result) => {
+                let Json(mut value) = anonymized(&session, result);
+                crate::redaction::redact_value(&crate::redaction::policy(), &mut value);
+                Json(value)
+            }
+            Err(e) => Json(serde_json::json!({"error": e})),
+        };
+    }
+    Json(serde_json::json!({"error": "Sharkd not initialized"}))
 }
 
 /// Convert protocol nodes from sharkd format to response format
@@ -430,6 +796,8 @@ fn convert_protocol_nodes(
             protocol: n.protocol.clone(),
             frames: n.frames,
             bytes: n.bytes,
+            frame_percent: n.frame_percent,
+            filter: n.filter.clone(),
             children: convert_protocol_nodes(&n.children),
         })
         .collect()
@@ -437,28 +805,39 @@ fn convert_protocol_nodes(
 
 /// Count total protocols in hierarchy
 fn count_protocols(nodes: &[crate::sharkd_client::ProtocolNode]) -> usize {
-    nodes
-        .iter()
-        .map(|n| 1 + count_protocols(&n.children))
-        .sum()
+    nodes.iter().map(|n| 1 + count_protocols(&n.children)).sum()
 }
 
-/// Start the HTTP bridge server on port 8766
-pub async fn start_http_bridge() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// The bridge's routes
+pub fn router() -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let app = Router::new()
+    Router::new()
         .route("/health", get(health_handler))
+        .route("/capabilities", get(capabilities_handler))
         .route("/frames", post(get_frames_handler))
         .route("/frame-details", post(get_frame_details_handler))
         .route("/check-filter", post(check_filter_handler))
         .route("/search", post(search_handler))
         .route("/stream", post(stream_handler))
         .route("/capture-stats", get(capture_stats_handler))
-        .layer(cors);
+        .route("/overlay-stats", get(overlay_stats_handler))
+        .route("/mobile", get(mobile_handler))
+        .route("/field-info", get(field_info_handler))
+        .route("/fields", get(fields_handler))
+        .route("/search-fields", get(search_fields_handler))
+        .route("/query", post(query_handler))
+        .route("/saved-filters", get(saved_filters_handler))
+        .route("/filter-history", get(filter_history_handler))
+        .layer(cors)
+}
+
+/// Start the HTTP bridge server on port 8766
+pub async fn start_http_bridge() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let app = router();
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 8766));
     println!("Rust HTTP bridge listening on {}", addr);
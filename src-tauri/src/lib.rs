@@ -1,196 +1,2654 @@
+mod anonymize;
 mod auth;
+mod automation;
+mod benchmarks;
+mod bundle;
+mod byte_search;
+mod cache_manager;
+mod capture_edit;
+mod capture_file;
+mod capture_filter;
+mod coloring_rules;
+mod columns;
+mod comm_graph;
+mod comments;
+mod dashboards;
+mod decryption;
+mod decryption_keys;
+mod dissection;
+mod evidence;
+mod external_tools;
+mod features;
+mod field_catalog;
+mod filter_compare;
+mod filter_suggestions;
+mod frame_export;
+mod frame_marks;
+mod har;
+mod hex_dump;
+mod history;
 mod http_bridge;
+mod log_ingest;
+mod mobile;
+mod narration;
+mod notifications;
+mod palette;
+mod profile;
+mod protobuf;
 mod python_sidecar;
+mod query;
+mod redaction;
+mod report;
+mod saved_filters;
+mod scheduler;
+mod selection;
+mod sessions;
+mod sharkd_chaos;
 mod sharkd_client;
+mod sharkd_trace;
+mod stats_cache;
+mod storage;
+mod tcp_graph;
+#[cfg(feature = "testsupport")]
+pub mod testsupport;
+mod text_search;
+mod time_format;
+mod undo;
+mod upload;
+mod wireshark_tools;
+mod wlan;
+mod workspace;
 
-use parking_lot::Mutex;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use selection::FrameRange;
 use serde::{Deserialize, Serialize};
-use sharkd_client::{Frame, InstallHealthStatus, SharkdClient, Status};
-use std::sync::OnceLock;
-use tauri::Emitter;
+use sharkd_client::{
+    AddressKind, CaptureStats, Conversation, Crash, Endpoint, ExportObject, FlowGraph, Frame,
+    FrameDetails, HttpStats, InstallHealthStatus, IoGraph, OverlayStats, RtpStream, SharkdClient,
+    SrtTable, Status, StreamData, StreamDirection, VoipCall,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::mpsc;
 
-// Global sharkd client instance
-static SHARKD: OnceLock<Mutex<Option<SharkdClient>>> = OnceLock::new();
+// Crash reports from supervised sharkd clients, handled by `handle_sharkd_crashes`
+static SHARKD_CRASHES: OnceLock<mpsc::UnboundedSender<Crash>> = OnceLock::new();
 
-fn get_sharkd() -> &'static Mutex<Option<SharkdClient>> {
-    SHARKD.get_or_init(|| Mutex::new(None))
+/// Crashes within this window count towards `MAX_CRASH_RESTARTS`
+const CRASH_WINDOW: Duration = Duration::from_secs(60);
+
+/// Automatic restarts allowed within `CRASH_WINDOW` before giving up
+const MAX_CRASH_RESTARTS: usize = 3;
+
+/// A request still running this long when its operation is cancelled is
+/// aborted by respawning sharkd; shorter ones are left to finish
+const CANCEL_RESPAWN_AFTER: Duration = Duration::from_secs(2);
+
+/// Upper bound on I/O graph buckets; longer captures get wider intervals
+const MAX_IO_GRAPH_BUCKETS: u32 = 10_000;
+
+/// Default number of frames drawn in a flow graph
+const DEFAULT_FLOW_GRAPH_FRAMES: u32 = 10_000;
+
+/// The sharkd client of a session (the active one when `session_id` is None)
+pub(crate) fn sharkd(session_id: Option<&str>) -> Result<Arc<SharkdClient>, String> {
+    sessions::get(session_id)
+}
+
+// App handle, set during setup so backend modules can emit events
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Emit an event to the frontend (no-op before the app is set up)
+pub(crate) fn emit_event<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = app_handle.emit(event, payload);
+    }
+}
+
+/// Emit an event about one session to the windows showing it
+pub(crate) fn emit_session_event<S: Serialize + Clone>(session_id: &str, event: &str, payload: S) {
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = app_handle.emit_filter(event, payload, |target| match target {
+            EventTarget::Window { label }
+            | EventTarget::Webview { label }
+            | EventTarget::WebviewWindow { label } => sessions::routes_to(session_id, label),
+            _ => true,
+        });
+    }
+}
+
+/// Response for load_pcap command
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoadResult {
+    pub success: bool,
+    pub frame_count: u64,
+    pub duration: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Response for apply_filter command
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilterCount {
+    /// Frames matching the filter
+    pub matched: u64,
+    /// Frames in the capture
+    pub total: u64,
+}
+
+/// Response for get_frames command
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FramesResult {
+    pub frames: Vec<FrameData>,
+    pub total: u64,
+}
+
+/// Audio of an RTP stream
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RtpAudio {
+    /// MIME type of the audio (`audio/x-wav`)
+    pub mime: String,
+    pub bytes: u64,
+    /// Base64 audio, when it wasn't saved to a file
+    pub data: Option<String>,
+}
+
+/// The two hosts of a conversation a flow graph is restricted to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlowConversation {
+    pub address_a: String,
+    pub address_b: String,
+}
+
+impl FlowConversation {
+    fn filter(&self) -> String {
+        let field = if self.address_a.contains(':') {
+            "ipv6.addr"
+        } else {
+            "ip.addr"
+        };
+        format!(
+            "{} == {} && {} == {}",
+            field, self.address_a, field, self.address_b
+        )
+    }
+}
+
+/// Simplified frame data for frontend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameData {
+    pub number: u32,
+    pub time: String,
+    pub source: String,
+    pub destination: String,
+    pub protocol: String,
+    pub length: String,
+    pub info: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    /// 1-based position within the current (possibly filtered) result set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<u64>,
+    /// Values of the configured custom columns, in column order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom: Vec<String>,
+    /// Whether the frame is marked
+    #[serde(default)]
+    pub marked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthModeCapability {
+    pub mode: String,
+    pub label: String,
+    pub supported: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthCapabilities {
+    pub modes: Vec<AuthModeCapability>,
+}
+
+impl FrameData {
+    /// A frame of `session`'s capture, flagged if marked there
+    pub(crate) fn new(frame: Frame, session: &str) -> Self {
+        // sharkd columns: Index 0: No., 1: Time, 2: Source, 3: Destination, 4: Protocol, 5: Length, 6: Info
+        let cols = &frame.columns;
+        FrameData {
+            number: frame.number,
+            time: cols.get(1).cloned().unwrap_or_default(),
+            source: cols.get(2).cloned().unwrap_or_default(),
+            destination: cols.get(3).cloned().unwrap_or_default(),
+            protocol: cols.get(4).cloned().unwrap_or_default(),
+            length: cols.get(5).cloned().unwrap_or_default(),
+            info: cols.get(6).cloned().unwrap_or_default(),
+            background: frame.background,
+            foreground: frame.foreground,
+            position: None,
+            custom: Vec::new(),
+            marked: frame_marks::is_marked(session, frame.number),
+        }
+    }
+}
+
+/// Convert a page of `session`'s frames, recording each frame's position within the
+/// result set. `skip` is the offset of the page, so positions stay correct while paging
+/// a filtered view.
+pub(crate) fn frames_with_positions(
+    session: &str,
+    frames: Vec<Frame>,
+    skip: u32,
+) -> Vec<FrameData> {
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(i, frame)| FrameData {
+            position: Some(skip as u64 + i as u64 + 1),
+            ..FrameData::new(frame, session)
+        })
+        .collect()
+}
+
+/// Spawn sharkd and apply the stored decryption keys and protobuf preferences
+async fn spawn_sharkd() -> Result<SharkdClient, String> {
+    let client = SharkdClient::new().await?;
+    decryption_keys::apply_keys(&client).await;
+    protobuf::apply_prefs(&client).await;
+    Ok(client)
+}
+
+/// Share a freshly spawned client, supervising it for crashes
+fn supervised(client: SharkdClient) -> Arc<SharkdClient> {
+    let client = Arc::new(client);
+    if let Some(crashes) = SHARKD_CRASHES.get() {
+        sharkd_client::supervise(&client, crashes.clone());
+    }
+    client
+}
+
+/// Respawn sharkd when a supervised client's process dies, reloading the
+/// capture that was open. Gives up after repeated crashes in quick succession.
+async fn handle_sharkd_crashes(mut crashes: mpsc::UnboundedReceiver<Crash>) {
+    let mut recent: Vec<Instant> = Vec::new();
+    while let Some(crash) = crashes.recv().await {
+        // Ignore clients that were replaced or closed before the report arrived
+        let session_id = match crash.client.upgrade().and_then(|c| sessions::find(&c)) {
+            Some(id) => id,
+            None => continue,
+        };
+
+        eprintln!("Warning: {}", crash.reason);
+        emit_session_event(&session_id, "sharkd-crashed", crash.reason.clone());
+
+        recent.retain(|at| at.elapsed() < CRASH_WINDOW);
+        recent.push(Instant::now());
+        if recent.len() > MAX_CRASH_RESTARTS {
+            emit_session_event(
+                &session_id,
+                "sharkd-error",
+                format!(
+                    "sharkd crashed {} times within a minute; not restarting it again",
+                    recent.len()
+                ),
+            );
+            continue;
+        }
+
+        match restart_sharkd(&session_id).await {
+            Ok(()) => println!("Sharkd restarted after crash"),
+            Err(e) => {
+                eprintln!("Warning: Failed to restart sharkd: {}", e);
+                emit_session_event(&session_id, "sharkd-error", e);
+            }
+        }
+    }
+}
+
+/// The client of a session, spawning sharkd for it on first use
+async fn session_client(session_id: &str) -> Result<Arc<SharkdClient>, String> {
+    if let Ok(client) = sessions::get(Some(session_id)) {
+        return Ok(client);
+    }
+    let client = supervised(spawn_sharkd().await?);
+    // Another caller may have finished spawning while this one was starting up
+    Ok(sessions::insert_if_absent(session_id, client))
+}
+
+/// Initialize sharkd (spawn the process) for a session
+#[tauri::command]
+async fn init_sharkd(session_id: Option<String>) -> Result<String, String> {
+    let session_id = sessions::resolve(session_id.as_deref());
+    if sessions::contains(&session_id) {
+        return Ok("Sharkd already initialized".to_string());
+    }
+
+    session_client(&session_id).await?;
+    Ok("Sharkd initialized successfully".to_string())
+}
+
+/// Respawn a session's sharkd (e.g. after its configuration changed) and
+/// reload the capture that was open, if any
+async fn restart_sharkd(session_id: &str) -> Result<(), String> {
+    // Drop the old handle before spawning its replacement; the process is
+    // killed once in-flight requests release it
+    let previous = sessions::take(session_id);
+    let loaded_file = previous.and_then(|c| c.loaded_file());
+
+    let client = spawn_sharkd().await?;
+    decryption::apply_env_keylog(&client).await;
+    if let Some(path) = loaded_file {
+        reopen_capture(&client, &path).await?;
+    }
+    sessions::replace(session_id, supervised(client));
+    Ok(())
+}
+
+/// Load a capture again after sharkd's dissection settings changed. Unlike
+/// `load_pcap`, the session's marks, selection, undo history, and time
+/// references are kept, since the frames are the same.
+async fn reopen_capture(client: &SharkdClient, path: &str) -> Result<(), String> {
+    client.load(path).await?;
+    dashboards::clear_cache();
+    decryption::clear_cache();
+    byte_search::clear_cache();
+    stats_cache::invalidate(path);
+    comments::apply_comments(client, path).await;
+    Ok(())
+}
+
+/// Restart every session's sharkd so a configuration change reaches all of them
+async fn restart_all_sharkd() -> Result<(), String> {
+    for session_id in sessions::ids() {
+        restart_sharkd(&session_id).await?;
+    }
+    Ok(())
+}
+
+/// Cancel a session's long-running operations: loading, full-capture
+/// searches and exports, and statistics. Paged operations stop before their
+/// next sharkd request; a request sharkd has been working on for a while is
+/// aborted by killing it and spawning a fresh sharkd, which reopens the
+/// capture unless the request was loading it. Returns whether sharkd was
+/// respawned.
+#[tauri::command]
+async fn cancel_operations(session_id: Option<String>) -> Result<bool, String> {
+    let session_id = sessions::resolve(session_id.as_deref());
+    let client = match sessions::get(Some(&session_id)) {
+        Ok(client) => client,
+        // Nothing runs in a session without sharkd
+        Err(_) => return Ok(false),
+    };
+    let loading = match client.cancel_operations() {
+        Some((method, elapsed)) if method == "load" || elapsed >= CANCEL_RESPAWN_AFTER => {
+            method == "load"
+        }
+        _ => return Ok(false),
+    };
+    drop(client);
+
+    // The capture a load was replacing had already been closed
+    abort_sharkd(&session_id, !loading).await?;
+    Ok(true)
+}
+
+/// Kill a session's sharkd mid-request and spawn a fresh one, reopening the
+/// capture that was open when `reload` is set
+async fn abort_sharkd(session_id: &str, reload: bool) -> Result<(), String> {
+    // Removed from the session first so the supervisor doesn't take the
+    // kill for a crash
+    let previous = sessions::take(session_id);
+    let loaded_file = previous.as_ref().and_then(|c| c.loaded_file());
+    if let Some(previous) = previous {
+        previous.kill();
+    }
+
+    let client = spawn_sharkd().await?;
+    decryption::apply_env_keylog(&client).await;
+    if let Some(path) = loaded_file.filter(|_| reload) {
+        reopen_capture(&client, &path).await?;
+    }
+    sessions::replace(session_id, supervised(client));
+    Ok(())
+}
+
+/// Load a PCAP file into a session, spawning its sharkd if needed
+#[tauri::command]
+async fn load_pcap(path: String, session_id: Option<String>) -> Result<LoadResult, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let client = session_client(&session).await?;
+
+    if let Some(previous) = client.loaded_file() {
+        evidence::record_close(&previous);
+        cache_manager::release_capture(&previous);
+    }
+
+    decryption::apply_env_keylog(&client).await;
+
+    // Load the file
+    let operation = client.operation();
+    if let Err(e) = client.load(&path).await {
+        let error = if operation.is_cancelled() {
+            "Loading cancelled".to_string()
+        } else {
+            e
+        };
+        return Ok(LoadResult {
+            success: false,
+            frame_count: 0,
+            duration: None,
+            error: Some(error),
+        });
+    }
+
+    comments::apply_comments(&client, &path).await;
+    // Only this session's state belongs to the capture it replaced
+    frame_marks::clear(&session);
+    selection::clear_selection(&session);
+    undo::clear(&session);
+    time_format::clear_references(&session);
+    dashboards::clear_cache();
+    decryption::clear_cache();
+    byte_search::clear_cache();
+    stats_cache::invalidate(&path);
+    log_ingest::clear_triage(&session);
+    capture_file::watch(&path);
+    if let Some(layout) = columns::apply_capture_layout(&path) {
+        emit_session_event(&session, "column-layout-changed", layout);
+    }
+
+    evidence::record_open(&path);
+
+    // History entries carry a summary of the capture so it can be searched later
+    let history_path = path.clone();
+    let summary_client = client.clone();
+    scheduler::spawn(
+        "Summarize capture",
+        scheduler::Priority::Background,
+        Some(&session),
+        async move {
+            let summary = match history::summarize(&summary_client).await {
+                Ok(summary) => Some(summary),
+                Err(e) => {
+                    eprintln!("Warning: Failed to summarize capture: {}", e);
+                    None
+                }
+            };
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = history::record_open(&history_path, summary) {
+                    eprintln!("Warning: Failed to record capture history: {}", e);
+                }
+            })
+            .await;
+        },
+    );
+
+    // Surface TLS decryption hints once the load has finished
+    let hints_client = client.clone();
+    let hints_session = session.clone();
+    scheduler::spawn(
+        "Decryption hints",
+        scheduler::Priority::Prefetch,
+        Some(&session),
+        async move {
+            match decryption::decryption_hints(&hints_client).await {
+                Ok(hints) if hints.tls_sessions > 0 => {
+                    emit_session_event(&hints_session, "decryption-hints", hints)
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Warning: Failed to compute decryption hints: {}", e),
+            }
+        },
+    );
+
+    // Get status to get frame count
+    let status = client.status().await?;
+
+    Ok(LoadResult {
+        success: true,
+        frame_count: status.frames.unwrap_or(0),
+        duration: status.duration,
+        error: None,
+    })
+}
+
+/// Get frames with pagination, optionally restricted to a display filter.
+/// With `include_position`, each frame also carries its position within the result set
+/// alongside its absolute frame number.
+#[tauri::command]
+async fn get_frames(
+    skip: u32,
+    limit: u32,
+    filter: Option<String>,
+    include_position: Option<bool>,
+    session_id: Option<String>,
+) -> Result<FramesResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    // Ignored frames are masked by folding an exclusion into the filter
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref());
+    let frames = match &filter {
+        Some(f) => client.search_frames(f, skip, limit).await?.0,
+        None => client.frames(skip, limit).await?,
+    };
+    // Filtered listings page through the matching frames, not the capture
+    let total = match &filter {
+        Some(f) => stats_cache::count_frames(&client, f).await?,
+        None => client
+            .status()
+            .await?
+            .frames
+            .unwrap_or(0)
+            .saturating_sub(frame_marks::ignored_count(&session)),
+    };
+
+    let mut frames: Vec<FrameData> = if include_position.unwrap_or(false) {
+        frames_with_positions(&session, frames, skip)
+    } else {
+        frames
+            .into_iter()
+            .map(|frame| FrameData::new(frame, &session))
+            .collect()
+    };
+
+    // Other time formats and time references come from a custom column
+    if !time_format::is_default(&session) && !frames.is_empty() {
+        let settings = time_format::settings(&session);
+        let references: Vec<u32> = settings.references.iter().copied().collect();
+        let times: HashMap<u32, String> = client
+            .frames_with_refs(
+                filter.as_deref().unwrap_or_default(),
+                skip,
+                limit,
+                &[settings.format.field()],
+                &references,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|f| Some((f.number, f.columns.into_iter().next()?)))
+            .collect();
+        for frame in &mut frames {
+            if let Some(time) = times.get(&frame.number) {
+                frame.time = if settings.references.contains(&frame.number) {
+                    "*REF*".to_string()
+                } else {
+                    time.clone()
+                };
+            }
+        }
+    }
+
+    Ok(FramesResult { frames, total })
+}
+
+/// Get frames like `get_frames`, with the configured custom columns' values
+/// in each frame's `custom` list
+#[tauri::command]
+async fn get_frames_with_columns(
+    skip: u32,
+    limit: u32,
+    filter: Option<String>,
+    include_position: Option<bool>,
+    session_id: Option<String>,
+) -> Result<FramesResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let mut result = get_frames(
+        skip,
+        limit,
+        filter.clone(),
+        include_position,
+        session_id.clone(),
+    )
+    .await?;
+
+    let specs: Vec<String> = columns::get_columns().iter().map(|c| c.spec()).collect();
+    if specs.is_empty() || result.frames.is_empty() {
+        return Ok(result);
+    }
+    let specs: Vec<&str> = specs.iter().map(String::as_str).collect();
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref()).unwrap_or_default();
+    let values: HashMap<u32, Vec<String>> = client
+        .frames_with_columns(&filter, skip, limit, &specs)
+        .await?
+        .into_iter()
+        .map(|f| (f.number, f.columns))
+        .collect();
+
+    for frame in &mut result.frames {
+        if let Some(custom) = values.get(&frame.number) {
+            frame.custom = custom.clone();
+        }
+    }
+    Ok(result)
+}
+
+/// Write the (optionally filtered) packet list with the current columns to
+/// `path` as CSV or JSON Lines
+#[tauri::command]
+async fn export_frames(
+    filter: Option<String>,
+    format: frame_export::FrameExportFormat,
+    path: String,
+    session_id: Option<String>,
+) -> Result<frame_export::FrameExportResult, String> {
+    let result = frame_export::export_frames(filter, format, &path, session_id).await?;
+    evidence::record_export(&path, "frames");
+    Ok(result)
+}
+
+/// Get current status
+#[tauri::command]
+async fn get_status(session_id: Option<String>) -> Result<Status, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.status().await
+}
+
+/// Check if a display filter is valid
+#[tauri::command]
+async fn check_filter(filter: String, session_id: Option<String>) -> Result<bool, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.check_filter(&filter).await
+}
+
+/// Frames whose Info, Source or Destination contains `query` (a regular
+/// expression with `regex`), optionally among those matching a display
+/// filter. Case-insensitive unless `case_sensitive`. Ignored frames are skipped.
+#[tauri::command]
+async fn search_text(
+    query: String,
+    regex: bool,
+    case_sensitive: Option<bool>,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<text_search::TextSearchResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let matcher = text_search::TextMatcher::new(&query, regex, case_sensitive.unwrap_or(false))?;
+
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref());
+    text_search::search_text(&client, &matcher, filter.as_deref()).await
+}
+
+/// The next or previous frame from `from_frame` matching `query`, read as a
+/// display filter, summary text, or a regular expression over the summary,
+/// for find next / find previous. Wraps around the capture unless `wrap` is
+/// false; None when nothing matches. Ignored frames are skipped.
+#[tauri::command]
+async fn find_frame(
+    query: String,
+    mode: text_search::FindMode,
+    case_sensitive: Option<bool>,
+    from_frame: u32,
+    direction: text_search::FindDirection,
+    wrap: Option<bool>,
+    session_id: Option<String>,
+) -> Result<Option<u32>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let query = match mode {
+        text_search::FindMode::Filter => {
+            if !client.check_filter(&query).await? {
+                return Err(format!("Invalid filter expression: {}", query));
+            }
+            text_search::FindQuery::Filter(query)
+        }
+        text_search::FindMode::Text | text_search::FindMode::Regex => {
+            text_search::FindQuery::Text(text_search::TextMatcher::new(
+                &query,
+                mode == text_search::FindMode::Regex,
+                case_sensitive.unwrap_or(false),
+            )?)
+        }
+    };
+
+    let exclusions = frame_marks::with_exclusions(&session, None);
+    text_search::find_frame(
+        &client,
+        &query,
+        exclusions.as_deref(),
+        from_frame,
+        direction,
+        wrap.unwrap_or(true),
+    )
+    .await
+}
+
+/// Frames whose bytes contain a hex pattern or a string, with the offsets of
+/// each occurrence. `ignore_case` compares ASCII letters case-insensitively.
+/// Ignored frames are skipped.
+#[tauri::command]
+async fn search_bytes(
+    pattern: String,
+    kind: byte_search::PatternKind,
+    ignore_case: Option<bool>,
+    session_id: Option<String>,
+) -> Result<byte_search::ByteSearchResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let pattern = byte_search::parse_pattern(&pattern, kind)?;
+
+    let exclusions = frame_marks::with_exclusions(&session, None);
+    let result = byte_search::search_bytes(
+        &client,
+        pattern,
+        ignore_case.unwrap_or(false),
+        exclusions.as_deref(),
+    )
+    .await?;
+    Ok((*result).clone())
+}
+
+/// Record that a display filter was applied; repeated filters are counted
+/// rather than listed again
+#[tauri::command]
+fn add_filter_history(filter: String) -> Result<saved_filters::FilterHistoryEntry, String> {
+    saved_filters::add_filter_history(&filter)
+}
+
+/// Recently applied display filters, most recent first
+#[tauri::command]
+fn list_filter_history(limit: Option<usize>) -> Vec<saved_filters::FilterHistoryEntry> {
+    saved_filters::list_filter_history(limit)
+}
+
+/// Forget the display filter history
+#[tauri::command]
+fn clear_filter_history() -> Result<(), String> {
+    saved_filters::clear_filter_history()
+}
+
+/// Save a display filter under a name, replacing one with the same name.
+/// The filter is checked first when a capture is loaded.
+#[tauri::command]
+async fn save_named_filter(
+    name: String,
+    filter: String,
+    description: Option<String>,
+    session_id: Option<String>,
+) -> Result<saved_filters::SavedFilter, String> {
+    evidence::ensure_writable("Changing saved filters")?;
+    if let Ok(client) = sharkd(session_id.as_deref()) {
+        if client.loaded_file().is_some() && !client.check_filter(&filter).await? {
+            return Err(format!("Invalid filter expression: {}", filter));
+        }
+    }
+    saved_filters::save_named_filter(&name, &filter, description)
+}
+
+/// Saved display filters
+#[tauri::command]
+fn list_saved_filters() -> Vec<saved_filters::SavedFilter> {
+    saved_filters::list_saved_filters()
+}
+
+/// Remove a saved display filter
+#[tauri::command]
+fn remove_saved_filter(name: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing saved filters")?;
+    saved_filters::remove_saved_filter(&name)
+}
+
+/// Compile a capture (BPF) filter for an interface, reporting libpcap's error
+/// and where in the filter it points
+#[tauri::command(async)]
+fn check_capture_filter(
+    filter: String,
+    interface: Option<String>,
+) -> Result<capture_filter::CaptureFilterCheck, String> {
+    capture_filter::check_capture_filter(&filter, interface.as_deref())
+}
+
+/// Validate a display filter and count the frames it matches.
+/// Note: sharkd doesn't support global filter state - filters are per-request,
+/// so the count comes from running the protocol hierarchy tap with the filter.
+/// Ignored frames are left out of both counts.
+#[tauri::command]
+async fn apply_filter(filter: String, session_id: Option<String>) -> Result<FilterCount, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    // Validate the filter
+    if !filter.is_empty() && !client.check_filter(&filter).await? {
+        return Err("Invalid filter expression".to_string());
+    }
+
+    let status = client.status().await?;
+    let total = status
+        .frames
+        .unwrap_or(0)
+        .saturating_sub(frame_marks::ignored_count(&session));
+    let matched = match frame_marks::with_exclusions(&session, Some(&filter)) {
+        Some(filter) => client.count_frames(&filter).await?,
+        None => total,
+    };
+    Ok(FilterCount { matched, total })
+}
+
+/// Get detailed frame information: the protocol tree with each item's byte
+/// range, and the frame's bytes with a hex/ASCII rendering to highlight them in
+#[tauri::command]
+async fn get_frame_details(
+    frame_num: u32,
+    session_id: Option<String>,
+) -> Result<FrameDetails, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.frame_details(frame_num).await
+}
+
+/// Ready-made filters for the protocol tree item at `path` (indexes into the
+/// top-level items, then into children) of a frame: selected / not selected,
+/// combined with `current_filter`, and the frame's conversation filters
+#[tauri::command]
+async fn suggest_filters(
+    frame_num: u32,
+    path: Vec<usize>,
+    current_filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<filter_suggestions::FilterSuggestions, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    filter_suggestions::suggest_filters(&client, frame_num, &path, current_filter.as_deref()).await
+}
+
+/// Describe a frame in one plain English sentence, for screen readers
+#[tauri::command]
+async fn narrate_frame(frame_num: u32, session_id: Option<String>) -> Result<String, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    narration::narrate_frame(&client, frame_num).await
+}
+
+/// Get a frame's comment; several comments are joined by newlines
+#[tauri::command]
+async fn get_frame_comment(
+    frame_num: u32,
+    session_id: Option<String>,
+) -> Result<Option<String>, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    let frame_comments = client.frame_comments(frame_num).await?;
+    if frame_comments.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(frame_comments.join("\n")))
+}
+
+/// Set a frame's comment, or remove it with an empty or missing comment.
+/// Comments are kept with the capture and written into exported files.
+#[tauri::command]
+async fn set_frame_comment(
+    frame_num: u32,
+    comment: Option<String>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    evidence::ensure_writable("Commenting packets")?;
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let source = client
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    undo::track_comment(
+        &session,
+        &source,
+        frame_num,
+        &format!("Comment frame {}", frame_num),
+        comments::set_comment(&client, &source, frame_num, comment.as_deref()),
+    )
+    .await
+}
+
+/// Follow a TCP, UDP, HTTP, TLS, WebSocket, or QUIC stream. TLS streams return
+/// decrypted application data when keys are available (see `tls` in the
+/// result); WebSocket streams also list their frames (`messages`). For
+/// QUIC, `stream_id` is the connection number and `sub_stream` the stream
+/// within it.
+#[tauri::command]
+async fn follow_stream(
+    protocol: String,
+    stream_id: u32,
+    sub_stream: Option<u32>,
+    session_id: Option<String>,
+) -> Result<StreamData, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.follow_stream(&protocol, stream_id, sub_stream).await
+}
+
+/// Save a followed stream's payload to `path`: either direction's raw bytes,
+/// both in capture order, or the combined ASCII view. Returns the bytes written.
+#[tauri::command]
+async fn save_stream(
+    protocol: String,
+    stream_id: u32,
+    sub_stream: Option<u32>,
+    direction: StreamDirection,
+    path: String,
+    session_id: Option<String>,
+) -> Result<u64, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    let stream = client
+        .follow_stream(&protocol, stream_id, sub_stream)
+        .await?;
+    let payload = stream.payload(direction)?;
+    std::fs::write(&path, &payload).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    evidence::record_export(&path, "stream");
+    Ok(payload.len() as u64)
+}
+
+/// Stream IDs in a QUIC connection
+#[tauri::command]
+async fn list_quic_streams(
+    connection: u32,
+    session_id: Option<String>,
+) -> Result<Vec<u32>, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.quic_streams(connection).await
+}
+
+/// Get protocol hierarchy, conversations, and endpoints, optionally limited
+/// to a display filter ("limit to display filter"). Ignored frames are excluded.
+/// Results are cached per capture and filter; `force_refresh` recomputes them.
+#[tauri::command]
+async fn get_capture_stats(
+    filter: Option<String>,
+    force_refresh: Option<bool>,
+    session_id: Option<String>,
+) -> Result<CaptureStats, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    stats_cache::capture_stats(
+        &client,
+        frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+        force_refresh.unwrap_or(false),
+    )
+    .await
+}
+
+/// Get the conversations at one layer (`ethernet`, `ipv4`, `ipv6`, `tcp`, or
+/// `udp`), optionally limited to a display filter. Ignored frames are excluded.
+#[tauri::command]
+async fn get_conversations(
+    kind: AddressKind,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<Conversation>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .conversations(
+            kind,
+            frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+        )
+        .await
+}
+
+/// Get the endpoints at one layer (`ethernet`, `ipv4`, `ipv6`, `tcp`, or
+/// `udp`) with frame/byte totals and a filter for each, busiest first,
+/// optionally limited to a display filter. Ignored frames are excluded.
+#[tauri::command]
+async fn get_endpoints(
+    kind: AddressKind,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<Endpoint>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .endpoints(
+            kind,
+            frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+        )
+        .await
+}
+
+/// Get HTTP request methods, response codes, and requests per host, optionally
+/// limited to a display filter. Ignored frames are excluded.
+#[tauri::command]
+async fn get_http_stats(
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<HttpStats, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .http_stats(frame_marks::with_exclusions(&session, filter.as_deref()).as_deref())
+        .await
+}
+
+/// Get service response times (min/max/avg per procedure) for `smb`, `smb2`,
+/// `rpc`, `dcerpc,<uuid>,<version>`, `http`, ..., optionally limited to a
+/// display filter. Ignored frames are excluded.
+#[tauri::command]
+async fn get_srt(
+    protocol: String,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<SrtTable>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .service_response_times(
+            &protocol,
+            frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+        )
+        .await
+}
+
+/// List RTP streams with packet loss, jitter and delta statistics, for
+/// diagnosing call quality. Ignored frames are excluded.
+#[tauri::command]
+async fn get_rtp_streams(
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<RtpStream>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .rtp_streams(frame_marks::with_exclusions(&session, filter.as_deref()).as_deref())
+        .await
+}
+
+/// Decode an RTP stream from `get_rtp_streams` to audio for playback. The
+/// audio is written to `dest` when given, otherwise returned base64-encoded.
+#[tauri::command]
+async fn save_rtp_audio(
+    stream: RtpStream,
+    dest: Option<String>,
+    session_id: Option<String>,
+) -> Result<RtpAudio, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    let audio = client.rtp_audio(&stream).await?;
+    if audio.data.is_empty() {
+        return Err(format!(
+            "No audio could be decoded from RTP stream 0x{:08x} ({})",
+            stream.ssrc, stream.payload
+        ));
+    }
+    let data = match &dest {
+        Some(dest) => {
+            std::fs::write(dest, &audio.data)
+                .map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+            evidence::record_export(dest, "rtp-audio");
+            None
+        }
+        None => Some(BASE64.encode(&audio.data)),
+    };
+    Ok(RtpAudio {
+        mime: audio.mime,
+        bytes: audio.data.len() as u64,
+        data,
+    })
+}
+
+/// List VoIP calls (SIP, H.323, ... with their RTP) with start/stop times,
+/// parties, state, and a filter for each call. Ignored frames are excluded.
+#[tauri::command]
+async fn get_voip_calls(
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<VoipCall>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .voip_calls(frame_marks::with_exclusions(&session, filter.as_deref()).as_deref())
+        .await
+}
+
+/// GTP tunnels and sessions, Diameter sessions, and SCTP association health.
+/// Ignored frames are excluded.
+#[tauri::command]
+async fn get_mobile_summary(
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<mobile::MobileSummary, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    mobile::mobile_summary(
+        &client,
+        frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+    )
+    .await
+}
+
+/// 802.11 networks (BSSID, SSID, channel), channel usage, and per-station
+/// frame type counts. Ignored frames are excluded.
+#[tauri::command]
+async fn get_wlan_stats(
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<wlan::WlanStats, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    wlan::wlan_stats(
+        &client,
+        frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+    )
+    .await
+}
+
+/// Packets and bytes over time for one or more display filters (an empty
+/// filter for all frames), for throughput charts. The interval defaults to one
+/// second and is widened when the capture would need more than
+/// `MAX_IO_GRAPH_BUCKETS` buckets. Ignored frames are excluded.
+#[tauri::command]
+async fn get_io_graph(
+    filters: Vec<String>,
+    interval_ms: Option<u32>,
+    session_id: Option<String>,
+) -> Result<IoGraph, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    for filter in filters.iter().filter(|f| !f.trim().is_empty()) {
+        if !client.check_filter(filter).await? {
+            return Err(format!("Invalid filter expression: {}", filter));
+        }
+    }
+
+    let duration_ms = client.status().await?.duration.unwrap_or(0.0) * 1000.0;
+    let min_interval = (duration_ms / MAX_IO_GRAPH_BUCKETS as f64).ceil() as u32;
+    let interval_ms = interval_ms.unwrap_or(1000).max(min_interval).max(1);
+
+    let filters = if filters.is_empty() {
+        vec![String::new()]
+    } else {
+        filters
+    };
+    let mut series = Vec::with_capacity(filters.len());
+    for filter in filters {
+        let scoped = frame_marks::with_exclusions(&session, Some(&filter));
+        let mut graph = client.intervals(scoped.as_deref(), interval_ms).await?;
+        graph.filter = filter;
+        series.push(graph);
+    }
+    Ok(IoGraph {
+        interval_ms,
+        series,
+    })
+}
+
+/// Ladder-diagram data for frames matching a filter, optionally only those
+/// between two hosts. At most `max_frames` frames are read (10,000 by
+/// default); `truncated` tells whether more matched. Ignored frames are excluded.
+#[tauri::command]
+async fn get_flow_graph(
+    filter: Option<String>,
+    conversation: Option<FlowConversation>,
+    max_frames: Option<u32>,
+    session_id: Option<String>,
+) -> Result<FlowGraph, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    let filter = filter.filter(|f| !f.trim().is_empty());
+    if let Some(filter) = &filter {
+        if !client.check_filter(filter).await? {
+            return Err(format!("Invalid filter expression: {}", filter));
+        }
+    }
+    let filter = match (filter, conversation) {
+        (Some(filter), Some(conversation)) => {
+            Some(format!("({}) && {}", filter, conversation.filter()))
+        }
+        (None, Some(conversation)) => Some(conversation.filter()),
+        (filter, None) => filter,
+    };
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref()).unwrap_or_default();
+
+    client
+        .flow_graph(
+            &filter,
+            max_frames.unwrap_or(DEFAULT_FLOW_GRAPH_FRAMES).max(1),
+        )
+        .await
+}
+
+/// Graph a TCP stream: `time_sequence`, `rtt`, `throughput`, or `window_size`,
+/// with one series per direction. Ignored frames are excluded.
+#[tauri::command]
+async fn get_tcp_stream_graph(
+    stream_id: u32,
+    kind: tcp_graph::TcpGraphKind,
+    session_id: Option<String>,
+) -> Result<tcp_graph::TcpStreamGraph, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    tcp_graph::tcp_stream_graph(
+        &client,
+        stream_id,
+        kind,
+        frame_marks::with_exclusions(&session, None).as_deref(),
+    )
+    .await
+}
+
+/// Get conversations/endpoints broken out per VLAN and per tunnel (GRE, VXLAN, IP-in-IP)
+#[tauri::command]
+async fn get_overlay_stats(session_id: Option<String>) -> Result<OverlayStats, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .overlay_stats(frame_marks::with_exclusions(&session, None).as_deref())
+        .await
+}
+
+/// Export a host communication graph weighted by bytes: JSON for the UI's
+/// force-directed view, DOT/GraphML for external tools. Optionally grouped by
+/// GeoIP country/AS and written to `dest`; returns the rendered graph.
+#[tauri::command]
+async fn export_comm_graph(
+    format: Option<comm_graph::GraphFormat>,
+    group_by: Option<comm_graph::GraphGrouping>,
+    filter: Option<String>,
+    dest: Option<String>,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref());
+    let graph = comm_graph::export_comm_graph(
+        &client,
+        format.unwrap_or_default(),
+        group_by.unwrap_or_default(),
+        filter.as_deref(),
+        dest.as_deref(),
+    )
+    .await?;
+    if let Some(dest) = &dest {
+        evidence::record_export(dest, "comm-graph");
+    }
+    Ok(graph)
+}
+
+/// Diff the protocol hierarchy and top conversations under two display filters,
+/// e.g. before vs after a point in time within one capture
+#[tauri::command]
+async fn compare_filters(
+    filter_a: String,
+    filter_b: String,
+    session_id: Option<String>,
+) -> Result<filter_compare::FilterComparison, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    filter_compare::compare_filters(
+        &client,
+        &sessions::resolve(session_id.as_deref()),
+        &filter_a,
+        &filter_b,
+    )
+    .await
+}
+
+// ============================================
+// Session Commands
+// ============================================
+
+/// Open a new session with its own sharkd, for a capture in another tab.
+/// Returns the session ID to pass to other commands.
+#[tauri::command]
+async fn open_session() -> Result<String, String> {
+    let session_id = sessions::new_id();
+    session_client(&session_id).await?;
+    Ok(session_id)
+}
+
+/// Close a session, stopping its sharkd
+#[tauri::command]
+fn close_session(session_id: String) -> Result<(), String> {
+    let client =
+        sessions::take(&session_id).ok_or_else(|| format!("Unknown session: {}", session_id))?;
+    if let Some(path) = client.loaded_file() {
+        evidence::record_close(&path);
+        cache_manager::release_capture(&path);
+    }
+    log_ingest::clear_triage(&session_id);
+    frame_marks::clear(&session_id);
+    selection::clear_selection(&session_id);
+    undo::clear(&session_id);
+    time_format::clear_references(&session_id);
+    Ok(())
+}
+
+/// Open sessions and the captures loaded in them
+#[tauri::command]
+fn list_sessions() -> Vec<sessions::SessionInfo> {
+    sessions::list()
+}
+
+/// Make a session the default for commands called without a session ID and
+/// for the AI sidecar's HTTP bridge
+#[tauri::command]
+fn set_active_session(session_id: String) -> Result<(), String> {
+    sessions::set_active(&session_id)
+}
+
+/// Open a session in a window of its own, e.g. to compare captures side by
+/// side on several monitors. Without a session ID a new session is opened.
+/// Events about the session go to that window instead of the main one.
+#[tauri::command]
+async fn open_in_new_window(app: AppHandle, session_id: Option<String>) -> Result<String, String> {
+    let session_id = match session_id.filter(|id| !id.is_empty()) {
+        Some(id) => id,
+        None => sessions::new_id(),
+    };
+    let client = session_client(&session_id).await?;
+
+    let label = format!("window-{}", session_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        window.set_focus().map_err(|e| e.to_string())?;
+        return Ok(label);
+    }
+
+    let title = match client.loaded_file() {
+        Some(path) => format!(
+            "PacketPilot - {}",
+            std::path::Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(path)
+        ),
+        None => "PacketPilot".to_string(),
+    };
+    sessions::bind_window(&label, &session_id);
+    let url = WebviewUrl::App(format!("index.html?session={}", session_id).into());
+    if let Err(e) = WebviewWindowBuilder::new(&app, &label, url)
+        .title(title)
+        .inner_size(1200.0, 800.0)
+        .build()
+    {
+        sessions::unbind_window(&label);
+        return Err(format!("Failed to open window: {}", e));
+    }
+    Ok(label)
+}
+
+/// Session shown in the calling window, if it has one of its own
+#[tauri::command]
+fn get_window_session(window: tauri::Window) -> Option<String> {
+    sessions::window_session(window.label())
+}
+
+/// Scheduled background tasks (summaries, precomputed stats, ...) and their
+/// priority classes
+#[tauri::command]
+fn get_task_queue() -> Vec<scheduler::TaskInfo> {
+    scheduler::task_queue()
+}
+
+// ============================================
+// Query Commands
+// ============================================
+
+/// Run an aggregate query over capture fields,
+/// e.g. `top 10 dns.qry.name by count where dns.flags.rcode==3`
+#[tauri::command]
+async fn query(expr: String, session_id: Option<String>) -> Result<query::QueryResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    query::run_query(&client, &sessions::resolve(session_id.as_deref()), &expr).await
+}
+
+/// Saved query expressions
+#[tauri::command]
+fn list_saved_queries() -> Vec<dashboards::SavedQuery> {
+    dashboards::list_queries()
+}
+
+/// Add or replace a saved query
+#[tauri::command]
+fn save_query(saved: dashboards::SavedQuery) -> Result<(), String> {
+    evidence::ensure_writable("Changing saved queries")?;
+    dashboards::save_query(saved)
+}
+
+/// Remove a saved query
+#[tauri::command]
+fn remove_saved_query(id: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing saved queries")?;
+    dashboards::remove_query(&id)
+}
+
+/// Dashboard definitions
+#[tauri::command]
+fn list_dashboards() -> Vec<dashboards::Dashboard> {
+    dashboards::list_dashboards()
+}
+
+/// Add or replace a dashboard
+#[tauri::command]
+fn save_dashboard(dashboard: dashboards::Dashboard) -> Result<(), String> {
+    evidence::ensure_writable("Changing dashboards")?;
+    dashboards::save_dashboard(dashboard)
+}
+
+/// Remove a dashboard
+#[tauri::command]
+fn remove_dashboard(id: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing dashboards")?;
+    dashboards::remove_dashboard(&id)
+}
+
+/// Run every query of a dashboard against the loaded capture
+#[tauri::command]
+async fn run_dashboard(
+    id: String,
+    session_id: Option<String>,
+) -> Result<dashboards::DashboardResult, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    dashboards::run_dashboard(&client, &sessions::resolve(session_id.as_deref()), &id).await
+}
+
+// ============================================
+// Automation Commands
+// ============================================
+
+/// Saved automations (named sequences of load/filter/export/report steps)
+#[tauri::command]
+fn list_automations() -> Vec<automation::Automation> {
+    automation::list_automations()
+}
+
+/// Add or replace (by name) an automation
+#[tauri::command]
+fn save_automation(automation: automation::Automation) -> Result<(), String> {
+    evidence::ensure_writable("Changing automations")?;
+    automation::save_automation(automation)
+}
+
+/// Remove an automation
+#[tauri::command]
+fn remove_automation(name: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing automations")?;
+    automation::remove_automation(&name)
+}
+
+/// Run an automation, filling `{{name}}` references in its steps from
+/// `params`. Progress is reported through `automation-progress` events.
+#[tauri::command]
+async fn run_automation(
+    name: String,
+    params: Option<HashMap<String, String>>,
+    session_id: Option<String>,
+) -> Result<automation::AutomationResult, String> {
+    automation::run_automation(&name, &params.unwrap_or_default(), session_id.as_deref()).await
+}
+
+// ============================================
+// Capture Integrity Commands
+// ============================================
+
+/// Hash a capture file and validate its structure, detecting truncation
+#[tauri::command(async)]
+fn verify_capture(path: String) -> Result<capture_file::CaptureVerification, String> {
+    capture_file::verify_capture(&path)
+}
+
+/// File properties of a capture (size, format, encapsulation, packet count,
+/// first/last packet times, capture hardware and OS) without loading it
+#[tauri::command(async)]
+fn get_capture_info(path: String) -> Result<capture_file::CaptureInfo, String> {
+    capture_file::capture_info(&path)
+}
+
+/// Integrity state of the loaded capture (recorded hash, modified-on-disk flag)
+#[tauri::command]
+fn get_capture_integrity() -> Option<capture_file::CaptureIntegrity> {
+    capture_file::get_integrity()
+}
+
+/// Write an HTML report of the loaded capture using the chosen template
+#[tauri::command]
+async fn generate_report(
+    dest: String,
+    options: Option<report::ReportOptions>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    let status = client.status().await?;
+    let stats = stats_cache::capture_stats(
+        &client,
+        frame_marks::with_exclusions(&session, None).as_deref(),
+        false,
+    )
+    .await?;
+
+    report::generate_report(&status, &stats, &dest, &options.unwrap_or_default())?;
+    evidence::record_export(&dest, "report");
+    Ok(())
+}
+
+/// Package the current analysis (marked and ignored frames, selection, decode-as, saved
+/// filters, report, and optionally the trimmed capture) into a single bundle file
+#[tauri::command]
+async fn export_analysis_bundle(
+    dest: String,
+    include_capture: Option<bool>,
+    session_id: Option<String>,
+) -> Result<bundle::BundleExportResult, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let include_capture = include_capture.unwrap_or(true);
+    // A capture trimmed of ignored frames is a modified copy of the evidence
+    if include_capture && frame_marks::ignored_count(&session) > 0 {
+        evidence::ensure_writable("Exporting a trimmed capture")?;
+    }
+
+    let client = sharkd(session_id.as_deref())?;
+
+    let result = bundle::export_analysis_bundle(&client, &session, &dest, include_capture).await?;
+    evidence::record_export(&dest, "analysis-bundle");
+    Ok(result)
+}
+
+/// Open an analysis bundle: restores its profile files, loads its capture, and
+/// re-applies its marked and ignored frames and selection
+#[tauri::command]
+async fn import_analysis_bundle(
+    path: String,
+    dest_dir: Option<String>,
+    session_id: Option<String>,
+) -> Result<bundle::BundleImportResult, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    evidence::ensure_writable("Importing an analysis bundle")?;
+    let imported = bundle::import_analysis_bundle(&path, dest_dir.as_deref())?;
+
+    // Restored decode-as/filter files only take effect in a fresh sharkd
+    if !imported.profile_files.is_empty() {
+        restart_all_sharkd().await?;
+    }
+
+    if let Some(capture_path) = &imported.capture_path {
+        let result = load_pcap(capture_path.clone(), session_id.clone()).await?;
+        if let Some(e) = result.error {
+            return Err(e);
+        }
+        frame_marks::mark_frames(&session, imported.marked_frames.iter().copied());
+        frame_marks::ignore_frames(&session, imported.ignored_frames.iter().copied());
+        if let Some(range) = imported.selection {
+            selection::select_range(&session, range.start, range.end)?;
+        }
+    }
+
+    Ok(imported)
+}
+
+// ============================================
+// Decryption Commands
+// ============================================
+
+/// List TLS sessions in the loaded capture and which ones keylogs found in
+/// common locations (SSLKEYLOGFILE, next to the capture, home) would decrypt
+#[tauri::command]
+async fn decryption_hints(
+    session_id: Option<String>,
+) -> Result<decryption::DecryptionHints, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    decryption::decryption_hints(&client).await
+}
+
+/// Decrypt TLS with a keylog file (SSLKEYLOGFILE format), or stop with no
+/// path, and reload the open capture so it takes effect
+#[tauri::command]
+async fn configure_tls_keylog(
+    path: Option<String>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    evidence::ensure_writable("Configuring a TLS keylog")?;
+    let client = sharkd(session_id.as_deref())?;
+
+    decryption::configure_keylog(&client, path.as_deref()).await?;
+    if let Some(loaded) = client.loaded_file() {
+        reopen_capture(&client, &loaded).await?;
+    }
+    Ok(())
+}
+
+/// IPsec SAs, 802.11 keys, and SNMPv3 users used for decryption
+#[tauri::command]
+fn list_decryption_keys() -> Vec<decryption_keys::DecryptionKeyConfig> {
+    decryption_keys::list_keys()
+}
+
+/// Add or replace a decryption key. sharkd is restarted (reloading the open
+/// capture) since its key tables can only be appended to.
+#[tauri::command]
+async fn save_decryption_key(key: decryption_keys::DecryptionKeyConfig) -> Result<(), String> {
+    evidence::ensure_writable("Changing decryption keys")?;
+    decryption_keys::save_key(key)?;
+    restart_all_sharkd().await?;
+    Ok(())
+}
+
+/// Remove a decryption key, restarting sharkd to drop it
+#[tauri::command]
+async fn remove_decryption_key(id: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing decryption keys")?;
+    decryption_keys::remove_key(&id)?;
+    restart_all_sharkd().await?;
+    Ok(())
+}
+
+// ============================================
+// Protobuf Commands
+// ============================================
+
+/// `.proto` search paths and UDP message type mappings used to decode
+/// protobuf/gRPC payloads
+#[tauri::command]
+fn get_protobuf_config() -> protobuf::ProtobufConfig {
+    protobuf::get_protobuf_config()
+}
+
+/// Replace the protobuf preferences, restarting sharkd (and reloading the open
+/// capture) so they take effect
+#[tauri::command]
+async fn save_protobuf_config(config: protobuf::ProtobufConfig) -> Result<(), String> {
+    evidence::ensure_writable("Changing protobuf settings")?;
+    protobuf::save_protobuf_config(config)?;
+    restart_all_sharkd().await?;
+    Ok(())
+}
+
+// ============================================
+// Evidence Mode Commands
+// ============================================
+
+/// Enable or disable read-only mode, which refuses all mutating operations
+#[tauri::command]
+fn set_read_only(enabled: bool) {
+    evidence::set_read_only(enabled)
+}
+
+/// Whether read-only mode is on
+#[tauri::command]
+fn get_read_only() -> bool {
+    evidence::is_read_only()
+}
+
+/// Get the chain-of-custody log recorded while in read-only mode,
+/// with the result of verifying its signatures
+#[tauri::command]
+fn get_custody_log() -> Result<evidence::CustodyLog, String> {
+    evidence::get_custody_log()
+}
+
+// ============================================
+// Capture History Commands
+// ============================================
+
+/// Check whether a capture with the same contents was analyzed before
+#[tauri::command(async)]
+fn check_known_capture(path: String) -> Result<history::KnownCapture, String> {
+    history::check_known_capture(&path)
+}
+
+/// List the backups kept for a persisted store (e.g. "capture-history")
+#[tauri::command]
+fn list_workspace_backups(store: String) -> Result<Vec<storage::BackupInfo>, String> {
+    storage::list_backups(&store)
+}
+
+/// Restore a persisted store from a backup (generation 1 is the most recent).
+/// Stores that sharkd reads when it opens a capture (comments, decryption
+/// keys and protobuf settings) restart sharkd to pick up the restored copy.
+#[tauri::command]
+async fn restore_workspace_backup(store: String, generation: Option<u32>) -> Result<(), String> {
+    evidence::ensure_writable("Restoring a backup")?;
+    let (reload, restart): (fn(), bool) = match store.as_str() {
+        history::STORE_NAME => (history::reload, false),
+        workspace::STORE_NAME => (workspace::reload, false),
+        columns::STORE_NAME => (columns::reload, false),
+        saved_filters::STORE_NAME => (saved_filters::reload, false),
+        dashboards::STORE_NAME => (dashboards::reload, false),
+        automation::STORE_NAME => (automation::reload, false),
+        notifications::STORE_NAME => (notifications::reload, false),
+        external_tools::STORE_NAME => (external_tools::reload, false),
+        upload::STORE_NAME => (upload::reload, false),
+        redaction::STORE_NAME => (redaction::reload, false),
+        time_format::STORE_NAME => (time_format::reload, false),
+        cache_manager::STORE_NAME => (cache_manager::reload, false),
+        comments::STORE_NAME => (comments::reload, true),
+        decryption_keys::STORE_NAME => (decryption_keys::reload, true),
+        protobuf::STORE_NAME => (protobuf::reload, true),
+        _ => return Err(format!("Unknown store: {}", store)),
+    };
+    storage::restore_backup(&store, generation.unwrap_or(1))?;
+    reload();
+    if restart {
+        restart_all_sharkd().await?;
+    }
+    Ok(())
+}
+
+/// List recently analyzed captures, most recent first
+#[tauri::command]
+fn list_recent_captures(limit: Option<usize>) -> Vec<history::CaptureRecord> {
+    history::list_recent(limit.unwrap_or(20))
+}
+
+/// Replace the tags of a capture in the history (by content hash)
+#[tauri::command]
+fn tag_capture(hash: String, tags: Vec<String>) -> Result<history::CaptureRecord, String> {
+    evidence::ensure_writable("Tagging captures")?;
+    history::tag_capture(&hash, tags)
+}
+
+/// Search past captures by text (file name, tags, top hosts), tags, capture
+/// time range, and host, most recent first
+#[tauri::command]
+fn search_captures(query: history::CaptureSearch) -> Vec<history::CaptureRecord> {
+    history::search_captures(&query)
+}
+
+/// Export a small capture of the frames around an evidence frame
+/// (time window, optionally limited to that frame's conversation)
+#[tauri::command]
+async fn export_excerpt(
+    frame_num: u32,
+    seconds_before: f64,
+    seconds_after: f64,
+    conversation_only: Option<bool>,
+    dest: String,
+    session_id: Option<String>,
+) -> Result<capture_edit::ExcerptResult, String> {
+    evidence::ensure_writable("Exporting an excerpt")?;
+    let client = sharkd(session_id.as_deref())?;
+    let source = client
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let result = capture_edit::export_excerpt(
+        &client,
+        &source,
+        frame_num,
+        seconds_before,
+        seconds_after,
+        conversation_only.unwrap_or(true),
+        &dest,
+    )
+    .await?;
+    evidence::record_export(&dest, "excerpt");
+    Ok(result)
+}
+
+/// Merge capture files (per-interface or rotated sets) into `out_path` in
+/// timestamp order, then load the merged capture into the session
+#[tauri::command]
+async fn merge_captures(
+    paths: Vec<String>,
+    out_path: String,
+    session_id: Option<String>,
+) -> Result<LoadResult, String> {
+    evidence::ensure_writable("Merging captures")?;
+    capture_edit::merge_captures(&paths, &out_path)?;
+    evidence::record_export(&out_path, "merge");
+
+    load_pcap(out_path, session_id).await
+}
+
+/// Shift every timestamp of the loaded capture by `offset_secs` (negative to
+/// move earlier) into a new file, written to `dest` or next to the original,
+/// and load it in place of the original
+#[tauri::command]
+async fn time_shift_capture(
+    offset_secs: f64,
+    dest: Option<String>,
+    session_id: Option<String>,
+) -> Result<LoadResult, String> {
+    evidence::ensure_writable("Time-shifting a capture")?;
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let shifted = capture_edit::time_shift(&source, offset_secs, dest.as_deref())?;
+    evidence::record_export(&shifted, "time-shift");
+    load_pcap(shifted, session_id).await
+}
+
+/// Strip frames duplicating one of the previous `window` frames (5 by default)
+/// from a capture into `<name>-dedup.pcapng`, and load the cleaned file
+#[tauri::command]
+async fn deduplicate_capture(
+    path: String,
+    window: Option<u32>,
+    session_id: Option<String>,
+) -> Result<LoadResult, String> {
+    evidence::ensure_writable("Deduplicating a capture")?;
+    let cleaned = capture_edit::deduplicate(&path, window)?;
+    evidence::record_export(&cleaned, "dedup");
+    load_pcap(cleaned, session_id).await
+}
+
+/// Split a capture into chunks of `value` packets or seconds, written next to
+/// it or into `out_dir`. Returns the chunk files in order.
+#[tauri::command(async)]
+fn split_capture(
+    path: String,
+    mode: capture_edit::SplitMode,
+    value: u64,
+    out_dir: Option<String>,
+) -> Result<Vec<String>, String> {
+    evidence::ensure_writable("Splitting a capture")?;
+    let files = capture_edit::split_capture(&path, mode, value, out_dir.as_deref())?;
+    for file in &files {
+        evidence::record_export(file, "split");
+    }
+    Ok(files)
+}
+
+// ============================================
+// Workspace Commands
+// ============================================
+
+/// Save the analysis context (open capture, filter, column layout, marked
+/// frames, scroll position) to restore when the app is reopened
+#[tauri::command]
+fn save_session(
+    filter: Option<String>,
+    scroll_frame: Option<u32>,
+    selected_frame: Option<u32>,
+    session_id: Option<String>,
+) -> Result<workspace::Workspace, String> {
+    let file = sharkd(session_id.as_deref())
+        .ok()
+        .and_then(|client| client.loaded_file());
+    workspace::save_workspace(
+        &sessions::resolve(session_id.as_deref()),
+        file,
+        filter,
+        scroll_frame,
+        selected_frame,
+    )
+}
+
+/// Reopen the saved analysis context's capture and re-apply its layout and
+/// marks. The frontend applies the returned filter and scroll position.
+#[tauri::command]
+async fn restore_session(
+    session_id: Option<String>,
+) -> Result<Option<workspace::RestoredWorkspace>, String> {
+    workspace::restore_workspace(session_id).await
+}
+
+/// Forget the saved analysis context
+#[tauri::command]
+fn clear_saved_session() -> Result<(), String> {
+    workspace::clear_workspace()
+}
+
+// ============================================
+// Anonymization Commands
+// ============================================
+
+/// The session's anonymization policy
+#[tauri::command]
+fn get_anonymization_policy(
+    session_id: Option<String>,
+) -> Result<anonymize::AnonymizationPolicy, String> {
+    anonymize::policy(&sessions::resolve(session_id.as_deref()))
+}
+
+/// Set the session's anonymization policy. With `enabled`, frames and frame
+/// details served to the AI sidecar are anonymized.
+#[tauri::command]
+fn set_anonymization_policy(
+    policy: anonymize::AnonymizationPolicy,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    anonymize::set_policy(&sessions::resolve(session_id.as_deref()), policy)
+}
+
+/// Write a copy of the loaded capture with addresses rewritten (and payloads
+/// stripped) per the session's policy
+#[tauri::command(async)]
+fn export_anonymized_capture(
+    dest: String,
+    session_id: Option<String>,
+) -> Result<anonymize::AnonymizeResult, String> {
+    evidence::ensure_writable("Exporting an anonymized capture")?;
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let anonymizer = anonymize::anonymizer(&sessions::resolve(session_id.as_deref()))?;
+    let result = anonymizer.anonymize_capture(&source, &dest)?;
+    evidence::record_export(&dest, "anonymized");
+    Ok(result)
+}
+
+/// Frame summaries anonymized per the session's policy, whether or not it is
+/// enabled for the sidecar
+#[tauri::command]
+async fn get_anonymized_frames(
+    skip: u32,
+    limit: u32,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<FrameData>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let frames = match frame_marks::with_exclusions(&session, filter.as_deref()) {
+        Some(filter) => client.search_frames(&filter, skip, limit).await?.0,
+        None => client.frames(skip, limit).await?,
+    };
+    let mut frames: Vec<FrameData> = frames
+        .into_iter()
+        .map(|frame| FrameData::new(frame, &session))
+        .collect();
+    anonymize::anonymizer(&session)?.anonymize_frames(&mut frames);
+    Ok(frames)
+}
+
+/// What the HTTP bridge masks in payloads before they reach the sidecar
+#[tauri::command]
+fn get_redaction_policy() -> redaction::RedactionPolicy {
+    redaction::policy()
+}
+
+/// Turn masking of card numbers, auth headers, cookies and credentials on or off
+#[tauri::command]
+fn set_redaction_policy(policy: redaction::RedactionPolicy) -> Result<(), String> {
+    redaction::set_policy(policy)
+}
+
+// ============================================
+// HAR Export Commands
+// ============================================
+
+/// Export the capture's HTTP request/response pairs (including decrypted TLS
+/// when keys are configured) as a HAR file for browser devtools
+#[tauri::command(async)]
+fn export_har(
+    dest: String,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<har::HarExportResult, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let filter = frame_marks::with_exclusions(&session, filter.as_deref());
+    let result = har::export_har(&source, &dest, filter.as_deref())?;
+    evidence::record_export(&dest, "har");
+    Ok(result)
+}
+
+/// Export the full dissection of frames (the selection when no range is
+/// given) as PDML, JSON, or plain text. Written to `dest` when given,
+/// otherwise returned as `content`. Ignored frames are left out.
+#[tauri::command(async)]
+fn export_dissection(
+    frame_range: Option<selection::FrameRange>,
+    format: Option<dissection::DissectionFormat>,
+    dest: Option<String>,
+    include_bytes: Option<bool>,
+    session_id: Option<String>,
+) -> Result<dissection::DissectionExport, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+    let range = match frame_range {
+        Some(range) => selection::FrameRange::new(range.start, range.end),
+        None => selection::require_selection(&session)?,
+    };
+    let format = format.unwrap_or_default();
+
+    let content = dissection::dissect_frames(
+        &source,
+        range,
+        format,
+        frame_marks::with_exclusions(&session, None).as_deref(),
+        include_bytes.unwrap_or(false),
+    )?;
+    let (path, content) = match dest {
+        Some(dest) => {
+            std::fs::write(&dest, content)
+                .map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+            evidence::record_export(&dest, "dissection");
+            (Some(dest), None)
+        }
+        None => (None, Some(content)),
+    };
+    Ok(dissection::DissectionExport {
+        format,
+        range,
+        path,
+        content,
+    })
+}
+
+/// Export frames (the selection when none are given) as offset/hex/ASCII
+/// dumps. Written to `dest` when given, otherwise returned as `content` for
+/// the clipboard. Ignored frames are left out.
+#[tauri::command]
+async fn export_hex_dump(
+    frames: Option<Vec<u32>>,
+    dest: Option<String>,
+    session_id: Option<String>,
+) -> Result<hex_dump::HexDumpExport, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+    let frames = match frames {
+        Some(frames) => frames,
+        None => {
+            let range = selection::require_selection(&session)?;
+            (range.start..=range.end).collect()
+        }
+    };
+    let ignored = frame_marks::ignored_frames(&session);
+    let frames: Vec<u32> = frames
+        .into_iter()
+        .filter(|f| !ignored.contains(f))
+        .collect();
+
+    let content = hex_dump::dump_frames(&client, &frames).await?;
+    let (path, content) = match dest {
+        Some(dest) => {
+            std::fs::write(&dest, content)
+                .map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+            evidence::record_export(&dest, "hex-dump");
+            (Some(dest), None)
+        }
+        None => (None, Some(content)),
+    };
+    Ok(hex_dump::HexDumpExport {
+        frames,
+        path,
+        content,
+    })
+}
+
+/// List files carried in the capture (HTTP, SMB, IMF, TFTP, DICOM,
+/// FTP-DATA), or only those of `protocol`. Ignored frames are excluded.
+#[tauri::command]
+async fn list_export_objects(
+    protocol: Option<String>,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<ExportObject>, String> {
+    let client = sharkd(session_id.as_deref())?;
+    let session = sessions::resolve(session_id.as_deref());
+
+    client
+        .export_objects(
+            protocol.as_deref(),
+            frame_marks::with_exclusions(&session, filter.as_deref()).as_deref(),
+        )
+        .await
+}
+
+/// Write an export object, by the token from `list_export_objects`, to
+/// `dest`. Returns the number of bytes written.
+#[tauri::command]
+async fn save_export_object(
+    token: String,
+    dest: String,
+    session_id: Option<String>,
+) -> Result<u64, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    let download = client.download(&token).await?;
+    std::fs::write(&dest, &download.data)
+        .map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+    evidence::record_export(&dest, "object");
+    Ok(download.data.len() as u64)
 }
 
-/// Response for load_pcap command
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoadResult {
-    pub success: bool,
-    pub frame_count: u64,
-    pub duration: Option<f64>,
-    pub error: Option<String>,
+// ============================================
+// External Tool Commands
+// ============================================
+
+/// External programs captures can be opened in
+#[tauri::command]
+fn list_external_tools() -> Vec<external_tools::ExternalTool> {
+    external_tools::list_tools()
 }
 
-/// Response for get_frames command
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FramesResult {
-    pub frames: Vec<FrameData>,
-    pub total: u64,
+/// Add or replace an external tool definition
+#[tauri::command]
+fn save_external_tool(tool: external_tools::ExternalTool) -> Result<(), String> {
+    external_tools::save_tool(tool)
 }
 
-/// Simplified frame data for frontend
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FrameData {
-    pub number: u32,
-    pub time: String,
-    pub source: String,
-    pub destination: String,
-    pub protocol: String,
-    pub length: String,
-    pub info: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub background: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub foreground: Option<String>,
+/// Remove an external tool definition
+#[tauri::command]
+fn remove_external_tool(id: String) -> Result<(), String> {
+    external_tools::remove_tool(&id)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthModeCapability {
-    pub mode: String,
-    pub label: String,
-    pub supported: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reason: Option<String>,
+/// Export the whole capture, the selection, or a filter's frames to a temp
+/// capture and launch an external tool (Wireshark, Zeek, Suricata, ...) on it
+#[tauri::command(async)]
+fn open_in_external_tool(
+    tool: String,
+    scope: Option<external_tools::ToolScope>,
+    session_id: Option<String>,
+) -> Result<external_tools::ExternalLaunch, String> {
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let launch = external_tools::open_in_external_tool(
+        &tool,
+        &scope.unwrap_or_default(),
+        &sessions::resolve(session_id.as_deref()),
+        &source,
+    )?;
+    evidence::record_export(&launch.capture_path, "external-tool");
+    Ok(launch)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthCapabilities {
-    pub modes: Vec<AuthModeCapability>,
+// ============================================
+// Triage Commands
+// ============================================
+
+/// Parse a Zeek conn/dns log or Suricata EVE JSON covering the loaded capture
+/// and add the records that match its packets (by 5-tuple and time) to the
+/// triage queue
+#[tauri::command]
+async fn ingest_logs(
+    path: String,
+    kind: log_ingest::LogKind,
+    session_id: Option<String>,
+) -> Result<log_ingest::IngestResult, String> {
+    let session_id = sessions::resolve(session_id.as_deref());
+    let client = sharkd(Some(&session_id))?;
+
+    let result = log_ingest::ingest_logs(&client, &session_id, &path, kind).await?;
+    emit_session_event(&session_id, "triage-updated", &session_id);
+    Ok(result)
 }
 
-impl From<Frame> for FrameData {
-    fn from(frame: Frame) -> Self {
-        // sharkd columns: Index 0: No., 1: Time, 2: Source, 3: Destination, 4: Protocol, 5: Length, 6: Info
-        let cols = &frame.columns;
-        FrameData {
-            number: frame.number,
-            time: cols.get(1).cloned().unwrap_or_default(),
-            source: cols.get(2).cloned().unwrap_or_default(),
-            destination: cols.get(3).cloned().unwrap_or_default(),
-            protocol: cols.get(4).cloned().unwrap_or_default(),
-            length: cols.get(5).cloned().unwrap_or_default(),
-            info: cols.get(6).cloned().unwrap_or_default(),
-            background: frame.background,
-            foreground: frame.foreground,
-        }
-    }
+/// Log records correlated with the capture, most severe first
+#[tauri::command]
+fn list_triage(session_id: Option<String>) -> Vec<log_ingest::TriageItem> {
+    log_ingest::triage_queue(&sessions::resolve(session_id.as_deref()))
 }
 
-/// Initialize sharkd (spawn the process)
+/// Empty the triage queue
 #[tauri::command]
-fn init_sharkd() -> Result<String, String> {
-    let mut client_guard = get_sharkd().lock();
+fn clear_triage(session_id: Option<String>) {
+    log_ingest::clear_triage(&sessions::resolve(session_id.as_deref()));
+}
 
-    if client_guard.is_some() {
-        return Ok("Sharkd already initialized".to_string());
-    }
+// ============================================
+// Notification Commands
+// ============================================
 
-    let client = SharkdClient::new()?;
-    *client_guard = Some(client);
+/// Configured notification sinks (webhook, syslog, desktop)
+#[tauri::command]
+fn list_notification_sinks() -> Vec<notifications::NotificationSink> {
+    notifications::list_sinks()
+}
 
-    Ok("Sharkd initialized successfully".to_string())
+/// Add or replace a notification sink
+#[tauri::command]
+fn save_notification_sink(sink: notifications::NotificationSink) -> Result<(), String> {
+    evidence::ensure_writable("Changing notification sinks")?;
+    notifications::save_sink(sink)
 }
 
-/// Load a PCAP file
+/// Remove a notification sink
 #[tauri::command]
-fn load_pcap(path: String) -> Result<LoadResult, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized. Call init_sharkd first.".to_string())?;
+fn remove_notification_sink(id: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing notification sinks")?;
+    notifications::remove_sink(&id)
+}
 
-    // Load the file
-    if let Err(e) = client.load(&path) {
-        return Ok(LoadResult {
-            success: false,
-            frame_count: 0,
-            duration: None,
-            error: Some(e),
-        });
+/// Forward a finding to the sinks routed its severity
+#[tauri::command(async)]
+fn send_notification(notification: notifications::Notification) -> Vec<notifications::Delivery> {
+    notifications::notify(&notification)
+}
+
+/// Send a test notification to one sink
+#[tauri::command(async)]
+fn test_notification(id: String) -> Result<notifications::Delivery, String> {
+    notifications::test_sink(&id)
+}
+
+// ============================================
+// Frame Selection Commands
+// ============================================
+
+/// Select an inclusive range of frames for range operations
+#[tauri::command]
+fn select_range(
+    start_frame: u32,
+    end_frame: u32,
+    session_id: Option<String>,
+) -> Result<FrameRange, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    selection::select_range(&session, start_frame, end_frame)
+}
+
+/// Get the current frame selection
+#[tauri::command]
+fn get_selection(session_id: Option<String>) -> Option<FrameRange> {
+    let session = sessions::resolve(session_id.as_deref());
+    selection::get_selection(&session)
+}
+
+/// Clear the current frame selection
+#[tauri::command]
+fn clear_selection(session_id: Option<String>) {
+    let session = sessions::resolve(session_id.as_deref());
+    selection::clear_selection(&session)
+}
+
+/// Build a display filter matching the selected frames
+#[tauri::command]
+fn get_selection_filter(session_id: Option<String>) -> Result<String, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    Ok(selection::require_selection(&session)?.filter())
+}
+
+/// Export the selected frames of the loaded capture to a new file
+#[tauri::command(async)]
+fn export_selection(dest: String, session_id: Option<String>) -> Result<FrameRange, String> {
+    evidence::ensure_writable("Exporting selected frames")?;
+    let session = sessions::resolve(session_id.as_deref());
+    let source = sharkd(session_id.as_deref())?
+        .loaded_file()
+        .ok_or_else(|| "No capture file loaded".to_string())?;
+
+    let range = selection::export_selection(&session, &source, &dest)?;
+    evidence::record_export(&dest, "selection");
+    Ok(range)
+}
+
+// ============================================
+// Marked and Ignored Frame Commands
+// ============================================
+
+/// Flip the mark on the given frames. Returns the marked frame numbers.
+#[tauri::command]
+fn toggle_frame_marks(frame_numbers: Vec<u32>, session_id: Option<String>) -> Vec<u32> {
+    let session = sessions::resolve(session_id.as_deref());
+    undo::track_marked(&session, "Toggle marks", || {
+        frame_marks::toggle_marked(&session, frame_numbers)
+    });
+    frame_marks::marked_frames(&session)
+}
+
+/// Mark every frame in the current selection. Returns the number of marked frames.
+#[tauri::command]
+fn mark_selection(session_id: Option<String>) -> Result<usize, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let range = selection::require_selection(&session)?;
+    Ok(undo::track_marked(&session, "Mark selection", || {
+        frame_marks::mark_frames(&session, range.start..=range.end)
+    }))
+}
+
+/// Unmark the given frames (all frames when omitted)
+#[tauri::command]
+fn unmark_frames(frame_numbers: Option<Vec<u32>>, session_id: Option<String>) -> usize {
+    let session = sessions::resolve(session_id.as_deref());
+    undo::track_marked(&session, "Unmark frames", || match frame_numbers {
+        Some(frames) => frame_marks::unmark_frames(&session, frames),
+        None => frame_marks::unmark_frames(&session, frame_marks::marked_frames(&session)),
+    })
+}
+
+/// List marked frame numbers
+#[tauri::command]
+fn list_marked_frames(session_id: Option<String>) -> Vec<u32> {
+    let session = sessions::resolve(session_id.as_deref());
+    frame_marks::marked_frames(&session)
+}
+
+/// Ignore frames by number and/or by display filter; ignored frames are masked
+/// from frame listings, counts, and statistics. Returns the number of ignored frames.
+#[tauri::command]
+async fn ignore_frames(
+    frame_numbers: Option<Vec<u32>>,
+    filter: Option<String>,
+    session_id: Option<String>,
+) -> Result<usize, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let mut frames = frame_numbers.unwrap_or_default();
+
+    if let Some(filter) = filter.filter(|f| !f.is_empty()) {
+        let client = sharkd(session_id.as_deref())?;
+
+        if !client.check_filter(&filter).await? {
+            return Err("Invalid filter expression".to_string());
+        }
+        for row in client
+            .column_values(&filter, &["frame.number"], u32::MAX)
+            .await?
+        {
+            if let Some(n) = row.first().and_then(|n| n.parse::<u32>().ok()) {
+                frames.push(n);
+            }
+        }
     }
 
-    // Get status to get frame count
-    let status = client.status()?;
+    Ok(undo::track_ignored(&session, "Ignore frames", || {
+        frame_marks::ignore_frames(&session, frames)
+    }))
+}
 
-    Ok(LoadResult {
-        success: true,
-        frame_count: status.frames.unwrap_or(0),
-        duration: status.duration,
-        error: None,
+/// Ignore every frame in the current selection
+#[tauri::command]
+fn ignore_selection(session_id: Option<String>) -> Result<usize, String> {
+    let session = sessions::resolve(session_id.as_deref());
+    let range = selection::require_selection(&session)?;
+    Ok(undo::track_ignored(&session, "Ignore selection", || {
+        frame_marks::ignore_frames(&session, range.start..=range.end)
+    }))
+}
+
+/// Stop ignoring the given frames (all frames when omitted)
+#[tauri::command]
+fn unignore_frames(frame_numbers: Option<Vec<u32>>, session_id: Option<String>) -> usize {
+    let session = sessions::resolve(session_id.as_deref());
+    undo::track_ignored(&session, "Unignore frames", || match frame_numbers {
+        Some(frames) => frame_marks::unignore_frames(&session, frames),
+        None => frame_marks::unignore_frames(&session, frame_marks::ignored_frames(&session)),
     })
 }
 
-/// Get frames with pagination
+/// List ignored frame numbers
+#[tauri::command]
+fn list_ignored_frames(session_id: Option<String>) -> Vec<u32> {
+    let session = sessions::resolve(session_id.as_deref());
+    frame_marks::ignored_frames(&session)
+}
+
+// ============================================
+// Field Catalog Commands
+// ============================================
+
+/// The full catalog of dissector fields (name, type, description, parent
+/// protocol), optionally only one protocol's. Built on first use and cached.
+#[tauri::command(async)]
+fn get_field_catalog(protocol: Option<String>) -> Result<Vec<field_catalog::FieldInfo>, String> {
+    field_catalog::list_fields(protocol.as_deref())
+}
+
+/// Search dissector fields by name, display name, or description
+#[tauri::command(async)]
+fn search_fields(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<field_catalog::FieldInfo>, String> {
+    field_catalog::search_fields(&query, limit.unwrap_or(field_catalog::DEFAULT_SEARCH_LIMIT))
+}
+
+/// Rebuild the field catalog from tshark (e.g. after a Wireshark upgrade).
+/// Returns the number of fields.
+#[tauri::command(async)]
+fn rebuild_field_catalog() -> Result<usize, String> {
+    Ok(field_catalog::rebuild_catalog()?.fields.len())
+}
+
+/// Time column format and the capture's time reference frames
+#[tauri::command]
+fn get_time_settings(session_id: Option<String>) -> time_format::TimeSettings {
+    let session = sessions::resolve(session_id.as_deref());
+    time_format::settings(&session)
+}
+
+/// Show frame times as absolute, relative, delta, or UTC times
+#[tauri::command]
+fn set_time_format(format: time_format::TimeFormat) -> Result<(), String> {
+    time_format::set_format(format)
+}
+
+/// Mark a frame as a time reference (or unmark it with `enabled: false`);
+/// relative times of later frames count from it. Returns the references.
+#[tauri::command]
+fn set_time_reference(
+    frame: u32,
+    enabled: Option<bool>,
+    session_id: Option<String>,
+) -> Result<Vec<u32>, String> {
+    evidence::ensure_writable("Setting time references")?;
+    let session = sessions::resolve(session_id.as_deref());
+    Ok(time_format::set_reference(
+        &session,
+        frame,
+        enabled.unwrap_or(true),
+    ))
+}
+
+/// Custom packet list columns
+#[tauri::command]
+fn get_columns() -> Vec<columns::CustomColumn> {
+    columns::get_columns()
+}
+
+/// Replace the custom packet list columns
+#[tauri::command(async)]
+fn set_columns(
+    columns: Vec<columns::CustomColumn>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    evidence::ensure_writable("Changing columns")?;
+    let session = sessions::resolve(session_id.as_deref());
+    undo::track_columns(&session, "Change columns", || columns::set_columns(columns))
+}
+
+/// Saved column layouts and the active one's name
 #[tauri::command]
-fn get_frames(skip: u32, limit: u32) -> Result<FramesResult, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized".to_string())?;
+fn list_layouts() -> (Vec<columns::ColumnLayout>, Option<String>) {
+    (columns::list_layouts(), columns::active_layout())
+}
+
+/// Save a named column layout (visibility, order, widths, custom columns)
+#[tauri::command(async)]
+fn save_layout(layout: columns::ColumnLayout) -> Result<(), String> {
+    evidence::ensure_writable("Changing column layouts")?;
+    columns::save_layout(layout)
+}
 
-    let frames = client.frames(skip, limit)?;
-    let status = client.status()?;
+/// Delete a column layout
+#[tauri::command]
+fn delete_layout(name: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing column layouts")?;
+    columns::delete_layout(&name)
+}
 
-    Ok(FramesResult {
-        frames: frames.into_iter().map(FrameData::from).collect(),
-        total: status.frames.unwrap_or(0),
+/// Switch to a column layout, replacing the custom columns with its own
+#[tauri::command]
+fn apply_layout(name: String, session_id: Option<String>) -> Result<columns::ColumnLayout, String> {
+    evidence::ensure_writable("Changing column layouts")?;
+    let session = sessions::resolve(session_id.as_deref());
+    undo::track_columns(&session, &format!("Apply layout {}", name), || {
+        columns::apply_layout(&name)
     })
 }
 
-/// Get current status
+/// Set the layout applied whenever a capture is opened, or clear it with no name
 #[tauri::command]
-fn get_status() -> Result<Status, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized".to_string())?;
+fn set_capture_layout(path: String, name: Option<String>) -> Result<(), String> {
+    evidence::ensure_writable("Changing column layouts")?;
+    columns::set_capture_layout(&path, name.as_deref())
+}
+
+// ============================================
+// Undo Commands
+// ============================================
 
-    client.status()
+/// Undo the last workspace change (marks, ignored frames, comments, columns).
+/// Returns what was undone, or nothing when the history is empty.
+#[tauri::command]
+async fn undo(session_id: Option<String>) -> Result<Option<String>, String> {
+    evidence::ensure_writable("Undoing changes")?;
+    let session = sessions::resolve(session_id.as_deref());
+    undo::undo(&session).await
 }
 
-/// Check if a display filter is valid
+/// Redo the last undone workspace change
 #[tauri::command]
-fn check_filter(filter: String) -> Result<bool, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized".to_string())?;
+async fn redo(session_id: Option<String>) -> Result<Option<String>, String> {
+    evidence::ensure_writable("Redoing changes")?;
+    let session = sessions::resolve(session_id.as_deref());
+    undo::redo(&session).await
+}
 
-    client.check_filter(&filter)
+/// Changes available to undo and redo, most recent last
+#[tauri::command]
+fn get_undo_history(session_id: Option<String>) -> undo::UndoHistory {
+    let session = sessions::resolve(session_id.as_deref());
+    undo::history(&session)
 }
 
-/// Validate a display filter and return the total frame count
-/// Note: sharkd doesn't support global filter state - filters are per-request
+// ============================================
+// Upload Commands
+// ============================================
+
+/// List configured upload destinations
 #[tauri::command]
-fn apply_filter(filter: String) -> Result<u64, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized".to_string())?;
+fn list_upload_destinations() -> Vec<upload::UploadDestinationConfig> {
+    upload::list_destinations()
+}
 
-    // Validate the filter
-    if !filter.is_empty() && !client.check_filter(&filter)? {
-        return Err("Invalid filter expression".to_string());
+/// Add or replace an upload destination
+#[tauri::command]
+fn save_upload_destination(destination: upload::UploadDestinationConfig) -> Result<(), String> {
+    upload::save_destination(destination)
+}
+
+/// Remove an upload destination
+#[tauri::command]
+fn remove_upload_destination(id: String) -> Result<(), String> {
+    upload::remove_destination(&id)
+}
+
+/// Upload a capture (or report) to a configured destination.
+/// Progress is reported through `upload-progress` events.
+#[tauri::command(async)]
+fn upload_capture(dest_id: String, path: String) -> Result<upload::UploadResult, String> {
+    upload::upload_file(&dest_id, &path)
+}
+
+/// Copy the user's Wireshark profile (colorfilters, dfilters, preferences,
+/// decode-as, ...) into PacketPilot's managed profile and restart sharkd to apply it
+#[tauri::command]
+async fn import_wireshark_config(profile: Option<String>) -> Result<profile::ImportResult, String> {
+    evidence::ensure_writable("Importing Wireshark configuration")?;
+    let result = profile::import_wireshark_config(profile.as_deref())?;
+    restart_all_sharkd().await?;
+    Ok(result)
+}
+
+/// Recolor the coloring rules in a color-blind-safe palette (`deuteranopia`,
+/// `protanopia`, or `default` to restore them) and restart sharkd to apply it
+#[tauri::command]
+async fn apply_palette(preset: palette::PalettePreset) -> Result<palette::PaletteResult, String> {
+    evidence::ensure_writable("Changing the coloring palette")?;
+    let result = palette::apply_palette(preset)?;
+    restart_all_sharkd().await?;
+    Ok(result)
+}
+
+/// Coloring rules of the profile in priority order, with `#rrggbb` colors
+#[tauri::command]
+fn list_coloring_rules() -> Result<Vec<coloring_rules::ColoringRule>, String> {
+    coloring_rules::list_rules()
+}
+
+/// Add a coloring rule (first in priority unless `position` is given) and
+/// restart sharkd to apply it. Returns the updated rules.
+#[tauri::command]
+async fn add_coloring_rule(
+    name: Option<String>,
+    filter: String,
+    colors: coloring_rules::RuleColors,
+    position: Option<usize>,
+    session_id: Option<String>,
+) -> Result<Vec<coloring_rules::ColoringRule>, String> {
+    evidence::ensure_writable("Changing the coloring rules")?;
+    if !sharkd(session_id.as_deref())?.check_filter(&filter).await? {
+        return Err(format!("Invalid filter expression: {}", filter));
     }
 
-    // Return total frame count (sharkd doesn't have global filter state)
-    let status = client.status()?;
-    Ok(status.frames.unwrap_or(0))
+    let name = name.unwrap_or_else(|| filter.clone());
+    let rules = coloring_rules::add_rule(&name, &filter, &colors, position)?;
+    restart_all_sharkd().await?;
+    Ok(rules)
+}
+
+/// Remove a coloring rule by name and restart sharkd to apply it
+#[tauri::command]
+async fn remove_coloring_rule(name: String) -> Result<Vec<coloring_rules::ColoringRule>, String> {
+    evidence::ensure_writable("Changing the coloring rules")?;
+    let rules = coloring_rules::remove_rule(&name)?;
+    restart_all_sharkd().await?;
+    Ok(rules)
+}
+
+/// Restart sharkd so edits made to the coloring rules outside the app apply
+#[tauri::command]
+async fn reload_coloring_rules() -> Result<Vec<coloring_rules::ColoringRule>, String> {
+    restart_all_sharkd().await?;
+    coloring_rules::list_rules()
+}
+
+/// Wireshark preferences of a protocol module (`tcp`, `http`, ...), or all of
+/// them, with their types, current values and enum choices
+#[tauri::command]
+async fn get_prefs(
+    module: Option<String>,
+    session_id: Option<String>,
+) -> Result<Vec<sharkd_client::Preference>, String> {
+    let client = sharkd(session_id.as_deref())?;
+
+    client.prefs(module.as_deref()).await
 }
 
-/// Get detailed frame information (protocol tree + hex bytes)
+/// Change a Wireshark preference. It is saved to the managed profile and
+/// sharkd is restarted (reloading the open capture) to apply it.
 #[tauri::command]
-fn get_frame_details(frame_num: u32) -> Result<serde_json::Value, String> {
-    let client_guard = get_sharkd().lock();
-    let client = client_guard
-        .as_ref()
-        .ok_or_else(|| "Sharkd not initialized".to_string())?;
+async fn set_pref(name: String, value: String, session_id: Option<String>) -> Result<(), String> {
+    evidence::ensure_writable("Changing preferences")?;
+    // sharkd rejects unknown preferences and invalid values
+    sharkd(session_id.as_deref())?
+        .set_pref(&name, &value)
+        .await?;
+    profile::set_preference(&name, Some(&value))?;
+    restart_all_sharkd().await
+}
 
-    client.frame(frame_num)
+/// Return a Wireshark preference to its default, restarting sharkd to apply it
+#[tauri::command]
+async fn reset_pref(name: String) -> Result<(), String> {
+    evidence::ensure_writable("Changing preferences")?;
+    profile::set_preference(&name, None)?;
+    restart_all_sharkd().await
 }
 
 #[tauri::command]
@@ -230,7 +2688,6 @@ fn chatgpt_login() -> Result<auth::AuthTokens, String> {
     auth::exchange_code(&code, &verifier)
 }
 
-
 // ============================================
 // AI Sidecar Commands
 // ============================================
@@ -285,6 +2742,62 @@ fn get_install_health() -> InstallHealthStatus {
     sharkd_client::get_install_health()
 }
 
+/// Which features work in this installation (sharkd taps and follow
+/// protocols, Wireshark tools, read-only mode, AI sidecar)
+#[tauri::command]
+async fn get_feature_flags() -> features::FeatureFlags {
+    features::feature_flags().await
+}
+
+/// Turn recording of sharkd requests and responses on or off, for debugging
+#[tauri::command]
+fn set_sharkd_tracing(enabled: bool) {
+    sharkd_trace::set_enabled(enabled);
+}
+
+/// Recent sharkd request/response pairs with sizes and latencies, oldest first
+#[tauri::command]
+fn get_sharkd_trace() -> Vec<sharkd_trace::TraceEntry> {
+    sharkd_trace::entries()
+}
+
+/// Drop the recorded sharkd trace
+#[tauri::command]
+fn clear_sharkd_trace() {
+    sharkd_trace::clear();
+}
+
+/// Time frame paging, search, capture stats and field extraction on a
+/// session's open capture. Development builds only.
+#[tauri::command]
+async fn run_benchmarks(
+    session_id: Option<String>,
+) -> Result<Vec<benchmarks::BenchmarkResult>, String> {
+    if !cfg!(debug_assertions) {
+        return Err("Benchmarks are only available in development builds".to_string());
+    }
+    let client = sharkd(session_id.as_deref())?;
+    benchmarks::run(&client).await
+}
+
+/// Disk used by temporary files, per category, with each category's quota
+#[tauri::command(async)]
+fn get_cache_usage() -> Vec<cache_manager::CacheUsage> {
+    cache_manager::usage()
+}
+
+/// Delete temporary files of a category, or of all categories when omitted
+#[tauri::command(async)]
+fn clear_cache(category: Option<cache_manager::CacheCategory>) {
+    cache_manager::clear(category);
+}
+
+/// Set a category's size quota in bytes, evicting old files to fit it
+#[tauri::command(async)]
+fn set_cache_quota(category: cache_manager::CacheCategory, bytes: u64) -> Result<(), String> {
+    cache_manager::set_quota(category, bytes)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -295,26 +2808,202 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             init_sharkd,
             load_pcap,
+            cancel_operations,
+            open_session,
+            close_session,
+            list_sessions,
+            set_active_session,
+            open_in_new_window,
+            get_window_session,
+            get_task_queue,
             get_frames,
+            get_frames_with_columns,
+            export_frames,
             get_status,
             check_filter,
+            search_text,
+            find_frame,
+            search_bytes,
+            add_filter_history,
+            list_filter_history,
+            clear_filter_history,
+            save_named_filter,
+            list_saved_filters,
+            remove_saved_filter,
+            check_capture_filter,
             apply_filter,
             get_frame_details,
+            suggest_filters,
+            narrate_frame,
+            get_frame_comment,
+            set_frame_comment,
+            follow_stream,
+            save_stream,
+            list_quic_streams,
+            get_capture_stats,
+            get_conversations,
+            get_endpoints,
+            get_http_stats,
+            get_srt,
+            get_rtp_streams,
+            save_rtp_audio,
+            get_voip_calls,
+            get_mobile_summary,
+            get_wlan_stats,
+            get_io_graph,
+            get_tcp_stream_graph,
+            get_flow_graph,
+            get_overlay_stats,
+            compare_filters,
+            export_comm_graph,
+            export_har,
+            export_dissection,
+            export_hex_dump,
+            list_export_objects,
+            save_export_object,
+            list_external_tools,
+            save_external_tool,
+            remove_external_tool,
+            open_in_external_tool,
+            ingest_logs,
+            list_triage,
+            clear_triage,
+            list_notification_sinks,
+            save_notification_sink,
+            remove_notification_sink,
+            send_notification,
+            test_notification,
+            decryption_hints,
+            configure_tls_keylog,
+            list_decryption_keys,
+            save_decryption_key,
+            remove_decryption_key,
+            get_protobuf_config,
+            save_protobuf_config,
+            query,
+            list_saved_queries,
+            save_query,
+            remove_saved_query,
+            list_dashboards,
+            save_dashboard,
+            remove_dashboard,
+            list_automations,
+            save_automation,
+            remove_automation,
+            run_automation,
+            run_dashboard,
+            verify_capture,
+            get_capture_info,
+            get_capture_integrity,
+            generate_report,
+            export_analysis_bundle,
+            import_analysis_bundle,
+            set_read_only,
+            get_read_only,
+            get_custody_log,
+            check_known_capture,
+            list_recent_captures,
+            tag_capture,
+            search_captures,
+            list_workspace_backups,
+            restore_workspace_backup,
+            export_excerpt,
+            merge_captures,
+            split_capture,
+            time_shift_capture,
+            deduplicate_capture,
+            save_session,
+            restore_session,
+            clear_saved_session,
+            get_anonymization_policy,
+            set_anonymization_policy,
+            export_anonymized_capture,
+            get_anonymized_frames,
+            get_redaction_policy,
+            set_redaction_policy,
+            select_range,
+            get_selection,
+            clear_selection,
+            get_selection_filter,
+            export_selection,
+            search_fields,
+            get_field_catalog,
+            rebuild_field_catalog,
+            get_time_settings,
+            set_time_format,
+            set_time_reference,
+            get_columns,
+            set_columns,
+            list_layouts,
+            save_layout,
+            delete_layout,
+            apply_layout,
+            set_capture_layout,
+            undo,
+            redo,
+            get_undo_history,
+            import_wireshark_config,
+            apply_palette,
+            list_coloring_rules,
+            add_coloring_rule,
+            remove_coloring_rule,
+            reload_coloring_rules,
+            get_prefs,
+            set_pref,
+            reset_pref,
+            list_upload_destinations,
+            save_upload_destination,
+            remove_upload_destination,
+            upload_capture,
+            toggle_frame_marks,
+            mark_selection,
+            unmark_frames,
+            list_marked_frames,
+            ignore_frames,
+            ignore_selection,
+            unignore_frames,
+            list_ignored_frames,
             get_ai_auth_capabilities,
             chatgpt_login,
             get_install_health,
+            get_feature_flags,
+            set_sharkd_tracing,
+            get_sharkd_trace,
+            clear_sharkd_trace,
+            run_benchmarks,
+            get_cache_usage,
+            clear_cache,
+            set_cache_quota,
             start_ai_sidecar,
             stop_ai_sidecar,
             get_ai_sidecar_status
         ])
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                sessions::unbind_window(window.label());
+            }
+        })
         .setup(|app| {
+            let _ = APP_HANDLE.set(app.handle().clone());
+            match app.path().app_data_dir() {
+                Ok(dir) => storage::init(dir),
+                Err(e) => eprintln!("Warning: Failed to resolve app data dir: {}", e),
+            }
+
+            // Restart sharkd if it crashes
+            let (crash_tx, crash_rx) = mpsc::unbounded_channel();
+            let _ = SHARKD_CRASHES.set(crash_tx);
+            tauri::async_runtime::spawn(handle_sharkd_crashes(crash_rx));
+
             // Try to initialize sharkd on startup
             let app_handle = app.handle().clone();
-            std::thread::spawn(move || {
-                let mut client_guard = get_sharkd().lock();
-                match SharkdClient::new() {
+            tauri::async_runtime::spawn(async move {
+                // Must happen before sharkd starts so it picks up the imported profile
+                profile::import_on_first_run();
+
+                match spawn_sharkd().await {
                     Ok(client) => {
-                        *client_guard = Some(client);
+                        sessions::insert_if_absent(sessions::DEFAULT_SESSION, supervised(client));
                         println!("Sharkd initialized successfully");
                     }
                     Err(e) => {
@@ -325,18 +3014,29 @@ pub fn run() {
                 }
             });
 
+            // Build the dissector field catalog ahead of first use
+            field_catalog::warm();
+
             // Start HTTP bridge for Python sidecar communication
-            std::thread::spawn(|| {
-                let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-                rt.block_on(async {
-                    if let Err(e) = http_bridge::start_http_bridge().await {
-                        eprintln!("HTTP bridge error: {}", e);
-                    }
-                });
+            tauri::async_runtime::spawn(async {
+                if let Err(e) = http_bridge::start_http_bridge().await {
+                    eprintln!("HTTP bridge error: {}", e);
+                }
             });
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                for path in sessions::loaded_files() {
+                    evidence::record_close(&path);
+                }
+                evidence::flush();
+                cache_manager::clear(None);
+                // Don't lose debounced writes on shutdown
+                storage::flush_pending();
+            }
+        });
 }
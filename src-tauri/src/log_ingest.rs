@@ -0,0 +1,498 @@
+//! Ingesting Zeek and Suricata logs produced for the loaded capture, and
+//! correlating their flows and alerts with its packets.
+//!
+//! Each log record is matched to frames by 5-tuple and time; records that
+//! match land in the session's triage queue, so an alert can be opened
+//! straight at the packets that triggered it.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::notifications::{self, Notification, Severity};
+use crate::sharkd_client::SharkdClient;
+
+/// Records correlated per ingest; each one costs a sharkd query
+const MAX_CORRELATED_RECORDS: usize = 500;
+
+/// Frames listed per triage item
+const MAX_FRAMES_PER_ITEM: u32 = 1000;
+
+/// Slack around a record's time span when matching frames, to absorb clock
+/// rounding and Zeek's connection timeouts
+const TIME_SLACK_SECS: f64 = 1.0;
+
+/// Supported log formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogKind {
+    /// Zeek `conn.log` (TSV or JSON)
+    ZeekConn,
+    /// Zeek `dns.log` (TSV or JSON)
+    ZeekDns,
+    /// Suricata EVE JSON (`eve.json`); alert and flow events are used
+    SuricataEve,
+}
+
+/// A log record matched to frames of the loaded capture
+#[derive(Debug, Clone, Serialize)]
+pub struct TriageItem {
+    pub id: String,
+    pub kind: LogKind,
+    /// Unix timestamp of the record
+    pub ts: f64,
+    pub duration: Option<f64>,
+    pub src: String,
+    pub src_port: Option<u16>,
+    pub dst: String,
+    pub dst_port: Option<u16>,
+    pub proto: String,
+    /// One-line description: alert signature, DNS query, connection state, ...
+    pub summary: String,
+    /// Suricata alert severity (1 is most severe)
+    pub severity: Option<u8>,
+    /// Matching frame numbers
+    pub frames: Vec<u64>,
+}
+
+/// Outcome of an ingest
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestResult {
+    /// Usable records in the log
+    pub records: usize,
+    /// Records within the capture's time span
+    pub in_window: usize,
+    /// Records matched to frames and added to the triage queue
+    pub matched: usize,
+    /// True when records beyond `MAX_CORRELATED_RECORDS` were skipped
+    pub truncated: bool,
+}
+
+struct LogRecord {
+    ts: f64,
+    duration: Option<f64>,
+    src: String,
+    src_port: Option<u16>,
+    dst: String,
+    dst_port: Option<u16>,
+    proto: String,
+    summary: String,
+    severity: Option<u8>,
+}
+
+static TRIAGE: OnceLock<Mutex<BTreeMap<String, Vec<TriageItem>>>> = OnceLock::new();
+
+fn get_triage() -> &'static Mutex<BTreeMap<String, Vec<TriageItem>>> {
+    TRIAGE.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Triage queue of a session, most severe and earliest first
+pub fn triage_queue(session_id: &str) -> Vec<TriageItem> {
+    get_triage()
+        .lock()
+        .get(session_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Empty a session's triage queue (e.g. when its capture changes)
+pub fn clear_triage(session_id: &str) {
+    get_triage().lock().remove(session_id);
+}
+
+/// Zeek's unset/empty field markers
+fn zeek_field(value: Option<&String>) -> Option<&str> {
+    value
+        .map(String::as_str)
+        .filter(|v| !v.is_empty() && *v != "-" && *v != "(empty)")
+}
+
+/// Zeek log rows as field name -> value maps, from either the TSV format
+/// (with its `#fields` header) or JSON lines
+fn zeek_rows(content: &str) -> Vec<HashMap<String, String>> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+
+    for line in content.lines() {
+        if line.starts_with('{') {
+            if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(line) {
+                rows.push(
+                    map.into_iter()
+                        .map(|(k, v)| match v {
+                            Value::String(s) => (k, s),
+                            Value::Array(items) => (
+                                k,
+                                items
+                                    .iter()
+                                    .map(|i| {
+                                        i.as_str().map_or_else(|| i.to_string(), str::to_string)
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join(","),
+                            ),
+                            other => (k, other.to_string()),
+                        })
+                        .collect(),
+                );
+            }
+        } else if let Some(header) = line.strip_prefix("#fields\t") {
+            fields = header.split('\t').map(str::to_string).collect();
+        } else if !line.starts_with('#') && !line.is_empty() && !fields.is_empty() {
+            rows.push(
+                fields
+                    .iter()
+                    .cloned()
+                    .zip(line.split('\t').map(str::to_string))
+                    .collect(),
+            );
+        }
+    }
+    rows
+}
+
+fn zeek_record(row: &HashMap<String, String>, kind: LogKind) -> Option<LogRecord> {
+    let field = |name: &str| zeek_field(row.get(name));
+    let ts = field("ts")?.parse::<f64>().ok()?;
+    let proto = field("proto").unwrap_or("").to_string();
+
+    let summary = match kind {
+        LogKind::ZeekDns => {
+            let mut summary = format!(
+                "DNS {} {}",
+                field("qtype_name").unwrap_or("query"),
+                field("query").unwrap_or("?")
+            );
+            if let Some(rcode) = field("rcode_name") {
+                summary.push_str(&format!(" ({})", rcode));
+            }
+            if let Some(answers) = field("answers") {
+                summary.push_str(&format!(" -> {}", answers));
+            }
+            summary
+        }
+        _ => {
+            let service = field("service").unwrap_or(&proto).to_string();
+            match field("conn_state") {
+                Some(state) => format!("{} connection ({})", service, state),
+                None => format!("{} connection", service),
+            }
+        }
+    };
+
+    Some(LogRecord {
+        ts,
+        duration: field("duration").and_then(|d| d.parse().ok()),
+        src: field("id.orig_h")?.to_string(),
+        src_port: field("id.orig_p").and_then(|p| p.parse().ok()),
+        dst: field("id.resp_h")?.to_string(),
+        dst_port: field("id.resp_p").and_then(|p| p.parse().ok()),
+        proto,
+        summary,
+        severity: None,
+    })
+}
+
+/// Days since the Unix epoch for a civil date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parse an EVE timestamp such as `2024-01-15T10:20:30.123456+0000`
+fn parse_eve_timestamp(ts: &str) -> Option<f64> {
+    let (date, time) = ts.split_once('T')?;
+    let mut date_parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+
+    // Split off the UTC offset (+hhmm, +hh:mm, or Z)
+    let (clock, offset_secs) = match time.find(['+', '-', 'Z']) {
+        Some(i) => {
+            let (clock, zone) = time.split_at(i);
+            let digits: String = zone.chars().filter(char::is_ascii_digit).collect();
+            let offset = if digits.len() >= 4 {
+                let hours = digits[..2].parse::<i64>().ok()?;
+                let minutes = digits[2..4].parse::<i64>().ok()?;
+                (hours * 3_600 + minutes * 60) as f64
+            } else {
+                0.0
+            };
+            (
+                clock,
+                if zone.starts_with('-') {
+                    -offset
+                } else {
+                    offset
+                },
+            )
+        }
+        None => (time, 0.0),
+    };
+
+    let mut clock_parts = clock.split(':');
+    let hours = clock_parts.next()?.parse::<f64>().ok()?;
+    let minutes = clock_parts.next()?.parse::<f64>().ok()?;
+    let seconds = clock_parts.next()?.parse::<f64>().ok()?;
+
+    let days = days_from_civil(year, month, day) as f64;
+    Some(days * 86_400.0 + hours * 3_600.0 + minutes * 60.0 + seconds - offset_secs)
+}
+
+fn eve_record(event: &Value) -> Option<LogRecord> {
+    let str_field = |name: &str| event.get(name).and_then(Value::as_str);
+    let port = |name: &str| {
+        event
+            .get(name)
+            .and_then(Value::as_u64)
+            .and_then(|p| u16::try_from(p).ok())
+    };
+
+    let (summary, severity, duration) = match str_field("event_type")? {
+        "alert" => {
+            let alert = event.get("alert")?;
+            (
+                alert
+                    .get("signature")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Suricata alert")
+                    .to_string(),
+                alert
+                    .get("severity")
+                    .and_then(Value::as_u64)
+                    .and_then(|s| u8::try_from(s).ok()),
+                None,
+            )
+        }
+        "flow" => {
+            let flow = event.get("flow");
+            let start = flow
+                .and_then(|f| f.get("start"))
+                .and_then(Value::as_str)
+                .and_then(parse_eve_timestamp);
+            let end = flow
+                .and_then(|f| f.get("end"))
+                .and_then(Value::as_str)
+                .and_then(parse_eve_timestamp);
+            let app_proto = str_field("app_proto").unwrap_or("unknown");
+            let state = flow
+                .and_then(|f| f.get("state"))
+                .and_then(Value::as_str)
+                .unwrap_or("");
+            (
+                format!("{} flow {}", app_proto, state).trim().to_string(),
+                None,
+                start.zip(end).map(|(s, e)| e - s),
+            )
+        }
+        _ => return None,
+    };
+
+    // Flow events are stamped at their end; match from the flow's start
+    let ts = event
+        .get("flow")
+        .filter(|_| duration.is_some())
+        .and_then(|f| f.get("start"))
+        .and_then(Value::as_str)
+        .or_else(|| str_field("timestamp"))
+        .and_then(parse_eve_timestamp)?;
+
+    Some(LogRecord {
+        ts,
+        duration,
+        src: str_field("src_ip")?.to_string(),
+        src_port: port("src_port"),
+        dst: str_field("dest_ip")?.to_string(),
+        dst_port: port("dest_port"),
+        proto: str_field("proto").unwrap_or("").to_lowercase(),
+        summary,
+        severity,
+    })
+}
+
+fn parse_log(content: &str, kind: LogKind) -> Vec<LogRecord> {
+    match kind {
+        LogKind::ZeekConn | LogKind::ZeekDns => zeek_rows(content)
+            .iter()
+            .filter_map(|row| zeek_record(row, kind))
+            .collect(),
+        LogKind::SuricataEve => content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter_map(|event| eve_record(&event))
+            .collect(),
+    }
+}
+
+/// Display filter matching a record's frames by 5-tuple and time
+fn record_filter(record: &LogRecord) -> String {
+    let addr_field = |addr: &str| {
+        if addr.contains(':') {
+            "ipv6.addr"
+        } else {
+            "ip.addr"
+        }
+    };
+    let mut clauses = vec![
+        format!("{} == {}", addr_field(&record.src), record.src),
+        format!("{} == {}", addr_field(&record.dst), record.dst),
+    ];
+
+    let port_field = match record.proto.as_str() {
+        "tcp" => Some("tcp.port"),
+        "udp" => Some("udp.port"),
+        "sctp" => Some("sctp.port"),
+        _ => None,
+    };
+    match port_field {
+        Some(port_field) => {
+            for port in [record.src_port, record.dst_port].into_iter().flatten() {
+                clauses.push(format!("{} == {}", port_field, port));
+            }
+        }
+        None => {
+            if !record.proto.is_empty() {
+                clauses.push(record.proto.clone());
+            }
+        }
+    }
+
+    let start = record.ts - TIME_SLACK_SECS;
+    let end = record.ts + record.duration.unwrap_or(0.0) + TIME_SLACK_SECS;
+    clauses.push(format!("frame.time_epoch >= {:.6}", start));
+    clauses.push(format!("frame.time_epoch <= {:.6}", end));
+    clauses.join(" && ")
+}
+
+/// Time span of the loaded capture as Unix timestamps
+async fn capture_window(client: &SharkdClient) -> Result<(f64, f64), String> {
+    let status = client.status().await?;
+    let start = client
+        .column_values("frame.number == 1", &["frame.time_epoch"], 1)
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|row| row.first().and_then(|t| t.parse::<f64>().ok()))
+        .ok_or("No capture loaded")?;
+    Ok((start, start + status.duration.unwrap_or(0.0)))
+}
+
+/// Parse the log at `path`, correlate its records with the capture loaded in
+/// `client`, and add the matches to the session's triage queue
+pub async fn ingest_logs(
+    client: &SharkdClient,
+    session_id: &str,
+    path: &str,
+    kind: LogKind,
+) -> Result<IngestResult, String> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let records = parse_log(&content, kind);
+    if records.is_empty() {
+        return Err(format!("No usable records found in {}", path));
+    }
+
+    let (start, end) = capture_window(client).await?;
+    let in_window: Vec<&LogRecord> = records
+        .iter()
+        .filter(|r| {
+            r.ts + r.duration.unwrap_or(0.0) >= start - TIME_SLACK_SECS
+                && r.ts <= end + TIME_SLACK_SECS
+        })
+        .collect();
+
+    let mut items = Vec::new();
+    for record in in_window.iter().take(MAX_CORRELATED_RECORDS) {
+        let frames: Vec<u64> = client
+            .column_values(
+                &record_filter(record),
+                &["frame.number"],
+                MAX_FRAMES_PER_ITEM,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|row| row.first().and_then(|n| n.parse().ok()))
+            .collect();
+        if frames.is_empty() {
+            continue;
+        }
+        items.push(TriageItem {
+            id: String::new(),
+            kind,
+            ts: record.ts,
+            duration: record.duration,
+            src: record.src.clone(),
+            src_port: record.src_port,
+            dst: record.dst.clone(),
+            dst_port: record.dst_port,
+            proto: record.proto.clone(),
+            summary: record.summary.clone(),
+            severity: record.severity,
+            frames,
+        });
+    }
+
+    let matched = items.len();
+    let capture = client.loaded_file();
+    notifications::notify_in_background(
+        items
+            .iter()
+            .filter_map(|item| {
+                // Suricata severities run 1 (high) to 3 (low), 4 for informational
+                let severity = match item.severity? {
+                    1 => Severity::High,
+                    2 => Severity::Medium,
+                    3 => Severity::Low,
+                    _ => Severity::Info,
+                };
+                Some(Notification {
+                    title: item.summary.clone(),
+                    message: format!(
+                        "{} {}:{} -> {}:{} ({} frames)",
+                        item.proto,
+                        item.src,
+                        item.src_port.unwrap_or(0),
+                        item.dst,
+                        item.dst_port.unwrap_or(0),
+                        item.frames.len()
+                    ),
+                    severity,
+                    source: "suricata".to_string(),
+                    capture: capture.clone(),
+                })
+            })
+            .collect(),
+    );
+
+    let mut triage = get_triage().lock();
+    let queue = triage.entry(session_id.to_string()).or_default();
+    let first_id = queue.len();
+    for (i, mut item) in items.into_iter().enumerate() {
+        item.id = format!("triage-{}", first_id + i + 1);
+        queue.push(item);
+    }
+    // Alerts first, by severity, then in time order
+    queue.sort_by(|a, b| {
+        a.severity
+            .unwrap_or(u8::MAX)
+            .cmp(&b.severity.unwrap_or(u8::MAX))
+            .then(a.ts.total_cmp(&b.ts))
+    });
+
+    Ok(IngestResult {
+        records: records.len(),
+        in_window: in_window.len(),
+        matched,
+        truncated: in_window.len() > MAX_CORRELATED_RECORDS,
+    })
+}
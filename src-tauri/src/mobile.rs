@@ -0,0 +1,406 @@
+//! Mobile core summary: GTP tunnels and sessions, Diameter sessions, and
+//! SCTP association health, aggregated from dissector fields.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Upper bound on frames scanned per protocol
+const MOBILE_SCAN_LIMIT: u32 = 1_000_000;
+
+/// SCTP chunk types tracked for association health
+const SCTP_INIT: u32 = 1;
+const SCTP_INIT_ACK: u32 = 2;
+const SCTP_HEARTBEAT: u32 = 4;
+const SCTP_HEARTBEAT_ACK: u32 = 5;
+const SCTP_ABORT: u32 = 6;
+const SCTP_SHUTDOWN: u32 = 7;
+
+/// A GTP-U tunnel (user plane traffic for one TEID)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GtpTunnel {
+    pub teid: String,
+    /// Outer source and destination of the tunnel's first packet
+    pub src: String,
+    pub dst: String,
+    pub packets: u64,
+    pub bytes: u64,
+    pub filter: String,
+}
+
+/// A GTP-C session: control messages for one subscriber
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GtpSession {
+    pub imsi: Option<String>,
+    pub apn: Option<String>,
+    /// TEIDs allocated in the session (control and user plane)
+    pub teids: Vec<String>,
+    pub messages: u64,
+    pub first_frame: u32,
+}
+
+/// A Diameter session, by Session-Id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiameterSession {
+    pub session_id: String,
+    pub application_id: Option<String>,
+    /// Command codes used in the session
+    pub commands: Vec<u32>,
+    pub requests: u64,
+    pub answers: u64,
+    /// Distinct Result-Code values in the answers
+    pub result_codes: Vec<u32>,
+    /// Requests without an answer in the capture
+    pub unanswered: u64,
+    pub filter: String,
+}
+
+/// An SCTP association between two endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SctpAssociation {
+    pub endpoint_a: String,
+    pub endpoint_b: String,
+    pub packets: u64,
+    /// An INIT was seen (the association was set up during the capture)
+    pub initiated: bool,
+    /// An INIT ACK was seen
+    pub established: bool,
+    pub aborted: bool,
+    pub shutdown: bool,
+    pub heartbeats: u64,
+    pub heartbeat_acks: u64,
+    /// `healthy`, `aborted`, `shut_down`, `heartbeat_loss`, or `not_established`
+    pub health: String,
+    pub filter: String,
+}
+
+/// Mobile core protocols in the capture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MobileSummary {
+    pub gtp_tunnels: Vec<GtpTunnel>,
+    pub gtp_sessions: Vec<GtpSession>,
+    pub diameter_sessions: Vec<DiameterSession>,
+    pub sctp_associations: Vec<SctpAssociation>,
+}
+
+/// `base` restricted to frames matching `filter`, if any
+fn restrict(base: &str, filter: Option<&str>) -> String {
+    match filter.filter(|f| !f.trim().is_empty()) {
+        Some(filter) => format!("({}) && ({})", base, filter),
+        None => base.to_string(),
+    }
+}
+
+/// Comma-separated occurrences of a field value
+fn occurrences(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn first(value: &str) -> Option<String> {
+    occurrences(value).next().map(String::from)
+}
+
+async fn gtp_tunnels(
+    client: &SharkdClient,
+    filter: Option<&str>,
+) -> Result<Vec<GtpTunnel>, String> {
+    // Message type 255 is G-PDU (user data)
+    let rows = client
+        .column_values(
+            &restrict("gtp.message == 255", filter),
+            &["gtp.teid", "ip.src:1", "ip.dst:1", "frame.len"],
+            MOBILE_SCAN_LIMIT,
+        )
+        .await?;
+
+    let mut tunnels: BTreeMap<String, GtpTunnel> = BTreeMap::new();
+    for row in rows {
+        let teid = match row.first().and_then(|t| first(t)) {
+            Some(teid) => teid,
+            None => continue,
+        };
+        let field = |i: usize| row.get(i).cloned().unwrap_or_default();
+        let tunnel = tunnels.entry(teid.clone()).or_insert_with(|| GtpTunnel {
+            filter: format!("gtp.teid == {}", teid),
+            teid,
+            src: field(1),
+            dst: field(2),
+            packets: 0,
+            bytes: 0,
+        });
+        tunnel.packets += 1;
+        tunnel.bytes += field(3).parse::<u64>().unwrap_or(0);
+    }
+    Ok(tunnels.into_values().collect())
+}
+
+async fn gtp_sessions(
+    client: &SharkdClient,
+    filter: Option<&str>,
+) -> Result<Vec<GtpSession>, String> {
+    let rows = client
+        .column_values(
+            &restrict("gtpv2 || (gtp && gtp.message != 255)", filter),
+            &[
+                "frame.number",
+                "e212.imsi",
+                "gtpv2.apn",
+                "gtp.apn",
+                "gtpv2.f_teid_gre_key",
+                "gtp.teid_cp",
+                "gtp.teid_data",
+                "gtpv2.teid",
+                "gtp.teid",
+            ],
+            MOBILE_SCAN_LIMIT,
+        )
+        .await?;
+
+    let mut sessions: Vec<GtpSession> = Vec::new();
+    let mut by_imsi: HashMap<String, usize> = HashMap::new();
+    let mut by_teid: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let field = |i: usize| row.get(i).map(String::as_str).unwrap_or_default();
+        let imsi = first(field(1));
+        let apn = first(field(2)).or_else(|| first(field(3)));
+        let allocated: Vec<String> = (4..=6)
+            .flat_map(|i| occurrences(field(i)))
+            .filter(|teid| !is_zero_teid(teid))
+            .map(String::from)
+            .collect();
+        // Messages without an IMSI are matched by the TEID they're sent to
+        let header_teid = first(field(7)).or_else(|| first(field(8)));
+
+        let existing = imsi
+            .as_ref()
+            .and_then(|imsi| by_imsi.get(imsi))
+            .or_else(|| header_teid.as_ref().and_then(|teid| by_teid.get(teid)))
+            .copied();
+        let index = match existing {
+            Some(index) => index,
+            None if imsi.is_some() || !allocated.is_empty() => {
+                sessions.push(GtpSession {
+                    imsi: None,
+                    apn: None,
+                    teids: Vec::new(),
+                    messages: 0,
+                    first_frame: field(0).parse().unwrap_or(0),
+                });
+                sessions.len() - 1
+            }
+            None => continue,
+        };
+
+        let session = &mut sessions[index];
+        session.messages += 1;
+        if session.imsi.is_none() {
+            if let Some(imsi) = imsi {
+                by_imsi.insert(imsi.clone(), index);
+                session.imsi = Some(imsi);
+            }
+        }
+        if session.apn.is_none() {
+            session.apn = apn;
+        }
+        for teid in allocated {
+            if !session.teids.contains(&teid) {
+                by_teid.insert(teid.clone(), index);
+                session.teids.push(teid);
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn is_zero_teid(teid: &str) -> bool {
+    teid.trim_start_matches("0x").chars().all(|c| c == '0')
+}
+
+async fn diameter_sessions(
+    client: &SharkdClient,
+    filter: Option<&str>,
+) -> Result<Vec<DiameterSession>, String> {
+    let rows = client
+        .column_values(
+            &restrict("diameter", filter),
+            &[
+                "diameter.Session-Id",
+                "diameter.cmd.code",
+                "diameter.flags.request",
+                "diameter.Result-Code",
+                "diameter.applicationId",
+            ],
+            MOBILE_SCAN_LIMIT,
+        )
+        .await?;
+
+    let mut sessions: BTreeMap<String, DiameterSession> = BTreeMap::new();
+    for row in rows {
+        let field = |i: usize| row.get(i).map(String::as_str).unwrap_or_default();
+        // An SCTP packet can bundle several messages
+        let session_ids: Vec<&str> = occurrences(field(0)).collect();
+        let commands: Vec<&str> = occurrences(field(1)).collect();
+        let requests: Vec<&str> = occurrences(field(2)).collect();
+        let applications: Vec<&str> = occurrences(field(4)).collect();
+        // Result-Codes can only be attributed when the packet has one message
+        let result_code = if session_ids.len() == 1 {
+            first(field(3)).and_then(|code| code.parse::<u32>().ok())
+        } else {
+            None
+        };
+
+        for (i, session_id) in session_ids.iter().enumerate() {
+            let session =
+                sessions
+                    .entry(session_id.to_string())
+                    .or_insert_with(|| DiameterSession {
+                        session_id: session_id.to_string(),
+                        application_id: applications.get(i).map(|a| a.to_string()),
+                        commands: Vec::new(),
+                        requests: 0,
+                        answers: 0,
+                        result_codes: Vec::new(),
+                        unanswered: 0,
+                        filter: format!("diameter.Session-Id == \"{}\"", session_id),
+                    });
+            if let Some(command) = commands.get(i).and_then(|c| c.parse::<u32>().ok()) {
+                if !session.commands.contains(&command) {
+                    session.commands.push(command);
+                }
+            }
+            if matches!(requests.get(i), Some(&("1" | "True" | "true"))) {
+                session.requests += 1;
+            } else {
+                session.answers += 1;
+                if let Some(code) = result_code {
+                    if !session.result_codes.contains(&code) {
+                        session.result_codes.push(code);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(sessions
+        .into_values()
+        .map(|mut session| {
+            session.unanswered = session.requests.saturating_sub(session.answers);
+            session
+        })
+        .collect())
+}
+
+async fn sctp_associations(
+    client: &SharkdClient,
+    filter: Option<&str>,
+) -> Result<Vec<SctpAssociation>, String> {
+    let rows = client
+        .column_values(
+            &restrict("sctp", filter),
+            &[
+                "ip.src:1",
+                "ip.dst:1",
+                "ipv6.src:1",
+                "ipv6.dst:1",
+                "sctp.srcport",
+                "sctp.dstport",
+                "sctp.chunk_type",
+            ],
+            MOBILE_SCAN_LIMIT,
+        )
+        .await?;
+
+    let mut associations: BTreeMap<(String, String), SctpAssociation> = BTreeMap::new();
+    for row in rows {
+        let field = |i: usize| row.get(i).map(String::as_str).unwrap_or_default();
+        let (src, dst) = if field(0).is_empty() {
+            (format!("[{}]", field(2)), format!("[{}]", field(3)))
+        } else {
+            (field(0).to_string(), field(1).to_string())
+        };
+        let a = format!("{}:{}", src, field(4));
+        let b = format!("{}:{}", dst, field(5));
+        let key = if a <= b { (a, b) } else { (b, a) };
+
+        let association = associations
+            .entry(key.clone())
+            .or_insert_with(|| SctpAssociation {
+                filter: sctp_filter(&src, field(4), &dst, field(5)),
+                endpoint_a: key.0,
+                endpoint_b: key.1,
+                packets: 0,
+                initiated: false,
+                established: false,
+                aborted: false,
+                shutdown: false,
+                heartbeats: 0,
+                heartbeat_acks: 0,
+                health: String::new(),
+            });
+        association.packets += 1;
+        let chunks: BTreeSet<u32> = occurrences(field(6))
+            .filter_map(|c| c.parse().ok())
+            .collect();
+        for chunk in chunks {
+            match chunk {
+                SCTP_INIT => association.initiated = true,
+                SCTP_INIT_ACK => association.established = true,
+                SCTP_HEARTBEAT => association.heartbeats += 1,
+                SCTP_HEARTBEAT_ACK => association.heartbeat_acks += 1,
+                SCTP_ABORT => association.aborted = true,
+                SCTP_SHUTDOWN => association.shutdown = true,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(associations
+        .into_values()
+        .map(|mut association| {
+            association.health = if association.aborted {
+                "aborted"
+            } else if association.shutdown {
+                "shut_down"
+            } else if association.heartbeats > association.heartbeat_acks {
+                "heartbeat_loss"
+            } else if association.initiated && !association.established {
+                "not_established"
+            } else {
+                "healthy"
+            }
+            .to_string();
+            association
+        })
+        .collect())
+}
+
+fn sctp_filter(src: &str, sport: &str, dst: &str, dport: &str) -> String {
+    let addr = if src.starts_with('[') {
+        "ipv6.addr"
+    } else {
+        "ip.addr"
+    };
+    format!(
+        "{addr}=={} && {addr}=={} && sctp.port=={} && sctp.port=={}",
+        src.trim_matches(|c| c == '[' || c == ']'),
+        dst.trim_matches(|c| c == '[' || c == ']'),
+        sport,
+        dport,
+        addr = addr
+    )
+}
+
+/// Summarize GTP, Diameter and SCTP in the capture, optionally restricted by
+/// a display filter
+pub async fn mobile_summary(
+    client: &SharkdClient,
+    filter: Option<&str>,
+) -> Result<MobileSummary, String> {
+    Ok(MobileSummary {
+        gtp_tunnels: gtp_tunnels(client, filter).await?,
+        gtp_sessions: gtp_sessions(client, filter).await?,
+        diameter_sessions: diameter_sessions(client, filter).await?,
+        sctp_associations: sctp_associations(client, filter).await?,
+    })
+}
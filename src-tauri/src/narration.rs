@@ -0,0 +1,262 @@
+//! One-sentence English descriptions of packets ("TCP SYN from
+//! 10.0.0.5:51234 to 93.184.216.34:443, window 64k") for screen readers and
+//! as grounding text for the AI.
+//!
+//! Built from dissector fields with fixed rules, so the same packet always
+//! reads the same way.
+
+use crate::sharkd_client::SharkdClient;
+
+/// Fields fetched for a narration, in the order of `Fields`' accessors
+const FIELDS: &[&str] = &[
+    "frame.protocols",
+    "frame.len",
+    "eth.src",
+    "eth.dst",
+    "ip.src",
+    "ip.dst",
+    "ipv6.src",
+    "ipv6.dst",
+    "tcp.srcport",
+    "tcp.dstport",
+    "udp.srcport",
+    "udp.dstport",
+    "tcp.flags.syn",
+    "tcp.flags.ack",
+    "tcp.flags.fin",
+    "tcp.flags.reset",
+    "tcp.window_size",
+    "tcp.len",
+    "dns.flags.response",
+    "dns.qry.name",
+    "http.request.method",
+    "http.host",
+    "http.request.uri",
+    "http.response.code",
+    "tls.handshake.type",
+    "tls.handshake.extensions_server_name",
+    "icmp.type",
+    "icmpv6.type",
+    "arp.opcode",
+    "arp.src.proto_ipv4",
+    "arp.dst.proto_ipv4",
+    "arp.src.hw_mac",
+];
+
+/// Display names of protocols whose name isn't just upper-cased
+const PROTOCOL_NAMES: &[(&str, &str)] = &[
+    ("ipv6", "IPv6"),
+    ("icmpv6", "ICMPv6"),
+    ("eth", "Ethernet"),
+    ("http2", "HTTP/2"),
+];
+
+/// Protocols that only carry others and don't describe a packet
+const CARRIER_PROTOCOLS: &[&str] = &["frame", "eth", "ethertype", "data", "vlan", "sll"];
+
+/// Field values of one frame
+struct Fields(Vec<String>);
+
+impl Fields {
+    /// First value of a field, or None when the frame doesn't have it
+    fn get(&self, field: &str) -> Option<&str> {
+        let index = FIELDS.iter().position(|f| *f == field)?;
+        self.0
+            .get(index)
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn flag(&self, field: &str) -> bool {
+        matches!(self.get(field), Some("1" | "True" | "true"))
+    }
+
+    fn number(&self, field: &str) -> Option<u64> {
+        self.get(field).and_then(|value| value.parse().ok())
+    }
+}
+
+fn protocol_name(protocol: &str) -> String {
+    PROTOCOL_NAMES
+        .iter()
+        .find(|(name, _)| *name == protocol)
+        .map(|(_, display)| display.to_string())
+        .unwrap_or_else(|| protocol.to_uppercase())
+}
+
+/// "10.0.0.5:51234", "[2001:db8::1]:443", or the bare address without a port
+fn endpoint(address: &str, port: Option<&str>) -> String {
+    match port {
+        Some(port) if address.contains(':') => format!("[{}]:{}", address, port),
+        Some(port) => format!("{}:{}", address, port),
+        None => address.to_string(),
+    }
+}
+
+/// Window sizes as "64k" once they reach a kilobyte
+fn window(size: u64) -> String {
+    if size >= 1024 {
+        format!("{}k", (size + 512) / 1024)
+    } else {
+        size.to_string()
+    }
+}
+
+fn tcp_flags(fields: &Fields) -> &'static str {
+    let syn = fields.flag("tcp.flags.syn");
+    let ack = fields.flag("tcp.flags.ack");
+    if fields.flag("tcp.flags.reset") {
+        "RST"
+    } else if syn && ack {
+        "SYN-ACK"
+    } else if syn {
+        "SYN"
+    } else if fields.flag("tcp.flags.fin") {
+        "FIN"
+    } else {
+        "ACK"
+    }
+}
+
+fn icmp_kind(icmp_type: u64, v6: bool) -> &'static str {
+    match (v6, icmp_type) {
+        (false, 0) | (true, 129) => "echo reply",
+        (false, 8) | (true, 128) => "echo request",
+        (false, 3) | (true, 1) => "destination unreachable",
+        (false, 11) | (true, 3) => "time exceeded",
+        (true, 135) => "neighbor solicitation",
+        (true, 136) => "neighbor advertisement",
+        _ => "message",
+    }
+}
+
+/// Describe a frame from its field values
+fn narrate(fields: &Fields) -> String {
+    let protocols: Vec<&str> = fields
+        .get("frame.protocols")
+        .map(|p| p.split(':').collect())
+        .unwrap_or_default();
+    let top = protocols
+        .iter()
+        .rev()
+        .find(|p| !CARRIER_PROTOCOLS.contains(p))
+        .copied()
+        .unwrap_or("frame");
+    let has = |protocol: &str| protocols.contains(&protocol);
+
+    // ARP has no IP header to describe it by
+    if has("arp") {
+        let target = fields.get("arp.dst.proto_ipv4").unwrap_or("an address");
+        let sender = fields.get("arp.src.proto_ipv4").unwrap_or("unknown");
+        return match fields.number("arp.opcode") {
+            Some(2) => format!(
+                "ARP reply: {} is at {}",
+                sender,
+                fields.get("arp.src.hw_mac").unwrap_or("an unknown address")
+            ),
+            _ => format!("ARP request: who has {}? Tell {}", target, sender),
+        };
+    }
+
+    let (src_port, dst_port) = if has("tcp") {
+        (fields.get("tcp.srcport"), fields.get("tcp.dstport"))
+    } else if has("udp") {
+        (fields.get("udp.srcport"), fields.get("udp.dstport"))
+    } else {
+        (None, None)
+    };
+    let (src, dst) = match (fields.get("ip.src"), fields.get("ipv6.src")) {
+        (Some(src), _) => (src, fields.get("ip.dst").unwrap_or("unknown")),
+        (None, Some(src)) => (src, fields.get("ipv6.dst").unwrap_or("unknown")),
+        (None, None) => (
+            fields.get("eth.src").unwrap_or("unknown"),
+            fields.get("eth.dst").unwrap_or("unknown"),
+        ),
+    };
+    let route = format!(
+        "from {} to {}",
+        endpoint(src, src_port),
+        endpoint(dst, dst_port)
+    );
+
+    match top {
+        "tcp" => {
+            let mut text = format!("TCP {} {}", tcp_flags(fields), route);
+            if let Some(size) = fields.number("tcp.window_size") {
+                text.push_str(&format!(", window {}", window(size)));
+            }
+            match fields.number("tcp.len") {
+                Some(len) if len > 0 => text.push_str(&format!(", {} bytes of data", len)),
+                _ => {}
+            }
+            text
+        }
+        "dns" => {
+            let name = fields.get("dns.qry.name").unwrap_or("an unnamed query");
+            let kind = if fields.flag("dns.flags.response") {
+                "response"
+            } else {
+                "query"
+            };
+            format!("DNS {} for {} {}", kind, name, route)
+        }
+        "http" => match (
+            fields.get("http.request.method"),
+            fields.get("http.response.code"),
+        ) {
+            (Some(method), _) => format!(
+                "HTTP {} request for {}{} {}",
+                method,
+                fields.get("http.host").unwrap_or(""),
+                fields.get("http.request.uri").unwrap_or("/"),
+                route
+            ),
+            (None, Some(code)) => format!("HTTP {} response {}", code, route),
+            (None, None) => format!("HTTP data {}", route),
+        },
+        "tls" => match (
+            fields.number("tls.handshake.type"),
+            fields.get("tls.handshake.extensions_server_name"),
+        ) {
+            (Some(1), Some(server_name)) => {
+                format!("TLS Client Hello for {} {}", server_name, route)
+            }
+            (Some(1), None) => format!("TLS Client Hello {}", route),
+            (Some(2), _) => format!("TLS Server Hello {}", route),
+            _ => format!("TLS encrypted data {}", route),
+        },
+        "icmp" | "icmpv6" => {
+            let v6 = top == "icmpv6";
+            let icmp_type = fields
+                .number(if v6 { "icmpv6.type" } else { "icmp.type" })
+                .unwrap_or(u64::MAX);
+            format!(
+                "{} {} {}",
+                protocol_name(top),
+                icmp_kind(icmp_type, v6),
+                route
+            )
+        }
+        _ => {
+            let len = fields
+                .number("frame.len")
+                .map(|len| format!(", {} bytes", len))
+                .unwrap_or_default();
+            format!("{} packet {}{}", protocol_name(top), route, len)
+        }
+    }
+}
+
+/// A screen-reader-friendly sentence describing one frame
+pub async fn narrate_frame(client: &SharkdClient, frame: u32) -> Result<String, String> {
+    let frames = client
+        .frames_with_columns(&format!("frame.number == {}", frame), 0, 1, FIELDS)
+        .await?;
+    let values = frames
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Frame {} not found", frame))?
+        .columns;
+    Ok(narrate(&Fields(values)))
+}
@@ -0,0 +1,322 @@
+//! Forwarding severe findings to configured notification sinks: webhooks,
+//! syslog collectors, and desktop notifications.
+//!
+//! Each sink has a minimum severity, so e.g. only critical findings page the
+//! on-call webhook while everything from medium up reaches syslog. Like
+//! uploads, webhooks shell out to `curl` and desktop notifications to the
+//! platform's notifier.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::net::UdpSocket;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use crate::storage;
+
+pub const STORE_NAME: &str = "notification-sinks";
+
+/// Finding severity, least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Syslog severity code (RFC 5424)
+    fn syslog_code(self) -> u8 {
+        match self {
+            Severity::Critical => 2,
+            Severity::High => 3,
+            Severity::Medium => 4,
+            Severity::Low => 5,
+            Severity::Info => 6,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+fn default_syslog_port() -> u16 {
+    514
+}
+
+fn default_facility() -> u8 {
+    // local0
+    16
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Kind-specific sink settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkKind {
+    /// JSON POST of the notification to a URL
+    Webhook {
+        url: String,
+        /// Extra headers as `Name: value`
+        #[serde(default)]
+        headers: Vec<String>,
+    },
+    /// RFC 5424 message over UDP
+    Syslog {
+        host: String,
+        #[serde(default = "default_syslog_port")]
+        port: u16,
+        #[serde(default = "default_facility")]
+        facility: u8,
+    },
+    /// Notification on this machine's desktop
+    Desktop,
+}
+
+/// A configured notification sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSink {
+    pub id: String,
+    pub name: String,
+    /// Findings below this severity aren't sent to the sink
+    pub min_severity: Severity,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub kind: SinkKind,
+}
+
+/// A finding to forward
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub title: String,
+    pub message: String,
+    pub severity: Severity,
+    /// What raised it, e.g. "suricata" or "ai"
+    #[serde(default)]
+    pub source: String,
+    /// Capture the finding is about
+    #[serde(default)]
+    pub capture: Option<String>,
+}
+
+/// Outcome of delivering a notification to one sink
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub sink_id: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SinkStore {
+    sinks: Vec<NotificationSink>,
+}
+
+static SINKS: OnceLock<Mutex<SinkStore>> = OnceLock::new();
+
+fn get_sinks() -> &'static Mutex<SinkStore> {
+    SINKS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_sinks().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Configured notification sinks
+pub fn list_sinks() -> Vec<NotificationSink> {
+    get_sinks().lock().sinks.clone()
+}
+
+/// Add or replace (by id) a notification sink
+pub fn save_sink(sink: NotificationSink) -> Result<(), String> {
+    match &sink.kind {
+        SinkKind::Webhook { url, .. } if !url.starts_with("http") => {
+            return Err(format!("Webhook URL must be http(s): {}", url));
+        }
+        SinkKind::Syslog { host, .. } if host.trim().is_empty() => {
+            return Err("A syslog sink needs a host".to_string());
+        }
+        _ => {}
+    }
+    let mut store = get_sinks().lock();
+    store.sinks.retain(|s| s.id != sink.id);
+    store.sinks.push(sink);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove a notification sink
+pub fn remove_sink(id: &str) -> Result<(), String> {
+    let mut store = get_sinks().lock();
+    store.sinks.retain(|s| s.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+fn send_webhook(url: &str, headers: &[String], notification: &Notification) -> Result<(), String> {
+    let body = serde_json::to_string(notification).map_err(|e| e.to_string())?;
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(["-X", "POST", "-H", "Content-Type: application/json"]);
+    for header in headers {
+        command.arg("-H").arg(header);
+    }
+    let output = command
+        .args(["--data-binary", body.as_str(), url])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Webhook failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn send_syslog(
+    host: &str,
+    port: u16,
+    facility: u8,
+    notification: &Notification,
+) -> Result<(), String> {
+    let priority = u16::from(facility.min(23)) * 8 + u16::from(notification.severity.syslog_code());
+    // <PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD MSG, leaving
+    // the timestamp and hostname to the collector
+    let msg_id: String = notification
+        .source
+        .replace(' ', "_")
+        .chars()
+        .take(32)
+        .collect();
+    let message = format!(
+        "<{}>1 - - packetpilot - {} - [{}] {}: {}",
+        priority,
+        if msg_id.is_empty() { "-" } else { &msg_id },
+        notification.severity.as_str(),
+        notification.title,
+        notification.message
+    );
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .or_else(|_| UdpSocket::bind("[::]:0"))
+        .map_err(|e| format!("Failed to open syslog socket: {}", e))?;
+    socket
+        .send_to(message.as_bytes(), (host, port))
+        .map_err(|e| format!("Failed to send to syslog {}:{}: {}", host, port, e))?;
+    Ok(())
+}
+
+fn send_desktop(notification: &Notification) -> Result<(), String> {
+    let title = format!("PacketPilot: {}", notification.title);
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            quote(&notification.message),
+            quote(&title)
+        ));
+        command
+    } else if cfg!(target_os = "windows") {
+        return Err("Desktop notifications aren't supported on Windows yet".to_string());
+    } else {
+        let urgency = if notification.severity >= Severity::High {
+            "critical"
+        } else {
+            "normal"
+        };
+        let mut command = Command::new("notify-send");
+        command.args([
+            "-u",
+            urgency,
+            "-a",
+            "PacketPilot",
+            title.as_str(),
+            notification.message.as_str(),
+        ]);
+        command
+    };
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to show desktop notification: {}", e))?;
+    if !status.success() {
+        return Err(format!("Desktop notifier failed ({})", status));
+    }
+    Ok(())
+}
+
+fn deliver(sink: &NotificationSink, notification: &Notification) -> Delivery {
+    let result = match &sink.kind {
+        SinkKind::Webhook { url, headers } => send_webhook(url, headers, notification),
+        SinkKind::Syslog {
+            host,
+            port,
+            facility,
+        } => send_syslog(host, *port, *facility, notification),
+        SinkKind::Desktop => send_desktop(notification),
+    };
+    if let Err(e) = &result {
+        eprintln!("Notification to {} failed: {}", sink.id, e);
+    }
+    Delivery {
+        sink_id: sink.id.clone(),
+        ok: result.is_ok(),
+        error: result.err(),
+    }
+}
+
+/// Send a notification to every enabled sink routed its severity
+pub fn notify(notification: &Notification) -> Vec<Delivery> {
+    list_sinks()
+        .iter()
+        .filter(|s| s.enabled && notification.severity >= s.min_severity)
+        .map(|s| deliver(s, notification))
+        .collect()
+}
+
+/// Send notifications from a background thread, for callers that shouldn't
+/// wait on webhooks
+pub fn notify_in_background(notifications: Vec<Notification>) {
+    if notifications.is_empty() || list_sinks().iter().all(|s| !s.enabled) {
+        return;
+    }
+    std::thread::spawn(move || {
+        for notification in &notifications {
+            notify(notification);
+        }
+    });
+}
+
+/// Send a test notification to one sink, regardless of its routing
+pub fn test_sink(id: &str) -> Result<Delivery, String> {
+    let sink = list_sinks()
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Unknown notification sink: {}", id))?;
+    let notification = Notification {
+        title: "Test notification".to_string(),
+        message: format!("Notifications to '{}' are working.", sink.name),
+        severity: sink.min_severity,
+        source: "packetpilot".to_string(),
+        capture: None,
+    };
+    Ok(deliver(&sink, &notification))
+}
@@ -0,0 +1,318 @@
+//! Color-blind-safe recoloring of the managed profile's coloring rules.
+//!
+//! Each rule's colors are mapped by hue onto a preset palette, keeping their
+//! lightness so the packet list reads the same way, and the `colorfilters`
+//! file is rewritten. The rules as they were before the first recoloring are
+//! kept alongside it, so presets can be switched or undone without drift.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{profile, wireshark_tools};
+
+/// Coloring rules file in a Wireshark profile
+const COLORFILTERS: &str = "colorfilters";
+
+/// The rules as they were before recoloring
+const ORIGINAL_COLORFILTERS: &str = "colorfilters.original";
+
+/// First line of a recolored file, followed by the preset name
+const HEADER: &str = "# PacketPilot palette:";
+
+/// Below this HSL saturation a color is treated as gray and left alone
+const GRAY_SATURATION: f64 = 0.15;
+
+/// Minimum text/background contrast ratio before falling back to black or white
+const MIN_CONTRAST: f64 = 4.5;
+
+/// Palette presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PalettePreset {
+    /// The rules' own colors
+    Default,
+    /// For reduced green sensitivity
+    Deuteranopia,
+    /// For reduced red sensitivity
+    Protanopia,
+}
+
+impl PalettePreset {
+    const ALL: [PalettePreset; 3] = [
+        PalettePreset::Default,
+        PalettePreset::Deuteranopia,
+        PalettePreset::Protanopia,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Default => "default",
+            PalettePreset::Deuteranopia => "deuteranopia",
+            PalettePreset::Protanopia => "protanopia",
+        }
+    }
+
+    /// Replacement colors for red, orange, yellow, green, cyan, blue and
+    /// purple hues
+    fn colors(self) -> Option<[Rgb; 7]> {
+        match self {
+            PalettePreset::Default => None,
+            // Okabe-Ito
+            PalettePreset::Deuteranopia => Some([
+                Rgb::hex(0xd55e00),
+                Rgb::hex(0xe69f00),
+                Rgb::hex(0xf0e442),
+                Rgb::hex(0x56b4e9),
+                Rgb::hex(0x009e73),
+                Rgb::hex(0x0072b2),
+                Rgb::hex(0xcc79a7),
+            ]),
+            // IBM Design color-blind-safe palette
+            PalettePreset::Protanopia => Some([
+                Rgb::hex(0xfe6100),
+                Rgb::hex(0xffb000),
+                Rgb::hex(0xffe680),
+                Rgb::hex(0x648fff),
+                Rgb::hex(0xa0c4ff),
+                Rgb::hex(0x785ef0),
+                Rgb::hex(0xdc267f),
+            ]),
+        }
+    }
+}
+
+/// Result of applying a palette
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaletteResult {
+    pub preset: PalettePreset,
+    /// Coloring rules in the rewritten file
+    pub rules: usize,
+}
+
+/// An sRGB color with components in 0..=1
+#[derive(Debug, Clone, Copy)]
+struct Rgb(f64, f64, f64);
+
+impl Rgb {
+    fn hex(value: u32) -> Self {
+        let channel = |shift: u32| f64::from((value >> shift) & 0xff) / 255.0;
+        Rgb(channel(16), channel(8), channel(0))
+    }
+
+    /// `[r,g,b]` with 16-bit components, as in `colorfilters`
+    fn parse(text: &str) -> Option<Self> {
+        let inner = text.strip_prefix('[')?.strip_suffix(']')?;
+        let parts: Vec<f64> = inner
+            .split(',')
+            .map(|p| p.trim().parse::<u16>().ok().map(|v| f64::from(v) / 65535.0))
+            .collect::<Option<_>>()?;
+        match parts.as_slice() {
+            [r, g, b] => Some(Rgb(*r, *g, *b)),
+            _ => None,
+        }
+    }
+
+    fn format(self) -> String {
+        let channel = |v: f64| (v.clamp(0.0, 1.0) * 65535.0).round() as u16;
+        format!(
+            "[{},{},{}]",
+            channel(self.0),
+            channel(self.1),
+            channel(self.2)
+        )
+    }
+
+    /// HSL hue in degrees, saturation and lightness
+    fn hsl(self) -> (f64, f64, f64) {
+        let Rgb(r, g, b) = self;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (hue, saturation, lightness)
+    }
+
+    fn mix(self, other: Rgb, amount: f64) -> Rgb {
+        let blend = |a: f64, b: f64| a + (b - a) * amount;
+        Rgb(
+            blend(self.0, other.0),
+            blend(self.1, other.1),
+            blend(self.2, other.2),
+        )
+    }
+
+    /// WCAG relative luminance
+    fn luminance(self) -> f64 {
+        let linear = |v: f64| {
+            if v <= 0.03928 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+
+    fn contrast(self, other: Rgb) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+const BLACK: Rgb = Rgb(0.0, 0.0, 0.0);
+const WHITE: Rgb = Rgb(1.0, 1.0, 1.0);
+
+/// Index into a preset's colors for a hue
+fn hue_slot(hue: f64) -> usize {
+    match hue {
+        h if h < 20.0 => 0,
+        h if h < 45.0 => 1,
+        h if h < 70.0 => 2,
+        h if h < 165.0 => 3,
+        h if h < 200.0 => 4,
+        h if h < 260.0 => 5,
+        h if h < 330.0 => 6,
+        _ => 0,
+    }
+}
+
+/// The palette color for `color`'s hue, lightened or darkened to its lightness
+fn remap(color: Rgb, palette: &[Rgb; 7]) -> Rgb {
+    let (hue, saturation, lightness) = color.hsl();
+    if saturation < GRAY_SATURATION {
+        return color;
+    }
+    let target = palette[hue_slot(hue)];
+    let (_, _, target_lightness) = target.hsl();
+    if lightness > target_lightness {
+        target.mix(
+            WHITE,
+            (lightness - target_lightness) / (1.0 - target_lightness),
+        )
+    } else if target_lightness > 0.0 {
+        target.mix(BLACK, (target_lightness - lightness) / target_lightness)
+    } else {
+        target
+    }
+}
+
+/// Recolor one `@name@filter@[fg][bg]` rule line; other lines are kept as-is
+fn recolor_line(line: &str, palette: &[Rgb; 7]) -> String {
+    if !is_rule(line) {
+        return line.to_string();
+    }
+    let recolored = line.rsplit_once("@[").and_then(|(rule, colors)| {
+        let (fg, bg) = colors.trim_end().split_once("][")?;
+        let fg = Rgb::parse(&format!("[{}]", fg))?;
+        let bg = Rgb::parse(&format!("[{}", bg))?;
+        let bg = remap(bg, palette);
+        let mut fg = remap(fg, palette);
+        if fg.contrast(bg) < MIN_CONTRAST {
+            fg = if BLACK.contrast(bg) >= WHITE.contrast(bg) {
+                BLACK
+            } else {
+                WHITE
+            };
+        }
+        Some(format!("{}@{}{}", rule, fg.format(), bg.format()))
+    });
+    recolored.unwrap_or_else(|| line.to_string())
+}
+
+fn is_rule(line: &str) -> bool {
+    line.starts_with('@') || line.starts_with("!@")
+}
+
+/// Wireshark's default coloring rules, from its global configuration directory
+pub fn global_colorfilters() -> Result<PathBuf, String> {
+    let folders = wireshark_tools::run_tool("tshark", &["-G".to_string(), "folders".to_string()])?;
+    folders
+        .lines()
+        .find_map(|line| line.strip_prefix("Global configuration:"))
+        .map(|dir| Path::new(dir.trim()).join(COLORFILTERS))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| "Wireshark's default coloring rules were not found".to_string())
+}
+
+/// The rules to recolor: the saved original when the current file is one of
+/// ours, otherwise the current file (which then becomes the saved original)
+fn original_rules(dir: &Path) -> Result<String, String> {
+    let current = dir.join(COLORFILTERS);
+    let original = dir.join(ORIGINAL_COLORFILTERS);
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+    };
+
+    if current.is_file() {
+        let text = read(&current)?;
+        if !text.starts_with(HEADER) {
+            // Rules imported or edited since the last recoloring
+            std::fs::write(&original, &text)
+                .map_err(|e| format!("Failed to write {:?}: {}", original, e))?;
+            return Ok(text);
+        }
+        if original.is_file() {
+            return read(&original);
+        }
+    }
+    let text = read(&global_colorfilters()?)?;
+    std::fs::write(&original, &text)
+        .map_err(|e| format!("Failed to write {:?}: {}", original, e))?;
+    Ok(text)
+}
+
+/// The preset the profile's coloring rules are recolored in, if any
+pub fn active_preset(dir: &Path) -> Option<PalettePreset> {
+    let text = std::fs::read_to_string(dir.join(COLORFILTERS)).ok()?;
+    let name = text.lines().next()?.strip_prefix(HEADER)?.trim();
+    PalettePreset::ALL.into_iter().find(|p| p.name() == name)
+}
+
+/// The file holding the rules as written by the user: the saved original
+/// while a palette is applied, otherwise `colorfilters` itself
+pub fn rules_file(dir: &Path) -> PathBuf {
+    let original = dir.join(ORIGINAL_COLORFILTERS);
+    if active_preset(dir).is_some() && original.is_file() {
+        original
+    } else {
+        dir.join(COLORFILTERS)
+    }
+}
+
+/// Rewrite the managed profile's coloring rules in `preset`'s colors.
+/// `Default` restores the rules' own colors. sharkd must be restarted for
+/// the new colors to show.
+pub fn apply_palette(preset: PalettePreset) -> Result<PaletteResult, String> {
+    let dir = profile::profile_dir()?;
+    let rules = original_rules(&dir)?;
+    let path = dir.join(COLORFILTERS);
+
+    let text = match preset.colors() {
+        None => rules.clone(),
+        Some(palette) => {
+            let mut text = format!("{} {}\n", HEADER, preset.name());
+            for line in rules.lines() {
+                text.push_str(&recolor_line(line, &palette));
+                text.push('\n');
+            }
+            text
+        }
+    };
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+
+    Ok(PaletteResult {
+        preset,
+        rules: rules.lines().filter(|line| is_rule(line)).count(),
+    })
+}
@@ -0,0 +1,149 @@
+//! PacketPilot's managed Wireshark configuration profile.
+//!
+//! sharkd is pointed at this directory via `WIRESHARK_CONFIG_DIR`, so
+//! preferences, coloring rules, and decode-as entries live here rather than
+//! in the user's personal Wireshark profile. On first run the user's existing
+//! Wireshark configuration is copied in.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::storage;
+
+/// Profile files copied from a Wireshark profile
+const PROFILE_FILES: &[&str] = &[
+    "preferences",
+    "colorfilters",
+    "dfilters",
+    "cfilters",
+    "dfilter_buttons",
+    "dfilter_macros",
+    "decode_as_entries",
+    "disabled_protos",
+    "enabled_protos",
+    "heuristic_protos",
+    "custom_http_header_fields",
+];
+
+/// Marker written once the first-run import has been attempted
+const FIRST_RUN_MARKER: &str = ".imported";
+
+/// Result of importing a Wireshark profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportResult {
+    /// Profile directory the files were copied from
+    pub source: String,
+    /// Names of the files copied
+    pub copied: Vec<String>,
+}
+
+/// The managed profile directory (created on demand)
+pub fn profile_dir() -> Result<PathBuf, String> {
+    let dir = storage::data_dir()?.join("wireshark-profile");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+/// The user's personal Wireshark configuration directory, if present
+fn wireshark_config_dir() -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        candidates.push(PathBuf::from(appdata).join("Wireshark"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME") {
+            candidates.push(PathBuf::from(xdg).join("wireshark"));
+        }
+        if let Some(home) = std::env::var_os("HOME") {
+            let home = PathBuf::from(home);
+            candidates.push(home.join(".config").join("wireshark"));
+            // Legacy location used by older Wireshark releases
+            candidates.push(home.join(".wireshark"));
+        }
+    }
+
+    candidates.into_iter().find(|p| p.is_dir())
+}
+
+/// Copy the user's Wireshark profile (the default one, or a named profile)
+/// into the managed profile directory
+pub fn import_wireshark_config(profile: Option<&str>) -> Result<ImportResult, String> {
+    let config_dir = wireshark_config_dir()
+        .ok_or_else(|| "No Wireshark configuration directory found".to_string())?;
+    let source = match profile {
+        Some(name) if !name.is_empty() => config_dir.join("profiles").join(name),
+        _ => config_dir,
+    };
+    if !source.is_dir() {
+        return Err(format!("Wireshark profile not found: {}", source.display()));
+    }
+
+    let dest = profile_dir()?;
+    let mut copied = Vec::new();
+    for file in PROFILE_FILES {
+        let from = source.join(file);
+        if from.is_file() {
+            std::fs::copy(&from, dest.join(file))
+                .map_err(|e| format!("Failed to copy {:?}: {}", from, e))?;
+            copied.push(file.to_string());
+        }
+    }
+
+    println!("Imported {} Wireshark profile file(s) from {:?}", copied.len(), source);
+    Ok(ImportResult {
+        source: source.display().to_string(),
+        copied,
+    })
+}
+
+/// Set (or with `None`, remove) a preference in the managed profile's
+/// `preferences` file, so it applies to every sharkd spawned afterwards
+pub fn set_preference(name: &str, value: Option<&str>) -> Result<(), String> {
+    let path = profile_dir()?.join("preferences");
+    let current = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+    };
+
+    let mut lines = Vec::new();
+    let mut in_entry = false;
+    for line in current.lines() {
+        // Long values continue on lines starting with whitespace
+        if in_entry && line.starts_with(|c: char| c.is_whitespace()) && !line.trim().is_empty() {
+            continue;
+        }
+        in_entry = line
+            .split_once(':')
+            .is_some_and(|(key, _)| key.trim() == name && !line.starts_with('#'));
+        if !in_entry {
+            lines.push(line.to_string());
+        }
+    }
+    if let Some(value) = value {
+        lines.push(format!("{}: {}", name, value));
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// Import the user's Wireshark configuration the first time the app runs
+pub fn import_on_first_run() {
+    let marker = match profile_dir() {
+        Ok(dir) => dir.join(FIRST_RUN_MARKER),
+        Err(_) => return,
+    };
+    if marker.exists() {
+        return;
+    }
+    if let Err(e) = import_wireshark_config(None) {
+        println!("Skipping Wireshark configuration import: {}", e);
+    }
+    let _ = std::fs::write(&marker, storage::now_secs().to_string());
+}
@@ -0,0 +1,120 @@
+//! Protobuf decoding preferences, so gRPC and other protobuf payloads show
+//! their message fields in the detail tree instead of raw bytes.
+//!
+//! gRPC messages are typed from the request path once the matching `.proto`
+//! files are on a search path; protobuf carried directly over UDP needs an
+//! explicit port-to-message-type mapping. Both are Wireshark UAT tables, so
+//! changes restart sharkd the same way decryption keys do.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::sharkd_client::SharkdClient;
+use crate::storage;
+
+pub const STORE_NAME: &str = "protobuf";
+
+/// A directory searched for `.proto` files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtoSearchPath {
+    pub path: String,
+    /// Load every `.proto` file under the directory, not only those imported
+    /// by others (needed when no file imports the message definitions)
+    #[serde(default)]
+    pub load_all: bool,
+}
+
+/// Protobuf message type carried on UDP ports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UdpMessageType {
+    /// Port range, e.g. `8127` or `8000-8010`
+    pub ports: String,
+    /// Fully qualified message type, e.g. `tutorial.AddressBook`
+    pub message_type: String,
+}
+
+/// Protobuf preferences applied to sharkd
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProtobufConfig {
+    #[serde(default)]
+    pub search_paths: Vec<ProtoSearchPath>,
+    #[serde(default)]
+    pub udp_message_types: Vec<UdpMessageType>,
+    /// Register message fields as filterable fields (e.g. `pbf.tutorial.Person.name`)
+    #[serde(default)]
+    pub fields_as_filters: bool,
+}
+
+static CONFIG: OnceLock<Mutex<ProtobufConfig>> = OnceLock::new();
+
+fn get_config() -> &'static Mutex<ProtobufConfig> {
+    CONFIG.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_config().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Current protobuf preferences
+pub fn get_protobuf_config() -> ProtobufConfig {
+    get_config().lock().clone()
+}
+
+/// Replace the protobuf preferences. Search paths must be existing directories.
+pub fn save_protobuf_config(config: ProtobufConfig) -> Result<(), String> {
+    for search_path in &config.search_paths {
+        if !Path::new(&search_path.path).is_dir() {
+            return Err(format!("Not a directory: {}", search_path.path));
+        }
+    }
+    for mapping in &config.udp_message_types {
+        if mapping.ports.trim().is_empty() || mapping.message_type.trim().is_empty() {
+            return Err("A UDP mapping needs ports and a message type".to_string());
+        }
+    }
+
+    let mut current = get_config().lock();
+    *current = config;
+    storage::save_json(STORE_NAME, &*current)
+}
+
+/// Push the protobuf preferences to a freshly spawned sharkd
+pub async fn apply_prefs(client: &SharkdClient) {
+    let config = get_protobuf_config();
+
+    if config.fields_as_filters {
+        if let Err(e) = client.set_pref("protobuf.pbf_as_hf", "TRUE").await {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    for search_path in &config.search_paths {
+        let load_all = if search_path.load_all {
+            "TRUE"
+        } else {
+            "FALSE"
+        };
+        if let Err(e) = client
+            .add_uat_record(
+                "protobuf_search_paths",
+                &[search_path.path.as_str(), load_all],
+            )
+            .await
+        {
+            eprintln!("Warning: Failed to add .proto search path: {}", e);
+        }
+    }
+    for mapping in &config.udp_message_types {
+        if let Err(e) = client
+            .add_uat_record(
+                "protobuf_udp_message_types",
+                &[mapping.ports.as_str(), mapping.message_type.as_str()],
+            )
+            .await
+        {
+            eprintln!("Warning: Failed to add protobuf UDP mapping: {}", e);
+        }
+    }
+}
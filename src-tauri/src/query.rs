@@ -0,0 +1,419 @@
+//! Small aggregate query language over capture fields.
+//!
+//! Expressions are compiled into a plan (display filter + fields to extract +
+//! aggregation), executed by extracting field values through sharkd custom
+//! columns and aggregating in Rust. Grammar:
+//!
+//! ```text
+//! top N FIELD [by count | by sum(FIELD) | by avg(FIELD) | by min(FIELD) | by max(FIELD)] [where FILTER]
+//! count [distinct FIELD] [where FILTER]
+//! sum|avg|min|max FIELD [where FILTER]
+//! ```
+//!
+//! e.g. `top 10 dns.qry.name by count where dns.flags.rcode==3`
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::frame_marks;
+use crate::sharkd_client::SharkdClient;
+
+/// Maximum number of frames a query scans
+pub const QUERY_SCAN_LIMIT: u32 = 500_000;
+
+/// Numeric aggregate function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Function {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sum" => Some(Function::Sum),
+            "avg" => Some(Function::Avg),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Function::Sum => "sum",
+            Function::Avg => "avg",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+}
+
+/// What `top` ranks groups by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Metric {
+    Count,
+    Field { function: Function, field: String },
+}
+
+impl Metric {
+    fn label(&self) -> String {
+        match self {
+            Metric::Count => "count".to_string(),
+            Metric::Field { function, field } => format!("{}({})", function.name(), field),
+        }
+    }
+}
+
+/// Aggregation performed over the extracted values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Aggregation {
+    Top {
+        limit: usize,
+        key: String,
+        metric: Metric,
+    },
+    Count,
+    CountDistinct {
+        field: String,
+    },
+    Scalar {
+        function: Function,
+        field: String,
+    },
+}
+
+/// A compiled query: what to filter, which fields to extract, how to aggregate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryPlan {
+    /// Display filter selecting the frames to scan
+    pub filter: String,
+    /// Fields extracted per frame
+    pub columns: Vec<String>,
+    pub aggregation: Aggregation,
+}
+
+/// One group of a `top` query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRow {
+    pub key: String,
+    pub value: f64,
+    /// Frames contributing to this group
+    pub frames: u64,
+}
+
+/// Result of running a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub plan: QueryPlan,
+    /// Label of the computed value, e.g. `count` or `sum(frame.len)`
+    pub metric: String,
+    /// Groups for `top` queries
+    pub rows: Vec<QueryRow>,
+    /// Single value for count/scalar queries
+    pub value: Option<f64>,
+    pub frames_scanned: usize,
+    /// The scan stopped at `QUERY_SCAN_LIMIT`
+    pub truncated: bool,
+}
+
+fn is_field_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn parse_field(token: Option<&str>, context: &str) -> Result<String, String> {
+    match token {
+        Some(t) if is_field_name(t) => Ok(t.to_string()),
+        Some(t) => Err(format!("Invalid field name '{}' in {}", t, context)),
+        None => Err(format!("Expected a field name after {}", context)),
+    }
+}
+
+/// Parse `count`, `sum(field)` or `sum field` after `by`
+fn parse_metric(tokens: &[&str]) -> Result<Metric, String> {
+    let joined = tokens.join(" ");
+    let joined = joined.trim();
+    if joined.eq_ignore_ascii_case("count") {
+        return Ok(Metric::Count);
+    }
+
+    let (name, field) = match joined.find('(') {
+        Some(open) if joined.ends_with(')') => {
+            (&joined[..open], joined[open + 1..joined.len() - 1].trim())
+        }
+        _ => joined.split_once(' ').unwrap_or((joined, "")),
+    };
+    let function = Function::parse(name.trim()).ok_or_else(|| {
+        format!(
+            "Unknown metric '{}', expected count/sum/avg/min/max",
+            joined
+        )
+    })?;
+    let field = parse_field(Some(field.trim()).filter(|f| !f.is_empty()), name.trim())?;
+    Ok(Metric::Field { function, field })
+}
+
+/// Split off a trailing `where FILTER` clause (keyword is case-insensitive)
+fn split_where(expr: &str) -> (&str, Option<&str>) {
+    let lower = expr.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(pos) = lower[search_from..].find("where") {
+        let start = search_from + pos;
+        let end = start + "where".len();
+        let before_ok = start == 0 || lower.as_bytes()[start - 1].is_ascii_whitespace();
+        let after_ok = end == lower.len() || lower.as_bytes()[end].is_ascii_whitespace();
+        if before_ok && after_ok {
+            return (expr[..start].trim(), Some(expr[end..].trim()));
+        }
+        search_from = end;
+    }
+    (expr.trim(), None)
+}
+
+/// Require every extracted field to be present so frames without it don't
+/// produce empty groups
+fn scoped_filter(where_clause: Option<&str>, fields: &[&str]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(w) = where_clause.filter(|w| !w.is_empty()) {
+        parts.push(format!("({})", w));
+    }
+    let mut seen = HashSet::new();
+    parts.extend(
+        fields
+            .iter()
+            .filter(|f| seen.insert(**f))
+            .map(|f| f.to_string()),
+    );
+    parts.join(" && ")
+}
+
+/// Compile a query expression into a plan
+pub fn compile(expr: &str) -> Result<QueryPlan, String> {
+    let (head, where_clause) = split_where(expr);
+    if where_clause == Some("") {
+        return Err("Expected a display filter after 'where'".to_string());
+    }
+    let tokens: Vec<&str> = head.split_whitespace().collect();
+    let verb = tokens
+        .first()
+        .map(|t| t.to_ascii_lowercase())
+        .ok_or_else(|| "Empty query".to_string())?;
+
+    let (aggregation, fields): (Aggregation, Vec<String>) = match verb.as_str() {
+        "top" => {
+            let limit = tokens
+                .get(1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .ok_or_else(|| "Expected a positive count after 'top'".to_string())?;
+            let key = parse_field(tokens.get(2).copied(), "'top N'")?;
+            let metric = match tokens.get(3) {
+                None => Metric::Count,
+                Some(t) if t.eq_ignore_ascii_case("by") => parse_metric(&tokens[4..])?,
+                Some(t) => return Err(format!("Unexpected '{}', expected 'by' or 'where'", t)),
+            };
+            let mut fields = vec![key.clone()];
+            if let Metric::Field { field, .. } = &metric {
+                fields.push(field.clone());
+            }
+            (Aggregation::Top { limit, key, metric }, fields)
+        }
+        "count" => match tokens.get(1) {
+            None => (Aggregation::Count, vec![]),
+            Some(t) if t.eq_ignore_ascii_case("distinct") && tokens.len() == 3 => {
+                let field = parse_field(tokens.get(2).copied(), "'count distinct'")?;
+                (
+                    Aggregation::CountDistinct {
+                        field: field.clone(),
+                    },
+                    vec![field],
+                )
+            }
+            Some(_) => {
+                return Err("Expected 'count' or 'count distinct FIELD'".to_string());
+            }
+        },
+        other => {
+            let function = Function::parse(other).ok_or_else(|| {
+                format!(
+                    "Unknown query '{}', expected top/count/sum/avg/min/max",
+                    other
+                )
+            })?;
+            if tokens.len() != 2 {
+                return Err(format!("Expected '{} FIELD'", other));
+            }
+            let field = parse_field(tokens.get(1).copied(), other)?;
+            (
+                Aggregation::Scalar {
+                    function,
+                    field: field.clone(),
+                },
+                vec![field],
+            )
+        }
+    };
+
+    let field_refs: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let columns = if fields.is_empty() {
+        vec!["frame.number".to_string()]
+    } else {
+        fields.clone()
+    };
+
+    Ok(QueryPlan {
+        filter: scoped_filter(where_clause, &field_refs),
+        columns,
+        aggregation,
+    })
+}
+
+/// Split a multi-occurrence column value into its occurrences
+fn occurrences(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        return u64::from_str_radix(hex, 16).ok().map(|n| n as f64);
+    }
+    match value {
+        "True" | "true" => Some(1.0),
+        "False" | "false" => Some(0.0),
+        _ => value.parse().ok(),
+    }
+}
+
+/// Running numeric aggregate
+#[derive(Default)]
+struct Accumulator {
+    sum: f64,
+    count: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, n: f64) {
+        self.sum += n;
+        self.count += 1;
+        self.min = Some(self.min.map_or(n, |m| m.min(n)));
+        self.max = Some(self.max.map_or(n, |m| m.max(n)));
+    }
+
+    fn result(&self, function: Function) -> f64 {
+        match function {
+            Function::Sum => self.sum,
+            Function::Avg if self.count > 0 => self.sum / self.count as f64,
+            Function::Avg => 0.0,
+            Function::Min => self.min.unwrap_or(0.0),
+            Function::Max => self.max.unwrap_or(0.0),
+        }
+    }
+}
+
+/// Aggregate extracted rows according to the plan
+fn aggregate(plan: &QueryPlan, rows: &[Vec<String>]) -> (Vec<QueryRow>, Option<f64>) {
+    let column = |row: &[String], i: usize| row.get(i).cloned().unwrap_or_default();
+
+    match &plan.aggregation {
+        Aggregation::Count => (vec![], Some(rows.len() as f64)),
+        Aggregation::CountDistinct { .. } => {
+            let distinct: HashSet<String> = rows
+                .iter()
+                .flat_map(|row| {
+                    occurrences(&column(row, 0))
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            (vec![], Some(distinct.len() as f64))
+        }
+        Aggregation::Scalar { function, .. } => {
+            let mut acc = Accumulator::default();
+            for row in rows {
+                occurrences(&column(row, 0))
+                    .filter_map(parse_number)
+                    .for_each(|n| acc.add(n));
+            }
+            (vec![], Some(acc.result(*function)))
+        }
+        Aggregation::Top { limit, metric, .. } => {
+            let mut groups: HashMap<String, (u64, Accumulator)> = HashMap::new();
+            for row in rows {
+                let keys = column(row, 0);
+                let values: Vec<f64> = occurrences(&column(row, 1))
+                    .filter_map(parse_number)
+                    .collect();
+                // A frame with repeated keys (e.g. several DNS queries) counts once per distinct key
+                let distinct: HashSet<&str> = occurrences(&keys).collect();
+                for key in distinct {
+                    let (frames, acc) = groups.entry(key.to_string()).or_default();
+                    *frames += 1;
+                    values.iter().for_each(|&n| acc.add(n));
+                }
+            }
+
+            let mut result: Vec<QueryRow> = groups
+                .into_iter()
+                .map(|(key, (frames, acc))| QueryRow {
+                    value: match metric {
+                        Metric::Count => frames as f64,
+                        Metric::Field { function, .. } => acc.result(*function),
+                    },
+                    key,
+                    frames,
+                })
+                .collect();
+            result.sort_by(|a, b| b.value.total_cmp(&a.value).then_with(|| a.key.cmp(&b.key)));
+            result.truncate(*limit);
+            (result, None)
+        }
+    }
+}
+
+/// Compile and run a query against the capture loaded in `session` (its
+/// ignored frames excluded)
+pub async fn run_query(
+    client: &SharkdClient,
+    session: &str,
+    expr: &str,
+) -> Result<QueryResult, String> {
+    let plan = compile(expr)?;
+    let filter = frame_marks::with_exclusions(session, Some(&plan.filter)).unwrap_or_default();
+    if !filter.is_empty() && !client.check_filter(&filter).await? {
+        return Err(format!("Invalid filter in query: {}", plan.filter));
+    }
+
+    let columns: Vec<&str> = plan.columns.iter().map(String::as_str).collect();
+    let rows = client
+        .column_values(&filter, &columns, QUERY_SCAN_LIMIT)
+        .await?;
+    let (result_rows, value) = aggregate(&plan, &rows);
+
+    let metric = match &plan.aggregation {
+        Aggregation::Top { metric, .. } => metric.label(),
+        Aggregation::Count => "count".to_string(),
+        Aggregation::CountDistinct { field } => format!("count(distinct {})", field),
+        Aggregation::Scalar { function, field } => format!("{}({})", function.name(), field),
+    };
+
+    Ok(QueryResult {
+        metric,
+        rows: result_rows,
+        value,
+        frames_scanned: rows.len(),
+        truncated: rows.len() as u32 >= QUERY_SCAN_LIMIT,
+        plan,
+    })
+}
@@ -0,0 +1,200 @@
+//! Self-contained HTML report of the loaded capture.
+//!
+//! Templates (light, dark, compact) only change the stylesheet; the report
+//! content is the same. With `embed_images` the logo and protocol chart are
+//! inlined as data URIs/SVG so the file can be shared on its own.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+use crate::sharkd_client::{CaptureStats, Conversation, ProtocolNode, Status};
+
+const LOGO_PNG: &[u8] = include_bytes!("../icons/128x128.png");
+
+/// Number of conversations listed per table
+const TOP_CONVERSATIONS: usize = 10;
+
+/// Visual template for the report
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportTemplate {
+    #[default]
+    Light,
+    Dark,
+    Compact,
+}
+
+/// Options for `generate_report`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportOptions {
+    pub template: ReportTemplate,
+    /// Inline the logo and charts so the report has no external references
+    pub embed_images: bool,
+    pub title: Option<String>,
+}
+
+fn stylesheet(template: ReportTemplate) -> &'static str {
+    match template {
+        ReportTemplate::Light => {
+            "body{font-family:sans-serif;margin:2em;background:#fff;color:#1d1d1f}\
+             table{border-collapse:collapse;margin:1em 0}\
+             td,th{border:1px solid #d0d0d7;padding:4px 8px;text-align:left}\
+             th{background:#f2f2f5}h1,h2{color:#0b5cad}.bar{fill:#0b5cad}"
+        }
+        ReportTemplate::Dark => {
+            "body{font-family:sans-serif;margin:2em;background:#16181d;color:#e6e6e6}\
+             table{border-collapse:collapse;margin:1em 0}\
+             td,th{border:1px solid #3a3f4b;padding:4px 8px;text-align:left}\
+             th{background:#232730}h1,h2{color:#6cb6ff}.bar{fill:#6cb6ff}"
+        }
+        ReportTemplate::Compact => {
+            "body{font-family:sans-serif;font-size:12px;margin:0.5em;color:#222}\
+             table{border-collapse:collapse;margin:0.3em 0}\
+             td,th{border:1px solid #ccc;padding:1px 4px;text-align:left}\
+             h1{font-size:16px}h2{font-size:13px;margin:0.6em 0 0.2em}.bar{fill:#555}"
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Horizontal bar chart of the top-level protocols as inline SVG
+fn protocol_chart(nodes: &[ProtocolNode]) -> String {
+    let max = nodes.iter().map(|n| n.frames).max().unwrap_or(0).max(1);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"520\" height=\"{}\">",
+        nodes.len() * 22
+    );
+    for (i, node) in nodes.iter().enumerate() {
+        let y = i * 22;
+        let width = (node.frames as f64 / max as f64 * 360.0).round();
+        let _ = write!(
+            svg,
+            "<text x=\"0\" y=\"{}\" font-size=\"12\" fill=\"currentColor\">{}</text>\
+             <rect class=\"bar\" x=\"120\" y=\"{}\" width=\"{}\" height=\"16\"/>\
+             <text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"currentColor\">{}</text>",
+            y + 14,
+            escape_html(&node.protocol),
+            y + 2,
+            width,
+            125.0 + width,
+            y + 14,
+            node.frames
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn conversation_table(out: &mut String, heading: &str, conversations: &[Conversation]) {
+    let mut top: Vec<&Conversation> = conversations.iter().collect();
+    top.sort_by_key(|c| std::cmp::Reverse(c.rxb + c.txb));
+    top.truncate(TOP_CONVERSATIONS);
+
+    let _ = write!(out, "<h2>{}</h2>", escape_html(heading));
+    if top.is_empty() {
+        out.push_str("<p>None</p>");
+        return;
+    }
+    out.push_str("<table><tr><th>Address A</th><th>Address B</th><th>Frames</th><th>Bytes</th></tr>");
+    for c in top {
+        let endpoint = |addr: &str, port: &Option<String>| match port {
+            Some(p) => format!("{}:{}", addr, p),
+            None => addr.to_string(),
+        };
+        let _ = write!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&endpoint(&c.saddr, &c.sport)),
+            escape_html(&endpoint(&c.daddr, &c.dport)),
+            c.rxf + c.txf,
+            c.rxb + c.txb
+        );
+    }
+    out.push_str("</table>");
+}
+
+/// Render the report HTML
+pub fn render_report(status: &Status, stats: &CaptureStats, options: &ReportOptions) -> String {
+    let title = options
+        .title
+        .clone()
+        .or_else(|| status.filename.clone())
+        .unwrap_or_else(|| "Capture report".to_string());
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>",
+        escape_html(&title),
+        stylesheet(options.template)
+    );
+    if options.embed_images {
+        let _ = write!(
+            out,
+            "<img alt=\"PacketPilot\" width=\"48\" height=\"48\" src=\"data:image/png;base64,{}\">",
+            BASE64.encode(LOGO_PNG)
+        );
+    }
+    let _ = write!(out, "<h1>{}</h1>", escape_html(&title));
+
+    out.push_str("<h2>Summary</h2><table>");
+    let _ = write!(
+        out,
+        "<tr><th>Frames</th><td>{}</td></tr>",
+        status.frames.unwrap_or(0)
+    );
+    if let Some(duration) = status.duration {
+        let _ = write!(out, "<tr><th>Duration</th><td>{:.3} s</td></tr>", duration);
+    }
+    let _ = write!(
+        out,
+        "<tr><th>IPv4 endpoints</th><td>{}</td></tr></table>",
+        stats.endpoints.len()
+    );
+
+    // The top-level phs node is "frame"; its children are the link layers
+    let protocols: &[ProtocolNode] = match stats.protocol_hierarchy.first() {
+        Some(root) if root.protocol == "frame" => &root.children,
+        _ => &stats.protocol_hierarchy,
+    };
+    out.push_str("<h2>Protocols</h2>");
+    if options.embed_images && !protocols.is_empty() {
+        out.push_str(&protocol_chart(protocols));
+    }
+    out.push_str("<table><tr><th>Protocol</th><th>Frames</th><th>Bytes</th></tr>");
+    for node in protocols {
+        let _ = write!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&node.protocol),
+            node.frames,
+            node.bytes
+        );
+    }
+    out.push_str("</table>");
+
+    conversation_table(&mut out, "Top TCP conversations", &stats.tcp_conversations);
+    conversation_table(&mut out, "Top UDP conversations", &stats.udp_conversations);
+
+    out.push_str("</body></html>");
+    out
+}
+
+/// Render the report and write it to `dest`
+pub fn generate_report(
+    status: &Status,
+    stats: &CaptureStats,
+    dest: &str,
+    options: &ReportOptions,
+) -> Result<(), String> {
+    std::fs::write(dest, render_report(status, stats, options))
+        .map_err(|e| format!("Failed to write report to {}: {}", dest, e))
+}
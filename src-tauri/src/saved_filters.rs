@@ -0,0 +1,157 @@
+//! Display filter history and named saved filters, kept by the backend so
+//! the AI sidecar can read and suggest them too.
+//!
+//! History entries are deduplicated by filter text and count how often each
+//! filter was applied; applying a saved filter counts towards it as well.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::storage;
+
+pub const STORE_NAME: &str = "filters";
+
+/// Filters kept in the history; the least recently used go first
+const MAX_HISTORY: usize = 200;
+
+/// A filter applied before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterHistoryEntry {
+    pub filter: String,
+    pub use_count: u32,
+    /// Unix timestamp (seconds)
+    pub last_used: u64,
+}
+
+/// A named filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub name: String,
+    pub filter: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub use_count: u32,
+    /// Unix timestamp (seconds), None until first applied
+    #[serde(default)]
+    pub last_used: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FilterStore {
+    #[serde(default)]
+    history: Vec<FilterHistoryEntry>,
+    #[serde(default)]
+    saved: Vec<SavedFilter>,
+}
+
+static FILTERS: OnceLock<Mutex<FilterStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<FilterStore> {
+    FILTERS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Record that a filter was applied
+pub fn add_filter_history(filter: &str) -> Result<FilterHistoryEntry, String> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Err("Filter is empty".to_string());
+    }
+    let now = storage::now_secs();
+    let mut store = get_store().lock();
+
+    let entry = match store.history.iter().position(|e| e.filter == filter) {
+        Some(index) => {
+            let mut entry = store.history.remove(index);
+            entry.use_count += 1;
+            entry.last_used = now;
+            entry
+        }
+        None => FilterHistoryEntry {
+            filter: filter.to_string(),
+            use_count: 1,
+            last_used: now,
+        },
+    };
+    store.history.insert(0, entry.clone());
+    store.history.truncate(MAX_HISTORY);
+
+    for saved in store.saved.iter_mut().filter(|s| s.filter == filter) {
+        saved.use_count += 1;
+        saved.last_used = Some(now);
+    }
+    storage::save_json(STORE_NAME, &*store)?;
+    Ok(entry)
+}
+
+/// Applied filters, most recent first
+pub fn list_filter_history(limit: Option<usize>) -> Vec<FilterHistoryEntry> {
+    let store = get_store().lock();
+    store
+        .history
+        .iter()
+        .take(limit.unwrap_or(MAX_HISTORY))
+        .cloned()
+        .collect()
+}
+
+/// Forget the filter history (saved filters are kept)
+pub fn clear_filter_history() -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.history.clear();
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Add or replace (by name) a saved filter, keeping its usage count
+pub fn save_named_filter(
+    name: &str,
+    filter: &str,
+    description: Option<String>,
+) -> Result<SavedFilter, String> {
+    let name = name.trim();
+    let filter = filter.trim();
+    if name.is_empty() {
+        return Err("A saved filter needs a name".to_string());
+    }
+    if filter.is_empty() {
+        return Err(format!("Saved filter '{}' has no filter", name));
+    }
+
+    let mut store = get_store().lock();
+    let previous = store.saved.iter().position(|s| s.name == name);
+    let (use_count, last_used, old_description) = match previous {
+        Some(index) => {
+            let old = store.saved.remove(index);
+            (old.use_count, old.last_used, old.description)
+        }
+        None => (0, None, String::new()),
+    };
+    let saved = SavedFilter {
+        name: name.to_string(),
+        filter: filter.to_string(),
+        description: description.unwrap_or(old_description),
+        use_count,
+        last_used,
+    };
+    store.saved.push(saved.clone());
+    storage::save_json(STORE_NAME, &*store)?;
+    Ok(saved)
+}
+
+/// Saved filters, in the order they were saved
+pub fn list_saved_filters() -> Vec<SavedFilter> {
+    get_store().lock().saved.clone()
+}
+
+/// Remove a saved filter
+pub fn remove_saved_filter(name: &str) -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.saved.retain(|s| s.name != name);
+    storage::save_json(STORE_NAME, &*store)
+}
@@ -0,0 +1,182 @@
+//! Central scheduler for background work against sharkd (capture summaries,
+//! stats precompute, decryption hints, ...).
+//!
+//! Every sharkd request runs at a priority class. Requests made directly by
+//! commands are interactive; work started through `spawn` runs at its class
+//! and waits for in-flight interactive requests before each of its own, so a
+//! `get_frames` call queues behind at most one background request instead of
+//! a whole batch. Background classes also have a limited number of slots.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tokio::sync::{Notify, Semaphore};
+
+use crate::storage;
+
+/// Tasks of the prefetch class that may run at once
+const PREFETCH_SLOTS: usize = 2;
+
+/// Tasks of the background class that may run at once
+const BACKGROUND_SLOTS: usize = 1;
+
+/// Priority classes, most urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Requests the user is waiting on
+    Interactive,
+    /// Work the user is likely to need next
+    Prefetch,
+    /// Everything else: summaries, indexing, precomputed stats
+    Background,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Queued,
+    Running,
+}
+
+/// A scheduled task, as listed by `get_task_queue`
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    pub priority: Priority,
+    pub session_id: Option<String>,
+    pub state: TaskState,
+    pub queued_at: u64,
+    pub started_at: Option<u64>,
+}
+
+struct Tasks {
+    tasks: Vec<TaskInfo>,
+    next_id: u64,
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+static TASKS: OnceLock<Mutex<Tasks>> = OnceLock::new();
+
+/// Interactive sharkd requests in flight
+static INTERACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+static INTERACTIVE_IDLE: OnceLock<Notify> = OnceLock::new();
+static PREFETCH: OnceLock<Semaphore> = OnceLock::new();
+static BACKGROUND: OnceLock<Semaphore> = OnceLock::new();
+
+fn get_tasks() -> &'static Mutex<Tasks> {
+    TASKS.get_or_init(|| {
+        Mutex::new(Tasks {
+            tasks: Vec::new(),
+            next_id: 1,
+        })
+    })
+}
+
+fn interactive_idle() -> &'static Notify {
+    INTERACTIVE_IDLE.get_or_init(Notify::new)
+}
+
+fn slots(priority: Priority) -> Option<&'static Semaphore> {
+    match priority {
+        Priority::Interactive => None,
+        Priority::Prefetch => Some(PREFETCH.get_or_init(|| Semaphore::new(PREFETCH_SLOTS))),
+        Priority::Background => Some(BACKGROUND.get_or_init(|| Semaphore::new(BACKGROUND_SLOTS))),
+    }
+}
+
+/// Priority of the current task; anything not started through `spawn` is
+/// interactive
+pub fn current_priority() -> Priority {
+    PRIORITY.try_with(|p| *p).unwrap_or(Priority::Interactive)
+}
+
+/// Marks an interactive request in flight until dropped
+pub struct InteractiveGuard(());
+
+impl Drop for InteractiveGuard {
+    fn drop(&mut self) {
+        if INTERACTIVE.fetch_sub(1, Ordering::SeqCst) == 1 {
+            interactive_idle().notify_waiters();
+        }
+    }
+}
+
+/// Wait for a turn to send a sharkd request at the current priority.
+/// Interactive requests go straight through (and hold back lower classes
+/// while the returned guard lives); others wait until none are in flight.
+pub async fn request_turn() -> Option<InteractiveGuard> {
+    if current_priority() == Priority::Interactive {
+        INTERACTIVE.fetch_add(1, Ordering::SeqCst);
+        return Some(InteractiveGuard(()));
+    }
+    loop {
+        let idle = interactive_idle().notified();
+        tokio::pin!(idle);
+        // Register before checking, so a wakeup between the two isn't lost
+        idle.as_mut().enable();
+        if INTERACTIVE.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        idle.await;
+    }
+}
+
+/// Removes a task from the queue when it finishes (or panics)
+struct TaskEntry(u64);
+
+impl Drop for TaskEntry {
+    fn drop(&mut self) {
+        get_tasks().lock().tasks.retain(|t| t.id != self.0);
+    }
+}
+
+/// Run `task` in the background at `priority`, once a slot of its class is free
+pub fn spawn<F>(name: &str, priority: Priority, session_id: Option<&str>, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let id = {
+        let mut tasks = get_tasks().lock();
+        let id = tasks.next_id;
+        tasks.next_id += 1;
+        tasks.tasks.push(TaskInfo {
+            id,
+            name: name.to_string(),
+            priority,
+            session_id: session_id.map(str::to_string),
+            state: TaskState::Queued,
+            queued_at: storage::now_secs(),
+            started_at: None,
+        });
+        id
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let entry = TaskEntry(id);
+        let _permit = match slots(priority) {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        if let Some(info) = get_tasks().lock().tasks.iter_mut().find(|t| t.id == id) {
+            info.state = TaskState::Running;
+            info.started_at = Some(storage::now_secs());
+        }
+        PRIORITY.scope(priority, task).await;
+        drop(entry);
+    });
+}
+
+/// Scheduled tasks, running first, then by priority and age
+pub fn task_queue() -> Vec<TaskInfo> {
+    let mut tasks = get_tasks().lock().tasks.clone();
+    tasks.sort_by_key(|t| (t.state != TaskState::Running, t.priority, t.id));
+    tasks
+}
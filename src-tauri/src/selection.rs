@@ -0,0 +1,86 @@
+//! Backend-owned frame range selection.
+//!
+//! Keeping the selection here (rather than only in the UI) lets range
+//! operations behave the same whether they come from keyboard selection,
+//! deep links, or AI suggestions. Each session has its own selection.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::capture_edit;
+
+/// Inclusive range of absolute frame numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl FrameRange {
+    /// Create a range, normalizing so that `start <= end`
+    pub fn new(a: u32, b: u32) -> Self {
+        FrameRange {
+            start: a.min(b),
+            end: a.max(b),
+        }
+    }
+
+    /// Display filter selecting exactly the frames in this range
+    pub fn filter(&self) -> String {
+        if self.start == self.end {
+            format!("frame.number == {}", self.start)
+        } else {
+            format!(
+                "frame.number >= {} && frame.number <= {}",
+                self.start, self.end
+            )
+        }
+    }
+}
+
+/// Each session's selection, by session ID
+static SELECTION: OnceLock<Mutex<HashMap<String, FrameRange>>> = OnceLock::new();
+
+fn get_selection_state() -> &'static Mutex<HashMap<String, FrameRange>> {
+    SELECTION.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Select an inclusive range of frames (order of the endpoints doesn't matter)
+pub fn select_range(session: &str, start_frame: u32, end_frame: u32) -> Result<FrameRange, String> {
+    if start_frame == 0 || end_frame == 0 {
+        return Err("Frame numbers start at 1".to_string());
+    }
+    let range = FrameRange::new(start_frame, end_frame);
+    get_selection_state()
+        .lock()
+        .insert(session.to_string(), range);
+    Ok(range)
+}
+
+/// A session's selection, if any
+pub fn get_selection(session: &str) -> Option<FrameRange> {
+    get_selection_state().lock().get(session).copied()
+}
+
+/// Clear a session's selection
+pub fn clear_selection(session: &str) {
+    get_selection_state().lock().remove(session);
+}
+
+/// A session's selection, or an error if nothing is selected
+pub fn require_selection(session: &str) -> Result<FrameRange, String> {
+    get_selection(session).ok_or_else(|| "No frames selected".to_string())
+}
+
+/// Write a session's selected frames of `source` to a new capture file at
+/// `dest` (pcapng), keeping comments added to them in the app
+pub fn export_selection(session: &str, source: &str, dest: &str) -> Result<FrameRange, String> {
+    let range = require_selection(session)?;
+    if source == dest {
+        return Err("The exported selection can't overwrite the original".to_string());
+    }
+    capture_edit::write_filtered(source, &range.filter(), dest)?;
+    Ok(range)
+}
@@ -0,0 +1,191 @@
+//! Capture sessions: one sharkd instance per open capture, keyed by session ID,
+//! so several captures can be open in tabs without clobbering each other.
+//!
+//! Commands take an optional session ID; without one they act on the active
+//! session, which is also the one the HTTP bridge (and so the AI sidecar) sees.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Session used when the frontend doesn't open one explicitly
+pub const DEFAULT_SESSION: &str = "default";
+
+/// An open session, as listed to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub loaded_file: Option<String>,
+    pub active: bool,
+}
+
+struct Sessions {
+    clients: BTreeMap<String, Arc<SharkdClient>>,
+    active: String,
+    next_id: u64,
+    /// Window label -> session shown in that window
+    windows: BTreeMap<String, String>,
+}
+
+static SESSIONS: OnceLock<Mutex<Sessions>> = OnceLock::new();
+
+fn get_sessions() -> &'static Mutex<Sessions> {
+    SESSIONS.get_or_init(|| {
+        Mutex::new(Sessions {
+            clients: BTreeMap::new(),
+            active: DEFAULT_SESSION.to_string(),
+            next_id: 1,
+            windows: BTreeMap::new(),
+        })
+    })
+}
+
+/// The given session ID, or the active session's
+pub fn resolve(session_id: Option<&str>) -> String {
+    match session_id.filter(|id| !id.is_empty()) {
+        Some(id) => id.to_string(),
+        None => get_sessions().lock().active.clone(),
+    }
+}
+
+/// The sharkd client of a session (the active one when `session_id` is None).
+/// The lock is only held long enough to clone the handle, so requests never
+/// hold it across an await.
+pub fn get(session_id: Option<&str>) -> Result<Arc<SharkdClient>, String> {
+    let id = resolve(session_id);
+    get_sessions()
+        .lock()
+        .clients
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Sharkd not initialized for session {}", id))
+}
+
+/// Whether a session has a running sharkd
+pub fn contains(session_id: &str) -> bool {
+    get_sessions().lock().clients.contains_key(session_id)
+}
+
+/// Store a client for a session unless one was stored meanwhile; returns the
+/// session's client either way
+pub fn insert_if_absent(session_id: &str, client: Arc<SharkdClient>) -> Arc<SharkdClient> {
+    get_sessions()
+        .lock()
+        .clients
+        .entry(session_id.to_string())
+        .or_insert(client)
+        .clone()
+}
+
+/// Store a client for a session, replacing any previous one
+pub fn replace(session_id: &str, client: Arc<SharkdClient>) {
+    get_sessions()
+        .lock()
+        .clients
+        .insert(session_id.to_string(), client);
+}
+
+/// Remove a session's client; its sharkd exits once in-flight requests release it
+pub fn take(session_id: &str) -> Option<Arc<SharkdClient>> {
+    let mut sessions = get_sessions().lock();
+    let client = sessions.clients.remove(session_id);
+    sessions.windows.retain(|_, id| id != session_id);
+    if sessions.active == session_id {
+        sessions.active = sessions
+            .clients
+            .keys()
+            .next()
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_SESSION.to_string());
+    }
+    client
+}
+
+/// IDs of sessions with a running sharkd
+pub fn ids() -> Vec<String> {
+    get_sessions().lock().clients.keys().cloned().collect()
+}
+
+/// The session a client belongs to, if it is still current
+pub fn find(client: &Arc<SharkdClient>) -> Option<String> {
+    get_sessions()
+        .lock()
+        .clients
+        .iter()
+        .find(|(_, c)| Arc::ptr_eq(c, client))
+        .map(|(id, _)| id.clone())
+}
+
+/// Reserve a fresh session ID
+pub fn new_id() -> String {
+    let mut sessions = get_sessions().lock();
+    let id = format!("session-{}", sessions.next_id);
+    sessions.next_id += 1;
+    id
+}
+
+/// Open sessions with their loaded captures
+pub fn list() -> Vec<SessionInfo> {
+    let sessions = get_sessions().lock();
+    sessions
+        .clients
+        .iter()
+        .map(|(id, client)| SessionInfo {
+            id: id.clone(),
+            loaded_file: client.loaded_file(),
+            active: *id == sessions.active,
+        })
+        .collect()
+}
+
+/// Make a session the default target of commands and the HTTP bridge
+pub fn set_active(session_id: &str) -> Result<(), String> {
+    let mut sessions = get_sessions().lock();
+    if !sessions.clients.contains_key(session_id) {
+        return Err(format!("Unknown session: {}", session_id));
+    }
+    sessions.active = session_id.to_string();
+    Ok(())
+}
+
+/// Captures loaded across all sessions
+pub fn loaded_files() -> Vec<String> {
+    get_sessions()
+        .lock()
+        .clients
+        .values()
+        .filter_map(|c| c.loaded_file())
+        .collect()
+}
+
+/// Show a session in a window; its events go to that window from now on
+pub fn bind_window(label: &str, session_id: &str) {
+    get_sessions()
+        .lock()
+        .windows
+        .insert(label.to_string(), session_id.to_string());
+}
+
+/// Forget a closed window
+pub fn unbind_window(label: &str) {
+    get_sessions().lock().windows.remove(label);
+}
+
+/// Session shown in a window, if it is bound to one
+pub fn window_session(label: &str) -> Option<String> {
+    get_sessions().lock().windows.get(label).cloned()
+}
+
+/// Whether a window should receive a session's events: windows bound to the
+/// session, or, for sessions without a window of their own, the unbound
+/// windows (the main window with its tabs)
+pub fn routes_to(session_id: &str, label: &str) -> bool {
+    let sessions = get_sessions().lock();
+    match sessions.windows.get(label) {
+        Some(bound) => bound == session_id,
+        None => !sessions.windows.values().any(|id| id == session_id),
+    }
+}
@@ -0,0 +1,126 @@
+//! Fault injection for sharkd requests, to exercise crash restarts, timeouts
+//! and the like deterministically. Development builds only.
+//!
+//! Turned on per client by `PACKETPILOT_SHARKD_CHAOS` when sharkd is spawned,
+//! e.g. `delay=250,drop=0.05,exit=0.01,seed=7`:
+//!
+//! - `delay`: milliseconds to hold each request before sending it
+//! - `delay_rate`: share of requests delayed (default 1 when `delay` is set)
+//! - `drop`: share of requests whose response never arrives
+//! - `exit`: share of requests on which sharkd exits instead of answering
+//! - `seed`: seed of the fault sequence, so a run can be replayed
+//! - `after`: requests let through untouched first (default 1, so the
+//!   status check on spawn succeeds)
+
+use std::time::Duration;
+
+/// Environment variable holding the chaos settings
+pub const CHAOS_ENV: &str = "PACKETPILOT_SHARKD_CHAOS";
+
+/// What happens to one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Sent after a delay
+    Delay(Duration),
+    /// Sent and answered, but the answer is never delivered
+    Drop,
+    /// sharkd exits instead of answering
+    Exit,
+}
+
+/// Fault injection settings and state for one client
+#[derive(Debug, Clone)]
+pub struct ChaosSharkd {
+    delay: Duration,
+    delay_rate: f64,
+    drop_rate: f64,
+    exit_rate: f64,
+    after: u64,
+    requests: u64,
+    rng: u64,
+}
+
+impl ChaosSharkd {
+    /// Parse settings in the `CHAOS_ENV` format
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut chaos = ChaosSharkd {
+            delay: Duration::ZERO,
+            delay_rate: 1.0,
+            drop_rate: 0.0,
+            exit_rate: 0.0,
+            after: 1,
+            requests: 0,
+            rng: 1,
+        };
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", setting))?;
+            let number = |value: &str| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|n| n.is_finite() && *n >= 0.0)
+                    .ok_or_else(|| format!("Invalid value for {}: {}", key, value))
+            };
+            match key.trim() {
+                "delay" => chaos.delay = Duration::from_millis(number(value)? as u64),
+                "delay_rate" => chaos.delay_rate = number(value)?.min(1.0),
+                "drop" => chaos.drop_rate = number(value)?.min(1.0),
+                "exit" => chaos.exit_rate = number(value)?.min(1.0),
+                "after" => chaos.after = number(value)? as u64,
+                // xorshift can't start from zero
+                "seed" => chaos.rng = (number(value)? as u64).max(1),
+                other => return Err(format!("Unknown chaos setting: {}", other)),
+            }
+        }
+        Ok(chaos)
+    }
+
+    /// Settings from `CHAOS_ENV`, if set and this is a development build
+    pub fn from_env() -> Option<Self> {
+        if !cfg!(debug_assertions) {
+            return None;
+        }
+        let spec = std::env::var(CHAOS_ENV).ok()?;
+        match Self::parse(&spec) {
+            Ok(chaos) => {
+                eprintln!("sharkd chaos mode enabled: {}", spec);
+                Some(chaos)
+            }
+            Err(e) => {
+                eprintln!("Warning: Ignoring {}: {}", CHAOS_ENV, e);
+                None
+            }
+        }
+    }
+
+    /// Uniform sample in [0, 1) from a xorshift64* sequence
+    fn sample(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let value = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// The fault to inject into the next request, if any
+    pub fn next_fault(&mut self) -> Option<Fault> {
+        self.requests += 1;
+        if self.requests <= self.after {
+            return None;
+        }
+        // Draw all three every time so each rate's sequence is independent
+        let (exit, drop, delay) = (self.sample(), self.sample(), self.sample());
+        if exit < self.exit_rate {
+            Some(Fault::Exit)
+        } else if drop < self.drop_rate {
+            Some(Fault::Drop)
+        } else if !self.delay.is_zero() && delay < self.delay_rate {
+            Some(Fault::Delay(self.delay))
+        } else {
+            None
+        }
+    }
+}
@@ -1,11 +1,25 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::BTreeSet;
-use std::io::{BufRead, BufReader, Write};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
-use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::process::Stdio;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::hex_dump::HexLine;
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::sharkd_chaos::{ChaosSharkd, Fault};
+use crate::sharkd_trace;
+
+pub mod parse;
+
+use parse::tap_field;
 
 /// Frame data returned from sharkd
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +42,54 @@ pub struct Status {
     pub filename: Option<String>,
 }
 
+/// A frame's protocol tree and bytes, from sharkd `frame`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameDetails {
+    pub tree: Vec<TreeNode>,
+    /// The frame's bytes
+    pub bytes: Vec<u8>,
+    /// `bytes` as offset/hex/ASCII lines
+    pub hex: Vec<HexLine>,
+    /// Further byte sources tree nodes may point into (reassembled,
+    /// decompressed or decrypted data); a node's `data_source` 1 is the first
+    pub data_sources: Vec<DataSource>,
+    /// Streams the frame can be followed in
+    pub follow: Vec<FollowTarget>,
+}
+
+/// A protocol tree item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    pub label: String,
+    /// Display filter matching the item's value, e.g. `ip.src == 10.0.0.1`
+    pub filter: Option<String>,
+    /// `proto`, `url`, `framenum`, ... when not a plain field
+    pub kind: Option<String>,
+    /// Expert info severity
+    pub severity: Option<String>,
+    /// Position of the item's bytes within its data source
+    pub offset: Option<u32>,
+    pub length: Option<u32>,
+    /// 0 for the frame's own bytes, otherwise `data_sources[n - 1]`
+    pub data_source: u32,
+    pub children: Vec<TreeNode>,
+}
+
+/// A named byte source besides the frame itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataSource {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    pub hex: Vec<HexLine>,
+}
+
+/// A stream a frame belongs to, e.g. `TCP` / `tcp.stream eq 3`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowTarget {
+    pub protocol: String,
+    pub filter: String,
+}
+
 /// Frames response from sharkd
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +132,106 @@ pub struct StreamData {
     /// Payload segments
     #[serde(default)]
     pub payloads: Vec<StreamPayload>,
+    /// For TLS streams, whether the application data could be decrypted
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsDecryption>,
+    /// For WebSocket streams, the frames in capture order
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<WebSocketMessage>>,
+}
+
+/// Which part of a followed stream to save
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamDirection {
+    /// Raw bytes of both directions, in capture order
+    Both,
+    /// Raw bytes sent by the client
+    ClientToServer,
+    /// Raw bytes sent by the server
+    ServerToClient,
+    /// Both directions as text, each segment under a `[direction]` heading
+    Ascii,
+}
+
+impl StreamData {
+    /// The stream's payload for `direction`
+    pub fn payload(&self, direction: StreamDirection) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        for segment in &self.payloads {
+            let wanted = match direction {
+                StreamDirection::ClientToServer => segment.s == 0,
+                StreamDirection::ServerToClient => segment.s != 0,
+                StreamDirection::Both | StreamDirection::Ascii => true,
+            };
+            if !wanted {
+                continue;
+            }
+            let data = BASE64
+                .decode(&segment.d)
+                .map_err(|e| format!("Invalid stream payload: {}", e))?;
+            if direction == StreamDirection::Ascii {
+                if !out.is_empty() {
+                    out.extend_from_slice(b"\n\n");
+                }
+                let heading = if segment.s == 0 {
+                    "client_to_server"
+                } else {
+                    "server_to_client"
+                };
+                out.extend_from_slice(format!("[{}]\n", heading).as_bytes());
+                out.extend_from_slice(String::from_utf8_lossy(&data).as_bytes());
+            } else {
+                out.extend_from_slice(&data);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// One WebSocket frame of a followed stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketMessage {
+    /// Frame the WebSocket frame was carried in
+    pub frame: u32,
+    /// Seconds since the start of the capture
+    pub time: f64,
+    /// `client_to_server` or `server_to_client`
+    pub direction: String,
+    pub opcode: u8,
+    /// `continuation`, `text`, `binary`, `close`, `ping`, `pong`, or `reserved`
+    pub kind: String,
+    /// Last frame of a message
+    pub fin: bool,
+    /// Payload length
+    pub size: u64,
+    /// Base64 unmasked payload, when it could be matched to the follow data
+    pub data: Option<String>,
+}
+
+fn websocket_opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        0 => "continuation",
+        1 => "text",
+        2 => "binary",
+        8 => "close",
+        9 => "ping",
+        10 => "pong",
+        _ => "reserved",
+    }
+}
+
+/// Outcome of following a TLS stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsDecryption {
+    /// The payloads are decrypted application data
+    Decrypted,
+    /// The stream carries encrypted application data but no key material
+    /// decrypts it (add a keylog or the server's RSA key)
+    NoKeys,
+    /// The stream has no application data (e.g. only a handshake)
+    NoApplicationData,
 }
 
 /// Protocol hierarchy node from tap phs
@@ -87,6 +249,99 @@ pub struct ProtocolNode {
     /// Child protocols
     #[serde(rename = "protos", default)]
     pub children: Vec<ProtocolNode>,
+    /// Share of all frames that carry this protocol, in percent
+    #[serde(skip_deserializing)]
+    pub frame_percent: f64,
+    /// Share of all bytes in frames that carry this protocol, in percent
+    #[serde(skip_deserializing)]
+    pub byte_percent: f64,
+    /// Display filter selecting this protocol's frames, when it has one
+    #[serde(skip_deserializing)]
+    pub filter: Option<String>,
+}
+
+/// Protocol hierarchy names whose display filter differs from the name
+const PROTOCOL_FILTERS: &[(&str, &str)] = &[("ethertype", "eth.type"), ("ssl", "tls")];
+
+/// The display filter selecting a protocol hierarchy entry. Entries that
+/// aren't a filter name (free-text labels from some dissectors) have none.
+fn protocol_filter(protocol: &str) -> Option<String> {
+    if let Some((_, filter)) = PROTOCOL_FILTERS.iter().find(|(name, _)| *name == protocol) {
+        return Some(filter.to_string());
+    }
+    let is_field = !protocol.is_empty()
+        && protocol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    is_field.then(|| protocol.to_string())
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Fill in percentages of the capture totals and filters throughout a
+/// protocol hierarchy
+fn annotate_hierarchy(nodes: &mut [ProtocolNode]) {
+    // Every frame is counted once at the top level ("frame")
+    let frames: u64 = nodes.iter().map(|n| n.frames).sum();
+    let bytes: u64 = nodes.iter().map(|n| n.bytes).sum();
+    annotate_nodes(nodes, frames, bytes);
+}
+
+fn annotate_nodes(nodes: &mut [ProtocolNode], frames: u64, bytes: u64) {
+    for node in nodes {
+        node.frame_percent = percent(node.frames, frames);
+        node.byte_percent = percent(node.bytes, bytes);
+        node.filter = protocol_filter(&node.protocol);
+        annotate_nodes(&mut node.children, frames, bytes);
+    }
+}
+
+/// Address layer conversations and endpoints are counted at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressKind {
+    Ethernet,
+    Ipv4,
+    Ipv6,
+    Tcp,
+    Udp,
+}
+
+impl AddressKind {
+    /// Name of the layer in sharkd's `conv:` and `endpt:` taps
+    pub fn tap_name(self) -> &'static str {
+        match self {
+            AddressKind::Ethernet => "Ethernet",
+            AddressKind::Ipv4 => "IPv4",
+            AddressKind::Ipv6 => "IPv6",
+            AddressKind::Tcp => "TCP",
+            AddressKind::Udp => "UDP",
+        }
+    }
+
+    /// Display filter selecting an endpoint's traffic at this layer
+    pub fn endpoint_filter(self, host: &str, port: Option<&str>) -> String {
+        let address = match self {
+            AddressKind::Ethernet => "eth.addr",
+            _ if host.contains(':') => "ipv6.addr",
+            _ => "ip.addr",
+        };
+        match (self, port) {
+            (AddressKind::Tcp, Some(port)) => {
+                format!("{}=={} && tcp.port=={}", address, host, port)
+            }
+            (AddressKind::Udp, Some(port)) => {
+                format!("{}=={} && udp.port=={}", address, host, port)
+            }
+            _ => format!("{}=={}", address, host),
+        }
+    }
 }
 
 /// Conversation from tap conv
@@ -148,9 +403,21 @@ pub struct Endpoint {
     /// Transmitted bytes
     #[serde(default)]
     pub txb: u64,
+    /// Frames sent and received (filled in by `endpoints`)
+    #[serde(skip_deserializing)]
+    pub frames: u64,
+    /// Bytes sent and received (filled in by `endpoints`)
+    #[serde(skip_deserializing)]
+    pub bytes: u64,
     /// Filter to select this endpoint
     #[serde(default)]
     pub filter: Option<String>,
+    /// GeoIP country (only when a MaxMind database is configured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geoip_country: Option<String>,
+    /// GeoIP autonomous system organization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geoip_as_org: Option<String>,
 }
 
 /// Complete capture statistics
@@ -162,29 +429,498 @@ pub struct CaptureStats {
     pub endpoints: Vec<Endpoint>,
 }
 
-/// Generic JSON-RPC response
-#[derive(Debug, Deserialize)]
-struct JsonRpcResponse {
-    #[allow(dead_code)]
-    jsonrpc: String,
-    #[allow(dead_code)]
-    id: u64,
-    result: Option<Value>,
-    error: Option<JsonRpcError>,
+/// Traffic carried on a single VLAN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VlanStats {
+    /// VLAN ID (802.1Q tag)
+    pub vlan_id: String,
+    /// Filter to select this VLAN
+    pub filter: String,
+    pub conversations: Vec<Conversation>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// Traffic seen through one kind of tunnel, unwrapped one level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelStats {
+    /// Tunnel kind (GRE, VXLAN, IP-in-IP)
+    pub kind: String,
+    /// Filter to select this tunnel traffic
+    pub filter: String,
+    /// Outer conversations between the tunnel endpoints
+    pub tunnel_endpoints: Vec<Conversation>,
+    /// Conversations carried inside the tunnel
+    pub inner_conversations: Vec<Conversation>,
+}
+
+/// One arrow of a flow (ladder) diagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowItem {
+    pub frame: u32,
+    /// Seconds since the start of the capture
+    pub time: f64,
+    pub source: String,
+    pub destination: String,
+    pub source_port: Option<u16>,
+    pub destination_port: Option<u16>,
+    pub protocol: String,
+    /// The frame's Info column
+    pub summary: String,
+    /// `tcp.stream` or `udp.stream` number
+    pub stream: Option<u32>,
+}
+
+/// Flow (ladder) diagram: hosts as columns and frames as arrows between them
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FlowGraph {
+    /// Hosts in order of first appearance
+    pub nodes: Vec<String>,
+    pub items: Vec<FlowItem>,
+    /// Whether more frames matched than were read
+    pub truncated: bool,
+}
+
+/// Per-VLAN and per-tunnel breakdown of the capture
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OverlayStats {
+    pub vlans: Vec<VlanStats>,
+    pub tunnels: Vec<TunnelStats>,
+}
+
+/// Host-level (IPv4 and IPv6) conversations and endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HostTraffic {
+    pub conversations: Vec<Conversation>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// Frames and bytes per time bucket for one filter, from sharkd `intervals`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct IoGraphSeries {
+    /// Display filter, empty for all frames
+    pub filter: String,
+    /// Frame count per bucket, from the start of the capture
+    pub frames: Vec<u64>,
+    /// Byte count per bucket
+    pub bytes: Vec<u64>,
+}
+
+/// I/O graph: one series per filter, over buckets of `interval_ms`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct IoGraph {
+    pub interval_ms: u32,
+    pub series: Vec<IoGraphSeries>,
+}
+
+/// Node of a sharkd stats tree, as returned by the `stat:` taps
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StatsNode {
+    pub name: String,
+    #[serde(default)]
+    pub count: u64,
+    #[serde(rename = "sub", default)]
+    pub children: Vec<StatsNode>,
+}
+
+/// A name with how often it was seen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedCount {
+    pub name: String,
+    pub count: u64,
+}
+
+/// HTTP requests made to one host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpHostStats {
+    pub host: String,
+    pub requests: u64,
+    /// Requests per URI on this host
+    pub uris: Vec<NamedCount>,
+}
+
+/// HTTP request/response statistics from the `stat:http` and
+/// `stat:http_req` taps. Lists are sorted by count, highest first.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HttpStats {
+    pub total_packets: u64,
+    pub requests: u64,
+    pub responses: u64,
+    /// Requests per method (GET, POST, ...)
+    pub methods: Vec<NamedCount>,
+    /// Responses per status class ("2xx: Success", ...)
+    pub status_classes: Vec<NamedCount>,
+    /// Responses per status code ("200 OK", ...)
+    pub status_codes: Vec<NamedCount>,
+    pub hosts: Vec<HttpHostStats>,
+}
+
+/// Per-stream RTP analysis from the `rtp-analyse` tap
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RtpAnalysis {
+    /// Largest gap between consecutive packets, in ms
+    #[serde(default)]
+    pub max_delta: f64,
+    /// Frame number where the largest gap ends
+    #[serde(rename = "max_delta_nr", default)]
+    pub max_delta_frame: u64,
+    #[serde(default)]
+    pub max_jitter: f64,
+    #[serde(default)]
+    pub mean_jitter: f64,
+    /// Largest clock skew, in ms
+    #[serde(default)]
+    pub max_skew: f64,
+    /// Sequence number errors (lost or out of order packets)
+    #[serde(rename = "seq_err", default)]
+    pub sequence_errors: u64,
+    /// Stream duration, in seconds
+    #[serde(default)]
+    pub duration: f64,
 }
 
-#[derive(Debug, Deserialize)]
-struct JsonRpcError {
-    code: i64,
-    message: String,
+/// An RTP stream from the `rtp-streams` tap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RtpStream {
+    pub ssrc: u32,
+    /// Payload type name (g711U, opus, ...)
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub saddr: String,
+    #[serde(default)]
+    pub sport: u16,
+    #[serde(default)]
+    pub daddr: String,
+    #[serde(default)]
+    pub dport: u16,
+    #[serde(rename = "pkts", default)]
+    pub packets: u64,
+    /// Largest gap between consecutive packets, in ms
+    #[serde(default)]
+    pub max_delta: f64,
+    #[serde(default)]
+    pub max_jitter: f64,
+    #[serde(default)]
+    pub mean_jitter: f64,
+    /// Packets expected from the sequence numbers
+    #[serde(rename = "expectednr", default)]
+    pub expected: u64,
+    /// Packets received
+    #[serde(rename = "totalnr", default)]
+    pub received: u64,
+    /// Packets missing (negative when duplicated)
+    #[serde(skip_deserializing)]
+    pub lost: i64,
+    #[serde(skip_deserializing)]
+    pub loss_percent: f64,
+    /// sharkd flagged a problem (sequence errors, wrong timestamps, ...)
+    #[serde(default)]
+    pub problem: bool,
+    #[serde(default)]
+    pub ipver: u8,
+    /// Filter to select this stream
+    #[serde(skip_deserializing)]
+    pub filter: String,
+    #[serde(skip_deserializing)]
+    pub analysis: Option<RtpAnalysis>,
+}
+
+impl RtpStream {
+    /// sharkd's identifier for the stream in `rtp-analyse` tap names
+    fn tap_id(&self) -> String {
+        format!(
+            "{}_{}_{}_{}_0x{:08x}",
+            self.saddr, self.sport, self.daddr, self.dport, self.ssrc
+        )
+    }
+
+    fn stream_filter(&self) -> String {
+        let ip = if self.ipver == 6 { "ipv6" } else { "ip" };
+        format!(
+            "{ip}.src=={} && udp.srcport=={} && {ip}.dst=={} && udp.dstport=={} && rtp.ssrc==0x{:08x}",
+            self.saddr,
+            self.sport,
+            self.daddr,
+            self.dport,
+            self.ssrc,
+            ip = ip
+        )
+    }
+}
+
+/// A call from the `voip-calls` tap (SIP, H.323, MGCP, ... correlated with
+/// their RTP)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoipCall {
+    #[serde(rename = "call")]
+    pub id: u64,
+    /// Seconds since the start of the capture
+    #[serde(rename = "start_time", default)]
+    pub start: f64,
+    #[serde(rename = "stop_time", default)]
+    pub stop: f64,
+    /// Address of the party that set the call up
+    #[serde(default)]
+    pub initial_speaker: String,
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+    /// Signalling protocol
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub packets: u64,
+    /// CALL SETUP, RINGING, IN CALL, CANCELLED, COMPLETED, REJECTED, ...
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub comment: String,
+    /// Filter for the call's signalling and media: frames of its protocols
+    /// to or from the initial speaker within the call's time span
+    #[serde(skip_deserializing)]
+    pub filter: String,
+}
+
+impl VoipCall {
+    fn call_filter(&self) -> String {
+        let protocols = match self.protocol.to_uppercase().as_str() {
+            "SIP" => "sip || sdp || rtp || rtcp".to_string(),
+            "H.323" | "H323" => "h225 || h245 || rtp || rtcp".to_string(),
+            "" => "rtp || rtcp".to_string(),
+            other => format!("{} || rtp || rtcp", other.to_lowercase().replace('.', "")),
+        };
+        let mut filter = format!(
+            "({}) && frame.time_relative >= {:.6} && frame.time_relative <= {:.6}",
+            protocols, self.start, self.stop
+        );
+        if !self.initial_speaker.is_empty() {
+            let addr = if self.initial_speaker.contains(':') {
+                "ipv6.addr"
+            } else {
+                "ip.addr"
+            };
+            filter.push_str(&format!(" && {}=={}", addr, self.initial_speaker));
+        }
+        filter
+    }
+}
+
+/// Protocols files can be exported from, as named by sharkd's `eo:` taps
+pub const EXPORT_OBJECT_PROTOCOLS: &[&str] = &["http", "smb", "imf", "tftp", "dicom", "ftp-data"];
+
+/// Protocols with service response time statistics, as named by sharkd's
+/// `srt:` taps, plus `http` (computed from `http.time`). DCE-RPC also needs
+/// the interface: `dcerpc,<uuid>,<major>.<minor>`.
+pub const SRT_PROTOCOLS: &[&str] = &[
+    "afp", "camel", "dcerpc", "fc", "gtp", "http", "ldap", "ncp", "rpc", "scsi", "smb", "smb2",
+];
+
+/// Response times of one procedure (command, operation, method), in seconds
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SrtProcedure {
+    #[serde(rename = "n")]
+    pub procedure: String,
+    #[serde(rename = "num", default)]
+    pub calls: u64,
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default)]
+    pub max: f64,
+    #[serde(rename = "tot", default)]
+    pub total: f64,
+    #[serde(skip_deserializing)]
+    pub avg: f64,
+}
+
+/// One table of an `srt:` tap (some protocols have one per version or
+/// program)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SrtTable {
+    #[serde(rename = "n")]
+    pub name: String,
+    #[serde(rename = "r", default)]
+    pub procedures: Vec<SrtProcedure>,
+}
+
+/// A file carried in the capture, from an `eo:` tap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportObject {
+    /// Protocol the object was carried over (`http`, `smb`, ...)
+    #[serde(skip_deserializing)]
+    pub protocol: String,
+    /// Frame the object was completed in
+    #[serde(rename = "pkt")]
+    pub frame: u32,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(rename = "type", default)]
+    pub content_type: String,
+    #[serde(default)]
+    pub filename: String,
+    #[serde(rename = "len", default)]
+    pub size: u64,
+    /// Token passed to `download` to fetch the object's bytes
+    #[serde(rename = "_download")]
+    pub token: String,
+}
+
+/// An object's contents, from sharkd's `download` method
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub filename: String,
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Value type of a Wireshark preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrefKind {
+    Bool,
+    Uint,
+    Enum,
+    /// Strings, file and directory names
+    String,
+    /// Port or number ranges (`80,8080-8090`)
+    Range,
+    /// Tables, edited through their own UAT files
+    Uat,
+}
+
+/// A choice of an enum preference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefOption {
+    pub value: i64,
+    pub description: String,
+    pub selected: bool,
+}
+
+/// A Wireshark preference and its current value, from `dumpconf`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preference {
+    /// Full name (`tcp.relative_sequence_numbers`)
+    pub name: String,
+    pub kind: PrefKind,
+    /// Current value as `set_pref` takes it: `TRUE`/`FALSE` for booleans,
+    /// the selected option's description for enums
+    pub value: String,
+    /// Display base of unsigned integers other than decimal (16 for hex)
+    pub base: Option<u32>,
+    /// Choices of an enum preference
+    pub options: Vec<PrefOption>,
+}
+
+/// Tunnel encapsulations unwrapped by `overlay_stats`: (kind, display filter)
+const TUNNEL_KINDS: &[(&str, &str)] = &[
+    ("GRE", "gre"),
+    ("VXLAN", "vxlan"),
+    ("IP-in-IP", "ip.proto == 4"),
+];
+
+/// Taps sharkd accepts in one `tap` request
+const MAX_TAPS_PER_REQUEST: usize = 16;
+
+/// Upper bound on frames scanned when extracting field values for overlay stats
+const OVERLAY_SCAN_LIMIT: u32 = 50_000;
+
+/// Page size used when scanning frames for field values
+const COLUMN_SCAN_PAGE: u32 = 5_000;
+
+/// Upper bound on HTTP requests and responses scanned for response times
+const HTTP_SRT_SCAN_LIMIT: u32 = 1_000_000;
+
+/// Fields read for flow graph items, next to the standard columns
+const FLOW_FIELDS: &[&str] = &[
+    "frame.time_relative",
+    "tcp.srcport",
+    "tcp.dstport",
+    "udp.srcport",
+    "udp.dstport",
+    "tcp.stream",
+    "udp.stream",
+];
+
+/// Children of a stats tree node, as counts sorted highest first
+fn named_counts(nodes: &[StatsNode]) -> Vec<NamedCount> {
+    let mut counts: Vec<NamedCount> = nodes
+        .iter()
+        .filter(|n| n.count > 0)
+        .map(|n| NamedCount {
+            name: n.name.clone(),
+            count: n.count,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts
 }
 
 pub struct SharkdClient {
-    #[allow(dead_code)]
-    process: Child,
-    stdin: Mutex<ChildStdin>,
-    stdout: Mutex<BufReader<ChildStdout>>,
+    // The process is owned by `watch_process`; dropping this (or sending on
+    // it) kills it, so sharkd exits when the client is replaced
+    kill: Mutex<Option<oneshot::Sender<()>>>,
+    /// Set once sharkd has died, with the reason
+    exit: Arc<watch::Sender<Option<String>>>,
+    io: tokio::sync::Mutex<SharkdIo>,
     request_id: AtomicU64,
+    loaded_file: Mutex<Option<String>>,
+    /// Bumped by `cancel_operations`; operations started under an older
+    /// generation stop at their next check
+    cancel_generation: AtomicU64,
+    /// Method of the request sharkd is working on and when it was sent
+    in_flight: Mutex<Option<(String, Instant)>>,
+    /// Fault injection, in development builds when enabled by environment
+    chaos: Option<Mutex<ChaosSharkd>>,
+}
+
+/// An operation that can be cancelled between its requests
+pub struct Operation<'a> {
+    client: &'a SharkdClient,
+    generation: u64,
+}
+
+impl Operation<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        self.client.cancel_generation.load(Ordering::SeqCst) != self.generation
+    }
+
+    /// Err once the operation was cancelled, to stop it with `?`
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err("Operation cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Marks a request as the one sharkd is working on until dropped, so a
+/// request whose caller gives up doesn't look like it is still running
+struct InFlight<'a>(&'a Mutex<Option<(String, Instant)>>);
+
+impl<'a> InFlight<'a> {
+    fn start(slot: &'a Mutex<Option<(String, Instant)>>, method: &str, started: Instant) -> Self {
+        *slot.lock() = Some((method.to_string(), started));
+        InFlight(slot)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        *self.0.lock() = None;
+    }
+}
+
+/// sharkd's stdio pipes, locked together for a whole request/response exchange
+struct SharkdIo {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The rest of a request whose caller gave up partway through writing
+    /// it, sent before the next request so sharkd never sees a torn line
+    unsent: Vec<u8>,
+    /// A response line read partway by a caller that gave up, finished by
+    /// the next one
+    partial: Vec<u8>,
 }
 
 /// Installation issue returned to the frontend.
@@ -206,7 +942,7 @@ pub struct InstallHealthStatus {
 }
 
 /// Get the target triple for the current platform
-fn get_target_triple() -> &'static str {
+pub(crate) fn get_target_triple() -> &'static str {
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
     return "x86_64-unknown-linux-gnu";
 
@@ -232,7 +968,7 @@ fn get_target_triple() -> &'static str {
     return "unknown";
 }
 
-fn is_production_mode(exe_path: &Path) -> bool {
+pub(crate) fn is_production_mode(exe_path: &Path) -> bool {
     let path_str = exe_path.to_string_lossy();
     !path_str.contains("target/debug") && !path_str.contains("target/release")
 }
@@ -261,7 +997,7 @@ fn bundled_sharkd_candidates(exe_dir: &Path) -> Vec<PathBuf> {
 #[cfg(target_os = "windows")]
 fn add_windows_path_candidates(paths: &mut Vec<PathBuf>, debug_info: &mut Vec<String>) {
     // Prefer PATH detection in both dev and production fallback mode.
-    match Command::new("where").arg("sharkd").output() {
+    match std::process::Command::new("where").arg("sharkd").output() {
         Ok(output) if output.status.success() => {
             let found = String::from_utf8_lossy(&output.stdout)
                 .lines()
@@ -298,7 +1034,7 @@ fn system_sharkd_candidates(debug_info: &mut Vec<String>) -> Vec<PathBuf> {
 
     #[cfg(not(target_os = "windows"))]
     {
-        match Command::new("which").arg("sharkd").output() {
+        match std::process::Command::new("which").arg("sharkd").output() {
             Ok(output) if output.status.success() => {
                 let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
                 if !path.is_empty() {
@@ -358,7 +1094,9 @@ fn validate_windows_runtime(exe_dir: &Path, sharkd_path: &Path) -> Vec<InstallIs
     if exe_dir.join("glib-2.0-0.dll").exists() && !exe_dir.join("libglib-2.0-0.dll").exists() {
         issues.push(InstallIssue {
             code: "invalid_bundle".to_string(),
-            message: "Found glib-2.0-0.dll but missing libglib-2.0-0.dll expected by bundled sharkd.".to_string(),
+            message:
+                "Found glib-2.0-0.dll but missing libglib-2.0-0.dll expected by bundled sharkd."
+                    .to_string(),
             path: Some(exe_dir.display().to_string()),
         });
     }
@@ -460,7 +1198,9 @@ pub fn get_install_health() -> InstallHealthStatus {
     let mut checked_paths = Vec::new();
 
     let exe_path = std::env::current_exe().ok();
-    let exe_dir = exe_path.as_ref().and_then(|p| p.parent().map(PathBuf::from));
+    let exe_dir = exe_path
+        .as_ref()
+        .and_then(|p| p.parent().map(PathBuf::from));
     let is_production = exe_path
         .as_ref()
         .map(|p| is_production_mode(p))
@@ -511,7 +1251,7 @@ pub fn get_install_health() -> InstallHealthStatus {
 
     #[cfg(target_os = "windows")]
     if issues.is_empty() {
-        match Command::new(&sharkd_path)
+        match std::process::Command::new(&sharkd_path)
             .arg("-v")
             .stdout(Stdio::null())
             .stderr(Stdio::null())
@@ -552,18 +1292,86 @@ pub fn get_install_health() -> InstallHealthStatus {
     }
 }
 
+/// Record why sharkd died; only the first reason is kept
+fn mark_exited(exit: &watch::Sender<Option<String>>, reason: String) {
+    exit.send_if_modified(|current| {
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(reason);
+        true
+    });
+}
+
+/// Own the sharkd process: kill it once the client is dropped, and report
+/// when it exits on its own
+async fn watch_process(
+    mut process: Child,
+    kill: oneshot::Receiver<()>,
+    exit: Arc<watch::Sender<Option<String>>>,
+) {
+    tokio::select! {
+        status = process.wait() => {
+            let reason = match status {
+                Ok(status) => format!("sharkd exited unexpectedly ({})", status),
+                Err(e) => format!("Failed to wait for sharkd: {}", e),
+            };
+            mark_exited(&exit, reason);
+        }
+        // Resolves when the client (and with it the sender) is dropped
+        _ = kill => {
+            let _ = process.kill().await;
+        }
+    }
+}
+
+/// A supervised client whose sharkd died
+pub struct Crash {
+    pub client: Weak<SharkdClient>,
+    pub reason: String,
+}
+
+/// Watch a client and report on `crashes` if its sharkd dies. Only a weak
+/// reference is held, so a client that is dropped deliberately (e.g. replaced
+/// on restart) is killed normally and isn't reported.
+pub fn supervise(client: &Arc<SharkdClient>, crashes: mpsc::UnboundedSender<Crash>) {
+    let mut exit = client.exit.subscribe();
+    let weak = Arc::downgrade(client);
+    tokio::spawn(async move {
+        let reason = match exit.wait_for(|reason| reason.is_some()).await {
+            Ok(reason) => reason.clone().unwrap_or_default(),
+            // The client was dropped before sharkd died
+            Err(_) => return,
+        };
+        let _ = crashes.send(Crash {
+            client: weak,
+            reason,
+        });
+    });
+}
+
 impl SharkdClient {
     /// Spawn a new sharkd process in stdio mode
-    pub fn new() -> Result<Self, String> {
-        let sharkd_path = find_sharkd()?;
+    pub async fn new() -> Result<Self, String> {
+        Self::spawn(&find_sharkd()?).await
+    }
 
+    /// Spawn the sharkd at `sharkd_path` in stdio mode
+    pub async fn spawn(sharkd_path: &Path) -> Result<Self, String> {
         println!("Spawning sharkd from: {:?}", sharkd_path);
 
-        let mut process = Command::new(&sharkd_path)
+        let mut command = Command::new(sharkd_path);
+        // Use PacketPilot's managed profile instead of the personal Wireshark one
+        if let Ok(profile_dir) = crate::profile::profile_dir() {
+            command.env("WIRESHARK_CONFIG_DIR", profile_dir);
+        }
+
+        let mut process = command
             .arg("-") // stdio mode
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped()) // Capture stderr for debugging
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| {
                 format!(
@@ -586,17 +1394,30 @@ impl SharkdClient {
             .take()
             .ok_or_else(|| "Failed to get sharkd stdout".to_string())?;
 
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let exit = Arc::new(watch::channel(None).0);
+        tokio::spawn(watch_process(process, kill_rx, exit.clone()));
+
         let client = SharkdClient {
-            process,
-            stdin: Mutex::new(stdin),
-            stdout: Mutex::new(BufReader::new(stdout)),
+            kill: Mutex::new(Some(kill_tx)),
+            exit,
+            io: tokio::sync::Mutex::new(SharkdIo {
+                stdin,
+                stdout: BufReader::new(stdout),
+                unsent: Vec::new(),
+                partial: Vec::new(),
+            }),
             request_id: AtomicU64::new(1),
+            loaded_file: Mutex::new(None),
+            cancel_generation: AtomicU64::new(0),
+            in_flight: Mutex::new(None),
+            chaos: ChaosSharkd::from_env().map(Mutex::new),
         };
 
         // Note: "Hello in child." goes to stderr, not stdout
         // Verify sharkd is working by sending a status request
         println!("Sending status request to sharkd...");
-        let status = client.send_request("status", None)?;
+        let status = client.send_request("status", None).await?;
         if status.get("frames").is_some() || status.get("columns").is_some() {
             println!("Sharkd initialized successfully");
             return Ok(client);
@@ -606,7 +1427,7 @@ impl SharkdClient {
     }
 
     /// Send a JSON-RPC request and return the result
-    fn send_request(&self, method: &str, params: Option<Value>) -> Result<Value, String> {
+    async fn send_request(&self, method: &str, params: Option<Value>) -> Result<Value, String> {
         let id = self.request_id.fetch_add(1, Ordering::SeqCst);
 
         let request = if let Some(p) = params {
@@ -627,43 +1448,144 @@ impl SharkdClient {
         // CRITICAL: sharkd requires newline-delimited JSON
         let request_str = format!("{request}\n");
 
-        {
-            let mut stdin = self.stdin.lock();
-            stdin
-                .write_all(request_str.as_bytes())
-                .map_err(|e| format!("Failed to write to sharkd: {}", e))?;
-            stdin
-                .flush()
-                .map_err(|e| format!("Failed to flush sharkd stdin: {}", e))?;
+        // Background work yields to interactive requests between its own
+        let turn = crate::scheduler::request_turn().await;
+
+        // Hold the pipes for the whole exchange so concurrent requests
+        // can't read each other's responses
+        let mut io = self.io.lock().await;
+        let started = Instant::now();
+        let in_flight = InFlight::start(&self.in_flight, method, started);
+        let fault = self.chaos.as_ref().and_then(|c| c.lock().next_fault());
+        let line = self.exchange(&mut io, id, &request_str, fault).await;
+        drop(in_flight);
+        drop(io);
+        drop(turn);
+        if sharkd_trace::enabled() {
+            sharkd_trace::record(method, &request_str, &line, started.elapsed());
+        }
+        if fault == Some(Fault::Drop) {
+            // The response was consumed and the pipes and turn released, so
+            // only this caller waits, until it gives up on the request
+            std::future::pending::<()>().await;
         }
+        let line = line?;
 
-        let response: JsonRpcResponse = serde_json::from_value(self.read_response()?)
-            .map_err(|e| format!("Failed to parse sharkd response: {}", e))?;
+        Ok(parse::response(&line)?)
+    }
 
-        if let Some(error) = response.error {
-            return Err(format!("Sharkd error {}: {}", error.code, error.message));
+    /// Write one request line and read its response line. Responses to
+    /// earlier requests whose callers gave up before reading them (e.g. a
+    /// bridge request that timed out) are still in the pipe; they are
+    /// skipped so they can't be taken for this request's. Partly written
+    /// requests and partly read responses are kept in `io`, so a caller can
+    /// give up at any await without putting the pipes out of step.
+    async fn exchange(
+        &self,
+        io: &mut SharkdIo,
+        id: u64,
+        request: &str,
+        fault: Option<Fault>,
+    ) -> Result<String, String> {
+        let request = match fault {
+            Some(Fault::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+                request
+            }
+            // sharkd quits on "bye", which looks just like a crash from here
+            Some(Fault::Exit) => "{\"jsonrpc\":\"2.0\",\"id\":0,\"method\":\"bye\"}\n",
+            _ => request,
+        };
+
+        // Unlike `write_all`, each `write` either completes or writes nothing
+        io.unsent.extend_from_slice(request.as_bytes());
+        while !io.unsent.is_empty() {
+            let written = io
+                .stdin
+                .write(&io.unsent)
+                .await
+                .map_err(|e| self.lost(format!("Failed to write to sharkd: {}", e)))?;
+            if written == 0 {
+                return Err(self.lost("sharkd closed its input".to_string()));
+            }
+            io.unsent.drain(..written);
         }
+        io.stdin
+            .flush()
+            .await
+            .map_err(|e| self.lost(format!("Failed to flush sharkd stdin: {}", e)))?;
+
+        let line = loop {
+            // `read_until` keeps what it read in `partial` if it's cancelled
+            let read = io
+                .stdout
+                .read_until(b'\n', &mut io.partial)
+                .await
+                .map_err(|e| self.lost(format!("Failed to read from sharkd: {}", e)))?;
+            if read == 0 {
+                return Err(self.lost("sharkd closed its output".to_string()));
+            }
+            let line = String::from_utf8_lossy(&std::mem::take(&mut io.partial)).into_owned();
+            match parse::response_id(&line) {
+                Some(answered) if answered < id => {
+                    eprintln!(
+                        "Warning: Skipping stale sharkd response to request {} (expected {})",
+                        answered, id
+                    );
+                }
+                Some(answered) if answered > id => {
+                    return Err(format!(
+                        "sharkd answered request {} while request {} was pending",
+                        answered, id
+                    ));
+                }
+                _ => break line,
+            }
+        };
+        Ok(line)
+    }
 
-        response
-            .result
-            .ok_or_else(|| "No result in sharkd response".to_string())
+    /// Start an operation made of several requests, which checks between
+    /// them whether it was cancelled
+    pub fn operation(&self) -> Operation<'_> {
+        Operation {
+            client: self,
+            generation: self.cancel_generation.load(Ordering::SeqCst),
+        }
     }
 
-    /// Read a line from stdout and parse as JSON
-    fn read_response(&self) -> Result<Value, String> {
-        let mut stdout = self.stdout.lock();
-        let mut line = String::new();
-        stdout
-            .read_line(&mut line)
-            .map_err(|e| format!("Failed to read from sharkd: {}", e))?;
+    /// Cancel every operation in progress. Returns the method of the request
+    /// sharkd is busy with and how long it has been running, since sharkd
+    /// can't be interrupted mid-request short of killing it.
+    pub fn cancel_operations(&self) -> Option<(String, Duration)> {
+        self.cancel_generation.fetch_add(1, Ordering::SeqCst);
+        self.in_flight
+            .lock()
+            .as_ref()
+            .map(|(method, started)| (method.clone(), started.elapsed()))
+    }
 
-        serde_json::from_str(&line).map_err(|e| format!("Failed to parse JSON from sharkd: {}", e))
+    /// Kill sharkd now, even while requests still hold the client; they
+    /// fail as if it had crashed
+    pub fn kill(&self) {
+        if let Some(kill) = self.kill.lock().take() {
+            let _ = kill.send(());
+        }
+    }
+
+    /// Record that the pipes to sharkd broke, which means the process is gone
+    /// (or wedged), and pass the error through
+    fn lost(&self, error: String) -> String {
+        mark_exited(&self.exit, error.clone());
+        error
     }
 
     /// Load a PCAP file
-    pub fn load(&self, file_path: &str) -> Result<(), String> {
+    pub async fn load(&self, file_path: &str) -> Result<(), String> {
         println!("Loading file: {}", file_path);
-        let result = self.send_request("load", Some(json!({ "file": file_path })))?;
+        let result = self
+            .send_request("load", Some(json!({ "file": file_path })))
+            .await?;
         println!("Load result: {:?}", result);
 
         // Check if load was successful
@@ -671,6 +1593,7 @@ impl SharkdClient {
         if let Some(status) = result.get("status") {
             if status.as_str() == Some("OK") {
                 println!("File loaded successfully");
+                *self.loaded_file.lock() = Some(file_path.to_string());
                 return Ok(());
             }
         }
@@ -680,17 +1603,77 @@ impl SharkdClient {
         }
 
         // If we got here with no error, assume success
+        *self.loaded_file.lock() = Some(file_path.to_string());
         Ok(())
     }
 
+    /// Full path of the currently loaded capture file
+    /// (sharkd's status only reports the base name)
+    pub fn loaded_file(&self) -> Option<String> {
+        self.loaded_file.lock().clone()
+    }
+
+    /// Set a Wireshark preference for this sharkd session (e.g. `tls.keylog_file`).
+    /// Takes effect for captures loaded afterwards.
+    pub async fn set_pref(&self, name: &str, value: &str) -> Result<(), String> {
+        let result = self
+            .send_request("setconf", Some(json!({ "name": name, "value": value })))
+            .await?;
+        match result.get("err").and_then(|e| e.as_i64()) {
+            Some(err) if err != 0 => Err(format!(
+                "Failed to set preference {}: error code {}",
+                name, err
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Preferences of one protocol module (`tcp`, `http`, ...), or of every
+    /// module when `None`, sorted by name
+    pub async fn prefs(&self, module: Option<&str>) -> Result<Vec<Preference>, String> {
+        let params = module
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(|m| json!({ "pref": format!("{}.", m.trim_end_matches('.')) }));
+        let result = self.send_request("dumpconf", params).await?;
+        Ok(parse::prefs(result)?)
+    }
+
+    /// Append a record to a Wireshark UAT table (e.g. `esp_sa`). Records can't
+    /// be removed again; respawn sharkd to start from an empty table.
+    pub async fn add_uat_record(&self, table: &str, fields: &[&str]) -> Result<(), String> {
+        // Fields are quoted; characters that would end a field are hex-escaped
+        let record = fields
+            .iter()
+            .map(|field| {
+                let escaped: String = field
+                    .chars()
+                    .map(|c| match c {
+                        '"' | '\\' => format!("\\x{:02x}", c as u32),
+                        _ => c.to_string(),
+                    })
+                    .collect();
+                format!("\"{}\"", escaped)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        self.set_pref(&format!("uat:{}", table), &record).await
+    }
+
+    /// Get sharkd's build information: version, and the taps, follow
+    /// protocols and export-object types it supports
+    pub async fn info(&self) -> Result<Value, String> {
+        self.send_request("info", None).await
+    }
+
     /// Get the current status (frame count, duration, etc.)
-    pub fn status(&self) -> Result<Status, String> {
-        let result = self.send_request("status", None)?;
-        serde_json::from_value(result).map_err(|e| format!("Failed to parse status: {}", e))
+    pub async fn status(&self) -> Result<Status, String> {
+        let result = self.send_request("status", None).await?;
+        Ok(parse::status(result)?)
     }
 
     /// Get frames with pagination
-    pub fn frames(&self, skip: u32, limit: u32) -> Result<Vec<Frame>, String> {
+    pub async fn frames(&self, skip: u32, limit: u32) -> Result<Vec<Frame>, String> {
         let params = if skip > 0 {
             json!({
                 "skip": skip,
@@ -702,37 +1685,84 @@ impl SharkdClient {
             })
         };
 
-        let result = self.send_request("frames", Some(params))?;
+        let result = self.send_request("frames", Some(params)).await?;
+        Ok(parse::frames(result)?)
+    }
 
-        // sharkd returns frames as an array directly
-        let frames: Vec<Frame> =
-            serde_json::from_value(result).map_err(|e| format!("Failed to parse frames: {}", e))?;
+    /// Get detailed information about a specific frame (protocol tree)
+    pub async fn frame(&self, frame_num: u32) -> Result<Value, String> {
+        let result = self
+            .send_request(
+                "frame",
+                Some(json!({
+                    "frame": frame_num,
+                    "proto": true,
+                    "bytes": true
+                })),
+            )
+            .await?;
+        Ok(parse::tree(result)?)
+    }
 
-        Ok(frames)
+    /// A frame's protocol tree with each item's byte range, and its bytes
+    /// decoded and rendered as hex
+    pub async fn frame_details(&self, frame_num: u32) -> Result<FrameDetails, String> {
+        let result = self.frame(frame_num).await?;
+        Ok(parse::frame_details(result)?)
     }
 
-    /// Get detailed information about a specific frame (protocol tree)
-    pub fn frame(&self, frame_num: u32) -> Result<Value, String> {
-        self.send_request(
-            "frame",
-            Some(json!({
-                "frame": frame_num,
-                "proto": true,
-                "bytes": true
-            })),
-        )
+    /// Raw bytes of a frame, as captured
+    pub async fn frame_bytes(&self, frame_num: u32) -> Result<Vec<u8>, String> {
+        let result = self
+            .send_request(
+                "frame",
+                Some(json!({
+                    "frame": frame_num,
+                    "bytes": true
+                })),
+            )
+            .await?;
+        Ok(parse::frame_bytes(result)?)
+    }
+
+    /// Comments on a frame: those stored in the capture file and those added
+    /// with `set_comment`
+    pub async fn frame_comments(&self, frame_num: u32) -> Result<Vec<String>, String> {
+        let result = self
+            .send_request("frame", Some(json!({ "frame": frame_num })))
+            .await?;
+        Ok(parse::comments(result)?)
+    }
+
+    /// Replace a frame's comments with `comment`, or remove them with `None`.
+    /// Kept in sharkd's memory only; the capture file isn't changed.
+    pub async fn set_comment(&self, frame_num: u32, comment: Option<&str>) -> Result<(), String> {
+        let params = match comment {
+            Some(comment) => json!({ "frame": frame_num, "comment": comment }),
+            None => json!({ "frame": frame_num }),
+        };
+        let result = self.send_request("setcomment", Some(params)).await?;
+        match result.get("err").and_then(|e| e.as_i64()) {
+            Some(err) if err != 0 => Err(format!(
+                "Failed to set comment on frame {}: error code {}",
+                frame_num, err
+            )),
+            _ => Ok(()),
+        }
     }
 
     /// Check if a display filter is valid
-    pub fn check_filter(&self, filter: &str) -> Result<bool, String> {
-        let result = self.send_request("check", Some(json!({ "filter": filter })))?;
+    pub async fn check_filter(&self, filter: &str) -> Result<bool, String> {
+        let result = self
+            .send_request("check", Some(json!({ "filter": filter })))
+            .await?;
 
         // If there's an "err" field, the filter is invalid
         Ok(result.get("err").is_none())
     }
 
     /// Search frames with a display filter - passes filter to frames request
-    pub fn search_frames(
+    pub async fn search_frames(
         &self,
         filter: &str,
         skip: u32,
@@ -753,18 +1783,10 @@ impl SharkdClient {
             })
         };
 
-        let result = self.send_request("frames", Some(params))?;
+        let result = self.send_request("frames", Some(params)).await?;
 
         // sharkd returns frames as an array directly, or as {"frames": [...]}
-        let frames: Vec<Frame> = if result.is_array() {
-            serde_json::from_value(result.clone())
-                .map_err(|e| format!("Failed to parse frames: {}", e))?
-        } else if let Some(frames_arr) = result.get("frames") {
-            serde_json::from_value(frames_arr.clone())
-                .map_err(|e| format!("Failed to parse frames: {}", e))?
-        } else {
-            vec![]
-        };
+        let frames = parse::frames(result)?;
 
         // For filtered searches, we don't have an easy way to get total matching count
         // without doing a separate query. Use the returned count as an estimate.
@@ -773,80 +1795,794 @@ impl SharkdClient {
         Ok((frames, total))
     }
 
-    /// Follow a TCP, UDP, or HTTP stream
-    pub fn follow_stream(&self, protocol: &str, stream_id: u32) -> Result<StreamData, String> {
-        // Build the filter for the stream (e.g., "tcp.stream==0")
-        let filter = format!("{}.stream=={}", protocol.to_lowercase(), stream_id);
+    /// Follow a TCP, UDP, HTTP, TLS, WebSocket, or QUIC stream.
+    ///
+    /// WebSocket streams are addressed by TCP stream and also return the
+    /// individual frames (`messages`) with opcode and direction.
+    ///
+    /// TLS streams are addressed by TCP stream and return decrypted
+    /// application data, with `tls` telling whether keys were available.
+    /// QUIC streams are addressed by connection (`stream_id`, the
+    /// `quic.connection.number`) and the stream within it (`sub_stream`, the
+    /// `quic.stream_id`, default 0).
+    pub async fn follow_stream(
+        &self,
+        protocol: &str,
+        stream_id: u32,
+        sub_stream: Option<u32>,
+    ) -> Result<StreamData, String> {
+        let mut params = serde_json::Map::new();
+        params.insert("follow".to_string(), json!(protocol.to_uppercase()));
+        if protocol.eq_ignore_ascii_case("quic") {
+            let sub_stream = sub_stream.unwrap_or(0);
+            params.insert(
+                "filter".to_string(),
+                json!(format!(
+                    "quic.connection.number eq {} and quic.stream_id eq {}",
+                    stream_id, sub_stream
+                )),
+            );
+            params.insert("sub_stream".to_string(), json!(sub_stream));
+        } else if protocol.eq_ignore_ascii_case("tls") || protocol.eq_ignore_ascii_case("websocket")
+        {
+            params.insert(
+                "filter".to_string(),
+                json!(format!("tcp.stream eq {}", stream_id)),
+            );
+        } else {
+            // Build the filter for the stream (e.g., "tcp.stream==0")
+            params.insert(
+                "filter".to_string(),
+                json!(format!("{}.stream=={}", protocol.to_lowercase(), stream_id)),
+            );
+        }
 
-        let result = self.send_request(
-            "follow",
-            Some(json!({
-                "follow": protocol.to_uppercase(),
-                "filter": filter
-            })),
-        )?;
+        let result = self
+            .send_request("follow", Some(Value::Object(params)))
+            .await?;
+        let mut stream = parse::follow(result)?;
+
+        if protocol.eq_ignore_ascii_case("tls") {
+            stream.tls = Some(if !stream.payloads.is_empty() {
+                TlsDecryption::Decrypted
+            } else if self
+                .count_frames(&format!("tcp.stream eq {} && tls.app_data", stream_id))
+                .await?
+                > 0
+            {
+                TlsDecryption::NoKeys
+            } else {
+                TlsDecryption::NoApplicationData
+            });
+        }
+        if protocol.eq_ignore_ascii_case("websocket") {
+            stream.messages = Some(self.websocket_messages(stream_id, &stream).await?);
+        }
+        Ok(stream)
+    }
 
-        serde_json::from_value(result).map_err(|e| format!("Failed to parse stream data: {}", e))
+    /// The WebSocket frames of a TCP stream, with payloads taken from the
+    /// stream's follow data where a payload of the same direction and length
+    /// comes next
+    async fn websocket_messages(
+        &self,
+        stream_id: u32,
+        stream: &StreamData,
+    ) -> Result<Vec<WebSocketMessage>, String> {
+        let rows = self
+            .column_values(
+                &format!("tcp.stream eq {} && websocket", stream_id),
+                &[
+                    "frame.number",
+                    "frame.time_relative",
+                    "tcp.srcport",
+                    "websocket.opcode",
+                    "websocket.fin",
+                    "websocket.payload_length",
+                ],
+                u32::MAX,
+            )
+            .await?;
+
+        let mut payloads = stream.payloads.iter().peekable();
+        let mut messages = Vec::new();
+        for row in rows {
+            let field = |i: usize| row.get(i).map(String::as_str).unwrap_or_default();
+            let direction = if field(2) == stream.cport {
+                "client_to_server"
+            } else {
+                "server_to_client"
+            };
+            // A segment can carry several WebSocket frames
+            let fins: Vec<&str> = field(4).split(',').collect();
+            let sizes: Vec<&str> = field(5).split(',').collect();
+            for (i, opcode) in field(3).split(',').enumerate() {
+                let opcode = match opcode.trim().parse::<u8>() {
+                    Ok(opcode) => opcode,
+                    Err(_) => continue,
+                };
+                let size = sizes
+                    .get(i)
+                    .and_then(|n| n.trim().parse().ok())
+                    .unwrap_or(0);
+                let sender = if direction == "client_to_server" {
+                    0
+                } else {
+                    1
+                };
+                let data = match payloads.peek() {
+                    Some(p) if size > 0 && p.n == size && p.s == sender => {
+                        payloads.next().map(|p| p.d.clone())
+                    }
+                    _ => None,
+                };
+                messages.push(WebSocketMessage {
+                    frame: field(0).parse().unwrap_or(0),
+                    time: field(1).parse().unwrap_or(0.0),
+                    direction: direction.to_string(),
+                    opcode,
+                    kind: websocket_opcode_name(opcode).to_string(),
+                    fin: matches!(fins.get(i).map(|f| f.trim()), Some("1" | "True" | "true")),
+                    size,
+                    data,
+                });
+            }
+        }
+        Ok(messages)
     }
 
     /// Get capture statistics (protocol hierarchy, conversations, endpoints)
-    /// Uses a single batched tap request for performance
-    pub fn capture_stats(&self) -> Result<CaptureStats, String> {
+    /// Uses a single batched tap request for performance.
+    /// `filter` restricts the statistics to frames matching a display filter.
+    pub async fn capture_stats(&self, filter: Option<&str>) -> Result<CaptureStats, String> {
         // Batch all tap requests into a single sharkd call
         // Format: {"tap0": "phs", "tap1": "conv:TCP", ...}
         // Note: endpoint tap uses "endpt:" (not "endp:")
-        let result = self.send_request("tap", Some(json!({
-            "tap0": "phs",
-            "tap1": "conv:TCP",
-            "tap2": "conv:UDP",
-            "tap3": "endpt:IPv4"
-        })))?;
-
-        // Extract results from the batched response
         // Response format: {"taps": [{"tap": "phs", "protos": [...]}, {"tap": "conv:TCP", "convs": [...]}, ...]}
         // Note: taps may be returned in any order, so we find them by the "tap" field
-        let taps = match result.get("taps").and_then(|t| t.as_array()) {
-            Some(t) => t,
-            None => return Ok(CaptureStats::default()),
+        let taps = self
+            .run_taps(&["phs", "conv:TCP", "conv:UDP", "endpt:IPv4"], filter)
+            .await?;
+
+        // phs uses the "protos" field
+        let mut protocol_hierarchy: Vec<ProtocolNode> = tap_field(&taps, "phs", "protos")?;
+        annotate_hierarchy(&mut protocol_hierarchy);
+
+        Ok(CaptureStats {
+            protocol_hierarchy,
+            tcp_conversations: tap_field(&taps, "conv:TCP", "convs")?,
+            udp_conversations: tap_field(&taps, "conv:UDP", "convs")?,
+            // endpoints use the "hosts" field
+            endpoints: tap_field(&taps, "endpt:IPv4", "hosts")?,
+        })
+    }
+
+    /// Count the frames matching a display filter, from the protocol hierarchy
+    /// tap (every frame is counted once at the top level)
+    pub async fn count_frames(&self, filter: &str) -> Result<u64, String> {
+        let taps = self.run_taps(&["phs"], Some(filter)).await?;
+        let protocols: Vec<ProtocolNode> = tap_field(&taps, "phs", "protos")?;
+        Ok(match protocols.first() {
+            Some(root) if root.protocol == "frame" => root.frames,
+            _ => protocols.iter().map(|p| p.frames).sum(),
+        })
+    }
+
+    /// Count frames and bytes per `interval_ms` bucket, optionally restricted
+    /// by a display filter. Buckets without frames are filled in with zeros.
+    pub async fn intervals(
+        &self,
+        filter: Option<&str>,
+        interval_ms: u32,
+    ) -> Result<IoGraphSeries, String> {
+        let mut params = serde_json::Map::new();
+        params.insert("interval".to_string(), json!(interval_ms));
+        if let Some(f) = filter.filter(|f| !f.is_empty()) {
+            params.insert("filter".to_string(), json!(f));
+        }
+        let result = self
+            .send_request("intervals", Some(Value::Object(params)))
+            .await?;
+
+        // Response: {"intervals": [[index, frames, bytes], ...], "last": index, ...}
+        // with only the non-empty buckets listed
+        let buckets = result
+            .get("last")
+            .and_then(Value::as_u64)
+            .map_or(0, |last| last as usize + 1);
+        let mut series = IoGraphSeries {
+            filter: filter.unwrap_or("").to_string(),
+            frames: vec![0; buckets],
+            bytes: vec![0; buckets],
         };
+        for bucket in result
+            .get("intervals")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let field = |i: usize| bucket.get(i).and_then(Value::as_u64).unwrap_or(0);
+            let index = field(0) as usize;
+            if index >= series.frames.len() {
+                series.frames.resize(index + 1, 0);
+                series.bytes.resize(index + 1, 0);
+            }
+            series.frames[index] = field(1);
+            series.bytes[index] = field(2);
+        }
+        Ok(series)
+    }
 
-        // Helper closure to find a tap by its name
-        let find_tap = |name: &str| -> Option<&Value> {
-            taps.iter().find(|tap| {
-                tap.get("tap").and_then(|t| t.as_str()) == Some(name)
-            })
+    /// Run a batch of taps, optionally restricted by a display filter.
+    /// Returns the raw tap objects from sharkd's `taps` array.
+    async fn run_taps(&self, taps: &[&str], filter: Option<&str>) -> Result<Vec<Value>, String> {
+        let mut params = serde_json::Map::new();
+        for (i, tap) in taps.iter().enumerate() {
+            params.insert(format!("tap{}", i), json!(tap));
+        }
+        if let Some(f) = filter.filter(|f| !f.is_empty()) {
+            params.insert("filter".to_string(), json!(f));
+        }
+
+        let result = self
+            .send_request("tap", Some(Value::Object(params)))
+            .await?;
+        Ok(parse::taps(&result)?)
+    }
+
+    /// Get a page of frames with custom columns in place of the standard ones.
+    ///
+    /// Each column is a field name, optionally with an occurrence suffix
+    /// (`ip.src:1` for the outermost header); without one all occurrences are returned
+    /// comma-separated.
+    pub async fn frames_with_columns(
+        &self,
+        filter: &str,
+        skip: u32,
+        limit: u32,
+        columns: &[&str],
+    ) -> Result<Vec<Frame>, String> {
+        self.frames_with_refs(filter, skip, limit, columns, &[])
+            .await
+    }
+
+    /// Get a page of frames with custom columns like `frames_with_columns`,
+    /// with relative times measured from the given time reference frames
+    pub async fn frames_with_refs(
+        &self,
+        filter: &str,
+        skip: u32,
+        limit: u32,
+        columns: &[&str],
+        refs: &[u32],
+    ) -> Result<Vec<Frame>, String> {
+        let mut params = serde_json::Map::new();
+        if !filter.is_empty() {
+            params.insert("filter".to_string(), json!(filter));
+        }
+        // Note: sharkd requires skip > 0 if present, so omit when 0
+        if skip > 0 {
+            params.insert("skip".to_string(), json!(skip));
+        }
+        params.insert("limit".to_string(), json!(limit));
+        for (i, column) in columns.iter().enumerate() {
+            // sharkd only treats "field:occurrence" as a custom column
+            let spec = if column.contains(':') {
+                column.to_string()
+            } else {
+                format!("{}:0", column)
+            };
+            params.insert(format!("column{}", i), json!(spec));
+        }
+        if !refs.is_empty() {
+            let refs: Vec<String> = refs.iter().map(u32::to_string).collect();
+            params.insert("refs".to_string(), json!(refs.join(",")));
+        }
+
+        let result = self
+            .send_request("frames", Some(Value::Object(params)))
+            .await?;
+        Ok(parse::frames(result)?)
+    }
+
+    /// Stream IDs carried in a QUIC connection, for `follow_stream`'s
+    /// `sub_stream`
+    pub async fn quic_streams(&self, connection: u32) -> Result<Vec<u32>, String> {
+        let filter = format!(
+            "quic.connection.number eq {} and quic.stream_id",
+            connection
+        );
+        let rows = self
+            .column_values(&filter, &["quic.stream_id"], u32::MAX)
+            .await?;
+        let streams: BTreeSet<u32> = rows
+            .iter()
+            .filter_map(|row| row.first())
+            .flat_map(|ids| ids.split(','))
+            .filter_map(|id| id.trim().parse().ok())
+            .collect();
+        Ok(streams.into_iter().collect())
+    }
+
+    /// Flow diagram data for frames matching a filter (all frames when empty),
+    /// reading at most `max_frames` frames
+    pub async fn flow_graph(&self, filter: &str, max_frames: u32) -> Result<FlowGraph, String> {
+        let operation = self.operation();
+        let mut graph = FlowGraph::default();
+        let mut skip = 0u32;
+
+        // Addresses, protocol and Info come from the standard columns, ports
+        // and streams from a custom-column pass over the same page
+        while skip < max_frames {
+            operation.check()?;
+            let limit = COLUMN_SCAN_PAGE.min(max_frames - skip);
+            let frames = if filter.is_empty() {
+                self.frames(skip, limit).await?
+            } else {
+                self.search_frames(filter, skip, limit).await?.0
+            };
+            let fields = self
+                .frames_with_columns(filter, skip, limit, FLOW_FIELDS)
+                .await?;
+
+            let count = frames.len() as u32;
+            for (frame, fields) in frames.into_iter().zip(fields) {
+                if frame.number != fields.number {
+                    return Err("Frames changed while reading the flow graph".to_string());
+                }
+                let value = |i: usize| {
+                    fields
+                        .columns
+                        .get(i)
+                        .and_then(|v| v.split(',').next())
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                };
+                let port = |i: usize| value(i).and_then(|v| v.parse::<u16>().ok());
+                let stream = |i: usize| value(i).and_then(|v| v.parse::<u32>().ok());
+                let column = |i: usize| frame.columns.get(i).cloned().unwrap_or_default();
+
+                let item = FlowItem {
+                    frame: frame.number,
+                    time: value(0).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                    source: column(2),
+                    destination: column(3),
+                    source_port: port(1).or(port(3)),
+                    destination_port: port(2).or(port(4)),
+                    protocol: column(4),
+                    summary: column(6),
+                    stream: stream(5).or(stream(6)),
+                };
+                for host in [&item.source, &item.destination] {
+                    if !graph.nodes.contains(host) {
+                        graph.nodes.push(host.clone());
+                    }
+                }
+                graph.items.push(item);
+            }
+            if count < limit {
+                return Ok(graph);
+            }
+            skip += count;
+        }
+
+        // The page limit was hit exactly; check whether anything is left
+        let more = if filter.is_empty() {
+            self.frames(skip, 1).await?
+        } else {
+            self.search_frames(filter, skip, 1).await?.0
         };
+        graph.truncated = !more.is_empty();
+        Ok(graph)
+    }
 
-        // Extract protocol hierarchy from phs tap (uses "protos" field)
-        let protocol_hierarchy = find_tap("phs")
-            .and_then(|tap| tap.get("protos"))
-            .and_then(|protos| serde_json::from_value(protos.clone()).ok())
-            .unwrap_or_default();
-
-        // Extract TCP conversations
-        let tcp_conversations = find_tap("conv:TCP")
-            .and_then(|tap| tap.get("convs"))
-            .and_then(|convs| serde_json::from_value(convs.clone()).ok())
-            .unwrap_or_default();
-
-        // Extract UDP conversations
-        let udp_conversations = find_tap("conv:UDP")
-            .and_then(|tap| tap.get("convs"))
-            .and_then(|convs| serde_json::from_value(convs.clone()).ok())
-            .unwrap_or_default();
-
-        // Extract endpoints (uses "hosts" field)
-        let endpoints = find_tap("endpt:IPv4")
-            .and_then(|tap| tap.get("hosts"))
-            .and_then(|hosts| serde_json::from_value(hosts.clone()).ok())
-            .unwrap_or_default();
+    /// Extract field values for frames matching a filter using sharkd custom columns
+    /// (see `frames_with_columns`). Returns one row per frame, scanning at most
+    /// `max_frames` frames.
+    pub async fn column_values(
+        &self,
+        filter: &str,
+        columns: &[&str],
+        max_frames: u32,
+    ) -> Result<Vec<Vec<String>>, String> {
+        let operation = self.operation();
+        let mut rows = Vec::new();
+        let mut skip = 0u32;
+
+        while skip < max_frames {
+            operation.check()?;
+            let limit = COLUMN_SCAN_PAGE.min(max_frames - skip);
+            let frames = self
+                .frames_with_columns(filter, skip, limit, columns)
+                .await?;
+
+            let count = frames.len() as u32;
+            rows.extend(frames.into_iter().map(|f| f.columns));
+            if count < limit {
+                break;
+            }
+            skip += count;
+        }
 
-        Ok(CaptureStats {
-            protocol_hierarchy,
-            tcp_conversations,
-            udp_conversations,
+        Ok(rows)
+    }
+
+    /// Break traffic out per VLAN ID and unwrap GRE/VXLAN/IP-in-IP tunnels one level,
+    /// so overlay traffic isn't all attributed to the tunnel endpoints. Only
+    /// frames matching `scope` (when given) are counted; the filters reported
+    /// per VLAN and tunnel are left unscoped.
+    pub async fn overlay_stats(&self, scope: Option<&str>) -> Result<OverlayStats, String> {
+        let scoped = |filter: &str| match scope {
+            Some(scope) => format!("({}) && ({})", filter, scope),
+            None => filter.to_string(),
+        };
+
+        // Collect distinct VLAN IDs (stacked tags are reported comma-separated)
+        let vlan_ids: BTreeSet<String> = self
+            .column_values(&scoped("vlan"), &["vlan.id"], OVERLAY_SCAN_LIMIT)
+            .await?
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .flat_map(|ids| {
+                ids.split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let mut vlans = Vec::new();
+        for vlan_id in vlan_ids {
+            let filter = format!("vlan.id == {}", vlan_id);
+            let taps = self
+                .run_taps(&["conv:IPv4", "endpt:IPv4"], Some(&scoped(&filter)))
+                .await?;
+            vlans.push(VlanStats {
+                vlan_id,
+                conversations: tap_field(&taps, "conv:IPv4", "convs")?,
+                endpoints: tap_field(&taps, "endpt:IPv4", "hosts")?,
+                filter,
+            });
+        }
+
+        let mut tunnels = Vec::new();
+        for &(kind, filter) in TUNNEL_KINDS {
+            // The IPv4 conversation tap sees both the outer and the inner header
+            let taps = self.run_taps(&["conv:IPv4"], Some(&scoped(filter))).await?;
+            let conversations: Vec<Conversation> = tap_field(&taps, "conv:IPv4", "convs")?;
+            if conversations.is_empty() {
+                continue;
+            }
+
+            // The first IP header occurrence is the tunnel's outer header
+            let outer_pairs: BTreeSet<(String, String)> = self
+                .column_values(
+                    &scoped(filter),
+                    &["ip.src:1", "ip.dst:1"],
+                    OVERLAY_SCAN_LIMIT,
+                )
+                .await?
+                .into_iter()
+                .filter_map(|row| match row.as_slice() {
+                    [src, dst, ..] => Some((src.clone(), dst.clone())),
+                    _ => None,
+                })
+                .collect();
+
+            let (tunnel_endpoints, inner_conversations) =
+                conversations.into_iter().partition(|c: &Conversation| {
+                    outer_pairs.contains(&(c.saddr.clone(), c.daddr.clone()))
+                        || outer_pairs.contains(&(c.daddr.clone(), c.saddr.clone()))
+                });
+
+            tunnels.push(TunnelStats {
+                kind: kind.to_string(),
+                filter: filter.to_string(),
+                tunnel_endpoints,
+                inner_conversations,
+            });
+        }
+
+        Ok(OverlayStats { vlans, tunnels })
+    }
+
+    /// Get the conversations at one address layer, optionally restricted by a
+    /// display filter
+    pub async fn conversations(
+        &self,
+        kind: AddressKind,
+        filter: Option<&str>,
+    ) -> Result<Vec<Conversation>, String> {
+        let tap = format!("conv:{}", kind.tap_name());
+        let taps = self.run_taps(&[tap.as_str()], filter).await?;
+        Ok(tap_field(&taps, &tap, "convs")?)
+    }
+
+    /// Get the endpoints at one address layer with their frame and byte
+    /// totals, optionally restricted by a display filter
+    pub async fn endpoints(
+        &self,
+        kind: AddressKind,
+        filter: Option<&str>,
+    ) -> Result<Vec<Endpoint>, String> {
+        let tap = format!("endpt:{}", kind.tap_name());
+        let taps = self.run_taps(&[tap.as_str()], filter).await?;
+        let mut endpoints: Vec<Endpoint> = tap_field(&taps, &tap, "hosts")?;
+        for endpoint in &mut endpoints {
+            endpoint.frames = endpoint.rxf + endpoint.txf;
+            endpoint.bytes = endpoint.rxb + endpoint.txb;
+            if endpoint.filter.is_none() {
+                endpoint.filter =
+                    Some(kind.endpoint_filter(&endpoint.host, endpoint.port.as_deref()));
+            }
+        }
+        endpoints.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        Ok(endpoints)
+    }
+
+    /// Get address-level conversations and endpoints (IPv4 and IPv6),
+    /// optionally restricted by a display filter
+    pub async fn host_traffic(&self, filter: Option<&str>) -> Result<HostTraffic, String> {
+        let taps = self
+            .run_taps(
+                &["conv:IPv4", "conv:IPv6", "endpt:IPv4", "endpt:IPv6"],
+                filter,
+            )
+            .await?;
+
+        let mut conversations: Vec<Conversation> = tap_field(&taps, "conv:IPv4", "convs")?;
+        conversations.extend(tap_field::<Vec<Conversation>>(&taps, "conv:IPv6", "convs")?);
+        let mut endpoints: Vec<Endpoint> = tap_field(&taps, "endpt:IPv4", "hosts")?;
+        endpoints.extend(tap_field::<Vec<Endpoint>>(&taps, "endpt:IPv6", "hosts")?);
+
+        Ok(HostTraffic {
+            conversations,
             endpoints,
         })
     }
 
+    /// Get HTTP statistics: request methods, response codes, and requests per
+    /// host and URI, optionally restricted by a display filter
+    pub async fn http_stats(&self, filter: Option<&str>) -> Result<HttpStats, String> {
+        let taps = self
+            .run_taps(&["stat:http", "stat:http_req"], filter)
+            .await?;
+
+        // stat:http is one tree: "Total HTTP Packets" > "HTTP Request Packets"
+        // > methods, and "HTTP Response Packets" > status classes > codes
+        let counter: Vec<StatsNode> = tap_field(&taps, "stat:http", "stats")?;
+        let mut stats = HttpStats::default();
+        let total = counter.first().cloned().unwrap_or_default();
+        stats.total_packets = total.count;
+        for node in &total.children {
+            if node.name.starts_with("HTTP Request") {
+                stats.requests = node.count;
+                stats.methods = named_counts(&node.children);
+            } else if node.name.starts_with("HTTP Response") {
+                stats.responses = node.count;
+                stats.status_classes = named_counts(&node.children);
+                let codes: Vec<StatsNode> = node
+                    .children
+                    .iter()
+                    .flat_map(|class| class.children.iter().cloned())
+                    .collect();
+                stats.status_codes = named_counts(&codes);
+            }
+        }
+
+        // stat:http_req: "HTTP Requests by HTTP Host" > hosts > URIs
+        let requests: Vec<StatsNode> = tap_field(&taps, "stat:http_req", "stats")?;
+        let mut hosts: Vec<HttpHostStats> = requests
+            .first()
+            .map(|root| root.children.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|host| HttpHostStats {
+                host: host.name.clone(),
+                requests: host.count,
+                uris: named_counts(&host.children),
+            })
+            .collect();
+        hosts.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.host.cmp(&b.host))
+        });
+        stats.hosts = hosts;
+
+        Ok(stats)
+    }
+
+    /// List RTP streams with their loss and jitter, each analysed with the
+    /// `rtp-analyse` tap for delta, skew and sequence error details
+    pub async fn rtp_streams(&self, filter: Option<&str>) -> Result<Vec<RtpStream>, String> {
+        let taps = self.run_taps(&["rtp-streams"], filter).await?;
+        let mut streams: Vec<RtpStream> = tap_field(&taps, "rtp-streams", "streams")?;
+        for stream in &mut streams {
+            stream.lost = stream.expected as i64 - stream.received as i64;
+            stream.loss_percent = if stream.expected > 0 {
+                stream.lost as f64 * 100.0 / stream.expected as f64
+            } else {
+                0.0
+            };
+            stream.filter = stream.stream_filter();
+        }
+
+        let names: Vec<String> = streams
+            .iter()
+            .map(|s| format!("rtp-analyse:{}", s.tap_id()))
+            .collect();
+        for (chunk_index, chunk) in names.chunks(MAX_TAPS_PER_REQUEST).enumerate() {
+            let tap_names: Vec<&str> = chunk.iter().map(String::as_str).collect();
+            let taps = self.run_taps(&tap_names, filter).await?;
+            for (i, name) in chunk.iter().enumerate() {
+                let analysis = taps
+                    .iter()
+                    .find(|tap| tap.get("tap").and_then(Value::as_str) == Some(name.as_str()));
+                if let Some(analysis) = analysis {
+                    streams[chunk_index * MAX_TAPS_PER_REQUEST + i].analysis =
+                        Some(parse::tap_value(analysis, "rtp-analyse")?);
+                }
+            }
+        }
+
+        Ok(streams)
+    }
+
+    /// Fetch an RTP stream's audio, decoded by sharkd from the stream's
+    /// payloads (in sequence, with silence for lost packets) into a WAV file
+    pub async fn rtp_audio(&self, stream: &RtpStream) -> Result<Download, String> {
+        self.download(&format!("rtp:{}", stream.tap_id())).await
+    }
+
+    /// List VoIP calls with their parties, state and time span, optionally
+    /// restricted by a display filter
+    pub async fn voip_calls(&self, filter: Option<&str>) -> Result<Vec<VoipCall>, String> {
+        let taps = self.run_taps(&["voip-calls"], filter).await?;
+        let mut calls: Vec<VoipCall> = tap_field(&taps, "voip-calls", "calls")?;
+        for call in &mut calls {
+            call.filter = call.call_filter();
+        }
+        Ok(calls)
+    }
+
+    /// List files carried in the capture (HTTP bodies, SMB files, email
+    /// messages, ...), from every protocol in `EXPORT_OBJECT_PROTOCOLS` or
+    /// just `protocol`, optionally restricted by a display filter
+    pub async fn export_objects(
+        &self,
+        protocol: Option<&str>,
+        filter: Option<&str>,
+    ) -> Result<Vec<ExportObject>, String> {
+        let protocols: Vec<String> = match protocol {
+            Some(protocol) => vec![protocol.to_lowercase()],
+            None => EXPORT_OBJECT_PROTOCOLS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        };
+        let names: Vec<String> = protocols.iter().map(|p| format!("eo:{}", p)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let taps = self.run_taps(&names, filter).await?;
+
+        let mut objects = Vec::new();
+        for (protocol, name) in protocols.iter().zip(&names) {
+            let mut found: Vec<ExportObject> = tap_field(&taps, name, "objects")?;
+            for object in &mut found {
+                object.protocol = protocol.clone();
+            }
+            objects.extend(found);
+        }
+        objects.sort_by_key(|object| object.frame);
+        Ok(objects)
+    }
+
+    /// Service response times per procedure for a protocol in
+    /// `SRT_PROTOCOLS`, optionally restricted by a display filter
+    pub async fn service_response_times(
+        &self,
+        protocol: &str,
+        filter: Option<&str>,
+    ) -> Result<Vec<SrtTable>, String> {
+        let protocol = protocol.trim().to_lowercase();
+        let name = protocol.split(',').next().unwrap_or_default();
+        if !SRT_PROTOCOLS.contains(&name) {
+            return Err(format!("Unsupported response time protocol: {}", protocol));
+        }
+        if name == "dcerpc" && !protocol.contains(',') {
+            return Err(
+                "DCE-RPC response times need an interface: dcerpc,<uuid>,<version>".to_string(),
+            );
+        }
+
+        let mut tables = if name == "http" {
+            vec![self.http_response_times(filter).await?]
+        } else {
+            let tap = format!("srt:{}", protocol);
+            let taps = self.run_taps(&[tap.as_str()], filter).await?;
+            tap_field::<Vec<SrtTable>>(&taps, &tap, "tables")?
+        };
+        for table in &mut tables {
+            table.procedures.retain(|p| p.calls > 0);
+            for procedure in &mut table.procedures {
+                procedure.avg = procedure.total / procedure.calls as f64;
+            }
+        }
+        Ok(tables)
+    }
+
+    /// HTTP response times per request method, from responses' `http.time`
+    async fn http_response_times(&self, filter: Option<&str>) -> Result<SrtTable, String> {
+        let scoped = |base: &str| match filter {
+            Some(filter) => format!("{} && ({})", base, filter),
+            None => base.to_string(),
+        };
+        let methods: HashMap<String, String> = self
+            .column_values(
+                &scoped("http.request"),
+                &["frame.number", "http.request.method"],
+                HTTP_SRT_SCAN_LIMIT,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|row| {
+                let mut row = row.into_iter();
+                Some((row.next()?, row.next()?))
+            })
+            .collect();
+        let responses = self
+            .column_values(
+                &scoped("http.response && http.time"),
+                &["http.request_in", "http.time"],
+                HTTP_SRT_SCAN_LIMIT,
+            )
+            .await?;
+
+        let mut procedures: BTreeMap<String, SrtProcedure> = BTreeMap::new();
+        for row in responses {
+            let time = match row.get(1).and_then(|t| t.parse::<f64>().ok()) {
+                Some(time) => time,
+                None => continue,
+            };
+            let method = row
+                .first()
+                .and_then(|request| methods.get(request))
+                .cloned()
+                .unwrap_or_else(|| "(unknown)".to_string());
+            let procedure = procedures
+                .entry(method.clone())
+                .or_insert_with(|| SrtProcedure {
+                    procedure: method,
+                    min: time,
+                    max: time,
+                    ..Default::default()
+                });
+            procedure.calls += 1;
+            procedure.min = procedure.min.min(time);
+            procedure.max = procedure.max.max(time);
+            procedure.total += time;
+        }
+
+        Ok(SrtTable {
+            name: "HTTP".to_string(),
+            procedures: procedures.into_values().collect(),
+        })
+    }
+
+    /// Fetch the contents of an export object by its download token
+    pub async fn download(&self, token: &str) -> Result<Download, String> {
+        let result = self
+            .send_request("download", Some(json!({ "token": token })))
+            .await?;
+        Ok(parse::download(result)?)
+    }
+
+    /// Get address-level endpoints (IPv4 and IPv6) without conversations
+    pub async fn host_endpoints(&self) -> Result<Vec<Endpoint>, String> {
+        let taps = self.run_taps(&["endpt:IPv4", "endpt:IPv6"], None).await?;
+
+        let mut endpoints: Vec<Endpoint> = tap_field(&taps, "endpt:IPv4", "hosts")?;
+        endpoints.extend(tap_field::<Vec<Endpoint>>(&taps, "endpt:IPv6", "hosts")?);
+        Ok(endpoints)
+    }
 }
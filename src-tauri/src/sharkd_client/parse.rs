@@ -0,0 +1,374 @@
+//! Parsing of sharkd's JSON-RPC responses, kept free of I/O so it can be
+//! fuzzed (`cargo fuzz run sharkd_response` in `src-tauri`; targets are in
+//! `fuzz/`).
+//!
+//! Output from a Wireshark version we haven't seen must end in a
+//! `ParseError`, never a panic, and a response of the wrong shape is an error
+//! rather than an empty result. Missing optional parts (a tap that produced
+//! nothing, an absent field) still fall back to defaults.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+use super::{
+    DataSource, Download, FollowTarget, Frame, FrameDetails, PrefKind, PrefOption, Preference,
+    Status, StreamData, TreeNode,
+};
+use crate::hex_dump;
+
+/// Why a sharkd response couldn't be used
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The response line isn't JSON
+    Json(String),
+    /// Valid JSON, but not a JSON-RPC response
+    Envelope(String),
+    /// sharkd reported an error
+    Rpc { code: i64, message: String },
+    /// The result doesn't have the shape expected for `what`
+    Shape { what: &'static str, detail: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Json(e) => write!(f, "Failed to parse JSON from sharkd: {}", e),
+            ParseError::Envelope(e) => write!(f, "Failed to parse sharkd response: {}", e),
+            ParseError::Rpc { code, message } => write!(f, "Sharkd error {}: {}", code, message),
+            ParseError::Shape { what, detail } => write!(f, "Failed to parse {}: {}", what, detail),
+        }
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(e: ParseError) -> String {
+        e.to_string()
+    }
+}
+
+fn shape(what: &'static str, detail: impl fmt::Display) -> ParseError {
+    ParseError::Shape {
+        what,
+        detail: detail.to_string(),
+    }
+}
+
+/// Generic JSON-RPC response
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    #[allow(dead_code)]
+    jsonrpc: String,
+    #[allow(dead_code)]
+    id: u64,
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+/// Just the id of a response, read without building the rest of it
+#[derive(Debug, Deserialize)]
+struct ResponseId {
+    id: Option<u64>,
+}
+
+/// The request id a response line answers, when it has one
+pub fn response_id(line: &str) -> Option<u64> {
+    serde_json::from_str::<ResponseId>(line).ok()?.id
+}
+
+/// The result of one response line
+pub fn response(line: &str) -> Result<Value, ParseError> {
+    let value: Value = serde_json::from_str(line).map_err(|e| ParseError::Json(e.to_string()))?;
+
+    let response: JsonRpcResponse =
+        serde_json::from_value(value).map_err(|e| ParseError::Envelope(e.to_string()))?;
+
+    if let Some(error) = response.error {
+        return Err(ParseError::Rpc {
+            code: error.code,
+            message: error.message,
+        });
+    }
+
+    response
+        .result
+        .ok_or_else(|| ParseError::Envelope("No result in sharkd response".to_string()))
+}
+
+/// Result of `status`
+pub fn status(result: Value) -> Result<Status, ParseError> {
+    serde_json::from_value(result).map_err(|e| shape("status", e))
+}
+
+/// Result of `frames`: an array of frames, or `{"frames": [...]}`
+pub fn frames(result: Value) -> Result<Vec<Frame>, ParseError> {
+    let frames = match result {
+        Value::Array(_) => result,
+        Value::Object(mut object) => object
+            .remove("frames")
+            .ok_or_else(|| shape("frames", "no frames in response"))?,
+        other => return Err(shape("frames", format!("unexpected {}", other))),
+    };
+    serde_json::from_value(frames).map_err(|e| shape("frames", e))
+}
+
+/// Result of `follow`
+pub fn follow(result: Value) -> Result<StreamData, ParseError> {
+    serde_json::from_value(result).map_err(|e| shape("stream data", e))
+}
+
+/// Result of `frame`: an object whose protocol tree is an array
+pub fn tree(result: Value) -> Result<Value, ParseError> {
+    match result.get("tree") {
+        Some(Value::Array(_)) => Ok(result),
+        Some(other) => Err(shape("frame tree", format!("unexpected {}", other))),
+        None if result.is_object() => Err(shape("frame tree", "no tree in response")),
+        None => Err(shape("frame tree", format!("unexpected {}", result))),
+    }
+}
+
+fn decode_bytes(what: &'static str, value: Option<&Value>) -> Result<Vec<u8>, ParseError> {
+    match value {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(data)) => BASE64.decode(data).map_err(|e| shape(what, e)),
+        Some(other) => Err(shape(what, format!("unexpected {}", other))),
+    }
+}
+
+/// A tree item: `l` label, `f` filter, `t` type, `s` severity, `h` [offset,
+/// length], `ds` data source, `n` children
+fn tree_node(node: &Value) -> Result<TreeNode, ParseError> {
+    let object = node
+        .as_object()
+        .ok_or_else(|| shape("tree node", format!("unexpected {}", node)))?;
+    let text = |key: &str| object.get(key).and_then(Value::as_str).map(String::from);
+    let (offset, length) = match object.get("h") {
+        Some(Value::Array(range)) => match (range.first(), range.get(1)) {
+            (Some(offset), Some(length)) => (
+                offset.as_u64().map(|v| v as u32),
+                length.as_u64().map(|v| v as u32),
+            ),
+            _ => return Err(shape("tree node", "incomplete byte range")),
+        },
+        None => (None, None),
+        Some(other) => return Err(shape("tree node", format!("unexpected range {}", other))),
+    };
+    let children = match object.get("n") {
+        Some(Value::Array(nodes)) => nodes.iter().map(tree_node).collect::<Result<_, _>>()?,
+        None => Vec::new(),
+        Some(other) => return Err(shape("tree node", format!("unexpected children {}", other))),
+    };
+    Ok(TreeNode {
+        label: text("l").unwrap_or_default(),
+        filter: text("f"),
+        kind: text("t"),
+        severity: text("s"),
+        offset,
+        length,
+        data_source: object.get("ds").and_then(Value::as_u64).unwrap_or(0) as u32,
+        children,
+    })
+}
+
+/// Result of `frame` requested with `proto` and `bytes`
+pub fn frame_details(result: Value) -> Result<FrameDetails, ParseError> {
+    let tree = match result.get("tree") {
+        Some(Value::Array(nodes)) => nodes.iter().map(tree_node).collect::<Result<_, _>>()?,
+        Some(other) => return Err(shape("frame tree", format!("unexpected {}", other))),
+        None => return Err(shape("frame tree", "no tree in response")),
+    };
+    let bytes = decode_bytes("frame bytes", result.get("bytes"))?;
+
+    let data_sources = match result.get("ds") {
+        Some(Value::Array(sources)) => sources
+            .iter()
+            .map(|source| {
+                let bytes = decode_bytes("data source", source.get("bytes"))?;
+                Ok(DataSource {
+                    name: source
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    hex: hex_dump::hex_lines(&bytes),
+                    bytes,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?,
+        None | Some(Value::Null) => Vec::new(),
+        Some(other) => return Err(shape("data sources", format!("unexpected {}", other))),
+    };
+
+    // `fol` is a list of [protocol, filter] pairs
+    let follow = match result.get("fol") {
+        Some(Value::Array(targets)) => targets
+            .iter()
+            .filter_map(|target| match target.as_array().map(Vec::as_slice) {
+                Some([Value::String(protocol), Value::String(filter), ..]) => Some(FollowTarget {
+                    protocol: protocol.clone(),
+                    filter: filter.clone(),
+                }),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(FrameDetails {
+        tree,
+        hex: hex_dump::hex_lines(&bytes),
+        bytes,
+        data_sources,
+        follow,
+    })
+}
+
+/// Raw bytes of a `frame` result requested with `bytes`
+pub fn frame_bytes(result: Value) -> Result<Vec<u8>, ParseError> {
+    match result.get("bytes") {
+        Some(Value::String(data)) => BASE64.decode(data).map_err(|e| shape("frame bytes", e)),
+        Some(other) => Err(shape("frame bytes", format!("unexpected {}", other))),
+        None => Err(shape("frame bytes", "no bytes in response")),
+    }
+}
+
+/// Result of `download`: the object's name, MIME type and base64 contents
+pub fn download(result: Value) -> Result<Download, ParseError> {
+    let text = |key: &str| {
+        result
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let data = match result.get("data") {
+        Some(Value::String(data)) => BASE64.decode(data).map_err(|e| shape("download", e))?,
+        Some(other) => return Err(shape("download", format!("unexpected {}", other))),
+        None => return Err(shape("download", "no data in response")),
+    };
+    Ok(Download {
+        filename: text("file"),
+        mime: text("mime"),
+        data,
+    })
+}
+
+/// Result of `dumpconf`: `{"prefs": {name: value}}`, where the value's key
+/// tells its type (`b` bool, `u` uint, `e` enum, `s` string, `r` range, `t`
+/// UAT)
+pub fn prefs(result: Value) -> Result<Vec<Preference>, ParseError> {
+    let prefs = match result.get("prefs") {
+        Some(Value::Object(prefs)) => prefs,
+        Some(other) => return Err(shape("preferences", format!("unexpected {}", other))),
+        None => return Err(shape("preferences", "no prefs in response")),
+    };
+
+    let mut parsed = Vec::new();
+    for (name, value) in prefs {
+        let text = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let mut preference = Preference {
+            name: name.clone(),
+            kind: PrefKind::String,
+            value: String::new(),
+            base: None,
+            options: Vec::new(),
+        };
+        if let Some(b) = value.get("b") {
+            preference.kind = PrefKind::Bool;
+            preference.value = if text(b) == "1" { "TRUE" } else { "FALSE" }.to_string();
+        } else if let Some(u) = value.get("u") {
+            preference.kind = PrefKind::Uint;
+            preference.value = text(u);
+            preference.base = value.get("ub").and_then(|b| text(b).parse().ok());
+        } else if let Some(e) = value.get("e") {
+            preference.kind = PrefKind::Enum;
+            let options = e
+                .as_array()
+                .ok_or_else(|| shape("preferences", format!("enum {} is not an array", name)))?;
+            for option in options {
+                let option = PrefOption {
+                    value: option.get("v").and_then(Value::as_i64).unwrap_or(0),
+                    description: option.get("d").map(text).unwrap_or_default(),
+                    selected: option.get("s").and_then(Value::as_i64) == Some(1),
+                };
+                if option.selected {
+                    preference.value = option.description.clone();
+                }
+                preference.options.push(option);
+            }
+        } else if let Some(r) = value.get("r") {
+            preference.kind = PrefKind::Range;
+            preference.value = text(r);
+        } else if value.get("t").is_some() {
+            preference.kind = PrefKind::Uat;
+        } else if let Some(s) = value.get("s") {
+            preference.value = text(s);
+        } else {
+            // Types sharkd doesn't dump (colors, obsolete preferences, ...)
+            continue;
+        }
+        parsed.push(preference);
+    }
+    parsed.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(parsed)
+}
+
+/// Comments of a `frame` result: an array of strings (a single string in
+/// older sharkd versions), or absent when the frame has none
+pub fn comments(result: Value) -> Result<Vec<String>, ParseError> {
+    match result.get("comment") {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(comment)) => Ok(vec![comment.clone()]),
+        Some(Value::Array(comments)) => comments
+            .iter()
+            .map(|c| {
+                c.as_str()
+                    .map(String::from)
+                    .ok_or_else(|| shape("comments", format!("unexpected {}", c)))
+            })
+            .collect(),
+        Some(other) => Err(shape("comments", format!("unexpected {}", other))),
+    }
+}
+
+/// The tap objects of a `tap` result
+pub fn taps(result: &Value) -> Result<Vec<Value>, ParseError> {
+    match result.get("taps") {
+        Some(Value::Array(taps)) => Ok(taps.clone()),
+        Some(other) => Err(shape("taps", format!("unexpected {}", other))),
+        None => Err(shape("taps", "no taps in response")),
+    }
+}
+
+/// Deserialize `field` from the tap named `name` in a batched tap response.
+/// A missing tap or field gives the default; a malformed one is an error.
+pub fn tap_field<T: DeserializeOwned + Default>(
+    taps: &[Value],
+    name: &str,
+    field: &str,
+) -> Result<T, ParseError> {
+    let value = taps
+        .iter()
+        .find(|tap| tap.get("tap").and_then(|t| t.as_str()) == Some(name))
+        .and_then(|tap| tap.get(field));
+    match value {
+        Some(value) => serde_json::from_value(value.clone()).map_err(|e| shape("tap", e)),
+        None => Ok(T::default()),
+    }
+}
+
+/// Deserialize a whole tap object, for taps whose fields are at the top level
+pub fn tap_value<T: DeserializeOwned>(tap: &Value, what: &'static str) -> Result<T, ParseError> {
+    serde_json::from_value(tap.clone()).map_err(|e| shape(what, e))
+}
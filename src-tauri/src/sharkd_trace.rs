@@ -0,0 +1,102 @@
+//! Debug tracing of sharkd JSON-RPC traffic.
+//!
+//! When enabled, every request/response pair is kept in a ring buffer with
+//! its size and latency, so a misbehaving tap or filter can be diagnosed on
+//! the user's own Wireshark version. Bodies are truncated to keep memory
+//! bounded; tracing is off by default and not persisted.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::storage;
+
+/// Exchanges kept in the ring buffer
+const TRACE_CAPACITY: usize = 500;
+
+/// Characters of each request/response body kept
+const BODY_LIMIT: usize = 2_000;
+
+/// One traced request/response pair
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEntry {
+    /// When the request was sent (Unix seconds)
+    pub at: u64,
+    pub method: String,
+    pub request_bytes: usize,
+    pub response_bytes: usize,
+    /// Time from writing the request to reading the response
+    pub latency_ms: f64,
+    pub request: String,
+    /// Response body, or the I/O error when the exchange failed
+    pub response: String,
+    pub truncated: bool,
+    pub ok: bool,
+}
+
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+static TRACE: OnceLock<Mutex<VecDeque<TraceEntry>>> = OnceLock::new();
+
+fn get_trace() -> &'static Mutex<VecDeque<TraceEntry>> {
+    TRACE.get_or_init(|| Mutex::new(VecDeque::with_capacity(TRACE_CAPACITY)))
+}
+
+/// Whether sharkd traffic is being traced
+pub fn enabled() -> bool {
+    TRACING.load(Ordering::Relaxed)
+}
+
+/// Turn tracing on or off. Turning it off keeps the trace for inspection.
+pub fn set_enabled(enabled: bool) {
+    TRACING.store(enabled, Ordering::Relaxed);
+}
+
+fn truncate(body: &str) -> (String, bool) {
+    let body = body.trim_end();
+    match body.char_indices().nth(BODY_LIMIT) {
+        Some((end, _)) => (format!("{}...", &body[..end]), true),
+        None => (body.to_string(), false),
+    }
+}
+
+/// Record an exchange; `response` is the raw response line or the error
+pub fn record(method: &str, request: &str, response: &Result<String, String>, latency: Duration) {
+    let (response_text, ok) = match response {
+        Ok(line) => (line.as_str(), !line.contains("\"error\"")),
+        Err(e) => (e.as_str(), false),
+    };
+    let (request_body, request_truncated) = truncate(request);
+    let (response_body, response_truncated) = truncate(response_text);
+
+    let entry = TraceEntry {
+        at: storage::now_secs(),
+        method: method.to_string(),
+        request_bytes: request.trim_end().len(),
+        response_bytes: response.as_ref().map(|l| l.trim_end().len()).unwrap_or(0),
+        latency_ms: latency.as_secs_f64() * 1000.0,
+        request: request_body,
+        response: response_body,
+        truncated: request_truncated || response_truncated,
+        ok,
+    };
+
+    let mut trace = get_trace().lock();
+    if trace.len() == TRACE_CAPACITY {
+        trace.pop_front();
+    }
+    trace.push_back(entry);
+}
+
+/// Traced exchanges, oldest first
+pub fn entries() -> Vec<TraceEntry> {
+    get_trace().lock().iter().cloned().collect()
+}
+
+/// Drop all traced exchanges
+pub fn clear() {
+    get_trace().lock().clear();
+}
@@ -0,0 +1,127 @@
+//! Capture statistics and filtered frame counts cached per loaded capture
+//! and filter, so the stats panel, the `/capture-stats` bridge route, and
+//! filtered packet list paging don't rerun sharkd's taps over an unchanged
+//! file.
+//!
+//! Entries are dropped when a capture is (re)loaded or changes on disk;
+//! callers can also bypass the cache with `force_refresh`. Each capture has a
+//! generation that invalidation bumps, so a result fetched while the capture
+//! was being invalidated isn't cached as current.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::sharkd_client::{CaptureStats, SharkdClient};
+
+/// Statistics kept in the cache
+const MAX_CACHED_STATS: usize = 32;
+
+/// Frame counts kept in the cache
+const MAX_CACHED_COUNTS: usize = 256;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    capture: String,
+    /// The filter as sent to sharkd, ignored-frame exclusion included
+    filter: Option<String>,
+}
+
+static CACHE: OnceLock<Mutex<HashMap<CacheKey, CaptureStats>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<HashMap<CacheKey, CaptureStats>> {
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static COUNTS: OnceLock<Mutex<HashMap<CacheKey, u64>>> = OnceLock::new();
+
+fn get_counts() -> &'static Mutex<HashMap<CacheKey, u64>> {
+    COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Times each capture was invalidated, by path
+static GENERATIONS: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+
+fn generation(capture: &str) -> u64 {
+    GENERATIONS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .get(capture)
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Forget the statistics of one capture, e.g. after it was reloaded or
+/// changed on disk
+pub fn invalidate(capture: &str) {
+    // Bumped first: fetches still running check it before inserting
+    *GENERATIONS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .entry(capture.to_string())
+        .or_default() += 1;
+    get_cache().lock().retain(|key, _| key.capture != capture);
+    get_counts().lock().retain(|key, _| key.capture != capture);
+}
+
+/// Statistics of the loaded capture for `filter`, from the cache unless
+/// `force_refresh` is set
+pub async fn capture_stats(
+    client: &SharkdClient,
+    filter: Option<&str>,
+    force_refresh: bool,
+) -> Result<CaptureStats, String> {
+    let capture = match client.loaded_file() {
+        Some(capture) => capture,
+        // Nothing to key on; sharkd reports the missing capture itself
+        None => return client.capture_stats(filter).await,
+    };
+    let key = CacheKey {
+        capture,
+        filter: filter.map(String::from),
+    };
+    if !force_refresh {
+        if let Some(stats) = get_cache().lock().get(&key) {
+            return Ok(stats.clone());
+        }
+    }
+
+    let started = generation(&key.capture);
+    let stats = client.capture_stats(filter).await?;
+    let mut cache = get_cache().lock();
+    if generation(&key.capture) != started {
+        return Ok(stats);
+    }
+    if cache.len() >= MAX_CACHED_STATS {
+        cache.clear();
+    }
+    cache.insert(key, stats.clone());
+    Ok(stats)
+}
+
+/// Number of frames in the loaded capture matching `filter`
+pub async fn count_frames(client: &SharkdClient, filter: &str) -> Result<u64, String> {
+    let capture = match client.loaded_file() {
+        Some(capture) => capture,
+        None => return client.count_frames(filter).await,
+    };
+    let key = CacheKey {
+        capture,
+        filter: Some(filter.to_string()),
+    };
+    if let Some(&count) = get_counts().lock().get(&key) {
+        return Ok(count);
+    }
+
+    let started = generation(&key.capture);
+    let count = client.count_frames(filter).await?;
+    let mut counts = get_counts().lock();
+    if generation(&key.capture) != started {
+        return Ok(count);
+    }
+    if counts.len() >= MAX_CACHED_COUNTS {
+        counts.clear();
+    }
+    counts.insert(key, count);
+    Ok(count)
+}
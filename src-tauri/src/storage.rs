@@ -0,0 +1,211 @@
+//! JSON persistence for backend-owned state.
+//!
+//! Each store is a single JSON file in the app data directory, which is
+//! resolved once during app setup. Writes can be debounced through
+//! `schedule_save`, and every write keeps the previous versions as
+//! numbered backups so a bad write or crash can be rolled back.
+
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// App data directory, set during setup
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Quiet period before a scheduled save is written
+const AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Number of previous versions kept per store
+const KEEP_BACKUPS: u32 = 5;
+
+/// A store backup on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// 1 is the most recent backup
+    pub generation: u32,
+    pub path: String,
+    /// Unix timestamp (seconds) of when the backup was written
+    pub modified: u64,
+}
+
+/// Pending debounced writes: store name -> (latest value, time of last change)
+static PENDING: OnceLock<Mutex<HashMap<String, (Value, Instant)>>> = OnceLock::new();
+
+fn get_pending() -> &'static Mutex<HashMap<String, (Value, Instant)>> {
+    PENDING.get_or_init(|| {
+        // Flush loop: write stores that have been quiet for the debounce period
+        std::thread::spawn(|| loop {
+            std::thread::sleep(Duration::from_millis(500));
+            let due: Vec<(String, Value)> = {
+                let mut pending = get_pending().lock();
+                let names: Vec<String> = pending
+                    .iter()
+                    .filter(|(_, (_, changed))| changed.elapsed() >= AUTOSAVE_DEBOUNCE)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                names
+                    .into_iter()
+                    .filter_map(|name| pending.remove(&name).map(|(value, _)| (name, value)))
+                    .collect()
+            };
+            for (name, value) in due {
+                if let Err(e) = save_json(&name, &value) {
+                    eprintln!("Warning: Autosave of {} failed: {}", name, e);
+                }
+            }
+        });
+        Mutex::new(HashMap::new())
+    })
+}
+
+/// Record the app data directory (called once from setup)
+pub fn init(dir: PathBuf) {
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Warning: Failed to create app data dir {:?}: {}", dir, e);
+    }
+    let _ = DATA_DIR.set(dir);
+}
+
+/// The app data directory
+pub fn data_dir() -> Result<PathBuf, String> {
+    DATA_DIR
+        .get()
+        .cloned()
+        .ok_or_else(|| "App data directory not initialized".to_string())
+}
+
+fn store_path(name: &str) -> Result<PathBuf, String> {
+    Ok(data_dir()?.join(format!("{}.json", name)))
+}
+
+/// Load a store, falling back to its default when missing or unreadable
+pub fn load_json<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = match store_path(name) {
+        Ok(p) => p,
+        Err(_) => return T::default(),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to parse {:?}: {}", path, e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+fn backup_path(name: &str, generation: u32) -> Result<PathBuf, String> {
+    Ok(data_dir()?
+        .join("backups")
+        .join(format!("{}.{}.json", name, generation)))
+}
+
+/// Shift existing backups up one generation and move the current file to generation 1
+fn rotate_backups(name: &str) -> Result<(), String> {
+    let current = store_path(name)?;
+    if !current.exists() {
+        return Ok(());
+    }
+    let backups_dir = data_dir()?.join("backups");
+    std::fs::create_dir_all(&backups_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", backups_dir, e))?;
+
+    for generation in (1..KEEP_BACKUPS).rev() {
+        let from = backup_path(name, generation)?;
+        if from.exists() {
+            let _ = std::fs::rename(&from, backup_path(name, generation + 1)?);
+        }
+    }
+    std::fs::copy(&current, backup_path(name, 1)?)
+        .map_err(|e| format!("Failed to back up {:?}: {}", current, e))?;
+    Ok(())
+}
+
+/// Write a store atomically (write to a temp file, then rename),
+/// keeping the previous version as a backup
+pub fn save_json<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    if let Err(e) = rotate_backups(name) {
+        eprintln!("Warning: {}", e);
+    }
+    write_json(name, value)
+}
+
+/// Write a store atomically without keeping backups (for caches that can be rebuilt)
+pub fn write_json<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let path = store_path(name)?;
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, text).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to replace {:?}: {}", path, e))
+}
+
+/// Queue a debounced write; rapid successive changes result in a single write
+pub fn schedule_save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let value = serde_json::to_value(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    get_pending()
+        .lock()
+        .insert(name.to_string(), (value, Instant::now()));
+    Ok(())
+}
+
+/// Write all pending debounced saves immediately (called on exit)
+pub fn flush_pending() {
+    let pending: Vec<(String, Value)> = get_pending()
+        .lock()
+        .drain()
+        .map(|(name, (value, _))| (name, value))
+        .collect();
+    for (name, value) in pending {
+        if let Err(e) = save_json(&name, &value) {
+            eprintln!("Warning: Failed to save {}: {}", name, e);
+        }
+    }
+}
+
+/// List the backups kept for a store, most recent first
+pub fn list_backups(name: &str) -> Result<Vec<BackupInfo>, String> {
+    let mut backups = Vec::new();
+    for generation in 1..=KEEP_BACKUPS {
+        let path = backup_path(name, generation)?;
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            backups.push(BackupInfo {
+                generation,
+                path: path.display().to_string(),
+                modified,
+            });
+        }
+    }
+    Ok(backups)
+}
+
+/// Replace a store with one of its backups. The replaced version is itself
+/// backed up first, so a restore can be undone by restoring generation 1.
+pub fn restore_backup(name: &str, generation: u32) -> Result<(), String> {
+    let backup = backup_path(name, generation)?;
+    let text = std::fs::read_to_string(&backup)
+        .map_err(|e| format!("No backup {} for {}: {}", generation, name, e))?;
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Backup {:?} is not valid JSON: {}", backup, e))?;
+    // Drop any pending write so it doesn't clobber the restored version
+    get_pending().lock().remove(name);
+    save_json(name, &value)
+}
+
+/// Seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,208 @@
+//! Per-stream TCP graphs (Wireshark's "TCP Stream Graphs"): time-sequence,
+//! round-trip time, throughput and receive window, one series per direction.
+//!
+//! Built from per-frame `tcp` and `tcp.analysis` fields, so the values match
+//! what the packet details show (relative sequence numbers, calculated
+//! window sizes).
+
+use serde::{Deserialize, Serialize};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Most frames of a stream read for a graph
+const GRAPH_SCAN_LIMIT: u32 = 1_000_000;
+
+/// Segments averaged over for each throughput point, as in Wireshark
+const THROUGHPUT_SEGMENTS: usize = 20;
+
+/// Fields read per frame, in the order of `Segment`'s fields
+const FIELDS: &[&str] = &[
+    "frame.number",
+    "frame.time_relative",
+    "ip.src",
+    "ipv6.src",
+    "tcp.srcport",
+    "tcp.seq",
+    "tcp.len",
+    "tcp.analysis.ack_rtt",
+    "tcp.window_size",
+];
+
+/// Kind of TCP stream graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpGraphKind {
+    /// Relative sequence number of each data segment (Stevens)
+    TimeSequence,
+    /// Round-trip time in milliseconds, from the ACKs acknowledging the data
+    Rtt,
+    /// Bits per second, averaged over the last 20 data segments
+    Throughput,
+    /// Advertised receive window in bytes
+    WindowSize,
+}
+
+/// A value at a point in the capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphPoint {
+    pub frame: u32,
+    /// Seconds since the start of the capture
+    pub time: f64,
+    pub value: f64,
+}
+
+/// The points of one direction of the stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSeries {
+    /// `source:port → destination:port` of the data the series describes
+    pub name: String,
+    pub points: Vec<GraphPoint>,
+}
+
+/// A graph of one TCP stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpStreamGraph {
+    pub stream: u32,
+    pub kind: TcpGraphKind,
+    pub series: Vec<GraphSeries>,
+}
+
+/// One frame of the stream
+struct Segment {
+    frame: u32,
+    time: f64,
+    /// Sending endpoint, `address:port`
+    sender: String,
+    seq: Option<f64>,
+    len: u64,
+    ack_rtt: Option<f64>,
+    window: Option<f64>,
+}
+
+impl Segment {
+    fn from_row(row: &[String]) -> Option<Segment> {
+        let value = |i: usize| {
+            row.get(i)
+                .and_then(|v| v.split(',').next())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let number = |i: usize| value(i).and_then(|v| v.parse::<f64>().ok());
+        let address = value(2).or(value(3))?;
+        let sender = if address.contains(':') {
+            format!("[{}]:{}", address, value(4)?)
+        } else {
+            format!("{}:{}", address, value(4)?)
+        };
+        Some(Segment {
+            frame: value(0)?.parse().ok()?,
+            time: number(1)?,
+            sender,
+            seq: number(5),
+            len: value(6).and_then(|v| v.parse().ok()).unwrap_or(0),
+            ack_rtt: number(7),
+            window: number(8),
+        })
+    }
+}
+
+/// Bits per second over the last `THROUGHPUT_SEGMENTS` data segments
+fn throughput(segments: &[&Segment]) -> Vec<GraphPoint> {
+    let mut points = Vec::new();
+    for end in 1..segments.len() {
+        let start = end.saturating_sub(THROUGHPUT_SEGMENTS - 1);
+        let elapsed = segments[end].time - segments[start].time;
+        if elapsed <= 0.0 {
+            continue;
+        }
+        // The first segment opens the window; its bytes were sent before it
+        let bytes: u64 = segments[start + 1..=end].iter().map(|s| s.len).sum();
+        points.push(GraphPoint {
+            frame: segments[end].frame,
+            time: segments[end].time,
+            value: bytes as f64 * 8.0 / elapsed,
+        });
+    }
+    points
+}
+
+/// The points of `kind` for data sent by `sender`
+fn series_points(segments: &[Segment], sender: &str, kind: TcpGraphKind) -> Vec<GraphPoint> {
+    let point = |s: &Segment, value: f64| GraphPoint {
+        frame: s.frame,
+        time: s.time,
+        value,
+    };
+    let sent = || segments.iter().filter(move |s| s.sender == sender);
+    match kind {
+        TcpGraphKind::TimeSequence => sent()
+            .filter(|s| s.len > 0)
+            .filter_map(|s| Some(point(s, s.seq?)))
+            .collect(),
+        // The RTT of this direction's data is measured on the peer's ACKs
+        TcpGraphKind::Rtt => segments
+            .iter()
+            .filter(|s| s.sender != sender)
+            .filter_map(|s| Some(point(s, s.ack_rtt? * 1000.0)))
+            .collect(),
+        TcpGraphKind::Throughput => {
+            let data: Vec<&Segment> = sent().filter(|s| s.len > 0).collect();
+            throughput(&data)
+        }
+        // The window a side advertises limits what its peer may send
+        TcpGraphKind::WindowSize => segments
+            .iter()
+            .filter(|s| s.sender != sender)
+            .filter_map(|s| Some(point(s, s.window?)))
+            .collect(),
+    }
+}
+
+/// Compute a graph of a TCP stream (`tcp.stream` number). `filter` is an extra
+/// display filter frames must also match (e.g. the ignored-frame exclusion).
+pub async fn tcp_stream_graph(
+    client: &SharkdClient,
+    stream: u32,
+    kind: TcpGraphKind,
+    filter: Option<&str>,
+) -> Result<TcpStreamGraph, String> {
+    let stream_filter = match filter {
+        Some(filter) => format!("tcp.stream eq {} && ({})", stream, filter),
+        None => format!("tcp.stream eq {}", stream),
+    };
+    let rows = client
+        .column_values(&stream_filter, FIELDS, GRAPH_SCAN_LIMIT)
+        .await?;
+    let segments: Vec<Segment> = rows.iter().filter_map(|r| Segment::from_row(r)).collect();
+    if segments.is_empty() {
+        return Err(format!("TCP stream {} not found", stream));
+    }
+
+    // The side that sent the first frame (usually the client) comes first
+    let mut endpoints: Vec<&str> = Vec::new();
+    for segment in &segments {
+        if !endpoints.contains(&segment.sender.as_str()) {
+            endpoints.push(&segment.sender);
+        }
+    }
+    let series = endpoints
+        .iter()
+        .map(|&sender| {
+            let receiver = endpoints
+                .iter()
+                .find(|&&e| e != sender)
+                .copied()
+                .unwrap_or("?");
+            GraphSeries {
+                name: format!("{} → {}", sender, receiver),
+                points: series_points(&segments, sender, kind),
+            }
+        })
+        .collect();
+
+    Ok(TcpStreamGraph {
+        stream,
+        kind,
+        series,
+    })
+}
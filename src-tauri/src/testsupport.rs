@@ -0,0 +1,59 @@
+//! Integration test support, built only with the `testsupport` feature.
+//!
+//! Tests generate small captures with `pcap`, run them through the
+//! `mock-sharkd` binary (see `mock_sharkd`) in place of a real sharkd, and
+//! drive `SharkdClient`, the stats taps and the HTTP bridge against it, so
+//! none of them need Wireshark installed:
+//!
+//! Setting `PACKETPILOT_SHARKD_CHAOS` (see `ChaosSharkd`) before starting a
+//! client injects delays, lost responses and sharkd exits into its requests.
+//!
+//! ```ignore
+//! let capture = dir.join("sample.pcap");
+//! pcap::write_pcap(&capture, &pcap::sample_capture())?;
+//! let client = install_client(mock_client(Path::new(env!("CARGO_BIN_EXE_mock-sharkd"))).await?);
+//! client.load(capture.to_str().unwrap()).await?;
+//! let stats = client.capture_stats(None).await?;
+//! ```
+
+pub mod mock_sharkd;
+pub mod pcap;
+
+use std::path::Path;
+use std::sync::Arc;
+
+pub use crate::anonymize::AnonymizationPolicy;
+use crate::sessions;
+pub use crate::sharkd_chaos::{ChaosSharkd, Fault, CHAOS_ENV};
+pub use crate::sharkd_client::{
+    parse as sharkd_parse, Conversation, Endpoint, ProtocolNode, SharkdClient, StatsNode,
+};
+
+/// Start a client talking to `program` (normally the `mock-sharkd` binary)
+/// instead of the sharkd found on this machine
+pub async fn mock_client(program: &Path) -> Result<SharkdClient, String> {
+    SharkdClient::spawn(program).await
+}
+
+/// Start a client on the sharkd found on this machine, as the app does
+pub async fn system_client() -> Result<SharkdClient, String> {
+    SharkdClient::new().await
+}
+
+/// Make `client` the default session's, where commands and the HTTP bridge
+/// look for it
+pub fn install_client(client: SharkdClient) -> Arc<SharkdClient> {
+    let client = Arc::new(client);
+    sessions::replace(sessions::DEFAULT_SESSION, client.clone());
+    client
+}
+
+/// Set the default session's anonymization policy, as the settings panel does
+pub fn set_anonymization_policy(policy: AnonymizationPolicy) -> Result<(), String> {
+    crate::anonymize::set_policy(sessions::DEFAULT_SESSION, policy)
+}
+
+/// The HTTP bridge's routes, for driving its handlers without binding a port
+pub fn bridge_router() -> axum::Router {
+    crate::http_bridge::router()
+}
@@ -0,0 +1,793 @@
+//! A stand-in for `sharkd -` speaking the same newline-delimited JSON-RPC
+//! over stdio, so `SharkdClient` can be exercised without Wireshark.
+//!
+//! It reads classic pcap files (as written by `pcap::write_pcap`) and
+//! dissects just enough of them (Ethernet, 802.1Q, IPv4, TCP, UDP, SCTP, DNS,
+//! HTTP) to answer `status`, `info`, `load`, `frames`, `check`, `intervals`,
+//! `bye` and the `phs`, `conv:IPv4`, `conv:TCP`, `conv:UDP` and `endpt:IPv4`
+//! taps. Display filters are limited to protocol names, field presence,
+//! `field == value` and `field != value`, combined with `&&`, `||`, `!` and
+//! parentheses. Other methods, or fixed results for any method, come from a
+//! JSON file of `{ "method": result }` named by
+//! `PACKETPILOT_MOCK_SHARKD_RESPONSES`.
+
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use super::pcap::{self, Packet, ETHERTYPE_VLAN, IPPROTO_SCTP, IPPROTO_TCP, IPPROTO_UDP};
+
+/// Environment variable naming the canned responses file
+pub const RESPONSES_ENV: &str = "PACKETPILOT_MOCK_SHARKD_RESPONSES";
+
+/// Protocols the mock dissects, usable as display filters
+const PROTOCOLS: &[&str] = &[
+    "frame", "eth", "vlan", "ip", "tcp", "udp", "sctp", "dns", "http",
+];
+
+/// Protocols accepted in display filters that generated captures never
+/// carry, so the app's overlay and mobile scans find nothing rather than fail
+const UNSEEN_PROTOCOLS: &[&str] = &["ipv6", "gre", "vxlan", "gtp", "gtpv2", "diameter", "e212"];
+
+const COLUMNS: &[&str] = &[
+    "No.",
+    "Time",
+    "Source",
+    "Destination",
+    "Protocol",
+    "Length",
+    "Info",
+];
+
+/// JSON-RPC error codes sharkd uses
+const ERR_METHOD_NOT_FOUND: i64 = -32601;
+const ERR_INVALID_PARAMS: i64 = -32602;
+const ERR_LOAD: i64 = -2001;
+
+/// What the mock knows about one frame
+#[derive(Debug, Clone, Default)]
+struct Dissected {
+    /// Protocol stack, outermost first
+    protocols: Vec<&'static str>,
+    src: String,
+    dst: String,
+    /// (transport, source port, destination port)
+    ports: Option<(&'static str, u16, u16)>,
+    vlan_id: Option<u16>,
+    ip_proto: Option<u8>,
+    sctp_chunk: Option<u8>,
+    info: String,
+}
+
+impl Dissected {
+    fn top_protocol(&self) -> String {
+        self.protocols.last().unwrap_or(&"frame").to_uppercase()
+    }
+
+    /// Value of a custom column field, empty when the frame doesn't have it
+    fn field(&self, number: usize, packet: &Packet, field: &str) -> String {
+        let port = |transport: &str, source: bool| match self.ports {
+            Some((t, s, d)) if t == transport => (if source { s } else { d }).to_string(),
+            _ => String::new(),
+        };
+        let number_of = |value: Option<u8>| value.map(|v| v.to_string()).unwrap_or_default();
+        match field {
+            "frame.number" => number.to_string(),
+            "frame.len" => packet.data.len().to_string(),
+            "ip.src" if self.protocols.contains(&"ip") => self.src.clone(),
+            "ip.dst" if self.protocols.contains(&"ip") => self.dst.clone(),
+            "ip.addr" if self.protocols.contains(&"ip") => format!("{},{}", self.src, self.dst),
+            "ip.proto" => number_of(self.ip_proto),
+            "vlan.id" => self.vlan_id.map(|id| id.to_string()).unwrap_or_default(),
+            "tcp.srcport" => port("tcp", true),
+            "tcp.dstport" => port("tcp", false),
+            "udp.srcport" => port("udp", true),
+            "udp.dstport" => port("udp", false),
+            "sctp.srcport" => port("sctp", true),
+            "sctp.dstport" => port("sctp", false),
+            "sctp.chunk_type" => number_of(self.sctp_chunk),
+            "_ws.col.protocol" => self.top_protocol(),
+            "_ws.col.info" => self.info.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+fn mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn tcp_flags(flags: u8) -> String {
+    let names: Vec<&str> = [
+        (pcap::TCP_SYN, "SYN"),
+        (pcap::TCP_FIN, "FIN"),
+        (pcap::TCP_PSH, "PSH"),
+        (pcap::TCP_ACK, "ACK"),
+    ]
+    .iter()
+    .filter(|(bit, _)| flags & bit != 0)
+    .map(|(_, name)| *name)
+    .collect();
+    names.join(", ")
+}
+
+fn dissect(packet: &Packet) -> Dissected {
+    let data = &packet.data;
+    let mut d = Dissected {
+        protocols: vec!["frame"],
+        ..Default::default()
+    };
+    if data.len() < 14 {
+        return d;
+    }
+    d.protocols.push("eth");
+    d.src = mac(&data[6..12]);
+    d.dst = mac(&data[0..6]);
+    d.info = "Ethernet II".to_string();
+
+    let mut ethertype = u16::from_be_bytes([data[12], data[13]]);
+    let mut ip = &data[14..];
+    if ethertype == ETHERTYPE_VLAN && ip.len() >= 4 {
+        d.protocols.push("vlan");
+        d.vlan_id = Some(u16::from_be_bytes([ip[0], ip[1]]) & 0x0fff);
+        ethertype = u16::from_be_bytes([ip[2], ip[3]]);
+        ip = &ip[4..];
+    }
+    if ethertype != 0x0800 || ip.len() < 20 {
+        return d;
+    }
+    d.protocols.push("ip");
+    d.src = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]).to_string();
+    d.dst = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]).to_string();
+    d.info = "IPv4".to_string();
+    d.ip_proto = Some(ip[9]);
+
+    let header_len = usize::from(ip[0] & 0x0f) * 4;
+    let segment = match ip.get(header_len..) {
+        Some(segment) if segment.len() >= 8 => segment,
+        _ => return d,
+    };
+    let sport = u16::from_be_bytes([segment[0], segment[1]]);
+    let dport = u16::from_be_bytes([segment[2], segment[3]]);
+
+    match ip[9] {
+        IPPROTO_TCP if segment.len() >= 20 => {
+            d.protocols.push("tcp");
+            d.ports = Some(("tcp", sport, dport));
+            let seq = u32::from_be_bytes([segment[4], segment[5], segment[6], segment[7]]);
+            let offset = usize::from(segment[12] >> 4) * 4;
+            let payload = segment.get(offset..).unwrap_or_default();
+            d.info = format!(
+                "{} \u{2192} {} [{}] Seq={} Len={}",
+                sport,
+                dport,
+                tcp_flags(segment[13]),
+                seq,
+                payload.len()
+            );
+            if !payload.is_empty() && (sport == 80 || dport == 80) {
+                d.protocols.push("http");
+                let text = String::from_utf8_lossy(payload);
+                d.info = text.lines().next().unwrap_or_default().to_string();
+            }
+        }
+        IPPROTO_UDP => {
+            d.protocols.push("udp");
+            d.ports = Some(("udp", sport, dport));
+            d.info = format!("{} \u{2192} {} Len={}", sport, dport, segment.len() - 8);
+            if (sport == 53 || dport == 53) && segment.len() >= 12 {
+                d.protocols.push("dns");
+                let response = segment[10] & 0x80 != 0;
+                d.info = if response {
+                    "Standard query response".to_string()
+                } else {
+                    "Standard query".to_string()
+                };
+            }
+        }
+        IPPROTO_SCTP if segment.len() >= 16 => {
+            d.protocols.push("sctp");
+            d.ports = Some(("sctp", sport, dport));
+            d.sctp_chunk = Some(segment[12]);
+            d.info = format!("{} \u{2192} {} Chunk={}", sport, dport, segment[12]);
+        }
+        _ => {}
+    }
+    d
+}
+
+/// A display filter, in the subset the mock understands
+#[derive(Debug)]
+enum Filter {
+    /// The protocol is in the frame's stack
+    Protocol(&'static str),
+    /// The frame has the field
+    Has(String),
+    /// Some occurrence of the field equals the value, or (`equal: false`)
+    /// the field is present and none does
+    Compare {
+        field: String,
+        value: String,
+        equal: bool,
+    },
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    fn matches(&self, number: usize, packet: &Packet, d: &Dissected) -> bool {
+        match self {
+            Filter::Protocol(protocol) => d.protocols.contains(protocol),
+            Filter::Has(field) => !d.field(number, packet, field).is_empty(),
+            Filter::Compare {
+                field,
+                value,
+                equal,
+            } => {
+                let values = d.field(number, packet, field);
+                let mut occurrences = values.split(',').filter(|v| !v.is_empty()).peekable();
+                if *equal {
+                    occurrences.any(|v| v == value)
+                } else {
+                    occurrences.peek().is_some() && occurrences.all(|v| v != value)
+                }
+            }
+            Filter::Not(inner) => !inner.matches(number, packet, d),
+            Filter::And(a, b) => a.matches(number, packet, d) && b.matches(number, packet, d),
+            Filter::Or(a, b) => a.matches(number, packet, d) || b.matches(number, packet, d),
+        }
+    }
+}
+
+/// Split a display filter into names, values, operators and parentheses
+fn tokenize(filter: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = filter.trim_start();
+    while !rest.is_empty() {
+        let len = if ["&&", "||", "==", "!="]
+            .iter()
+            .any(|op| rest.starts_with(op))
+        {
+            2
+        } else if rest.starts_with(['(', ')', '!']) {
+            1
+        } else {
+            rest.find(|c: char| c.is_whitespace() || "()!=&|".contains(c))
+                .unwrap_or(rest.len())
+                .max(1)
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+/// Recursive descent over filter tokens: `||` binds loosest, then `&&`,
+/// then `!`
+struct FilterParser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> FilterParser<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.tokens.get(self.pos) == Some(&token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.eat("||") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.unary()?;
+        while self.eat("&&") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        match self.next() {
+            Some("!") => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some("(") => {
+                let filter = self.or()?;
+                if !self.eat(")") {
+                    return Err("Missing )".to_string());
+                }
+                Ok(filter)
+            }
+            Some(name) => self.term(name),
+            None => Err("Unexpected end of filter".to_string()),
+        }
+    }
+
+    fn term(&mut self, name: &str) -> Result<Filter, String> {
+        if let Some(protocol) = PROTOCOLS
+            .iter()
+            .chain(UNSEEN_PROTOCOLS)
+            .find(|p| **p == name)
+        {
+            return Ok(Filter::Protocol(protocol));
+        }
+        let known = name.split_once('.').map_or(false, |(protocol, _)| {
+            protocol == "_ws"
+                || PROTOCOLS.contains(&protocol)
+                || UNSEEN_PROTOCOLS.contains(&protocol)
+        });
+        if !known {
+            return Err(format!("\"{}\" is not supported by the mock sharkd", name));
+        }
+        let equal = if self.eat("==") {
+            true
+        } else if self.eat("!=") {
+            false
+        } else {
+            return Ok(Filter::Has(name.to_string()));
+        };
+        let value = self
+            .next()
+            .ok_or_else(|| format!("Missing value after {}", name))?;
+        Ok(Filter::Compare {
+            field: name.to_string(),
+            value: value.trim_matches('"').to_string(),
+            equal,
+        })
+    }
+}
+
+/// Parse a display filter, None selecting all frames
+fn parse_filter(filter: &str) -> Result<Option<Filter>, String> {
+    let mut parser = FilterParser {
+        tokens: tokenize(filter),
+        pos: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Ok(None);
+    }
+    let parsed = parser.or()?;
+    match parser.next() {
+        None => Ok(Some(parsed)),
+        Some(token) => Err(format!("Unexpected \"{}\" in filter", token)),
+    }
+}
+
+/// Conversation or endpoint counters
+#[derive(Default)]
+struct Counters {
+    txf: u64,
+    txb: u64,
+    rxf: u64,
+    rxb: u64,
+    start: f64,
+    stop: f64,
+}
+
+/// Mock sharkd state: the loaded capture and canned responses
+#[derive(Default)]
+pub struct MockSharkd {
+    packets: Vec<(Packet, Dissected)>,
+    filename: Option<String>,
+    canned: BTreeMap<String, Value>,
+}
+
+impl MockSharkd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `method` with `result` regardless of the built-in handling
+    pub fn set_response(&mut self, method: &str, result: Value) {
+        self.canned.insert(method.to_string(), result);
+    }
+
+    /// Load canned responses from the file named by `RESPONSES_ENV`, if set
+    pub fn load_canned_from_env(&mut self) -> Result<(), String> {
+        let path = match std::env::var_os(RESPONSES_ENV) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let responses: BTreeMap<String, Value> = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid canned responses in {:?}: {}", path, e))?;
+        self.canned.extend(responses);
+        Ok(())
+    }
+
+    /// Load a capture as if by the `load` method
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        self.packets = pcap::read_pcap(path)?
+            .into_iter()
+            .map(|p| {
+                let d = dissect(&p);
+                (p, d)
+            })
+            .collect();
+        self.filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    fn first_ts(&self) -> f64 {
+        self.packets.first().map_or(0.0, |(p, _)| p.ts)
+    }
+
+    /// Frames matching a filter, with their 1-based numbers
+    fn matching(&self, params: &Value) -> Result<Vec<(usize, &Packet, &Dissected)>, (i64, String)> {
+        let filter = params.get("filter").and_then(Value::as_str).unwrap_or("");
+        let filter = parse_filter(filter).map_err(|e| (ERR_INVALID_PARAMS, e))?;
+        Ok(self
+            .packets
+            .iter()
+            .enumerate()
+            .filter(|(i, (p, d))| filter.as_ref().map_or(true, |f| f.matches(i + 1, p, d)))
+            .map(|(i, (p, d))| (i + 1, p, d))
+            .collect())
+    }
+
+    fn status(&self) -> Value {
+        let duration = self.packets.last().map_or(0.0, |(p, _)| p.ts) - self.first_ts();
+        let mut status = json!({
+            "frames": self.packets.len(),
+            "duration": duration,
+            "columns": COLUMNS,
+        });
+        if let Some(name) = &self.filename {
+            status["filename"] = json!(name);
+        }
+        status
+    }
+
+    fn info(&self) -> Value {
+        let taps: Vec<Value> = ["phs", "conv:IPv4", "conv:TCP", "conv:UDP", "endpt:IPv4"]
+            .iter()
+            .map(|tap| json!({ "name": tap, "tap": tap }))
+            .collect();
+        json!({
+            "version": "mock",
+            "columns": COLUMNS.iter().map(|c| json!({ "name": c })).collect::<Vec<_>>(),
+            "taps": taps,
+            "follow": [],
+            "eo": [],
+        })
+    }
+
+    fn frames(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let skip = params.get("skip").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let limit = params
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(usize::MAX, |l| l as usize);
+        let custom: Vec<String> = (0..)
+            .map_while(|i| params.get(format!("column{}", i)).and_then(Value::as_str))
+            .map(|spec| spec.split(':').next().unwrap_or_default().to_string())
+            .collect();
+
+        let first_ts = self.first_ts();
+        let frames: Vec<Value> = self
+            .matching(params)?
+            .into_iter()
+            .skip(skip)
+            .take(limit)
+            .map(|(number, packet, d)| {
+                let columns: Vec<String> = if custom.is_empty() {
+                    vec![
+                        number.to_string(),
+                        format!("{:.6}", packet.ts - first_ts),
+                        d.src.clone(),
+                        d.dst.clone(),
+                        d.top_protocol(),
+                        packet.data.len().to_string(),
+                        d.info.clone(),
+                    ]
+                } else {
+                    custom
+                        .iter()
+                        .map(|field| d.field(number, packet, field))
+                        .collect()
+                };
+                json!({ "c": columns, "num": number, "bg": "ffffff", "fg": "000000" })
+            })
+            .collect();
+        Ok(Value::Array(frames))
+    }
+
+    fn check(&self, params: &Value) -> Value {
+        let filter = params.get("filter").and_then(Value::as_str).unwrap_or("");
+        match parse_filter(filter) {
+            Ok(_) => json!({ "status": "OK" }),
+            Err(e) => json!({ "err": e }),
+        }
+    }
+
+    fn intervals(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let interval = params
+            .get("interval")
+            .and_then(Value::as_u64)
+            .unwrap_or(1000)
+            .max(1) as f64
+            / 1000.0;
+        let first_ts = self.first_ts();
+        let mut buckets: BTreeMap<u64, (u64, u64)> = BTreeMap::new();
+        let (mut frames, mut bytes) = (0, 0);
+        for (_, packet, _) in self.matching(params)? {
+            let index = ((packet.ts - first_ts) / interval).floor() as u64;
+            let bucket = buckets.entry(index).or_default();
+            bucket.0 += 1;
+            bucket.1 += packet.data.len() as u64;
+            frames += 1;
+            bytes += packet.data.len() as u64;
+        }
+        Ok(json!({
+            "intervals": buckets
+                .iter()
+                .map(|(index, (f, b))| json!([index, f, b]))
+                .collect::<Vec<_>>(),
+            "last": buckets.keys().next_back().copied().unwrap_or(0),
+            "frames": frames,
+            "bytes": bytes,
+        }))
+    }
+
+    /// Protocol hierarchy, nested by protocol stack
+    fn phs(frames: &[(usize, &Packet, &Dissected)]) -> Value {
+        fn node(protocol: &str, frames: &[(usize, &Packet, &Dissected)], depth: usize) -> Value {
+            let mut children: BTreeMap<&str, Vec<(usize, &Packet, &Dissected)>> = BTreeMap::new();
+            for frame in frames {
+                if let Some(child) = frame.2.protocols.get(depth + 1) {
+                    children.entry(*child).or_default().push(*frame);
+                }
+            }
+            json!({
+                "proto": protocol,
+                "frames": frames.len(),
+                "bytes": frames.iter().map(|(_, p, _)| p.data.len()).sum::<usize>(),
+                "protos": children
+                    .iter()
+                    .map(|(child, frames)| node(child, frames, depth + 1))
+                    .collect::<Vec<_>>(),
+            })
+        }
+        if frames.is_empty() {
+            return json!([]);
+        }
+        json!([node("frame", frames, 0)])
+    }
+
+    /// Conversations over `transport`, or between addresses for `ip`
+    fn conversations(transport: &str, frames: &[(usize, &Packet, &Dissected)]) -> Value {
+        let mut convs: BTreeMap<(String, u16, String, u16), Counters> = BTreeMap::new();
+        for (_, packet, d) in frames {
+            let (sport, dport) = match d.ports {
+                Some((t, sport, dport)) if t == transport => (sport, dport),
+                _ if transport == "ip" && d.protocols.contains(&"ip") => (0, 0),
+                _ => continue,
+            };
+            let forward = (d.src.clone(), sport, d.dst.clone(), dport);
+            let reverse = (d.dst.clone(), dport, d.src.clone(), sport);
+            let len = packet.data.len() as u64;
+            if let Some(conv) = convs.get_mut(&reverse) {
+                conv.rxf += 1;
+                conv.rxb += len;
+                conv.stop = packet.ts;
+                continue;
+            }
+            let conv = convs.entry(forward).or_insert_with(|| Counters {
+                start: packet.ts,
+                ..Default::default()
+            });
+            conv.txf += 1;
+            conv.txb += len;
+            conv.stop = packet.ts;
+        }
+        let first_ts = frames.first().map_or(0.0, |(_, p, _)| p.ts);
+        Value::Array(
+            convs
+                .into_iter()
+                .map(|((saddr, sport, daddr, dport), c)| {
+                    if transport == "ip" {
+                        return json!({
+                            "saddr": saddr,
+                            "daddr": daddr,
+                            "txf": c.txf,
+                            "txb": c.txb,
+                            "rxf": c.rxf,
+                            "rxb": c.rxb,
+                            "start": c.start - first_ts,
+                            "stop": c.stop - first_ts,
+                            "filter": format!("ip.addr=={} && ip.addr=={}", saddr, daddr),
+                        });
+                    }
+                    json!({
+                        "saddr": saddr,
+                        "daddr": daddr,
+                        "sport": sport.to_string(),
+                        "dport": dport.to_string(),
+                        "txf": c.txf,
+                        "txb": c.txb,
+                        "rxf": c.rxf,
+                        "rxb": c.rxb,
+                        "start": c.start - first_ts,
+                        "stop": c.stop - first_ts,
+                        "filter": format!(
+                            "ip.addr=={} && {t}.port=={} && ip.addr=={} && {t}.port=={}",
+                            saddr, sport, daddr, dport, t = transport
+                        ),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn endpoints(frames: &[(usize, &Packet, &Dissected)]) -> Value {
+        let mut hosts: BTreeMap<String, Counters> = BTreeMap::new();
+        for (_, packet, d) in frames {
+            if !d.protocols.contains(&"ip") {
+                continue;
+            }
+            let len = packet.data.len() as u64;
+            let src = hosts.entry(d.src.clone()).or_default();
+            src.txf += 1;
+            src.txb += len;
+            let dst = hosts.entry(d.dst.clone()).or_default();
+            dst.rxf += 1;
+            dst.rxb += len;
+        }
+        Value::Array(
+            hosts
+                .into_iter()
+                .map(|(host, c)| {
+                    json!({
+                        "host": host,
+                        "txf": c.txf,
+                        "txb": c.txb,
+                        "rxf": c.rxf,
+                        "rxb": c.rxb,
+                        "filter": format!("ip.addr=={}", host),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn taps(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let frames = self.matching(params)?;
+        let mut taps = Vec::new();
+        let names = (0..).map_while(|i| params.get(format!("tap{}", i)).and_then(Value::as_str));
+        for tap in names {
+            let result = match tap {
+                "phs" => json!({ "tap": tap, "type": "phs", "protos": Self::phs(&frames) }),
+                "conv:IPv4" => {
+                    json!({ "tap": tap, "type": "conv", "convs": Self::conversations("ip", &frames) })
+                }
+                "conv:TCP" => {
+                    json!({ "tap": tap, "type": "conv", "convs": Self::conversations("tcp", &frames) })
+                }
+                "conv:UDP" => {
+                    json!({ "tap": tap, "type": "conv", "convs": Self::conversations("udp", &frames) })
+                }
+                "endpt:IPv4" => {
+                    json!({ "tap": tap, "type": "host", "hosts": Self::endpoints(&frames) })
+                }
+                _ => {
+                    return Err((
+                        ERR_INVALID_PARAMS,
+                        format!("Tap {} is not supported by the mock sharkd", tap),
+                    ))
+                }
+            };
+            taps.push(result);
+        }
+        Ok(json!({ "taps": taps }))
+    }
+
+    /// Handle one request, returning its result or a JSON-RPC error
+    pub fn handle(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if let Some(result) = self.canned.get(method) {
+            return Ok(result.clone());
+        }
+        match method {
+            "status" => Ok(self.status()),
+            "info" => Ok(self.info()),
+            "load" => {
+                let file = params
+                    .get("file")
+                    .and_then(Value::as_str)
+                    .ok_or((ERR_INVALID_PARAMS, "Missing file".to_string()))?;
+                self.load(Path::new(file)).map_err(|e| (ERR_LOAD, e))?;
+                Ok(json!({ "status": "OK" }))
+            }
+            "frames" => self.frames(params),
+            "check" => Ok(self.check(params)),
+            "intervals" => self.intervals(params),
+            "tap" => self.taps(params),
+            "bye" => std::process::exit(0),
+            _ => Err((
+                ERR_METHOD_NOT_FOUND,
+                format!("The method \"{}\" is not supported", method),
+            )),
+        }
+    }
+
+    /// Handle one request line, returning the response line (without newline)
+    pub fn handle_line(&mut self, line: &str) -> String {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+                })
+                .to_string()
+            }
+        };
+        let id = request.get("id").cloned().unwrap_or(json!(0));
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = request
+            .get("params")
+            .cloned()
+            .unwrap_or_else(|| Value::Object(Map::new()));
+
+        let mut response = json!({ "jsonrpc": "2.0", "id": id });
+        match self.handle(method, &params) {
+            Ok(result) => response["result"] = result,
+            Err((code, message)) => {
+                response["error"] = json!({ "code": code, "message": message });
+            }
+        }
+        response.to_string()
+    }
+
+    /// Serve requests line by line until the input closes
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(writer, "{}", self.handle_line(&line))?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Entry point of the `mock-sharkd` binary: serve stdin/stdout like `sharkd -`
+pub fn run() {
+    let mut mock = MockSharkd::new();
+    if let Err(e) = mock.load_canned_from_env() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    // sharkd greets on stderr in stdio mode
+    eprintln!("Hello in child.");
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = mock.serve(stdin.lock(), stdout.lock()) {
+        eprintln!("mock sharkd: {}", e);
+        std::process::exit(1);
+    }
+}
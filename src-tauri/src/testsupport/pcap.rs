@@ -0,0 +1,467 @@
+//! Synthetic captures built in pure Rust: Ethernet/IPv4 frames carrying TCP,
+//! UDP and SCTP, optionally VLAN-tagged, with ready-made TCP handshakes, DNS lookups and HTTP exchanges,
+//! written as classic (libpcap) files that sharkd and the mock both read.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::Path;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const LINKTYPE_ETHERNET: u32 = 1;
+const SNAPLEN: u32 = 65_535;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+pub const IPPROTO_TCP: u8 = 6;
+pub const IPPROTO_UDP: u8 = 17;
+pub const IPPROTO_SCTP: u8 = 132;
+
+pub const TCP_FIN: u8 = 0x01;
+pub const TCP_SYN: u8 = 0x02;
+pub const TCP_PSH: u8 = 0x08;
+pub const TCP_ACK: u8 = 0x10;
+
+pub const SCTP_INIT: u8 = 1;
+pub const SCTP_INIT_ACK: u8 = 2;
+
+/// One captured frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    /// Capture time, Unix seconds
+    pub ts: f64,
+    /// Link-layer (Ethernet) bytes
+    pub data: Vec<u8>,
+}
+
+/// Locally administered MAC derived from an IPv4 address, so each host
+/// keeps one MAC across the capture
+fn mac_for(ip: Ipv4Addr) -> [u8; 6] {
+    let o = ip.octets();
+    [0x02, 0x00, o[0], o[1], o[2], o[3]]
+}
+
+/// Internet checksum over `data`, starting from a partial `sum`
+fn checksum(mut sum: u32, data: &[u8]) -> u16 {
+    for chunk in data.chunks(2) {
+        let word = match chunk {
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+            [hi] => u16::from_be_bytes([*hi, 0]),
+            _ => 0,
+        };
+        sum += u32::from(word);
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Sum of the TCP/UDP pseudo header
+fn pseudo_header_sum(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, len: usize) -> u32 {
+    let s = src.octets();
+    let d = dst.octets();
+    [
+        u16::from_be_bytes([s[0], s[1]]),
+        u16::from_be_bytes([s[2], s[3]]),
+        u16::from_be_bytes([d[0], d[1]]),
+        u16::from_be_bytes([d[2], d[3]]),
+        u16::from(protocol),
+        len as u16,
+    ]
+    .iter()
+    .map(|w| u32::from(*w))
+    .sum()
+}
+
+/// Wrap a transport segment in IPv4 and Ethernet headers
+pub fn ipv4_frame(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, segment: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(14 + 20 + segment.len());
+    frame.extend_from_slice(&mac_for(dst));
+    frame.extend_from_slice(&mac_for(src));
+    frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&((20 + segment.len()) as u16).to_be_bytes());
+    ip[6] = 0x40; // Don't fragment
+    ip[8] = 64;
+    ip[9] = protocol;
+    ip[12..16].copy_from_slice(&src.octets());
+    ip[16..20].copy_from_slice(&dst.octets());
+    let sum = checksum(0, &ip);
+    ip[10..12].copy_from_slice(&sum.to_be_bytes());
+
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(segment);
+    frame
+}
+
+/// An Ethernet/IPv4/TCP frame
+pub fn tcp_frame(
+    src: SocketAddrV4,
+    dst: SocketAddrV4,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut tcp = Vec::with_capacity(20 + payload.len());
+    tcp.extend_from_slice(&src.port().to_be_bytes());
+    tcp.extend_from_slice(&dst.port().to_be_bytes());
+    tcp.extend_from_slice(&seq.to_be_bytes());
+    tcp.extend_from_slice(&ack.to_be_bytes());
+    tcp.push(5 << 4);
+    tcp.push(flags);
+    tcp.extend_from_slice(&65_535u16.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp.extend_from_slice(payload);
+    let sum = checksum(
+        pseudo_header_sum(*src.ip(), *dst.ip(), IPPROTO_TCP, tcp.len()),
+        &tcp,
+    );
+    tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+    ipv4_frame(*src.ip(), *dst.ip(), IPPROTO_TCP, &tcp)
+}
+
+/// An Ethernet/IPv4/UDP frame
+pub fn udp_frame(src: SocketAddrV4, dst: SocketAddrV4, payload: &[u8]) -> Vec<u8> {
+    let mut udp = Vec::with_capacity(8 + payload.len());
+    udp.extend_from_slice(&src.port().to_be_bytes());
+    udp.extend_from_slice(&dst.port().to_be_bytes());
+    udp.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+    let sum = checksum(
+        pseudo_header_sum(*src.ip(), *dst.ip(), IPPROTO_UDP, udp.len()),
+        &udp,
+    );
+    udp[6..8].copy_from_slice(&sum.to_be_bytes());
+    ipv4_frame(*src.ip(), *dst.ip(), IPPROTO_UDP, &udp)
+}
+
+/// An Ethernet/IPv4/SCTP frame with a single, empty chunk. The checksum is
+/// left zero; the mock doesn't verify it.
+pub fn sctp_frame(src: SocketAddrV4, dst: SocketAddrV4, chunk_type: u8) -> Vec<u8> {
+    let mut sctp = Vec::with_capacity(16);
+    sctp.extend_from_slice(&src.port().to_be_bytes());
+    sctp.extend_from_slice(&dst.port().to_be_bytes());
+    sctp.extend_from_slice(&[0, 0, 0, 1]); // Verification tag
+    sctp.extend_from_slice(&[0, 0, 0, 0]);
+    sctp.extend_from_slice(&[chunk_type, 0]);
+    sctp.extend_from_slice(&4u16.to_be_bytes());
+    ipv4_frame(*src.ip(), *dst.ip(), IPPROTO_SCTP, &sctp)
+}
+
+/// Tag an Ethernet frame with an 802.1Q VLAN ID
+pub fn vlan_frame(vlan_id: u16, frame: &[u8]) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(frame.len() + 4);
+    tagged.extend_from_slice(&frame[..12]);
+    tagged.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+    tagged.extend_from_slice(&(vlan_id & 0x0fff).to_be_bytes());
+    tagged.extend_from_slice(&frame[12..]);
+    tagged
+}
+
+/// A TCP connection, tracking both sides' sequence numbers as segments are sent
+#[derive(Debug, Clone)]
+pub struct TcpFlow {
+    pub client: SocketAddrV4,
+    pub server: SocketAddrV4,
+    client_seq: u32,
+    server_seq: u32,
+}
+
+impl TcpFlow {
+    pub fn new(client: SocketAddrV4, server: SocketAddrV4) -> Self {
+        TcpFlow {
+            client,
+            server,
+            client_seq: 1_000,
+            server_seq: 5_000,
+        }
+    }
+
+    /// SYN, SYN/ACK, ACK, 1ms apart from `ts`
+    pub fn handshake(&mut self, ts: f64) -> Vec<Packet> {
+        let syn = tcp_frame(self.client, self.server, self.client_seq, 0, TCP_SYN, &[]);
+        self.client_seq += 1;
+        let syn_ack = tcp_frame(
+            self.server,
+            self.client,
+            self.server_seq,
+            self.client_seq,
+            TCP_SYN | TCP_ACK,
+            &[],
+        );
+        self.server_seq += 1;
+        let ack = tcp_frame(
+            self.client,
+            self.server,
+            self.client_seq,
+            self.server_seq,
+            TCP_ACK,
+            &[],
+        );
+        vec![
+            Packet { ts, data: syn },
+            Packet {
+                ts: ts + 0.001,
+                data: syn_ack,
+            },
+            Packet {
+                ts: ts + 0.002,
+                data: ack,
+            },
+        ]
+    }
+
+    /// Data from the client to the server
+    pub fn client_send(&mut self, ts: f64, payload: &[u8]) -> Packet {
+        let data = tcp_frame(
+            self.client,
+            self.server,
+            self.client_seq,
+            self.server_seq,
+            TCP_PSH | TCP_ACK,
+            payload,
+        );
+        self.client_seq = self.client_seq.wrapping_add(payload.len() as u32);
+        Packet { ts, data }
+    }
+
+    /// Data from the server to the client
+    pub fn server_send(&mut self, ts: f64, payload: &[u8]) -> Packet {
+        let data = tcp_frame(
+            self.server,
+            self.client,
+            self.server_seq,
+            self.client_seq,
+            TCP_PSH | TCP_ACK,
+            payload,
+        );
+        self.server_seq = self.server_seq.wrapping_add(payload.len() as u32);
+        Packet { ts, data }
+    }
+
+    /// A bare ACK from the client
+    pub fn client_ack(&self, ts: f64) -> Packet {
+        let data = tcp_frame(
+            self.client,
+            self.server,
+            self.client_seq,
+            self.server_seq,
+            TCP_ACK,
+            &[],
+        );
+        Packet { ts, data }
+    }
+}
+
+/// A TCP three-way handshake between two hosts
+pub fn tcp_handshake(client: SocketAddrV4, server: SocketAddrV4, ts: f64) -> Vec<Packet> {
+    TcpFlow::new(client, server).handshake(ts)
+}
+
+/// DNS message with one A question, plus its answer when `answer` is set
+fn dns_message(id: u16, name: &str, answer: Option<Ipv4Addr>) -> Vec<u8> {
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&id.to_be_bytes());
+    let flags: u16 = if answer.is_some() { 0x8180 } else { 0x0100 };
+    msg.extend_from_slice(&flags.to_be_bytes());
+    msg.extend_from_slice(&1u16.to_be_bytes());
+    msg.extend_from_slice(&u16::from(answer.is_some()).to_be_bytes());
+    msg.extend_from_slice(&[0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&[0, 1, 0, 1]); // A, IN
+    if let Some(address) = answer {
+        msg.extend_from_slice(&[0xc0, 0x0c]); // Pointer to the question name
+        msg.extend_from_slice(&[0, 1, 0, 1]);
+        msg.extend_from_slice(&300u32.to_be_bytes());
+        msg.extend_from_slice(&4u16.to_be_bytes());
+        msg.extend_from_slice(&address.octets());
+    }
+    msg
+}
+
+/// A DNS A query for `name` and the response resolving it to `answer`
+pub fn dns_query(
+    client: SocketAddrV4,
+    server: SocketAddrV4,
+    name: &str,
+    answer: Ipv4Addr,
+    ts: f64,
+) -> Vec<Packet> {
+    let id = 0x1a2b;
+    vec![
+        Packet {
+            ts,
+            data: udp_frame(client, server, &dns_message(id, name, None)),
+        },
+        Packet {
+            ts: ts + 0.010,
+            data: udp_frame(server, client, &dns_message(id, name, Some(answer))),
+        },
+    ]
+}
+
+/// A complete HTTP/1.1 GET: handshake, request, `200 OK` with `body`, ACK
+pub fn http_exchange(
+    client: SocketAddrV4,
+    server: SocketAddrV4,
+    host: &str,
+    path: &str,
+    body: &str,
+    ts: f64,
+) -> Vec<Packet> {
+    let mut flow = TcpFlow::new(client, server);
+    let mut packets = flow.handshake(ts);
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: packetpilot-test\r\n\r\n",
+        path, host
+    );
+    packets.push(flow.client_send(ts + 0.003, request.as_bytes()));
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    packets.push(flow.server_send(ts + 0.020, response.as_bytes()));
+    packets.push(flow.client_ack(ts + 0.021));
+    packets
+}
+
+/// A small capture with one of everything: a DNS lookup, then an HTTP
+/// exchange with the resolved server
+pub fn sample_capture() -> Vec<Packet> {
+    let client = Ipv4Addr::new(192, 168, 1, 10);
+    let resolver = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 53);
+    let server = Ipv4Addr::new(93, 184, 216, 34);
+    let ts = 1_700_000_000.0;
+
+    let mut packets = dns_query(
+        SocketAddrV4::new(client, 53_000),
+        resolver,
+        "example.com",
+        server,
+        ts,
+    );
+    packets.extend(http_exchange(
+        SocketAddrV4::new(client, 49_152),
+        SocketAddrV4::new(server, 80),
+        "example.com",
+        "/",
+        "hello",
+        ts + 0.050,
+    ));
+    packets
+}
+
+/// A medium-size capture for benchmarks: `exchanges` DNS lookups and HTTP
+/// exchanges between a few hundred clients and servers, 8 frames each, spread
+/// over about `exchanges / 10` seconds
+pub fn medium_capture(exchanges: u32) -> Vec<Packet> {
+    let resolver = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 53), 53);
+    let start = 1_700_000_000.0;
+
+    let mut packets = Vec::with_capacity(exchanges as usize * 8);
+    for i in 0..exchanges {
+        let client = Ipv4Addr::new(10, 1, (i / 250 % 250) as u8, (i % 250 + 1) as u8);
+        let server = Ipv4Addr::new(172, 16, (i % 7) as u8, (i % 13 + 1) as u8);
+        let port = 49_152 + (i % 16_000) as u16;
+        let host = format!("host{}.example", i % 50);
+        let ts = start + f64::from(i) * 0.1;
+
+        packets.extend(dns_query(
+            SocketAddrV4::new(client, port),
+            resolver,
+            &host,
+            server,
+            ts,
+        ));
+        packets.extend(http_exchange(
+            SocketAddrV4::new(client, port),
+            SocketAddrV4::new(server, 80),
+            &host,
+            &format!("/item/{}", i % 100),
+            "ok",
+            ts + 0.015,
+        ));
+    }
+    packets.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    packets
+}
+
+/// Serialize packets as a classic pcap file (microsecond timestamps, Ethernet)
+pub fn to_pcap_bytes(packets: &[Packet]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&4u16.to_le_bytes());
+    out.extend_from_slice(&0i32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&SNAPLEN.to_le_bytes());
+    out.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    for packet in packets {
+        let secs = packet.ts.trunc();
+        let micros = ((packet.ts - secs) * 1_000_000.0).round() as u32;
+        out.extend_from_slice(&(secs as u32).to_le_bytes());
+        out.extend_from_slice(&micros.min(999_999).to_le_bytes());
+        out.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&packet.data);
+    }
+    out
+}
+
+/// Write packets to a classic pcap file
+pub fn write_pcap(path: &Path, packets: &[Packet]) -> Result<(), String> {
+    std::fs::write(path, to_pcap_bytes(packets))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// Read a classic pcap file of either byte order (pcapng isn't supported)
+pub fn read_pcap(path: &Path) -> Result<Vec<Packet>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    if bytes.len() < 24 {
+        return Err("Not a pcap file: too short".to_string());
+    }
+    let swapped = match bytes[0..4] {
+        [0xd4, 0xc3, 0xb2, 0xa1] => false,
+        [0xa1, 0xb2, 0xc3, 0xd4] => true,
+        _ => return Err("Not a classic pcap file".to_string()),
+    };
+    let u32_at = |offset: usize| {
+        let raw = [
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ];
+        if swapped {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        }
+    };
+
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset + 16 <= bytes.len() {
+        let secs = u32_at(offset);
+        let micros = u32_at(offset + 4);
+        let len = u32_at(offset + 8) as usize;
+        offset += 16;
+        let data = bytes
+            .get(offset..offset + len)
+            .ok_or_else(|| format!("Truncated record at byte {}", offset))?;
+        packets.push(Packet {
+            ts: f64::from(secs) + f64::from(micros) / 1_000_000.0,
+            data: data.to_vec(),
+        });
+        offset += len;
+    }
+    Ok(packets)
+}
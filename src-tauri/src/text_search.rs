@@ -0,0 +1,235 @@
+//! Search of the packet list's text (Info, Source and Destination columns),
+//! for what display filters can't express: "the packet whose Info contains
+//! this session token".
+//!
+//! sharkd has no text search, so the frame list is paged through and
+//! matched here. Find next / previous (by text or display filter) scans a
+//! window of frames at a time from the current one, so it stops at the first
+//! match instead of collecting them all.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::sharkd_client::{Frame, SharkdClient};
+
+/// Frames fetched from sharkd per request
+const PAGE_SIZE: u32 = 5000;
+
+/// Most matching frames returned by one search
+const MAX_MATCHES: usize = 10_000;
+
+/// Columns searched: Source, Destination, Info
+const SEARCHED_COLUMNS: &[usize] = &[2, 3, 6];
+
+/// Result of a text search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextSearchResult {
+    /// Matching frame numbers, in capture order
+    pub frames: Vec<u32>,
+    /// More frames matched than are returned
+    pub truncated: bool,
+}
+
+/// A substring or regular expression to look for in frame summaries
+pub struct TextMatcher {
+    regex: Option<Regex>,
+    /// Lower-cased query, for case-insensitive substring search
+    needle: String,
+    case_sensitive: bool,
+}
+
+impl TextMatcher {
+    pub fn new(query: &str, regex: bool, case_sensitive: bool) -> Result<Self, String> {
+        if query.is_empty() {
+            return Err("Search text is empty".to_string());
+        }
+        let regex = if regex {
+            Some(
+                RegexBuilder::new(query)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|e| format!("Invalid regular expression: {}", e))?,
+            )
+        } else {
+            None
+        };
+        let needle = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        Ok(TextMatcher {
+            regex,
+            needle,
+            case_sensitive,
+        })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(text),
+            None if self.case_sensitive => text.contains(&self.needle),
+            None => text.to_lowercase().contains(&self.needle),
+        }
+    }
+
+    /// Whether any searched column of a frame matches
+    pub fn matches(&self, frame: &Frame) -> bool {
+        SEARCHED_COLUMNS
+            .iter()
+            .filter_map(|&i| frame.columns.get(i))
+            .any(|text| self.is_match(text))
+    }
+}
+
+/// Frames whose summary matches, among those matching `filter` (all when
+/// None), paging through sharkd
+pub async fn search_text(
+    client: &SharkdClient,
+    matcher: &TextMatcher,
+    filter: Option<&str>,
+) -> Result<TextSearchResult, String> {
+    let operation = client.operation();
+    let mut frames = Vec::new();
+    let mut skip = 0;
+    loop {
+        operation.check()?;
+        let page = match filter {
+            Some(filter) => client.search_frames(filter, skip, PAGE_SIZE).await?.0,
+            None => client.frames(skip, PAGE_SIZE).await?,
+        };
+        for frame in &page {
+            if matcher.matches(frame) {
+                if frames.len() == MAX_MATCHES {
+                    return Ok(TextSearchResult {
+                        frames,
+                        truncated: true,
+                    });
+                }
+                frames.push(frame.number);
+            }
+        }
+        let fetched = page.len() as u32;
+        skip += fetched;
+        if fetched < PAGE_SIZE {
+            break;
+        }
+    }
+    Ok(TextSearchResult {
+        frames,
+        truncated: false,
+    })
+}
+
+/// Which way `find_frame` looks from the current frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindDirection {
+    Next,
+    Previous,
+}
+
+/// How `find_frame` reads its query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindMode {
+    /// A display filter
+    Filter,
+    /// Text in the frame summary
+    Text,
+    /// A regular expression over the frame summary
+    Regex,
+}
+
+/// What `find_frame` looks for
+pub enum FindQuery {
+    /// Frames matching a display filter
+    Filter(String),
+    /// Frames whose summary matches
+    Text(TextMatcher),
+}
+
+/// The first frame in `first..=last` (the last with `Previous`) matching the
+/// query and `exclusions`, scanning a window of frames per sharkd request
+async fn find_in(
+    client: &SharkdClient,
+    query: &FindQuery,
+    exclusions: Option<&str>,
+    first: u32,
+    last: u32,
+    direction: FindDirection,
+) -> Result<Option<u32>, String> {
+    if first > last {
+        return Ok(None);
+    }
+    let operation = client.operation();
+    let mut lo = first;
+    let mut hi = last;
+    loop {
+        operation.check()?;
+        let (start, end) = match direction {
+            FindDirection::Next => (lo, lo.saturating_add(PAGE_SIZE - 1).min(hi)),
+            FindDirection::Previous => (hi.saturating_sub(PAGE_SIZE - 1).max(lo), hi),
+        };
+        let mut filter = format!("frame.number >= {} && frame.number <= {}", start, end);
+        if let FindQuery::Filter(query) = query {
+            filter = format!("({}) && {}", query, filter);
+        }
+        if let Some(exclusions) = exclusions {
+            filter = format!("({}) && {}", exclusions, filter);
+        }
+
+        let (page, _) = client.search_frames(&filter, 0, PAGE_SIZE).await?;
+        let matches = |frame: &&Frame| match query {
+            FindQuery::Filter(_) => true,
+            FindQuery::Text(matcher) => matcher.matches(frame),
+        };
+        let found = match direction {
+            FindDirection::Next => page.iter().find(matches),
+            FindDirection::Previous => page.iter().rev().find(matches),
+        };
+        if let Some(frame) = found {
+            return Ok(Some(frame.number));
+        }
+
+        match direction {
+            FindDirection::Next if end < hi => lo = end + 1,
+            FindDirection::Previous if start > lo => hi = start - 1,
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// The next or previous frame after `from_frame` matching the query,
+/// wrapping around the capture's end when `wrap` is set. `exclusions` is a
+/// filter frames must also match (the ignored-frame exclusion).
+pub async fn find_frame(
+    client: &SharkdClient,
+    query: &FindQuery,
+    exclusions: Option<&str>,
+    from_frame: u32,
+    direction: FindDirection,
+    wrap: bool,
+) -> Result<Option<u32>, String> {
+    let total = client.status().await?.frames.unwrap_or(0) as u32;
+    if total == 0 {
+        return Ok(None);
+    }
+    let (first, last, wrapped_first, wrapped_last) = match direction {
+        FindDirection::Next => (from_frame.saturating_add(1), total, 1, from_frame),
+        FindDirection::Previous => (1, from_frame.saturating_sub(1), from_frame, total),
+    };
+    let found = find_in(client, query, exclusions, first, last, direction).await?;
+    if found.is_some() || !wrap {
+        return Ok(found);
+    }
+    find_in(
+        client,
+        query,
+        exclusions,
+        wrapped_first.max(1),
+        wrapped_last.min(total),
+        direction,
+    )
+    .await
+}
@@ -0,0 +1,117 @@
+//! Time column display: the format of the packet list's time column and the
+//! frames used as time references. The format is a saved preference; time
+//! references belong to each session's loaded capture.
+//!
+//! sharkd's own time column is always relative to the first frame, so other
+//! formats (and relative times with references) are requested as a custom
+//! column and swapped in.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+use crate::storage;
+
+pub const STORE_NAME: &str = "time_format";
+
+/// How frame times are shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// Date and time of day, in local time
+    Absolute,
+    /// Seconds since the first frame, or the last time reference before it
+    #[default]
+    Relative,
+    /// Seconds since the previous displayed frame
+    Delta,
+    /// Date and time of day, in UTC
+    Utc,
+}
+
+impl TimeFormat {
+    /// Field giving frame times in this format
+    pub fn field(self) -> &'static str {
+        match self {
+            TimeFormat::Absolute => "frame.time",
+            TimeFormat::Relative => "frame.time_relative",
+            TimeFormat::Delta => "frame.time_delta_displayed",
+            TimeFormat::Utc => "frame.time_utc",
+        }
+    }
+}
+
+/// The time format and a session's time references
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeSettings {
+    pub format: TimeFormat,
+    /// Time reference frames, ascending
+    #[serde(skip)]
+    pub references: BTreeSet<u32>,
+}
+
+static SETTINGS: OnceLock<Mutex<TimeSettings>> = OnceLock::new();
+
+/// Each session's time references, by session ID
+static REFERENCES: OnceLock<Mutex<HashMap<String, BTreeSet<u32>>>> = OnceLock::new();
+
+fn get_settings() -> &'static Mutex<TimeSettings> {
+    SETTINGS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_settings().lock() = storage::load_json(STORE_NAME);
+}
+
+fn get_references() -> &'static Mutex<HashMap<String, BTreeSet<u32>>> {
+    REFERENCES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Current format and a session's time references
+pub fn settings(session: &str) -> TimeSettings {
+    TimeSettings {
+        format: get_settings().lock().format,
+        references: get_references()
+            .lock()
+            .get(session)
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+/// Change the time format
+pub fn set_format(format: TimeFormat) -> Result<(), String> {
+    let mut settings = get_settings().lock();
+    settings.format = format;
+    storage::save_json(STORE_NAME, &*settings)
+}
+
+/// Mark or unmark a frame as a time reference in a session, returning its
+/// references
+pub fn set_reference(session: &str, frame: u32, enabled: bool) -> Vec<u32> {
+    let mut sessions = get_references().lock();
+    let references = sessions.entry(session.to_string()).or_default();
+    if enabled {
+        references.insert(frame);
+    } else {
+        references.remove(&frame);
+    }
+    references.iter().copied().collect()
+}
+
+/// Drop a session's time references (called when it loads a new capture or
+/// closes)
+pub fn clear_references(session: &str) {
+    get_references().lock().remove(session);
+}
+
+/// Whether sharkd's default time column needs replacing in a session
+pub fn is_default(session: &str) -> bool {
+    get_settings().lock().format == TimeFormat::Relative
+        && get_references()
+            .lock()
+            .get(session)
+            .map_or(true, BTreeSet::is_empty)
+}
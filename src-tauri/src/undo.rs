@@ -0,0 +1,261 @@
+//! Undo/redo of workspace changes (marked and ignored frames, packet
+//! comments, custom columns).
+//!
+//! Each mutating command records the state it changed before and after, so
+//! undoing or redoing is just restoring one side. Each session has its own
+//! history, which belongs to its loaded capture and is dropped when another
+//! one is opened.
+//!
+//! Column changes are recorded in the history of the session they were made
+//! from, though the columns themselves are shared by all sessions.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::OnceLock;
+
+use crate::columns::{self, CustomColumn};
+use crate::{comments, frame_marks, storage};
+
+/// Changes kept for undo; the oldest are dropped beyond this
+const MAX_HISTORY: usize = 100;
+
+/// A reversible change to workspace state
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    MarkedFrames {
+        before: Vec<u32>,
+        after: Vec<u32>,
+    },
+    IgnoredFrames {
+        before: Vec<u32>,
+        after: Vec<u32>,
+    },
+    Columns {
+        before: Vec<CustomColumn>,
+        after: Vec<CustomColumn>,
+    },
+    /// A frame's comment in the session's loaded capture
+    Comment {
+        frame: u32,
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+impl Change {
+    fn is_noop(&self) -> bool {
+        match self {
+            Change::MarkedFrames { before, after } => before == after,
+            Change::IgnoredFrames { before, after } => before == after,
+            Change::Columns { before, after } => before == after,
+            Change::Comment { before, after, .. } => before == after,
+        }
+    }
+
+    /// Restore the state from before (`undo`) or after the change
+    async fn apply(&self, session: &str, undo: bool) -> Result<(), String> {
+        match self {
+            Change::MarkedFrames { before, after } => {
+                let frames = if undo { before } else { after };
+                frame_marks::set_marked_frames(session, frames.iter().copied());
+                Ok(())
+            }
+            Change::IgnoredFrames { before, after } => {
+                let frames = if undo { before } else { after };
+                frame_marks::set_ignored_frames(session, frames.iter().copied());
+                Ok(())
+            }
+            Change::Columns { before, after } => {
+                columns::set_columns(if undo { before } else { after }.clone())
+            }
+            Change::Comment {
+                frame,
+                before,
+                after,
+            } => {
+                let client = crate::sharkd(Some(session))?;
+                let capture = client
+                    .loaded_file()
+                    .ok_or_else(|| "No capture file loaded".to_string())?;
+                let comment = if undo { before } else { after };
+                comments::set_comment(&client, &capture, *frame, comment.as_deref()).await
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    label: String,
+    at: u64,
+    change: Change,
+}
+
+/// One step in the undo history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// What the change did ("Ignore frames", "Apply layout Triage", ...)
+    pub label: String,
+    /// When it was made, in seconds since the Unix epoch
+    pub at: u64,
+}
+
+/// Changes that can be undone and redone, most recent last
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoHistory {
+    pub undo: Vec<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Default)]
+struct UndoStack {
+    done: Vec<Entry>,
+    undone: Vec<Entry>,
+}
+
+/// Each session's history, by session ID
+static UNDO_STACKS: OnceLock<Mutex<HashMap<String, UndoStack>>> = OnceLock::new();
+
+/// Run `f` on a session's history
+fn with_stack<T>(session: &str, f: impl FnOnce(&mut UndoStack) -> T) -> T {
+    let mut stacks = UNDO_STACKS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock();
+    f(stacks.entry(session.to_string()).or_default())
+}
+
+fn record(session: &str, label: &str, change: Change) {
+    if change.is_noop() {
+        return;
+    }
+    with_stack(session, |stack| {
+        stack.undone.clear();
+        stack.done.push(Entry {
+            label: label.to_string(),
+            at: storage::now_secs(),
+            change,
+        });
+        if stack.done.len() > MAX_HISTORY {
+            let excess = stack.done.len() - MAX_HISTORY;
+            stack.done.drain(..excess);
+        }
+    });
+}
+
+/// Run a change to a session's marked frames, recording it for undo
+pub fn track_marked<T>(session: &str, label: &str, change: impl FnOnce() -> T) -> T {
+    let before = frame_marks::marked_frames(session);
+    let result = change();
+    let after = frame_marks::marked_frames(session);
+    record(session, label, Change::MarkedFrames { before, after });
+    result
+}
+
+/// Run a change to a session's ignored frames, recording it for undo
+pub fn track_ignored<T>(session: &str, label: &str, change: impl FnOnce() -> T) -> T {
+    let before = frame_marks::ignored_frames(session);
+    let result = change();
+    let after = frame_marks::ignored_frames(session);
+    record(session, label, Change::IgnoredFrames { before, after });
+    result
+}
+
+/// Run a change to the custom columns, recording it for undo in a session's
+/// history if it succeeds
+pub fn track_columns<T>(
+    session: &str,
+    label: &str,
+    change: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    let before = columns::get_columns();
+    let result = change()?;
+    let after = columns::get_columns();
+    record(session, label, Change::Columns { before, after });
+    Ok(result)
+}
+
+/// Run a change to a frame's comment in the capture loaded in a session,
+/// recording it for undo if it succeeds
+pub async fn track_comment<T>(
+    session: &str,
+    capture: &str,
+    frame: u32,
+    label: &str,
+    change: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    let before = comments::for_capture(capture).remove(&frame);
+    let result = change.await?;
+    let after = comments::for_capture(capture).remove(&frame);
+    record(
+        session,
+        label,
+        Change::Comment {
+            frame,
+            before,
+            after,
+        },
+    );
+    Ok(result)
+}
+
+/// Undo a session's most recent change, returning its label (None when
+/// there is nothing to undo)
+pub async fn undo(session: &str) -> Result<Option<String>, String> {
+    // Comments are restored through sharkd, so the entry is applied outside
+    // the lock
+    let entry = match with_stack(session, |stack| stack.done.pop()) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    if let Err(e) = entry.change.apply(session, true).await {
+        with_stack(session, |stack| stack.done.push(entry));
+        return Err(e);
+    }
+    let label = entry.label.clone();
+    with_stack(session, |stack| stack.undone.push(entry));
+    Ok(Some(label))
+}
+
+/// Redo a session's most recently undone change, returning its label (None
+/// when there is nothing to redo)
+pub async fn redo(session: &str) -> Result<Option<String>, String> {
+    let entry = match with_stack(session, |stack| stack.undone.pop()) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    if let Err(e) = entry.change.apply(session, false).await {
+        with_stack(session, |stack| stack.undone.push(entry));
+        return Err(e);
+    }
+    let label = entry.label.clone();
+    with_stack(session, |stack| stack.done.push(entry));
+    Ok(Some(label))
+}
+
+/// A session's undo and redo stacks
+pub fn history(session: &str) -> UndoHistory {
+    with_stack(session, |stack| {
+        let summarize = |entries: &[Entry]| {
+            entries
+                .iter()
+                .map(|entry| HistoryEntry {
+                    label: entry.label.clone(),
+                    at: entry.at,
+                })
+                .collect()
+        };
+        UndoHistory {
+            undo: summarize(&stack.done),
+            redo: summarize(&stack.undone),
+        }
+    })
+}
+
+/// Drop a session's history (called when it loads a new capture or closes)
+pub fn clear(session: &str) {
+    if let Some(stacks) = UNDO_STACKS.get() {
+        stacks.lock().remove(session);
+    }
+}
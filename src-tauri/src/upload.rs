@@ -0,0 +1,731 @@
+//! Uploading captures and reports to configured share destinations.
+//!
+//! Each destination kind implements `UploadDestination`; transfers shell out
+//! to `curl` (HTTPS PUT, S3-compatible via SigV4) or `scp`, like the OAuth
+//! token exchange does. Credentials and headers reach curl through a config
+//! on its stdin rather than its command line, where other local users could
+//! read them. Failed uploads are retried with backoff, and progress is
+//! reported through `upload-progress` events.
+//!
+//! Large files go to S3 as multipart uploads, so a retry resumes with the
+//! first part that wasn't stored. HTTPS PUT and SCP have no portable way to
+//! resume, so their retries start over.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::cache_manager::{self, CacheCategory};
+use crate::storage;
+
+pub const STORE_NAME: &str = "upload-destinations";
+
+/// Attempts per upload before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Files larger than this are sent to S3 in parts of this size (S3 needs at
+/// least 5 MiB per part)
+const S3_PART_SIZE: u64 = 16 << 20;
+
+/// Stands in for stored secrets in destinations handed to the frontend.
+/// Saving a destination with it keeps the stored secret.
+pub const REDACTED: &str = "********";
+
+/// Kind-specific destination settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DestinationKind {
+    /// S3-compatible object storage (AWS, MinIO, ...)
+    S3 {
+        /// Service endpoint, e.g. `https://s3.eu-west-1.amazonaws.com`
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+        #[serde(default)]
+        prefix: String,
+    },
+    /// Generic HTTPS PUT; the file name is appended to `url`
+    HttpsPut {
+        url: String,
+        #[serde(default)]
+        headers: Vec<String>,
+    },
+    /// SCP to a remote directory
+    Scp {
+        host: String,
+        user: String,
+        #[serde(default)]
+        port: Option<u16>,
+        remote_dir: String,
+        #[serde(default)]
+        identity_file: Option<String>,
+    },
+}
+
+/// A configured upload destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadDestinationConfig {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: DestinationKind,
+}
+
+/// Progress event payload for `upload-progress`
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub dest_id: String,
+    pub path: String,
+    pub attempt: u32,
+    /// "uploading", "retrying", "done", or "failed"
+    pub state: String,
+    /// Percentage complete, when the transport reports it
+    pub percent: Option<f64>,
+}
+
+/// Result of a completed upload
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadResult {
+    /// Remote location of the uploaded file
+    pub location: String,
+    pub attempts: u32,
+}
+
+/// A place files can be shared to
+pub trait UploadDestination {
+    /// Upload `path`, reporting percentage progress when available.
+    /// Returns the remote location.
+    fn upload(&self, path: &Path, progress: &dyn Fn(f64)) -> Result<String, String>;
+
+    /// Give up on the upload after the last failed attempt, discarding
+    /// anything kept to resume it
+    fn abort(&self) {}
+}
+
+/// An S3 multipart upload in progress, kept across retries
+struct Multipart {
+    url: String,
+    upload_id: String,
+    /// ETags of the parts stored so far, in part order
+    etags: Vec<String>,
+}
+
+struct S3Destination<'a> {
+    endpoint: &'a str,
+    bucket: &'a str,
+    region: &'a str,
+    access_key: &'a str,
+    secret_key: &'a str,
+    prefix: &'a str,
+    multipart: Mutex<Option<Multipart>>,
+}
+
+struct HttpsPutDestination<'a> {
+    url: &'a str,
+    headers: &'a [String],
+}
+
+struct ScpDestination<'a> {
+    host: &'a str,
+    user: &'a str,
+    port: Option<u16>,
+    remote_dir: &'a str,
+    identity_file: Option<&'a str>,
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Not a file path: {}", path.display()))
+}
+
+fn null_device() -> String {
+    if cfg!(target_os = "windows") {
+        "NUL"
+    } else {
+        "/dev/null"
+    }
+    .to_string()
+}
+
+/// Quote a value for a curl config file
+fn config_value(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    )
+}
+
+/// Start curl with `config` (credentials, headers, ...) fed to it on stdin
+fn spawn_curl(args: &[String], config: &str, stdout: Stdio) -> Result<Child, String> {
+    let mut child = Command::new("curl")
+        .args(["--fail", "--show-error", "-K", "-"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    // Dropping stdin closes it, so curl stops reading the config
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .map_err(|e| format!("Failed to pass curl its config: {}", e))?;
+    }
+    Ok(child)
+}
+
+/// Run curl quietly, returning what it wrote to stdout
+fn curl_output(args: &[String], config: &str) -> Result<String, String> {
+    let mut curl_args = vec!["--silent".to_string()];
+    curl_args.extend_from_slice(args);
+    let output = spawn_curl(&curl_args, config, Stdio::piped())?
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Upload failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Run curl with a progress bar, forwarding its percentage to `progress`
+fn run_curl(args: &[String], config: &str, progress: &dyn Fn(f64)) -> Result<(), String> {
+    let mut curl_args = vec![
+        "--progress-bar".to_string(),
+        "-o".to_string(),
+        null_device(),
+    ];
+    curl_args.extend_from_slice(args);
+    let mut child = spawn_curl(&curl_args, config, Stdio::null())?;
+
+    // The progress bar redraws with carriage returns, ending in e.g. " 42.5%"
+    let mut stderr_text = String::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        let mut buf = [0u8; 512];
+        let mut line = String::new();
+        while let Ok(n) = stderr.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for ch in String::from_utf8_lossy(&buf[..n]).chars() {
+                if ch == '\r' || ch == '\n' {
+                    if let Some(percent) = line
+                        .trim()
+                        .strip_suffix('%')
+                        .and_then(|l| l.rsplit(' ').next())
+                        .and_then(|p| p.parse::<f64>().ok())
+                    {
+                        progress(percent);
+                    } else if !line.trim().is_empty() {
+                        stderr_text.push_str(line.trim());
+                        stderr_text.push('\n');
+                    }
+                    line.clear();
+                } else {
+                    line.push(ch);
+                }
+            }
+        }
+        stderr_text.push_str(line.trim());
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for curl: {}", e))?;
+    if !status.success() {
+        return Err(format!(
+            "Upload failed ({}): {}",
+            status,
+            stderr_text.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Text of the first `<name>` element in an S3 XML response
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(xml[start..end].to_string())
+}
+
+/// Percent-encode a query string value
+fn query_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                String::from(b as char)
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl S3Destination<'_> {
+    /// Curl arguments signing the request for this destination
+    fn signing_args(&self) -> Vec<String> {
+        vec![
+            "--aws-sigv4".to_string(),
+            format!("aws:amz:{}:s3", self.region),
+        ]
+    }
+
+    fn credentials(&self) -> String {
+        format!(
+            "user = {}\n",
+            config_value(&format!("{}:{}", self.access_key, self.secret_key))
+        )
+    }
+
+    /// Start a multipart upload to `url`
+    fn start_multipart(&self, url: &str) -> Result<Multipart, String> {
+        let mut args = self.signing_args();
+        args.extend([
+            "-X".to_string(),
+            "POST".to_string(),
+            "--data-binary".to_string(),
+            String::new(),
+            format!("{}?uploads", url),
+        ]);
+        let response = curl_output(&args, &self.credentials())?;
+        let upload_id = xml_element(&response, "UploadId")
+            .ok_or_else(|| format!("S3 didn't start the upload: {}", response.trim()))?;
+        Ok(Multipart {
+            url: url.to_string(),
+            upload_id,
+            etags: Vec::new(),
+        })
+    }
+
+    /// Upload `path` in parts, resuming the multipart upload a failed
+    /// attempt left behind
+    fn upload_parts(
+        &self,
+        path: &Path,
+        size: u64,
+        url: &str,
+        progress: &dyn Fn(f64),
+    ) -> Result<(), String> {
+        let taken = self.multipart.lock().take();
+        let mut state = match taken {
+            Some(state) => state,
+            None => self.start_multipart(url)?,
+        };
+        let result = self.send_parts(&mut state, path, size, progress);
+        if result.is_err() {
+            *self.multipart.lock() = Some(state);
+        }
+        result
+    }
+
+    /// Send the parts of `path` not stored yet, then complete the upload
+    fn send_parts(
+        &self,
+        state: &mut Multipart,
+        path: &Path,
+        size: u64,
+        progress: &dyn Fn(f64),
+    ) -> Result<(), String> {
+        let url = state.url.clone();
+        let upload_id = query_encode(&state.upload_id);
+
+        let mut file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let part_path = cache_manager::new_entry(
+            CacheCategory::Scratch,
+            &format!("upload-{}-{}.part", std::process::id(), storage::now_secs()),
+            None,
+        )?;
+        let parts = size.div_ceil(S3_PART_SIZE);
+        for part in state.etags.len() as u64..parts {
+            let offset = part * S3_PART_SIZE;
+            let len = S3_PART_SIZE.min(size - offset);
+            let mut chunk = vec![0u8; len as usize];
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut chunk))
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            std::fs::write(&part_path, &chunk)
+                .map_err(|e| format!("Failed to write {}: {}", part_path.display(), e))?;
+
+            let mut args = self.signing_args();
+            args.extend([
+                "-D".to_string(),
+                "-".to_string(),
+                "-o".to_string(),
+                null_device(),
+                "-T".to_string(),
+                part_path.display().to_string(),
+                format!("{}?partNumber={}&uploadId={}", url, part + 1, upload_id),
+            ]);
+            let headers = curl_output(&args, &self.credentials());
+            let _ = std::fs::remove_file(&part_path);
+            let etag = headers?
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.trim()
+                        .eq_ignore_ascii_case("etag")
+                        .then(|| value.trim().to_string())
+                })
+                .ok_or_else(|| format!("S3 didn't return an ETag for part {}", part + 1))?;
+            state.etags.push(etag);
+            progress((offset + len) as f64 * 100.0 / size as f64);
+        }
+
+        let body: String = state
+            .etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    etag
+                )
+            })
+            .collect();
+        let config = format!(
+            "{}data-binary = {}\n",
+            self.credentials(),
+            config_value(&format!(
+                "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+                body
+            ))
+        );
+        let mut args = self.signing_args();
+        args.extend([
+            "-X".to_string(),
+            "POST".to_string(),
+            "-H".to_string(),
+            "Content-Type: application/xml".to_string(),
+            format!("{}?uploadId={}", url, upload_id),
+        ]);
+        // S3 can report a failed completion in a 200 response
+        let response = curl_output(&args, &config)?;
+        if response.contains("<Error>") {
+            return Err(format!(
+                "S3 didn't complete the upload: {}",
+                response.trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl UploadDestination for S3Destination<'_> {
+    fn upload(&self, path: &Path, progress: &dyn Fn(f64)) -> Result<String, String> {
+        let key = if self.prefix.is_empty() {
+            file_name(path)?
+        } else {
+            format!("{}/{}", self.prefix.trim_end_matches('/'), file_name(path)?)
+        };
+        let url = format!(
+            "{}/{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.bucket,
+            key
+        );
+        let size = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .len();
+        if size > S3_PART_SIZE {
+            self.upload_parts(path, size, &url, progress)?;
+            return Ok(url);
+        }
+
+        let mut args = self.signing_args();
+        args.extend(["-T".to_string(), path.display().to_string(), url.clone()]);
+        run_curl(&args, &self.credentials(), progress)?;
+        Ok(url)
+    }
+
+    fn abort(&self) {
+        // Stored parts are billed until the upload is completed or aborted
+        if let Some(state) = self.multipart.lock().take() {
+            let mut args = self.signing_args();
+            args.extend([
+                "-X".to_string(),
+                "DELETE".to_string(),
+                format!("{}?uploadId={}", state.url, query_encode(&state.upload_id)),
+            ]);
+            if let Err(e) = curl_output(&args, &self.credentials()) {
+                eprintln!("Failed to abort S3 upload to {}: {}", state.url, e);
+            }
+        }
+    }
+}
+
+impl UploadDestination for HttpsPutDestination<'_> {
+    fn upload(&self, path: &Path, progress: &dyn Fn(f64)) -> Result<String, String> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), file_name(path)?);
+        let config: String = self
+            .headers
+            .iter()
+            .map(|header| format!("header = {}\n", config_value(header)))
+            .collect();
+        let args = ["-T".to_string(), path.display().to_string(), url.clone()];
+        run_curl(&args, &config, progress)?;
+        Ok(url)
+    }
+}
+
+impl UploadDestination for ScpDestination<'_> {
+    fn upload(&self, path: &Path, _progress: &dyn Fn(f64)) -> Result<String, String> {
+        let target = format!(
+            "{}@{}:{}/{}",
+            self.user,
+            self.host,
+            self.remote_dir.trim_end_matches('/'),
+            file_name(path)?
+        );
+        let mut cmd = Command::new("scp");
+        cmd.arg("-B"); // batch mode: never prompt for passwords
+        if let Some(port) = self.port {
+            cmd.arg("-P").arg(port.to_string());
+        }
+        if let Some(identity) = self.identity_file {
+            cmd.arg("-i").arg(identity);
+        }
+        let output = cmd
+            .arg(path)
+            .arg(&target)
+            .output()
+            .map_err(|e| format!("Failed to run scp: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "scp failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(target)
+    }
+}
+
+impl DestinationKind {
+    /// The transport implementing this destination
+    pub fn destination(&self) -> Box<dyn UploadDestination + '_> {
+        match self {
+            DestinationKind::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+                prefix,
+            } => Box::new(S3Destination {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+                prefix,
+                multipart: Mutex::new(None),
+            }),
+            DestinationKind::HttpsPut { url, headers } => {
+                Box::new(HttpsPutDestination { url, headers })
+            }
+            DestinationKind::Scp {
+                host,
+                user,
+                port,
+                remote_dir,
+                identity_file,
+            } => Box::new(ScpDestination {
+                host,
+                user,
+                port: *port,
+                remote_dir,
+                identity_file: identity_file.as_deref(),
+            }),
+        }
+    }
+}
+
+/// Name of a `Name: value` header
+fn header_name(header: &str) -> &str {
+    header.split(':').next().unwrap_or_default().trim()
+}
+
+/// Whether a header likely carries a credential
+fn is_secret_header(header: &str) -> bool {
+    let name = header_name(header).to_ascii_lowercase();
+    ["auth", "token", "key", "secret", "cookie", "password"]
+        .iter()
+        .any(|s| name.contains(s))
+}
+
+impl UploadDestinationConfig {
+    /// This destination with its secrets replaced by `REDACTED`
+    fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        match &mut config.kind {
+            DestinationKind::S3 { secret_key, .. } => {
+                if !secret_key.is_empty() {
+                    *secret_key = REDACTED.to_string();
+                }
+            }
+            DestinationKind::HttpsPut { headers, .. } => {
+                for header in headers.iter_mut().filter(|h| is_secret_header(h)) {
+                    *header = format!("{}: {}", header_name(header), REDACTED);
+                }
+            }
+            DestinationKind::Scp { .. } => {}
+        }
+        config
+    }
+
+    /// Put the secrets of `stored` back where this config has `REDACTED`
+    fn restore_secrets(&mut self, stored: &UploadDestinationConfig) {
+        match (&mut self.kind, &stored.kind) {
+            (
+                DestinationKind::S3 { secret_key, .. },
+                DestinationKind::S3 {
+                    secret_key: stored_key,
+                    ..
+                },
+            ) => {
+                if secret_key == REDACTED {
+                    *secret_key = stored_key.clone();
+                }
+            }
+            (
+                DestinationKind::HttpsPut { headers, .. },
+                DestinationKind::HttpsPut {
+                    headers: stored_headers,
+                    ..
+                },
+            ) => {
+                for header in headers.iter_mut() {
+                    if header.split_once(':').map(|(_, v)| v.trim()) != Some(REDACTED) {
+                        continue;
+                    }
+                    if let Some(stored) = stored_headers
+                        .iter()
+                        .find(|h| header_name(h).eq_ignore_ascii_case(header_name(header)))
+                    {
+                        *header = stored.clone();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DestinationStore {
+    destinations: Vec<UploadDestinationConfig>,
+}
+
+static DESTINATIONS: OnceLock<Mutex<DestinationStore>> = OnceLock::new();
+
+fn get_destinations() -> &'static Mutex<DestinationStore> {
+    DESTINATIONS.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_destinations().lock() = storage::load_json(STORE_NAME);
+}
+
+/// Configured upload destinations, with their secrets redacted
+pub fn list_destinations() -> Vec<UploadDestinationConfig> {
+    get_destinations()
+        .lock()
+        .destinations
+        .iter()
+        .map(UploadDestinationConfig::redacted)
+        .collect()
+}
+
+/// Add or replace (by id) an upload destination. `REDACTED` secrets keep
+/// the ones stored for the destination being replaced.
+pub fn save_destination(mut config: UploadDestinationConfig) -> Result<(), String> {
+    let mut store = get_destinations().lock();
+    if let Some(stored) = store.destinations.iter().find(|d| d.id == config.id) {
+        config.restore_secrets(stored);
+    }
+    store.destinations.retain(|d| d.id != config.id);
+    store.destinations.push(config);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Remove an upload destination
+pub fn remove_destination(id: &str) -> Result<(), String> {
+    let mut store = get_destinations().lock();
+    store.destinations.retain(|d| d.id != id);
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Upload a file to a configured destination, retrying with backoff
+pub fn upload_file(dest_id: &str, path: &str) -> Result<UploadResult, String> {
+    let config = get_destinations()
+        .lock()
+        .destinations
+        .iter()
+        .find(|d| d.id == dest_id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown upload destination: {}", dest_id))?;
+    if !Path::new(path).is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+
+    let destination = config.kind.destination();
+    let emit = |attempt: u32, state: &str, percent: Option<f64>| {
+        crate::emit_event(
+            "upload-progress",
+            UploadProgress {
+                dest_id: dest_id.to_string(),
+                path: path.to_string(),
+                attempt,
+                state: state.to_string(),
+                percent,
+            },
+        );
+    };
+
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        emit(attempt, "uploading", None);
+        match destination.upload(Path::new(path), &|percent: f64| {
+            emit(attempt, "uploading", Some(percent))
+        }) {
+            Ok(location) => {
+                emit(attempt, "done", Some(100.0));
+                return Ok(UploadResult {
+                    location,
+                    attempts: attempt,
+                });
+            }
+            Err(e) => {
+                eprintln!("Upload attempt {} to {} failed: {}", attempt, dest_id, e);
+                last_error = e;
+                if attempt < MAX_ATTEMPTS {
+                    emit(attempt, "retrying", None);
+                    std::thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+                }
+            }
+        }
+    }
+
+    destination.abort();
+    emit(MAX_ATTEMPTS, "failed", None);
+    Err(last_error)
+}
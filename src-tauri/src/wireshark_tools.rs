@@ -0,0 +1,107 @@
+//! Discovery and invocation of the Wireshark command-line tools
+//! (editcap, tshark, mergecap, ...) used alongside sharkd.
+//!
+//! Lookup mirrors sharkd discovery: bundled binaries next to the executable
+//! in production, then the system PATH and standard install locations.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::sharkd_client::{get_target_triple, is_production_mode};
+
+fn exe_name(name: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn bundled_tool_candidates(name: &str) -> Vec<PathBuf> {
+    let exe_path = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(_) => return Vec::new(),
+    };
+    if !is_production_mode(&exe_path) {
+        return Vec::new();
+    }
+    let exe_dir = match exe_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => return Vec::new(),
+    };
+
+    vec![
+        exe_dir.join(exe_name(&format!("{}-{}", name, get_target_triple()))),
+        exe_dir.join(exe_name(name)),
+    ]
+}
+
+fn system_tool_candidates(name: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    let finder = if cfg!(target_os = "windows") { "where" } else { "which" };
+    if let Ok(output) = Command::new(finder).arg(name).output() {
+        if output.status.success() {
+            paths.extend(
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from),
+            );
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    for dir in [
+        r"C:\Program Files\Wireshark",
+        r"C:\Program Files (x86)\Wireshark",
+    ] {
+        paths.push(PathBuf::from(dir).join(exe_name(name)));
+    }
+
+    #[cfg(target_os = "macos")]
+    paths.push(PathBuf::from("/Applications/Wireshark.app/Contents/MacOS").join(name));
+
+    paths
+}
+
+/// Find a Wireshark command-line tool such as `editcap` or `tshark`
+pub fn find_tool(name: &str) -> Result<PathBuf, String> {
+    bundled_tool_candidates(name)
+        .into_iter()
+        .chain(system_tool_candidates(name))
+        .find(|p| p.exists())
+        .ok_or_else(|| {
+            format!(
+                "{} not found. PacketPilot expects it bundled alongside sharkd or from a Wireshark install in PATH.",
+                name
+            )
+        })
+}
+
+/// Run a Wireshark tool to completion, returning its stdout.
+/// A non-zero exit is reported as an error carrying the tool's stderr.
+pub fn run_tool(name: &str, args: &[String]) -> Result<String, String> {
+    let path = find_tool(name)?;
+    let mut command = Command::new(&path);
+    command.args(args);
+    // Use the same managed profile as sharkd (decode-as, TLS keys, ...)
+    if let Ok(profile_dir) = crate::profile::profile_dir() {
+        command.env("WIRESHARK_CONFIG_DIR", profile_dir);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {} at {:?}: {}", name, path, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed ({}): {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
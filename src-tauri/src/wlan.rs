@@ -0,0 +1,209 @@
+//! Wireless LAN statistics: networks (BSSIDs and their SSIDs), channel usage,
+//! and per-station frame type counts, aggregated from 802.11 fields.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::sharkd_client::SharkdClient;
+
+/// Upper bound on 802.11 frames scanned
+const WLAN_SCAN_LIMIT: u32 = 1_000_000;
+
+/// Broadcast address, used as BSSID by probe requests
+const BROADCAST: &str = "ff:ff:ff:ff:ff:ff";
+
+/// Fields read per frame, in the order `wlan_stats` indexes them
+const FIELDS: &[&str] = &[
+    "wlan.fc.type",
+    "wlan.fc.type_subtype",
+    "wlan.fc.retry",
+    "wlan.bssid",
+    "wlan.ssid",
+    "wlan_radio.channel",
+    "wlan.ta",
+    "frame.len",
+];
+
+/// Frame type subtype of beacons
+const BEACON: u32 = 0x0008;
+
+/// A BSS (access point or ad-hoc network)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WlanNetwork {
+    pub bssid: String,
+    /// Network name from beacons and probe responses; None when hidden
+    pub ssid: Option<String>,
+    pub channel: Option<u32>,
+    pub beacons: u64,
+    pub frames: u64,
+    pub bytes: u64,
+    /// Stations other than the access point seen sending in the BSS
+    pub stations: usize,
+    pub filter: String,
+}
+
+/// Traffic on one radio channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelUsage {
+    pub channel: u32,
+    pub frames: u64,
+    pub bytes: u64,
+    /// BSSIDs seen on the channel
+    pub networks: usize,
+}
+
+/// Frames sent by one station (transmitter address)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WlanStation {
+    pub address: String,
+    /// BSS the station was last seen in
+    pub bssid: Option<String>,
+    pub management: u64,
+    pub control: u64,
+    pub data: u64,
+    /// Frames with the retry flag set
+    pub retries: u64,
+    pub bytes: u64,
+    pub filter: String,
+}
+
+/// 802.11 traffic in the capture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WlanStats {
+    pub networks: Vec<WlanNetwork>,
+    pub channels: Vec<ChannelUsage>,
+    pub stations: Vec<WlanStation>,
+}
+
+/// First occurrence of a field value
+fn first(value: &str) -> Option<&str> {
+    value.split(',').map(str::trim).find(|v| !v.is_empty())
+}
+
+/// Summarize 802.11 networks, channels and stations, optionally restricted by
+/// a display filter
+pub async fn wlan_stats(client: &SharkdClient, filter: Option<&str>) -> Result<WlanStats, String> {
+    let base = match filter.filter(|f| !f.trim().is_empty()) {
+        Some(filter) => format!("wlan && ({})", filter),
+        None => "wlan".to_string(),
+    };
+    let rows = client.column_values(&base, FIELDS, WLAN_SCAN_LIMIT).await?;
+
+    let mut networks: BTreeMap<String, WlanNetwork> = BTreeMap::new();
+    let mut network_stations: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut channels: BTreeMap<u32, (u64, u64, BTreeSet<String>)> = BTreeMap::new();
+    let mut stations: BTreeMap<String, WlanStation> = BTreeMap::new();
+
+    for row in rows {
+        let value = |i: usize| row.get(i).and_then(|v| first(v));
+        let number = |i: usize| value(i).and_then(|v| parse_number(v));
+        let bytes = number(7).unwrap_or(0) as u64;
+        let bssid = value(3).filter(|b| *b != BROADCAST).map(String::from);
+        let transmitter = value(6).map(String::from);
+        let channel = number(5);
+
+        if let Some(bssid) = &bssid {
+            let network = networks
+                .entry(bssid.clone())
+                .or_insert_with(|| WlanNetwork {
+                    bssid: bssid.clone(),
+                    ssid: None,
+                    channel: None,
+                    beacons: 0,
+                    frames: 0,
+                    bytes: 0,
+                    stations: 0,
+                    filter: format!("wlan.bssid == {}", bssid),
+                });
+            network.frames += 1;
+            network.bytes += bytes;
+            if number(1) == Some(BEACON) {
+                network.beacons += 1;
+                // Channels of data frames can be off when captured while hopping
+                network.channel = channel.or(network.channel);
+            }
+            if let Some(ssid) = value(4) {
+                network.ssid = Some(ssid.to_string());
+            }
+            if let Some(station) = transmitter.as_ref().filter(|t| *t != bssid) {
+                network_stations
+                    .entry(bssid.clone())
+                    .or_default()
+                    .insert(station.clone());
+            }
+        }
+
+        if let Some(channel) = channel {
+            let usage = channels.entry(channel).or_default();
+            usage.0 += 1;
+            usage.1 += bytes;
+            if let Some(bssid) = &bssid {
+                usage.2.insert(bssid.clone());
+            }
+        }
+
+        // Some control frames (CTS, ACK) carry no transmitter address
+        if let Some(address) = transmitter {
+            let station = stations
+                .entry(address.clone())
+                .or_insert_with(|| WlanStation {
+                    filter: format!("wlan.ta == {}", address),
+                    address,
+                    bssid: None,
+                    management: 0,
+                    control: 0,
+                    data: 0,
+                    retries: 0,
+                    bytes: 0,
+                });
+            match number(0) {
+                Some(0) => station.management += 1,
+                Some(1) => station.control += 1,
+                Some(2) => station.data += 1,
+                _ => {}
+            }
+            if matches!(value(2), Some("1" | "True" | "true")) {
+                station.retries += 1;
+            }
+            station.bytes += bytes;
+            if bssid.is_some() {
+                station.bssid = bssid;
+            }
+        }
+    }
+
+    let mut networks: Vec<WlanNetwork> = networks
+        .into_values()
+        .map(|mut network| {
+            network.stations = network_stations
+                .get(&network.bssid)
+                .map_or(0, BTreeSet::len);
+            network
+        })
+        .collect();
+    networks.sort_by(|a, b| b.frames.cmp(&a.frames));
+    let mut stations: Vec<WlanStation> = stations.into_values().collect();
+    stations.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+    Ok(WlanStats {
+        networks,
+        channels: channels
+            .into_iter()
+            .map(|(channel, (frames, bytes, bssids))| ChannelUsage {
+                channel,
+                frames,
+                bytes,
+                networks: bssids.len(),
+            })
+            .collect(),
+        stations,
+    })
+}
+
+/// Decimal or `0x`-prefixed hex (sharkd shows `wlan.fc.type_subtype` in hex)
+fn parse_number(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
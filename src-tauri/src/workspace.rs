@@ -0,0 +1,124 @@
+//! The analysis context saved when the app closes and restored when it is
+//! reopened: the open capture, the display filter, the column layout, marked
+//! frames, and where the packet list was scrolled to.
+//!
+//! The filter and scroll position live in the frontend, so it passes them in
+//! when saving and applies them itself after restoring.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::{columns, frame_marks, sessions, storage, LoadResult};
+
+pub const STORE_NAME: &str = "workspace";
+
+/// A saved analysis context
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    /// Path of the capture that was open
+    pub file: Option<String>,
+    /// Display filter applied to the packet list
+    pub filter: Option<String>,
+    /// Column layout that was active
+    pub layout: Option<String>,
+    pub marked_frames: Vec<u32>,
+    /// First frame visible in the packet list
+    pub scroll_frame: Option<u32>,
+    /// Frame selected in the packet list
+    pub selected_frame: Option<u32>,
+}
+
+/// A restored workspace, with the result of reopening its capture
+#[derive(Debug, Serialize)]
+pub struct RestoredWorkspace {
+    pub workspace: Workspace,
+    /// None when the workspace had no capture open
+    pub load: Option<LoadResult>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WorkspaceStore {
+    last: Option<Workspace>,
+}
+
+static WORKSPACE: OnceLock<Mutex<WorkspaceStore>> = OnceLock::new();
+
+fn get_store() -> &'static Mutex<WorkspaceStore> {
+    WORKSPACE.get_or_init(|| Mutex::new(storage::load_json(STORE_NAME)))
+}
+
+/// Re-read the store from disk (after a backup restore)
+pub fn reload() {
+    *get_store().lock() = storage::load_json(STORE_NAME);
+}
+
+/// The last saved workspace
+pub fn last_workspace() -> Option<Workspace> {
+    get_store().lock().last.clone()
+}
+
+/// Save the analysis context of `file` (the capture open in `session`).
+/// The layout and the session's marked frames are taken from the backend's
+/// state.
+pub fn save_workspace(
+    session: &str,
+    file: Option<String>,
+    filter: Option<String>,
+    scroll_frame: Option<u32>,
+    selected_frame: Option<u32>,
+) -> Result<Workspace, String> {
+    let workspace = Workspace {
+        file,
+        filter: filter.filter(|f| !f.trim().is_empty()),
+        layout: columns::active_layout(),
+        marked_frames: frame_marks::marked_frames(session),
+        scroll_frame,
+        selected_frame,
+    };
+    let mut store = get_store().lock();
+    store.last = Some(workspace.clone());
+    storage::save_json(STORE_NAME, &*store)?;
+    Ok(workspace)
+}
+
+/// Forget the saved workspace
+pub fn clear_workspace() -> Result<(), String> {
+    let mut store = get_store().lock();
+    store.last = None;
+    storage::save_json(STORE_NAME, &*store)
+}
+
+/// Reopen the last saved workspace's capture in a session, then re-apply its
+/// column layout and marks. Returns None when nothing was saved.
+pub async fn restore_workspace(
+    session_id: Option<String>,
+) -> Result<Option<RestoredWorkspace>, String> {
+    let workspace = match last_workspace() {
+        Some(workspace) => workspace,
+        None => return Ok(None),
+    };
+
+    let session = sessions::resolve(session_id.as_deref());
+    let load = match &workspace.file {
+        Some(file) if std::path::Path::new(file).is_file() => {
+            Some(crate::load_pcap(file.clone(), Some(session.clone())).await?)
+        }
+        Some(file) => return Err(format!("The last capture no longer exists: {}", file)),
+        None => None,
+    };
+
+    // Loading clears marks and may apply the capture's own layout, so the
+    // workspace's are applied after it
+    if let Some(layout) = &workspace.layout {
+        if let Err(e) = columns::apply_layout(layout) {
+            eprintln!("Warning: Failed to restore column layout: {}", e);
+        }
+    }
+    if load.as_ref().map_or(true, |l| l.success) {
+        frame_marks::mark_frames(&session, workspace.marked_frames.iter().copied());
+    }
+
+    Ok(Some(RestoredWorkspace { workspace, load }))
+}
@@ -0,0 +1,113 @@
+//! Loading, paging, statistics and the HTTP bridge, driven against the mock
+//! sharkd on a generated capture.
+//!
+//! Run with `cargo test --features testsupport`.
+
+#![cfg(feature = "testsupport")]
+
+use packet_pilot_lib::testsupport::{
+    bridge_router, install_client, mock_client, pcap, SharkdClient,
+};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Write the sample capture to a file of its own for one test
+fn sample_capture(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "packetpilot-test-{}-{}.pcap",
+        name,
+        std::process::id()
+    ));
+    pcap::write_pcap(&path, &pcap::sample_capture()).expect("write capture");
+    path
+}
+
+async fn loaded_client(name: &str) -> SharkdClient {
+    let client = mock_client(Path::new(env!("CARGO_BIN_EXE_mock-sharkd")))
+        .await
+        .expect("start mock sharkd");
+    client
+        .load(&sample_capture(name).display().to_string())
+        .await
+        .expect("load capture");
+    client
+}
+
+#[tokio::test]
+async fn load_and_page_frames() {
+    let client = loaded_client("frames").await;
+    let total = pcap::sample_capture().len();
+
+    let status = client.status().await.expect("status");
+    assert_eq!(status.frames, Some(total as u64));
+
+    let page = client.frames(0, 3).await.expect("first page");
+    let numbers: Vec<u32> = page.iter().map(|f| f.number).collect();
+    assert_eq!(numbers, [1, 2, 3]);
+
+    let rest = client.frames(3, 100).await.expect("second page");
+    assert_eq!(rest.len(), total - 3);
+    assert_eq!(rest.first().map(|f| f.number), Some(4));
+
+    // The DNS query and its answer
+    let (dns, _) = client.search_frames("dns", 0, 100).await.expect("search");
+    assert_eq!(dns.len(), 2);
+}
+
+#[tokio::test]
+async fn capture_stats() {
+    let client = loaded_client("stats").await;
+    let total = pcap::sample_capture().len() as u64;
+
+    let stats = client.capture_stats(None).await.expect("capture stats");
+    let root = stats
+        .protocol_hierarchy
+        .first()
+        .expect("protocol hierarchy");
+    assert_eq!(root.protocol, "frame");
+    assert_eq!(root.frames, total);
+    assert_eq!(stats.tcp_conversations.len(), 1);
+    assert_eq!(stats.udp_conversations.len(), 1);
+    assert!(!stats.endpoints.is_empty());
+
+    assert_eq!(client.count_frames("udp").await.expect("count"), 2);
+}
+
+/// GET `path` from a server on `addr`, returning the status line and body
+async fn http_get(addr: std::net::SocketAddr, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).await.expect("connect");
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await.expect("send");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.expect("receive");
+
+    let (head, body) = response.split_once("\r\n\r\n").expect("HTTP response");
+    let status = head.lines().next().unwrap_or_default().to_string();
+    (status, body.to_string())
+}
+
+#[tokio::test]
+async fn bridge_capture_stats() {
+    install_client(loaded_client("bridge").await);
+    let total = pcap::sample_capture().len() as u64;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("address");
+    tokio::spawn(async move { axum::serve(listener, bridge_router()).await });
+
+    let (status, body) = http_get(addr, "/capture-stats").await;
+    assert!(status.contains("200"), "unexpected status: {}", status);
+    let stats: Value = serde_json::from_str(&body).expect("JSON body");
+    assert_eq!(stats["summary"]["total_frames"], total);
+    assert_eq!(stats["summary"]["tcp_conversation_count"], 1);
+
+    // A filter limits the statistics to the matching frames
+    let (_, body) = http_get(addr, "/capture-stats?filter=dns").await;
+    let stats: Value = serde_json::from_str(&body).expect("JSON body");
+    assert_eq!(stats["summary"]["total_frames"], 2);
+}
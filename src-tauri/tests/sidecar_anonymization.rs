@@ -0,0 +1,245 @@
+//! Every HTTP bridge endpoint that reports addresses applies the session's
+//! anonymization policy, driven against the mock sharkd.
+//!
+//! Run with `cargo test --features testsupport`.
+
+#![cfg(feature = "testsupport")]
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use packet_pilot_lib::testsupport::{
+    bridge_router, install_client, mock_client, mock_sharkd::RESPONSES_ENV, pcap,
+    set_anonymization_policy, AnonymizationPolicy,
+};
+use serde_json::{json, Value};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The bridge serves the default session, and the policy is per session, so
+/// the tests in this file take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const CLIENT: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+const RESOLVER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+const SERVER: Ipv4Addr = Ipv4Addr::new(93, 184, 216, 34);
+
+/// Run one test on a runtime of its own, after the previous one finished
+fn serial_test<F: std::future::Future>(test: F) -> F::Output {
+    let _turn = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    tokio::runtime::Runtime::new()
+        .expect("runtime")
+        .block_on(test)
+}
+
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "packetpilot-anon-{}-{}.{}",
+        name,
+        std::process::id(),
+        extension
+    ))
+}
+
+/// Load `packets` into a mock sharkd (answering with `canned` responses where
+/// given), install it as the default session's, anonymize that session, and
+/// serve the bridge
+async fn serve_capture(
+    name: &str,
+    packets: &[pcap::Packet],
+    canned: Option<Value>,
+    strip_payloads: bool,
+) -> SocketAddr {
+    let capture = temp_path(name, "pcap");
+    pcap::write_pcap(&capture, packets).expect("write capture");
+
+    // The mock reads its canned responses when it starts
+    let responses = temp_path(name, "json");
+    match &canned {
+        Some(canned) => {
+            std::fs::write(&responses, canned.to_string()).expect("write responses");
+            std::env::set_var(RESPONSES_ENV, &responses);
+        }
+        None => std::env::remove_var(RESPONSES_ENV),
+    }
+    let client = mock_client(Path::new(env!("CARGO_BIN_EXE_mock-sharkd"))).await;
+    std::env::remove_var(RESPONSES_ENV);
+    let client = client.expect("start mock sharkd");
+    client
+        .load(&capture.display().to_string())
+        .await
+        .expect("load capture");
+    install_client(client);
+
+    set_anonymization_policy(AnonymizationPolicy {
+        enabled: true,
+        ip_addresses: true,
+        mac_addresses: true,
+        strip_payloads,
+        key: Some("sidecar-anonymization-test".to_string()),
+    })
+    .expect("set policy");
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("address");
+    tokio::spawn(async move { axum::serve(listener, bridge_router()).await });
+    addr
+}
+
+/// Send a request to the bridge, returning the JSON body
+async fn request(addr: SocketAddr, method: &str, path: &str, body: Option<Value>) -> Value {
+    let mut stream = TcpStream::connect(addr).await.expect("connect");
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.expect("send");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.expect("receive");
+
+    let (head, body) = response.split_once("\r\n\r\n").expect("HTTP response");
+    assert!(head.contains(" 200 "), "unexpected response: {}", head);
+    serde_json::from_str(body).expect("JSON body")
+}
+
+/// Fail if any of `addresses` appears anywhere in the response
+fn assert_anonymized(response: &Value, addresses: &[Ipv4Addr]) {
+    let text = response.to_string();
+    for address in addresses {
+        assert!(
+            !text.contains(&address.to_string()),
+            "{} reached the sidecar: {}",
+            address,
+            text
+        );
+    }
+}
+
+#[test]
+fn capture_stats_are_anonymized() {
+    serial_test(async {
+        let addr = serve_capture("stats", &pcap::sample_capture(), None, true).await;
+        let stats = request(addr, "GET", "/capture-stats", None).await;
+
+        assert_eq!(stats["tcp_conversations"].as_array().map(Vec::len), Some(1));
+        assert_eq!(stats["endpoints"].as_array().map(Vec::len), Some(3));
+        assert_anonymized(&stats, &[CLIENT, RESOLVER, SERVER]);
+    });
+}
+
+#[test]
+fn overlay_stats_are_anonymized() {
+    serial_test(async {
+        let client = SocketAddrV4::new(CLIENT, 53_000);
+        let resolver = SocketAddrV4::new(RESOLVER, 53);
+        let packets: Vec<pcap::Packet> =
+            pcap::dns_query(client, resolver, "example.com", SERVER, 0.0)
+                .into_iter()
+                .map(|p| pcap::Packet {
+                    data: pcap::vlan_frame(10, &p.data),
+                    ..p
+                })
+                .collect();
+        let addr = serve_capture("overlay", &packets, None, true).await;
+        let overlay = request(addr, "GET", "/overlay-stats", None).await;
+
+        let vlan = &overlay["vlans"][0];
+        assert_eq!(vlan["vlan_id"], "10");
+        assert_eq!(vlan["conversations"].as_array().map(Vec::len), Some(1));
+        assert_eq!(vlan["endpoints"].as_array().map(Vec::len), Some(2));
+        assert_anonymized(&overlay, &[CLIENT, RESOLVER, SERVER]);
+    });
+}
+
+#[test]
+fn mobile_summary_is_anonymized() {
+    serial_test(async {
+        let mme = SocketAddrV4::new(Ipv4Addr::new(10, 20, 0, 1), 36_412);
+        let enb = SocketAddrV4::new(Ipv4Addr::new(10, 20, 0, 2), 36_412);
+        let packets = vec![
+            pcap::Packet {
+                ts: 0.0,
+                data: pcap::sctp_frame(enb, mme, pcap::SCTP_INIT),
+            },
+            pcap::Packet {
+                ts: 0.001,
+                data: pcap::sctp_frame(mme, enb, pcap::SCTP_INIT_ACK),
+            },
+        ];
+        let addr = serve_capture("mobile", &packets, None, true).await;
+        let mobile = request(addr, "GET", "/mobile", None).await;
+
+        let association = &mobile["sctp_associations"][0];
+        assert_eq!(association["packets"], 2);
+        assert_eq!(association["health"], "healthy");
+        assert_anonymized(&mobile, &[*mme.ip(), *enb.ip()]);
+    });
+}
+
+#[test]
+fn query_rows_are_anonymized() {
+    serial_test(async {
+        let addr = serve_capture("query", &pcap::sample_capture(), None, true).await;
+        let expr = format!("top 5 ip.src where ip.dst == {}", SERVER);
+        let result = request(addr, "POST", "/query", Some(json!({ "expr": expr }))).await;
+
+        assert_eq!(result["rows"].as_array().map(Vec::len), Some(1));
+        assert_anonymized(&result, &[CLIENT, SERVER]);
+    });
+}
+
+#[test]
+fn streams_are_anonymized() {
+    serial_test(async {
+        let request_text = format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", SERVER);
+        let follow = json!({
+            "shost": SERVER.to_string(),
+            "sport": "80",
+            "sbytes": 5,
+            "chost": CLIENT.to_string(),
+            "cport": "49152",
+            "cbytes": request_text.len(),
+            "payloads": [
+                { "n": request_text.len(), "d": BASE64.encode(&request_text), "s": 0 },
+                { "n": 5, "d": BASE64.encode("hello"), "s": 1 },
+            ],
+        });
+        let canned = json!({ "follow": follow });
+        let stream_request = json!({ "stream_id": 0 });
+
+        // Addresses written in the payload are rewritten too
+        let addr = serve_capture(
+            "stream",
+            &pcap::sample_capture(),
+            Some(canned.clone()),
+            false,
+        )
+        .await;
+        let stream = request(addr, "POST", "/stream", Some(stream_request.clone())).await;
+        assert_eq!(stream["server"]["port"], "80");
+        assert!(stream["segments"][0]["data"]
+            .as_str()
+            .is_some_and(|data| data.starts_with("GET / HTTP/1.1")));
+        assert_anonymized(&stream, &[CLIENT, SERVER]);
+
+        // With payload stripping only the endpoints and sizes are left
+        let addr = serve_capture(
+            "stream-stripped",
+            &pcap::sample_capture(),
+            Some(canned),
+            true,
+        )
+        .await;
+        let stream = request(addr, "POST", "/stream", Some(stream_request)).await;
+        assert_eq!(stream["segments"][1]["size"], 5);
+        assert!(stream["combined_text"].is_null());
+        assert!(!stream.to_string().contains("hello"));
+        assert_anonymized(&stream, &[CLIENT, SERVER]);
+    });
+}